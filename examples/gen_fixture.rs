@@ -0,0 +1,257 @@
+// Synthetic conversations for profiling the renderer and exercising the parsers and exporters.
+//
+//   cargo run --release --example gen_fixture -- --messages 5000 --code-lines 200 --cjk 30 -o big.jsonl
+//
+// Output is deterministic for a given seed, so fixtures can be regenerated instead of committed.
+
+use serde_json::json;
+use std::{fs, io::Write, process::ExitCode};
+
+const USAGE: &str = "\
+Usage: gen_fixture [options]
+
+  --messages N        messages after the system prompt (default 200)
+  --paragraphs N      paragraphs per assistant message (default 3)
+  --code-lines N      lines per code block, 0 for none (default 40)
+  --cjk PERCENT       share of paragraphs written in Japanese/Chinese (default 0)
+  --markdown-depth N  nesting of lists and quotes in assistant messages (default 0)
+  --tools             add tool results between turns
+  --timestamps        add a timestamp to every message
+  --no-system         leave out the system prompt
+  --format jsonl|json one message per line, or a single JSON array (default jsonl)
+  --seed N            seed for the generator (default 1)
+  -o, --out PATH      write to PATH instead of stdout";
+
+const WORDS: &[&str] = &[
+    "the", "model", "returns", "a", "token", "stream", "with", "latency", "budget", "cache", "prompt", "context",
+    "window", "parser", "handles", "nested", "fields", "and", "retries", "on", "timeout", "while", "logging", "every",
+    "request", "to", "disk", "before", "the", "renderer", "draws", "markdown", "tables", "lists", "quotes", "inline",
+    "code", "links", "images", "for", "each", "turn",
+];
+
+// Space-separated so the tokens can be picked one at a time
+const CJK: &str = "モデル は トークン を 生成 し ます 。 会話 の 履歴 が 長く なる と 描画 に 時間 が かかり ます 。 我们 需要 检查 解析器 是否 正确 处理 中文 文本 。";
+
+struct Options {
+    messages: usize,
+    paragraphs: usize,
+    code_lines: usize,
+    cjk: usize,
+    markdown_depth: usize,
+    tools: bool,
+    timestamps: bool,
+    system: bool,
+    json_array: bool,
+    seed: u64,
+    out: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            messages: 200,
+            paragraphs: 3,
+            code_lines: 40,
+            cjk: 0,
+            markdown_depth: 0,
+            tools: false,
+            timestamps: false,
+            system: true,
+            json_array: false,
+            seed: 1,
+            out: None,
+        }
+    }
+}
+
+fn value(args: &mut impl Iterator<Item = String>, name: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{name} needs a value"))
+}
+
+fn number(args: &mut impl Iterator<Item = String>, name: &str) -> Result<usize, String> {
+    let v = value(args, name)?;
+    v.parse().map_err(|_| format!("{name}: not a number: {v}"))
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut opts = Options::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let args = &mut args;
+        match arg.as_str() {
+            "--messages" => opts.messages = number(args, "--messages")?,
+            "--paragraphs" => opts.paragraphs = number(args, "--paragraphs")?,
+            "--code-lines" => opts.code_lines = number(args, "--code-lines")?,
+            "--cjk" => opts.cjk = number(args, "--cjk")?.min(100),
+            "--markdown-depth" => opts.markdown_depth = number(args, "--markdown-depth")?,
+            "--seed" => opts.seed = number(args, "--seed")? as u64,
+            "--tools" => opts.tools = true,
+            "--timestamps" => opts.timestamps = true,
+            "--no-system" => opts.system = false,
+            "--format" => match value(args, "--format")?.as_str() {
+                "jsonl" => opts.json_array = false,
+                "json" => opts.json_array = true,
+                other => return Err(format!("--format: expected jsonl or json, got {other}")),
+            },
+            "-o" | "--out" => opts.out = Some(value(args, "--out")?),
+            "-h" | "--help" => return Err(String::new()),
+            other => return Err(format!("Unknown option: {other}")),
+        }
+    }
+    Ok(opts)
+}
+
+// xorshift64*: small, deterministic and good enough for filler text
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+fn sentence(rng: &mut Rng, words: usize) -> String {
+    let mut s: Vec<&str> = (0..words).map(|_| rng.pick(WORDS)).collect();
+    let first = s[0].to_string();
+    let capitalized = first[..1].to_uppercase() + &first[1..];
+    s[0] = &capitalized;
+    format!("{}.", s.join(" "))
+}
+
+fn paragraph(rng: &mut Rng, opts: &Options) -> String {
+    if rng.below(100) < opts.cjk {
+        let tokens: Vec<&str> = CJK.split(' ').collect();
+        return (0..20 + rng.below(40)).map(|_| rng.pick(&tokens)).collect();
+    }
+    let mut p: Vec<String> = (0..2 + rng.below(4))
+        .map(|_| {
+            let words = 6 + rng.below(12);
+            sentence(rng, words)
+        })
+        .collect();
+    // Inline markup the Markdown renderer has to handle
+    match rng.below(4) {
+        0 => p.push(format!("See `{}` and **{}**.", rng.pick(WORDS), rng.pick(WORDS))),
+        1 => p.push(format!(
+            "[{}](https://example.com/{})",
+            rng.pick(WORDS),
+            rng.below(1000)
+        )),
+        _ => {}
+    }
+    p.join(" ")
+}
+
+fn code_block(rng: &mut Rng, lines: usize) -> String {
+    let mut code = String::from("```python\n");
+    for i in 0..lines {
+        let indent = "    ".repeat(rng.below(3));
+        code.push_str(&format!(
+            "{indent}value_{i} = compute(\"{}\", {})\n",
+            rng.pick(WORDS),
+            rng.below(10_000)
+        ));
+    }
+    code.push_str("```");
+    code
+}
+
+// Nested list with a block quote at every level, `depth` levels deep.
+fn nested_markdown(rng: &mut Rng, depth: usize) -> String {
+    let mut out = String::new();
+    for level in 0..depth {
+        let indent = "  ".repeat(level);
+        out.push_str(&format!("{indent}- {}\n", sentence(rng, 5)));
+        out.push_str(&format!("{indent}  > {}\n", sentence(rng, 8)));
+    }
+    out.push_str("\n| step | result |\n|---|---|\n");
+    for i in 0..3 {
+        out.push_str(&format!("| {i} | {} |\n", rng.pick(WORDS)));
+    }
+    out
+}
+
+fn assistant_message(rng: &mut Rng, opts: &Options) -> String {
+    let mut parts: Vec<String> = vec![format!("## {}", sentence(rng, 4))];
+    for i in 0..opts.paragraphs.max(1) {
+        parts.push(paragraph(rng, opts));
+        if i == 0 && opts.code_lines > 0 {
+            parts.push(code_block(rng, opts.code_lines));
+        }
+    }
+    if opts.markdown_depth > 0 {
+        parts.push(nested_markdown(rng, opts.markdown_depth));
+    }
+    parts.join("\n\n")
+}
+
+fn generate(opts: &Options) -> Vec<serde_json::Value> {
+    let mut rng = Rng(opts.seed.max(1));
+    // 2024-01-01T00:00:00Z
+    let mut time_ms: i64 = 1_704_067_200_000;
+    let mut out = Vec::new();
+    let mut push = |mut msg: serde_json::Value, rng: &mut Rng| {
+        if opts.timestamps {
+            time_ms += 1_000 + rng.below(120_000) as i64;
+            msg["timestamp"] = json!(time_ms);
+        }
+        out.push(msg);
+    };
+    if opts.system {
+        push(
+            json!({"role": "system", "content": "You are a helpful assistant used for rendering benchmarks."}),
+            &mut rng,
+        );
+    }
+    for i in 0..opts.messages {
+        let msg = if i % 2 == 0 {
+            json!({"role": "user", "content": paragraph(&mut rng, opts)})
+        } else if opts.tools && i % 6 == 3 {
+            let query = rng.pick(WORDS);
+            json!({"role": "tool", "name": "search", "content": format!("{{\"query\": \"{query}\", \"hits\": {}}}", rng.below(50))})
+        } else {
+            json!({"role": "assistant", "content": assistant_message(&mut rng, opts)})
+        };
+        push(msg, &mut rng);
+    }
+    out
+}
+
+fn main() -> ExitCode {
+    let opts = match parse_args() {
+        Ok(opts) => opts,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{e}\n");
+            }
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let messages = generate(&opts);
+    let text = if opts.json_array {
+        serde_json::to_string_pretty(&messages).expect("values serialize")
+    } else {
+        messages.iter().map(|m| m.to_string() + "\n").collect()
+    };
+    let written = match &opts.out {
+        Some(path) => fs::write(path, text),
+        None => std::io::stdout().write_all(text.as_bytes()),
+    };
+    if let Err(e) = written {
+        eprintln!("Failed to write output: {e}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
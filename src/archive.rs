@@ -0,0 +1,129 @@
+// ZIP archives such as the ChatGPT data export (`conversations.json` next to HTML and media):
+// the JSON/JSONL entries are listed and the one picked goes through the normal loading pipeline.
+
+use crate::{
+    parse::{load_from_bytes, read_limited},
+    AppState,
+};
+use anyhow::{Context, Result};
+use eframe::egui::{self, RichText, ScrollArea};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const ARCHIVE_WINDOW: &str = "Open from archive";
+
+#[derive(Default)]
+pub(crate) struct ArchiveUi {
+    pub show_window: bool,
+    path: Option<PathBuf>,
+    entries: Vec<ArchiveEntry>,
+}
+
+struct ArchiveEntry {
+    name: String,
+    // Uncompressed size in bytes
+    size: u64,
+}
+
+pub(crate) fn is_zip_file(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("zip"))
+}
+
+fn is_log_entry(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    // macOS "Compress" adds resource forks under __MACOSX/
+    !lower.starts_with("__macosx/") && (lower.ends_with(".json") || lower.ends_with(".jsonl"))
+}
+
+fn list_entries(path: &Path) -> Result<Vec<ArchiveEntry>> {
+    let file = fs::File::open(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut zip = zip::ZipArchive::new(file)?;
+    let mut entries = Vec::new();
+    for i in 0..zip.len() {
+        let entry = zip.by_index(i)?;
+        if entry.is_file() && is_log_entry(entry.name()) {
+            entries.push(ArchiveEntry {
+                name: entry.name().to_string(),
+                size: entry.size(),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+fn read_entry(path: &Path, name: &str) -> Result<Vec<u8>> {
+    let file = fs::File::open(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut zip = zip::ZipArchive::new(file)?;
+    let entry = zip.by_name(name)?;
+    read_limited(entry).with_context(|| format!("Failed to extract {name}"))
+}
+
+impl AppState {
+    // Lists the archive's JSON/JSONL files; the conversation is loaded from the window.
+    pub(crate) fn open_zip_file(&mut self, path: &Path) {
+        match list_entries(path) {
+            Ok(entries) if entries.is_empty() => self.errors.push("No JSON or JSONL files in this archive".to_string()),
+            Ok(entries) => {
+                self.archive.entries = entries;
+                self.archive.path = Some(path.to_path_buf());
+                self.archive.show_window = true;
+            }
+            Err(e) => self.errors.push(format!("Failed to read archive: {e}")),
+        }
+    }
+
+    pub(crate) fn archive_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.archive.show_window;
+        let mut picked = None;
+        let window = self.tool_window(ARCHIVE_WINDOW).default_width(460.0).open(&mut open);
+        let fmt = self.fmt;
+        let ui_state = &self.archive;
+        let resp = window.show(ctx, |ui| {
+            let file = ui_state
+                .path
+                .as_ref()
+                .map_or(String::new(), |p| p.display().to_string());
+            ui.label(RichText::new(file).monospace().small());
+            ui.separator();
+            ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                egui::Grid::new("archive_entries")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for entry in &ui_state.entries {
+                            if ui.link(&entry.name).clicked() {
+                                picked = Some(entry.name.clone());
+                            }
+                            ui.label(RichText::new(fmt.bytes(entry.size)).weak());
+                            ui.end_row();
+                        }
+                    });
+            });
+        });
+        if let Some(r) = resp {
+            self.remember_window(ARCHIVE_WINDOW, r.response.rect);
+        }
+
+        if let (Some(name), Some(path)) = (picked, self.archive.path.clone()) {
+            match read_entry(&path, &name).and_then(|bytes| load_from_bytes(&bytes, self.parse_options())) {
+                Ok(mut loaded) => {
+                    let archive = path
+                        .file_name()
+                        .map(|s| s.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    loaded.file_name = Some(format!("{archive}/{name}"));
+                    self.set_loaded(loaded);
+                    self.read_only = self.read_only_reason(Some(&path));
+                    open = false;
+                }
+                Err(e) => self.errors.push(format!("Failed to load {name}: {e}")),
+            }
+        }
+        self.archive.show_window = open;
+        if !open {
+            self.archive.entries.clear();
+        }
+    }
+}
@@ -0,0 +1,98 @@
+// Files the app is about to overwrite or delete (an export saved over an older one, code extracted
+// into a folder that already has those files, a diff applied to a working copy) are copied to a
+// backup folder in the config directory first. Each operation gets a folder named by its time
+// that mirrors where the files were, e.g. `backups/2024-05-01_10-32-07/home/me/project/src/main.rs`.
+// A file that cannot be backed up is left alone and the operation fails. On by default; the
+// setting is in the Tools menu.
+
+use crate::{config_dir, save_settings, AppState};
+use anyhow::{Context, Result};
+use chrono::Local;
+use eframe::egui::{self, RichText};
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+fn backups_dir() -> Option<PathBuf> {
+    config_dir().map(|d| d.join("backups"))
+}
+
+// Backups of one operation's files.
+pub(crate) struct Backup {
+    // None when backups are off
+    dir: Option<PathBuf>,
+    kept: usize,
+}
+
+// `path` as a relative path under the backup folder: `C:\Users\me\a.md` becomes `C/Users/me/a.md`.
+fn mirrored(path: &Path) -> Result<PathBuf> {
+    let absolute = std::path::absolute(path).with_context(|| format!("Failed to resolve {}", path.display()))?;
+    Ok(absolute
+        .components()
+        .filter_map(|c| match c {
+            Component::Prefix(prefix) => {
+                let drive = prefix.as_os_str().to_string_lossy().replace([':', '\\', '?'], "");
+                (!drive.is_empty()).then(|| PathBuf::from(drive))
+            }
+            Component::Normal(name) => Some(PathBuf::from(name)),
+            _ => None,
+        })
+        .collect())
+}
+
+impl Backup {
+    pub(crate) fn new(enabled: bool) -> Self {
+        let dir = backups_dir().filter(|_| enabled).map(|base| {
+            let stamp = Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+            // Two operations in the same second get folders of their own
+            (1..)
+                .map(|n| match n {
+                    1 => base.join(&stamp),
+                    n => base.join(format!("{stamp}-{n}")),
+                })
+                .find(|dir| !dir.exists())
+                .expect("unbounded")
+        });
+        Self { dir, kept: 0 }
+    }
+
+    // Copies `path`, if there is a file there, before it is replaced or removed.
+    pub(crate) fn keep(&mut self, path: &Path) -> Result<()> {
+        let Some(dir) = &self.dir else { return Ok(()) };
+        if !path.is_file() {
+            return Ok(());
+        }
+        let copy = dir.join(mirrored(path)?);
+        if let Some(parent) = copy.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::copy(path, &copy).with_context(|| format!("Failed to back up {}", path.display()))?;
+        self.kept += 1;
+        Ok(())
+    }
+
+    // Where the originals went, if there were any.
+    pub(crate) fn kept_in(&self) -> Option<&Path> {
+        self.dir.as_deref().filter(|_| self.kept > 0)
+    }
+}
+
+impl AppState {
+    // Backup setting in the Tools menu.
+    pub(crate) fn backup_ui(&mut self, ui: &mut egui::Ui) {
+        if ui
+            .checkbox(&mut self.settings.backups, "Back up files before replacing them")
+            .on_hover_text(
+                "Copy files that exports, Extract code and Apply diff overwrite or delete to the backup folder first",
+            )
+            .changed()
+        {
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+        let folder = backups_dir().map_or("(no config directory)".to_string(), |d| d.display().to_string());
+        ui.label(RichText::new(folder).monospace().small());
+    }
+}
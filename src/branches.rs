@@ -0,0 +1,137 @@
+// Tree-shaped conversations (ChatGPT keeps every regenerated answer and edited prompt) load along
+// the branch that was current when exported. Messages with alternatives get a "◀ 2/3 ▶" switcher
+// that swaps in a sibling branch and follows its newest replies down to a leaf.
+
+use crate::{
+    parse::{normalize_into, RawMsg},
+    roles, AppState, Loaded,
+};
+use eframe::egui::{self, RichText};
+
+#[derive(Clone, Debug)]
+pub(crate) struct TreeNode {
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    // None for nodes that are not shown (hidden scaffolding, empty messages)
+    pub message: Option<RawMsg>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct MessageTree {
+    nodes: Vec<TreeNode>,
+    // Shown branch as node indices, root first
+    path: Vec<usize>,
+}
+
+// Where the branch can be switched for one shown message.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Fork {
+    // Position in the path of the node that has siblings
+    at: usize,
+    pub index: usize,
+    pub count: usize,
+}
+
+impl MessageTree {
+    // A tree worth keeping: None when no node has alternatives.
+    pub(crate) fn new(nodes: Vec<TreeNode>, path: Vec<usize>) -> Option<Self> {
+        nodes.iter().any(|n| n.children.len() > 1).then_some(Self { nodes, path })
+    }
+
+    pub(crate) fn messages(&self) -> Vec<RawMsg> {
+        self.path.iter().filter_map(|&n| self.nodes[n].message.clone()).collect()
+    }
+
+    // One entry per message normalization keeps (the first system message becomes the system
+    // prompt): the fork between the previous shown message and this one, if any.
+    pub(crate) fn forks(&self) -> Vec<Option<Fork>> {
+        let mut forks = Vec::new();
+        let mut pending = None;
+        let mut system_taken = false;
+        for (at, &node) in self.path.iter().enumerate() {
+            let siblings = self.nodes[node].parent.map_or(&[][..], |p| &self.nodes[p].children);
+            if siblings.len() > 1 {
+                let index = siblings.iter().position(|&s| s == node).unwrap_or(0);
+                pending = Some(Fork { at, index, count: siblings.len() });
+            }
+            let Some(msg) = &self.nodes[node].message else { continue };
+            if !system_taken && roles::canonical_role(&msg.role) == "system" {
+                system_taken = true;
+                continue;
+            }
+            forks.push(pending.take());
+        }
+        forks
+    }
+
+    // Show sibling `index` in place of the node at `fork`, then the newest reply at every level.
+    fn switch(&mut self, fork: Fork, index: usize) {
+        let Some(parent) = self.nodes[self.path[fork.at]].parent else { return };
+        let Some(&sibling) = self.nodes[parent].children.get(index) else { return };
+        self.path.truncate(fork.at);
+        self.path.push(sibling);
+        // Bounded so malformed exports with cyclic child links cannot loop
+        let mut node = sibling;
+        while let Some(&child) = self.nodes[node].children.last() {
+            if self.path.len() > self.nodes.len() {
+                break;
+            }
+            self.path.push(child);
+            node = child;
+        }
+    }
+}
+
+// "◀ 2/3 ▶" in the row under a message; returns the sibling to switch to.
+pub(crate) fn branch_switcher(ui: &mut egui::Ui, fork: Fork) -> Option<usize> {
+    let mut target = None;
+    ui.add_enabled_ui(fork.index > 0, |ui| {
+        if ui.small_button("◀").on_hover_text("Previous branch").clicked() {
+            target = Some(fork.index - 1);
+        }
+    });
+    ui.label(RichText::new(format!("{}/{}", fork.index + 1, fork.count)).small())
+        .on_hover_text("Alternative versions of this message (regenerated or edited)");
+    ui.add_enabled_ui(fork.index + 1 < fork.count, |ui| {
+        if ui.small_button("▶").on_hover_text("Next branch").clicked() {
+            target = Some(fork.index + 1);
+        }
+    });
+    target
+}
+
+impl AppState {
+    // Forks for the shown messages, or nothing when they no longer line up with the tree (split
+    // into sessions, or a transform script dropped messages).
+    pub(crate) fn branch_forks(&self) -> Vec<Option<Fork>> {
+        let Some(tree) = &self.tree else { return Vec::new() };
+        let forks = tree.forks();
+        if forks.len() == self.messages.len() {
+            forks
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub(crate) fn switch_branch(&mut self, fork: Fork, index: usize) {
+        let Some(tree) = &mut self.tree else { return };
+        tree.switch(fork, index);
+        let (mut system, mut messages) = (None, Vec::new());
+        normalize_into(tree.messages(), &mut system, &mut messages);
+        let mut loaded = Loaded { system, messages, ..Default::default() };
+        self.scripts.transform_loaded(&mut loaded);
+        roles::apply_aliases(&mut loaded.messages, &self.settings.role_aliases);
+        self.errors.extend(loaded.errors);
+        self.system = loaded.system;
+        self.messages = loaded.messages;
+        // Switching to another conversation and back keeps the chosen branch
+        if let Some(session) = self.sessions.get_mut(self.active_session).filter(|_| !self.split_applied) {
+            session.system = self.system.clone();
+            session.messages = self.messages.clone();
+            session.tree = self.tree.clone();
+        }
+        self.reset_escapes(false);
+        self.judge.reset_for(self.system.as_deref(), &self.messages);
+        self.reset_badges();
+    }
+}
@@ -0,0 +1,72 @@
+// Images in messages and the Markdown renderer's state are kept until they are let go of: egui
+// holds on to every attachment and linked image it is given, both decoded and as a texture, so
+// memory grew with every log opened. What the previous conversation left behind is dropped when
+// another one is shown, and images are loaded again as they come into view. The status bar shows
+// how much is held, with "Clear caches" to drop it all.
+
+use crate::AppState;
+use eframe::egui::{self, load::BytesLoader as _};
+use egui_commonmark::CommonMarkCache;
+
+#[derive(Default)]
+pub(crate) struct Caches {
+    // Set when the conversation is replaced; the old one's entries go at the start of the next frame
+    stale: bool,
+}
+
+impl Caches {
+    pub(crate) fn mark_stale(&mut self) {
+        self.stale = true;
+    }
+}
+
+// Bytes held for images: attachments and downloads, decoded images and textures.
+fn image_bytes(ctx: &egui::Context) -> [usize; 3] {
+    let loaders = ctx.loaders();
+    let bytes = loaders.include.byte_size() + loaders.bytes.lock().iter().map(|l| l.byte_size()).sum::<usize>();
+    let decoded = loaders.image.lock().iter().map(|l| l.byte_size()).sum();
+    let textures = loaders.texture.lock().iter().map(|l| l.byte_size()).sum();
+    [bytes, decoded, textures]
+}
+
+impl AppState {
+    fn clear_caches(&mut self, ctx: &egui::Context) {
+        ctx.forget_all_images();
+        self.md_cache = CommonMarkCache::default();
+    }
+
+    pub(crate) fn evict_stale_caches(&mut self, ctx: &egui::Context) {
+        if std::mem::take(&mut self.caches.stale) {
+            self.clear_caches(ctx);
+        }
+    }
+
+    // Memory held by caches in the status bar, with "Clear caches".
+    pub(crate) fn cache_status_ui(&mut self, ui: &mut egui::Ui) {
+        let [bytes, decoded, textures] = image_bytes(ui.ctx());
+        let total = (bytes + decoded + textures) as u64;
+        ui.menu_button(format!("Caches: {}", self.fmt.bytes(total)), |ui| {
+            egui::Grid::new("cache_sizes").num_columns(2).show(ui, |ui| {
+                let rows = [
+                    ("Image files", bytes),
+                    ("Decoded images", decoded),
+                    ("Textures", textures),
+                ];
+                for (label, size) in rows {
+                    ui.label(label);
+                    ui.label(self.fmt.bytes(size as u64));
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+            if ui
+                .button("Clear caches")
+                .on_hover_text("Images and formatted text are loaded again as messages come into view")
+                .clicked()
+            {
+                self.clear_caches(ui.ctx());
+                ui.close_menu();
+            }
+        });
+    }
+}
@@ -0,0 +1,61 @@
+// Byte-order marks are stripped, and UTF-16 is transcoded whether it has a BOM or not (some
+// Windows tools write UTF-16LE without one); these are ordinary encodings and load silently.
+// Other text that is not valid UTF-8 is decoded rather than refused, with a warning saying how.
+// Clean Shift_JIS (common for Japanese tools on Windows) is decoded exactly. Otherwise a file that
+// is mostly UTF-8 has its bad bytes replaced with U+FFFD, and one with no UTF-8 sequences at all
+// is read as Latin-1 (Windows-1252).
+
+use encoding_rs::{Encoding, SHIFT_JIS, UTF_16BE, UTF_16LE, WINDOWS_1252};
+use std::borrow::Cow;
+
+// Start of a file without a BOM looked at to recognize UTF-16
+const SNIFF_BYTES: usize = 4096;
+
+// UTF-16 without a BOM, recognized by the zero high bytes of its ASCII characters. UTF-8 text
+// never contains zero bytes, so half of them in one position is unambiguous.
+fn bomless_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    if bytes.len() < 4 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let head = &bytes[..bytes.len().min(SNIFF_BYTES)];
+    let zeros = |offset: usize| head.iter().skip(offset).step_by(2).filter(|b| **b == 0).count();
+    let half = head.len() / 4;
+    match (zeros(0), zeros(1)) {
+        (0, odd) if odd > half => Some(UTF_16LE),
+        (even, 0) if even > half => Some(UTF_16BE),
+        _ => None,
+    }
+}
+
+// The text, and a warning when it had to be decoded from something other than UTF-8 or UTF-16.
+pub(crate) fn decode(bytes: &[u8]) -> (Cow<'_, str>, Option<String>) {
+    let (encoding, bytes) = match Encoding::for_bom(bytes) {
+        Some((encoding, bom)) => (Some(encoding), &bytes[bom..]),
+        None => (bomless_utf16(bytes), bytes),
+    };
+    if let Some(encoding) = encoding.filter(|e| *e == UTF_16LE || *e == UTF_16BE) {
+        let (text, _) = encoding.decode_without_bom_handling(bytes);
+        return (text, None);
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return (Cow::Borrowed(text), None);
+    }
+    if let Some(text) = SHIFT_JIS.decode_without_bom_handling_and_without_replacement(bytes) {
+        // Latin-1 text can also be valid Shift_JIS by chance; real Japanese text has kana
+        if text.chars().any(|c| matches!(c, '\u{3040}'..='\u{30ff}')) {
+            return (text, Some("Not UTF-8: decoded as Shift_JIS".to_string()));
+        }
+    }
+    let (mut valid_multibyte, mut invalid) = (0usize, 0usize);
+    for chunk in bytes.utf8_chunks() {
+        valid_multibyte += chunk.valid().chars().filter(|c| !c.is_ascii()).count();
+        invalid += usize::from(!chunk.invalid().is_empty());
+    }
+    if valid_multibyte >= invalid {
+        let text = String::from_utf8_lossy(bytes);
+        let warning = format!("Invalid UTF-8 in {invalid} place(s), shown as \u{fffd}");
+        return (text, Some(warning));
+    }
+    let (text, _) = WINDOWS_1252.decode_without_bom_handling(bytes);
+    (text, Some("Not UTF-8: decoded as Latin-1 (Windows-1252)".to_string()))
+}
@@ -0,0 +1,111 @@
+// Clipboard history: the last few things copied in the viewer, listed in the Clipboard pane so a
+// snippet needed again and again can be copied back with one click. Copies are picked up from the
+// frame's output, so every Copy button (and text selected and copied) is recorded.
+
+use crate::{save_settings, AppState};
+use chrono::{DateTime, Local};
+use eframe::egui::{self, RichText, ScrollArea};
+use std::collections::VecDeque;
+
+pub(crate) const DEFAULT_HISTORY_LEN: usize = 20;
+const MAX_HISTORY_LEN: usize = 200;
+const PREVIEW_CHARS: usize = 80;
+// Longer entries are cut in the hover text
+const HOVER_CHARS: usize = 2000;
+
+struct Copied {
+    text: String,
+    at: DateTime<Local>,
+}
+
+#[derive(Default)]
+pub(crate) struct ClipboardHistory {
+    // Most recent first
+    items: VecDeque<Copied>,
+}
+
+impl ClipboardHistory {
+    // Copying something already in the list moves it to the top instead of adding it twice.
+    fn record(&mut self, text: String, limit: usize) {
+        if text.trim().is_empty() {
+            return;
+        }
+        self.items.retain(|c| c.text != text);
+        self.items.push_front(Copied { text, at: Local::now() });
+        self.items.truncate(limit);
+    }
+}
+
+fn preview(text: &str) -> String {
+    let line = text.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or_default();
+    let mut short: String = line.chars().take(PREVIEW_CHARS).collect();
+    if short.len() < text.trim().len() {
+        short.push('…');
+    }
+    short
+}
+
+impl AppState {
+    // Called at the end of every frame, after all Copy buttons have run.
+    pub(crate) fn record_copied(&mut self, ctx: &egui::Context) {
+        let text = ctx.output(|o| o.copied_text.clone());
+        if !text.is_empty() {
+            self.clipboard.record(text, self.settings.clipboard_history);
+        }
+    }
+
+    pub(crate) fn clipboard_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Keep last");
+            let resp =
+                ui.add(egui::DragValue::new(&mut self.settings.clipboard_history).clamp_range(1..=MAX_HISTORY_LEN));
+            if resp.changed() {
+                self.clipboard.items.truncate(self.settings.clipboard_history);
+            }
+            if resp.lost_focus() || resp.drag_stopped() {
+                if let Err(e) = save_settings(&self.settings) {
+                    self.errors.push(format!("Failed to save settings: {e}"));
+                }
+            }
+            if ui
+                .add_enabled(!self.clipboard.items.is_empty(), egui::Button::new("Clear"))
+                .clicked()
+            {
+                self.clipboard.items.clear();
+            }
+        });
+        if self.clipboard.items.is_empty() {
+            ui.label(RichText::new("Nothing copied yet").italics().weak());
+            return;
+        }
+        ui.separator();
+        let mut copy = None;
+        ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+            egui::Grid::new("clipboard_history")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    for (i, item) in self.clipboard.items.iter().enumerate() {
+                        if ui.small_button("Copy").on_hover_text("Copy again").clicked() {
+                            copy = Some(i);
+                        }
+                        let hover: String = item.text.chars().take(HOVER_CHARS).collect();
+                        ui.label(RichText::new(preview(&item.text)).monospace().small())
+                            .on_hover_text(hover);
+                        let chars = self.fmt.count(item.text.chars().count());
+                        ui.label(
+                            RichText::new(format!("{chars} chars · {}", self.fmt.time(item.at)))
+                                .small()
+                                .weak(),
+                        );
+                        ui.end_row();
+                    }
+                });
+        });
+        if let Some(i) = copy {
+            // Recorded again at the end of the frame, which moves it to the top
+            let text = self.clipboard.items[i].text.clone();
+            ui.output_mut(|o| o.copied_text = text);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+// Code blocks longer than FOLD_LINES (a whole file, a long log pasted into a fence) show their
+// first PREVIEW_LINES with "Expand (123 lines)" under them, in the viewer and in the HTML export,
+// where a `<details>` element does the same without scripts. Fences are found the way the
+// exporters find them: an opening line starting with ``` and a closing line of just ```.
+
+use crate::html_escape;
+use eframe::egui::{self, Id, RichText};
+use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
+
+const FOLD_LINES: usize = 40;
+const PREVIEW_LINES: usize = 8;
+
+// A long code block in message text, by byte offset.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Fold {
+    // The opening fence line, and past the closing one (or the end of the text)
+    start: usize,
+    end: usize,
+    // Past the first PREVIEW_LINES lines of code
+    preview_end: usize,
+    lines: usize,
+}
+
+pub(crate) fn folds(text: &str) -> Vec<Fold> {
+    let mut folds = Vec::new();
+    // Start of the open block, and the end of each of its lines so far
+    let mut open: Option<(usize, Vec<usize>)> = None;
+    let mut at = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let end = at + line.len();
+        match open.as_mut() {
+            None if trimmed.starts_with("```") => open = Some((at, Vec::new())),
+            None => {}
+            Some(_) if trimmed == "```" => {
+                let (start, lines) = open.take().expect("checked above");
+                if lines.len() > FOLD_LINES {
+                    folds.push(Fold {
+                        start,
+                        end,
+                        preview_end: lines[PREVIEW_LINES - 1],
+                        lines: lines.len(),
+                    });
+                }
+            }
+            Some((_, lines)) => lines.push(end),
+        }
+        at = end;
+    }
+    if let Some((start, lines)) = open.filter(|(_, lines)| lines.len() > FOLD_LINES) {
+        folds.push(Fold {
+            start,
+            end: text.len(),
+            preview_end: lines[PREVIEW_LINES - 1],
+            lines: lines.len(),
+        });
+    }
+    folds
+}
+
+// Renders Markdown `text` with the viewer `id`, the long code blocks in `folds` folded.
+pub(crate) fn show(ui: &mut egui::Ui, cache: &mut CommonMarkCache, id: &str, text: &str, folds: &[Fold]) {
+    if folds.is_empty() {
+        CommonMarkViewer::new(id).show(ui, cache, text);
+        return;
+    }
+    let mut at = 0;
+    for (n, fold) in folds.iter().enumerate() {
+        let before = &text[at..fold.start];
+        if !before.trim().is_empty() {
+            CommonMarkViewer::new(format!("{id}:{n}")).show(ui, cache, before);
+        }
+        let open_id = Id::new(("code-fold", id, n));
+        let open = ui.data(|d| d.get_temp(open_id)).unwrap_or(false);
+        let code = if open {
+            text[fold.start..fold.end].to_string()
+        } else {
+            format!("{}```\n", &text[fold.start..fold.preview_end])
+        };
+        CommonMarkViewer::new(format!("{id}:{n}:code")).show(ui, cache, &code);
+        let label = if open {
+            "Collapse".to_string()
+        } else {
+            format!("Expand ({} lines)", fold.lines)
+        };
+        if ui.link(RichText::new(label).small()).clicked() {
+            ui.data_mut(|d| d.insert_temp(open_id, !open));
+        }
+        at = fold.end;
+    }
+    let after = &text[at..];
+    if !after.trim().is_empty() {
+        CommonMarkViewer::new(format!("{id}:{}", folds.len())).show(ui, cache, after);
+    }
+}
+
+// A code block in the HTML export; long ones show the preview until "Expand" is clicked.
+pub(crate) fn code_html(lang: Option<&str>, lines: &[&str]) -> String {
+    let open = match lang {
+        Some(lang) => format!("<pre><code class=\"language-{}\">", html_escape(lang)),
+        None => "<pre><code>".to_string(),
+    };
+    let body = |lines: &[&str]| lines.iter().map(|l| html_escape(l) + "\n").collect::<String>();
+    if lines.len() <= FOLD_LINES {
+        return format!("{open}{}</code></pre>\n", body(lines));
+    }
+    // The preview comes last and is shown first (see `.fold` in the export's CSS), so that it can
+    // be hidden once the block is open
+    format!(
+        "<div class=\"fold\"><details><summary><span class=\"more\">Expand ({} lines)</span><span class=\"less\">Collapse</span></summary>{open}{}</code></pre></details>{open}{}</code></pre></div>\n",
+        lines.len(),
+        body(lines),
+        body(&lines[..PREVIEW_LINES])
+    )
+}
@@ -0,0 +1,254 @@
+// CSV/TSV import: spreadsheets of chats have no fixed schema, so the columns holding the
+// role, content and (optionally) timestamp and conversation id are picked in a small dialog.
+
+use crate::{
+    charset,
+    parse::{normalize_sessions, timestamp_ms, RawMsg, RawSession},
+    AppState,
+};
+use anyhow::{anyhow, Context, Result};
+use eframe::egui::{self, Color32, RichText, ScrollArea};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const CSV_WINDOW: &str = "Import CSV";
+const PREVIEW_ROWS: usize = 5;
+
+#[derive(Default)]
+pub(crate) struct CsvImportUi {
+    pub show_window: bool,
+    path: Option<PathBuf>,
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+    mapping: ColumnMapping,
+    status: Option<String>,
+}
+
+#[derive(Default, Clone, Copy)]
+pub(crate) struct ColumnMapping {
+    role: Option<usize>,
+    content: Option<usize>,
+    timestamp: Option<usize>,
+    conversation: Option<usize>,
+}
+
+pub(crate) fn is_table_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("csv") || e.eq_ignore_ascii_case("tsv"))
+}
+
+// Tab for .tsv; otherwise whichever of , ; or tab is most common on the header line.
+fn sniff_delimiter(path: &Path, text: &str) -> u8 {
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("tsv")) {
+        return b'\t';
+    }
+    let header = text.lines().next().unwrap_or_default();
+    [b',', b'\t', b';']
+        .into_iter()
+        .max_by_key(|d| header.bytes().filter(|b| b == d).count())
+        .unwrap_or(b',')
+}
+
+pub(crate) fn read_table(path: &Path) -> Result<(Vec<String>, Vec<Vec<String>>)> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    // Excel's "Unicode text" export is UTF-16 with a BOM
+    let (text, _) = charset::decode(&bytes);
+    let text = text.as_ref();
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(sniff_delimiter(path, text))
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let headers: Vec<String> = reader.headers()?.iter().map(|h| h.trim().to_string()).collect();
+    if headers.iter().all(|h| h.is_empty()) {
+        return Err(anyhow!("No header row"));
+    }
+    let mut rows = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.with_context(|| format!("Row {}", i + 2))?;
+        rows.push(record.iter().map(str::to_string).collect());
+    }
+    Ok((headers, rows))
+}
+
+// First column whose header matches one of `names` (case-insensitive).
+fn find_column(headers: &[String], names: &[&str]) -> Option<usize> {
+    names
+        .iter()
+        .find_map(|n| headers.iter().position(|h| h.eq_ignore_ascii_case(n)))
+}
+
+pub(crate) fn guess_mapping(headers: &[String]) -> ColumnMapping {
+    ColumnMapping {
+        role: find_column(headers, &["role", "speaker", "author", "sender", "from"]),
+        content: find_column(headers, &["content", "message", "text", "body", "utterance"]),
+        timestamp: find_column(headers, &["timestamp", "time", "created_at", "date", "datetime"]),
+        conversation: find_column(
+            headers,
+            &[
+                "conversation_id",
+                "conversation",
+                "thread_id",
+                "thread",
+                "session_id",
+                "session",
+                "chat_id",
+            ],
+        ),
+    }
+}
+
+fn cell(row: &[String], col: Option<usize>) -> &str {
+    col.and_then(|c| row.get(c)).map_or("", |s| s.trim())
+}
+
+// One session per conversation id, in order of first appearance; rows without content are skipped.
+pub(crate) fn build_sessions(rows: &[Vec<String>], mapping: ColumnMapping) -> Vec<RawSession> {
+    let mut sessions: Vec<RawSession> = Vec::new();
+    let mut by_id: HashMap<String, usize> = HashMap::new();
+    for row in rows {
+        let content = cell(row, mapping.content);
+        if content.is_empty() {
+            continue;
+        }
+        let id = cell(row, mapping.conversation).to_string();
+        let idx = *by_id.entry(id.clone()).or_insert_with(|| {
+            sessions.push(RawSession {
+                title: (!id.is_empty()).then(|| id.clone()),
+                ..Default::default()
+            });
+            sessions.len() - 1
+        });
+        let time = cell(row, mapping.timestamp);
+        sessions[idx].messages.push(RawMsg {
+            role: cell(row, mapping.role).to_lowercase(),
+            content: content.to_string(),
+            timestamp: (!time.is_empty())
+                .then(|| timestamp_ms(&serde_json::Value::String(time.to_string())))
+                .flatten(),
+            ..Default::default()
+        });
+    }
+    sessions
+}
+
+fn column_combo(ui: &mut egui::Ui, label: &str, headers: &[String], value: &mut Option<usize>, optional: bool) {
+    ui.label(label);
+    let name = |v: Option<usize>| {
+        v.and_then(|i| headers.get(i))
+            .map_or("(none)".to_string(), |h| h.clone())
+    };
+    egui::ComboBox::from_id_source(("csv_column", label))
+        .selected_text(name(*value))
+        .show_ui(ui, |ui| {
+            if optional {
+                ui.selectable_value(value, None, "(none)");
+            }
+            for (i, h) in headers.iter().enumerate() {
+                ui.selectable_value(value, Some(i), h);
+            }
+        });
+    ui.end_row();
+}
+
+impl AppState {
+    // Reads the table and shows the mapping dialog; the conversation is loaded from there.
+    pub(crate) fn open_table_file(&mut self, path: &Path) {
+        match read_table(path) {
+            Ok((headers, rows)) => {
+                self.csv.mapping = guess_mapping(&headers);
+                self.csv.headers = headers;
+                self.csv.rows = rows;
+                self.csv.path = Some(path.to_path_buf());
+                self.csv.status = None;
+                self.csv.show_window = true;
+            }
+            Err(e) => self.errors.push(format!("Failed to read table: {e}")),
+        }
+    }
+
+    pub(crate) fn csv_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.csv.show_window;
+        let mut import = false;
+        let window = self.tool_window(CSV_WINDOW).default_width(560.0).open(&mut open);
+        let fmt = self.fmt;
+        let ui_state = &mut self.csv;
+        let resp = window.show(ctx, |ui| {
+            let file = ui_state
+                .path
+                .as_ref()
+                .map_or(String::new(), |p| p.display().to_string());
+            ui.label(RichText::new(file).monospace().small());
+            ui.label(RichText::new(format!("{} rows", fmt.count(ui_state.rows.len()))).weak());
+            ui.separator();
+
+            egui::Grid::new("csv_mapping").num_columns(2).show(ui, |ui| {
+                let m = &mut ui_state.mapping;
+                column_combo(ui, "Role", &ui_state.headers, &mut m.role, false);
+                column_combo(ui, "Content", &ui_state.headers, &mut m.content, false);
+                column_combo(ui, "Timestamp", &ui_state.headers, &mut m.timestamp, true);
+                column_combo(ui, "Conversation id", &ui_state.headers, &mut m.conversation, true);
+            });
+
+            ui.separator();
+            ScrollArea::both().max_height(160.0).show(ui, |ui| {
+                egui::Grid::new("csv_preview").striped(true).show(ui, |ui| {
+                    for h in &ui_state.headers {
+                        ui.label(RichText::new(h).strong());
+                    }
+                    ui.end_row();
+                    for row in ui_state.rows.iter().take(PREVIEW_ROWS) {
+                        for c in row {
+                            let short: String = c.chars().take(40).collect();
+                            ui.label(RichText::new(short).small()).on_hover_text(c);
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                let ready = ui_state.mapping.role.is_some() && ui_state.mapping.content.is_some();
+                if ui.add_enabled(ready, egui::Button::new("Import")).clicked() {
+                    import = true;
+                }
+                if !ready {
+                    ui.label(RichText::new("Pick the role and content columns").weak());
+                }
+            });
+            if let Some(msg) = &ui_state.status {
+                ui.colored_label(Color32::from_rgb(183, 28, 28), msg);
+            }
+        });
+        if let Some(r) = resp {
+            self.remember_window(CSV_WINDOW, r.response.rect);
+        }
+
+        if import {
+            let sessions = build_sessions(&self.csv.rows, self.csv.mapping);
+            if sessions.is_empty() {
+                self.csv.status = Some("No rows with content in the selected column".to_string());
+            } else {
+                let mut loaded = normalize_sessions(sessions);
+                loaded.format = Some("CSV / TSV");
+                loaded.file_name = self
+                    .csv
+                    .path
+                    .as_deref()
+                    .and_then(|p| p.file_name())
+                    .map(|s| s.to_string_lossy().to_string());
+                self.set_loaded(loaded);
+                self.read_only = self.read_only_reason(self.csv.path.as_deref());
+                open = false;
+            }
+        }
+        self.csv.show_window = open;
+        if !open {
+            self.csv.rows.clear();
+        }
+    }
+}
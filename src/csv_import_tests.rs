@@ -0,0 +1,18 @@
+// `csv_import`: guessing the column mapping and splitting rows into conversations.
+
+use crate::csv_import::{build_sessions, guess_mapping, read_table};
+use std::path::Path;
+
+#[test]
+fn chat_spreadsheets_split_by_conversation() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/formats/chats.csv");
+    let (headers, rows) = read_table(&path).unwrap();
+    let sessions = build_sessions(&rows, guess_mapping(&headers));
+    let summary: Vec<_> = sessions.iter().map(|s| (s.title.as_deref(), s.messages.len())).collect();
+    // The last row has no content
+    assert_eq!(summary, [(Some("a"), 2), (Some("b"), 1)]);
+    let first = &sessions[0].messages;
+    assert_eq!((first[0].role.as_str(), first[0].content.as_str()), ("user", "Hi, there"));
+    assert_eq!(first[1].content, "Hello!\nHow can I help?");
+    assert!(first[0].timestamp.is_some());
+}
@@ -0,0 +1,75 @@
+// How messages are laid out, chat bubbles or a document with the speaker as a small heading, and
+// message chrome that can be hidden for a clean reading view: avatars, role and speaker badges, and
+// the Copy bars under messages. The chrome choices also apply to Copy as image and the HTML export.
+
+use crate::{save_settings, AppState};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum MessageLayout {
+    #[default]
+    Bubbles,
+    // Role heading, full-width text and a rule between messages; avatars are not drawn
+    Document,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub(crate) struct DisplayOptions {
+    pub layout: MessageLayout,
+    pub avatars: bool,
+    pub role_badges: bool,
+    pub copy_bars: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            layout: MessageLayout::Bubbles,
+            avatars: true,
+            role_badges: true,
+            copy_bars: true,
+        }
+    }
+}
+
+impl AppState {
+    pub(crate) fn display_menu(&mut self, ui: &mut egui::Ui) {
+        let display = &mut self.settings.display;
+        let mut changed = false;
+        changed |= ui
+            .radio_value(&mut display.layout, MessageLayout::Bubbles, "Chat bubbles")
+            .changed();
+        changed |= ui
+            .radio_value(&mut display.layout, MessageLayout::Document, "Document")
+            .on_hover_text("Full-width text under a small role heading, for reading long answers")
+            .changed();
+        ui.separator();
+        changed |= ui
+            .add_enabled(
+                display.layout == MessageLayout::Bubbles,
+                egui::Checkbox::new(&mut display.avatars, "Avatars"),
+            )
+            .changed();
+        changed |= ui
+            .checkbox(&mut display.role_badges, "Role badges")
+            .on_hover_text("Speaker names and roles other than User and Assistant")
+            .changed();
+        changed |= ui
+            .checkbox(&mut display.copy_bars, "Copy bars")
+            .on_hover_text("Copy, Copy as image and Copy reference under each message")
+            .changed();
+        ui.separator();
+        ui.label(
+            egui::RichText::new("Also applies to Copy as image and the HTML export")
+                .weak()
+                .small(),
+        );
+        if changed {
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+    }
+}
@@ -0,0 +1,134 @@
+// Dockable layout for the main panes. Panes can be rearranged, tabbed together or dragged
+// out into floating windows; the arrangement is saved with the rest of the layout.
+
+use crate::AppState;
+use eframe::egui;
+use egui_dock::{DockArea, DockState, NodeIndex, Style, TabViewer};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Pane {
+    Conversation,
+    Sessions,
+    ToolOutput,
+    Clipboard,
+    Lint,
+}
+
+impl Pane {
+    // Panes the user can open/close from the "Panes" menu
+    pub const OPTIONAL: [Pane; 4] = [Pane::Sessions, Pane::ToolOutput, Pane::Clipboard, Pane::Lint];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Pane::Conversation => "Conversation",
+            Pane::Sessions => "Sessions",
+            Pane::ToolOutput => "Tool output",
+            Pane::Clipboard => "Clipboard",
+            Pane::Lint => "Lint",
+        }
+    }
+}
+
+pub(crate) fn default_dock() -> DockState<Pane> {
+    DockState::new(vec![Pane::Conversation])
+}
+
+struct Panes<'a> {
+    app: &'a mut AppState,
+    select_session: Option<usize>,
+}
+
+impl TabViewer for Panes<'_> {
+    type Tab = Pane;
+
+    fn title(&mut self, pane: &mut Pane) -> egui::WidgetText {
+        pane.title().into()
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, pane: &mut Pane) {
+        match pane {
+            Pane::Conversation => self.app.conversation_ui(ui),
+            Pane::Sessions => {
+                if let Some(i) = self.app.sessions_ui(ui) {
+                    self.select_session = Some(i);
+                }
+            }
+            Pane::ToolOutput => self.app.tool_output_ui(ui),
+            Pane::Clipboard => self.app.clipboard_ui(ui),
+            Pane::Lint => self.app.lint_ui(ui),
+        }
+    }
+
+    fn on_close(&mut self, _pane: &mut Pane) -> bool {
+        self.app.layout_dirty = true;
+        true
+    }
+
+    // The conversation always stays in the main area so the window is never empty
+    fn closeable(&mut self, pane: &mut Pane) -> bool {
+        *pane != Pane::Conversation
+    }
+
+    fn allowed_in_windows(&self, pane: &mut Pane) -> bool {
+        *pane != Pane::Conversation
+    }
+
+    // Panes bring their own scroll areas
+    fn scroll_bars(&self, _pane: &Pane) -> [bool; 2] {
+        [false, false]
+    }
+}
+
+impl AppState {
+    pub(crate) fn dock_area(&mut self, ui: &mut egui::Ui) {
+        // The viewer needs the whole app, so take the dock state out while drawing
+        let mut dock = std::mem::replace(&mut self.settings.layout.dock, DockState::new(Vec::new()));
+        let mut panes = Panes { app: self, select_session: None };
+        DockArea::new(&mut dock)
+            .style(Style::from_egui(ui.style().as_ref()))
+            .show_add_buttons(false)
+            .show_window_close_buttons(false)
+            .show_inside(ui, &mut panes);
+        let select_session = panes.select_session;
+        self.settings.layout.dock = dock;
+        // Tabs are moved and splits resized by dragging, so a release may have changed the dock
+        if ui.input(|i| i.pointer.any_released()) {
+            self.layout_dirty = true;
+        }
+        if let Some(i) = select_session {
+            self.select_session(i);
+        }
+    }
+
+    pub(crate) fn pane_open(&self, pane: Pane) -> bool {
+        self.settings.layout.dock.find_tab(&pane).is_some()
+    }
+
+    pub(crate) fn open_pane(&mut self, pane: Pane) {
+        if self.pane_open(pane) {
+            return;
+        }
+        self.layout_dirty = true;
+        let tree = self.settings.layout.dock.main_surface_mut();
+        match pane {
+            Pane::Sessions => {
+                tree.split_left(NodeIndex::root(), 0.22, vec![pane]);
+            }
+            Pane::ToolOutput => {
+                tree.split_below(NodeIndex::root(), 0.7, vec![pane]);
+            }
+            Pane::Clipboard | Pane::Lint => {
+                tree.split_right(NodeIndex::root(), 0.75, vec![pane]);
+            }
+            Pane::Conversation => tree.push_to_first_leaf(pane),
+        }
+    }
+
+    pub(crate) fn close_pane(&mut self, pane: Pane) {
+        if let Some(location) = self.settings.layout.dock.find_tab(&pane) {
+            self.settings.layout.dock.remove_tab(location);
+            self.layout_dirty = true;
+        }
+    }
+}
@@ -0,0 +1,153 @@
+// Logs that JSON-encode message text twice show literal `\n` and `\"` instead of line breaks and
+// quotes. Such conversations are detected on load and can be decoded for display; the original
+// text is kept and restored when decoding is turned off.
+
+use crate::{AppState, Msg};
+use eframe::egui::{self, Color32, Frame, RichText, Rounding};
+
+#[derive(Default)]
+pub(crate) struct Escapes {
+    // Decode every conversation of the current file
+    decode: bool,
+    // The shown conversation looks double-encoded
+    likely: bool,
+    // System prompt and message texts as loaded, while decoded
+    originals: Option<(Option<String>, Vec<String>)>,
+}
+
+// Literal escapes outnumber real line breaks. Code with `"\n"` in string literals has plenty of
+// real line breaks, so it does not trip this.
+fn looks_double_encoded(system: Option<&str>, messages: &[Msg]) -> bool {
+    let texts = || system.into_iter().chain(messages.iter().map(|m| m.content.as_str()));
+    let literal: usize = texts().map(|t| t.matches("\\n").count() + t.matches("\\\"").count()).sum();
+    let real: usize = texts().map(|t| t.matches('\n').count()).sum();
+    literal >= 3 && literal > real
+}
+
+// Undo one level of JSON string escaping. Unknown escapes are left as they are.
+pub(crate) fn unescape(text: &str) -> String {
+    // A whole JSON string literal, quotes included
+    if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') {
+        if let Ok(s) = serde_json::from_str::<String>(text) {
+            return s;
+        }
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('"') => out.push('"'),
+            Some('\\') => out.push('\\'),
+            Some('/') => out.push('/'),
+            Some('u') => match hex4(&mut chars) {
+                // High surrogate: the low half follows as another \uXXXX
+                Some(high @ 0xD800..=0xDBFF) => {
+                    let mut rest = chars.clone();
+                    let low = (rest.next() == Some('\\') && rest.next() == Some('u'))
+                        .then(|| hex4(&mut rest))
+                        .flatten()
+                        .filter(|low| (0xDC00..=0xDFFF).contains(low));
+                    let code = match low {
+                        Some(low) => {
+                            chars = rest;
+                            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                        }
+                        None => high,
+                    };
+                    out.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                Some(code) => out.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)),
+                None => out.push_str("\\u"),
+            },
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+// Four hex digits after `\u`, consumed only if they are all there.
+fn hex4(chars: &mut std::str::Chars) -> Option<u32> {
+    let hex: String = chars.clone().take(4).collect();
+    let code = u32::from_str_radix(&hex, 16).ok().filter(|_| hex.len() == 4)?;
+    chars.nth(3);
+    Some(code)
+}
+
+impl AppState {
+    // Re-check the shown conversation after it changed; called with the texts as loaded.
+    pub(crate) fn reset_escapes(&mut self, new_file: bool) {
+        if new_file {
+            self.escapes.decode = false;
+        }
+        self.escapes.originals = None;
+        self.escapes.likely = looks_double_encoded(self.system.as_deref(), &self.messages);
+        if self.escapes.decode {
+            self.decode_escapes();
+        }
+    }
+
+    fn decode_escapes(&mut self) {
+        if self.escapes.originals.is_some() {
+            return;
+        }
+        let originals = self.messages.iter().map(|m| m.content.clone()).collect();
+        self.escapes.originals = Some((self.system.clone(), originals));
+        self.system = self.system.as_deref().map(unescape);
+        for msg in &mut self.messages {
+            msg.content = unescape(&msg.content);
+        }
+    }
+
+    pub(crate) fn restore_escapes(&mut self) {
+        let Some((system, originals)) = self.escapes.originals.take() else { return };
+        self.system = system;
+        for (msg, original) in self.messages.iter_mut().zip(originals) {
+            msg.content = original;
+        }
+    }
+
+    // Offered above the conversation when the text looks double-encoded.
+    pub(crate) fn escapes_banner(&mut self, ui: &mut egui::Ui) {
+        if !self.escapes.likely && !self.escapes.decode {
+            return;
+        }
+        let accent = if self.theme_dark {
+            Color32::from_rgb(255, 202, 40)
+        } else {
+            Color32::from_rgb(230, 126, 0)
+        };
+        let mut decode = self.escapes.decode;
+        Frame::none()
+            .stroke(egui::Stroke::new(1.0, accent))
+            .rounding(Rounding::same(6.0))
+            .inner_margin(egui::Margin::symmetric(10.0, 6.0))
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width() - 20.0);
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(RichText::new("Text looks JSON-encoded twice (literal \\n and \\\")").color(accent));
+                    ui.checkbox(&mut decode, "Decode escaped content")
+                        .on_hover_text("Applies to every conversation in this file; the original text is kept");
+                });
+            });
+        ui.add_space(8.0);
+        if decode != self.escapes.decode {
+            self.escapes.decode = decode;
+            if decode {
+                self.decode_escapes();
+            } else {
+                self.restore_escapes();
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+// Golden-file tests for the exporters. Every `tests/golden/*.jsonl` conversation is exported in
+// each format and compared with the file next to it (`tool_calls.md`, `tool_calls.html`, …).
+//
+// After an intended change to an export, regenerate the expected files and review the diff:
+//
+//   UPDATE_GOLDEN=1 cargo test golden
+
+use crate::{
+    display::DisplayOptions, html::HtmlPolicy, parse::{load_from_bytes, ParseOptions}, provenance::Provenance, to_html, to_ipynb, to_markdown, to_markdown_flavor, to_outline, to_skeleton_json, MarkdownFlavor,
+    Transcript,
+};
+use std::{fs, path::PathBuf};
+
+type Exporter = fn(&Transcript) -> String;
+
+const EXPORTS: &[(&str, Exporter)] = &[
+    ("md", to_markdown),
+    ("obsidian.md", |t| to_markdown_flavor(t, MarkdownFlavor::Obsidian)),
+    ("notion.md", |t| to_markdown_flavor(t, MarkdownFlavor::Notion)),
+    ("html", to_html),
+    ("ipynb", to_ipynb),
+    ("skeleton.json", to_skeleton_json),
+    ("outline.md", |t| to_outline(t, true)),
+];
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+// First differing line, so a failure points at the change rather than dumping both files.
+fn first_difference(expected: &str, actual: &str) -> String {
+    let (mut expected_lines, mut actual_lines) = (expected.lines(), actual.lines());
+    for line in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => break,
+            (e, a) if e == a => continue,
+            (e, a) => return format!("line {line}:\n  expected: {e:?}\n  actual:   {a:?}"),
+        }
+    }
+    "only trailing whitespace differs".to_string()
+}
+
+#[test]
+fn golden_exports() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(golden_dir())
+        .expect("tests/golden exists")
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "jsonl"))
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "no fixtures in tests/golden");
+
+    let mut failures = Vec::new();
+    for fixture in &fixtures {
+        let loaded = load_from_bytes(&fs::read(fixture).expect("fixture is readable"), ParseOptions::default()).expect("fixture parses");
+        assert!(loaded.errors.is_empty(), "{}: {:?}", fixture.display(), loaded.errors);
+        let file_name = fixture.file_name().and_then(|n| n.to_str());
+        let transcript = Transcript {
+            system: loaded.system.as_deref(),
+            messages: &loaded.messages,
+            file_name,
+            dark: false,
+            html: HtmlPolicy::default(),
+            display: DisplayOptions::default(),
+            provenance: None,
+        };
+        for (ext, export) in EXPORTS {
+            let path = fixture.with_extension(ext);
+            let actual = export(&transcript);
+            if update {
+                fs::write(&path, &actual).expect("golden file is writable");
+                continue;
+            }
+            match fs::read_to_string(&path) {
+                Ok(expected) if expected == actual => {}
+                Ok(expected) => failures.push(format!("{}: {}", path.display(), first_difference(&expected, &actual))),
+                Err(_) => failures.push(format!("{}: missing (run with UPDATE_GOLDEN=1)", path.display())),
+            }
+        }
+    }
+    assert!(failures.is_empty(), "exports changed:\n\n{}", failures.join("\n\n"));
+}
+
+// The footer is added to Markdown and HTML exports, and a Markdown export read back has the same
+// messages as one without it.
+#[test]
+fn provenance_footer() {
+    let fixture = golden_dir().join("tool_calls.jsonl");
+    let loaded = load_from_bytes(&fs::read(fixture).expect("fixture is readable"), ParseOptions::default()).expect("fixture parses");
+    let mut transcript = Transcript {
+        system: loaded.system.as_deref(),
+        messages: &loaded.messages,
+        file_name: Some("tool_calls.jsonl"),
+        dark: false,
+        html: HtmlPolicy::default(),
+        display: DisplayOptions::default(),
+        provenance: None,
+    };
+    let without = to_markdown_flavor(&transcript, MarkdownFlavor::Plain);
+    transcript.provenance = Some(Provenance {
+        file: "tool_calls.jsonl".to_string(),
+        sha256: Some("ab".repeat(32)),
+        exported: "2024-05-01 10:32 UTC".to_string(),
+        version: "0.1.0",
+    });
+    let with = to_markdown_flavor(&transcript, MarkdownFlavor::Plain);
+    let footer = format!(
+        "---\n\n*Exported from `tool_calls.jsonl` · SHA-256 `{}` · 2024-05-01 10:32 UTC · LLM Log Viewer 0.1.0*\n",
+        "ab".repeat(32)
+    );
+    assert_eq!(with, format!("{without}{footer}"));
+    assert!(to_html(&transcript).contains("<footer class=\"provenance\">Exported from <code>tool_calls.jsonl</code>"));
+
+    let contents = |text: &str| -> Vec<String> {
+        let loaded = load_from_bytes(text.as_bytes(), ParseOptions::default()).expect("export parses");
+        loaded.messages.into_iter().map(|m| m.content).collect()
+    };
+    assert_eq!(contents(&with), contents(&without));
+}
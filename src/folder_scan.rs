@@ -0,0 +1,543 @@
+// "Open folder…" looks for logs in subfolders too, a few levels down, leaving out what a project
+// keeps out of version control: paths matched by `.gitignore` and `.ignore` files on the way, hidden
+// folders, and the exclude globs in the settings (`node_modules`, `checkpoint*`, …). Opening a
+// project directory would otherwise parse every JSON file of its dependencies and checkpoints.
+// Symbolic links to folders are followed unless turned off, each folder once. The scan and the
+// loading run on a thread, with progress and Cancel in the top bar, and a folder that does not
+// list within DIR_TIMEOUT or a file that does not load within FILE_TIMEOUT (a network mount that
+// went away) is skipped and reported. parse() hooks cannot leave the UI thread; they get the files'
+// text a few files a frame once the thread is done.
+//
+// Patterns follow `.gitignore`: `*` and `?` stay within a path segment and `**` crosses them, a
+// pattern with a `/` is relative to the file it is in (or to the opened folder for the excludes),
+// one without matches a name at any depth, a trailing `/` matches folders only and `!` brings a
+// path back in.
+
+use crate::{
+    incremental::ReadProgress,
+    mapping::FormatMapping,
+    parse::ParseOptions,
+    repaint, save_settings,
+    scripting::{load_mapped, read_text, with_file_name},
+    AppState, Loaded,
+};
+use anyhow::Result;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+// Levels of subfolders below the opened folder that are looked in
+const MAX_DEPTH: usize = 4;
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+// Files picked up
+pub(crate) const FOLDER_EXTENSIONS: &[&str] = &[
+    "json", "jsonl", "yaml", "yml", "md", "txt", "gz", "zst", "vscdb", "parquet",
+];
+// A folder that takes longer to list is on a mount that is not answering, and is skipped
+const DIR_TIMEOUT: Duration = Duration::from_secs(5);
+// Likewise a file that takes longer to load
+const FILE_TIMEOUT: Duration = Duration::from_secs(30);
+// Time each frame spends running parse() hooks over the files of a folder
+const PARSE_PER_FRAME: Duration = Duration::from_millis(30);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub(crate) struct FolderSettings {
+    // `.gitignore` syntax, relative to the opened folder
+    pub excludes: Vec<String>,
+    // Honor `.gitignore` and `.ignore` files
+    pub ignore_files: bool,
+    // Look in folders that symbolic links point to
+    pub follow_symlinks: bool,
+}
+
+impl Default for FolderSettings {
+    fn default() -> Self {
+        Self {
+            excludes: [
+                "node_modules/",
+                "__pycache__/",
+                "venv/",
+                "target/",
+                "checkpoint*/",
+                "wandb/",
+            ]
+            .map(String::from)
+            .to_vec(),
+            ignore_files: true,
+            follow_symlinks: true,
+        }
+    }
+}
+
+struct Rule {
+    // Folder the pattern is relative to
+    base: PathBuf,
+    glob: Vec<char>,
+    anchored: bool,
+    dir_only: bool,
+    negate: bool,
+}
+
+// One line of an ignore file; blank lines and comments give `None`.
+fn rule(line: &str, base: &Path) -> Option<Rule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negate, line) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+    let (dir_only, line) = match line.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let anchored = line.contains('/');
+    let line = line.strip_prefix('/').unwrap_or(line);
+    if line.is_empty() {
+        return None;
+    }
+    Some(Rule {
+        base: base.to_path_buf(),
+        glob: line.chars().collect(),
+        anchored,
+        dir_only,
+        negate,
+    })
+}
+
+pub(crate) fn glob_match(glob: &[char], text: &[char]) -> bool {
+    match glob {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            let rest = rest.strip_prefix(&['/']).unwrap_or(rest);
+            // A trailing `**` takes everything inside
+            rest.is_empty() || (0..=text.len()).any(|i| (i == 0 || text[i - 1] == '/') && glob_match(rest, &text[i..]))
+        }
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| glob_match(rest, &text[i..])),
+        ['?', rest @ ..] => text.first().is_some_and(|c| *c != '/') && glob_match(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
+}
+
+impl Rule {
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let Ok(relative) = path.strip_prefix(&self.base) else {
+            return false;
+        };
+        let text: Vec<char> = if self.anchored {
+            relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+                .chars()
+                .collect()
+        } else {
+            relative
+                .file_name()
+                .map(|n| n.to_string_lossy().chars().collect())
+                .unwrap_or_default()
+        };
+        glob_match(&self.glob, &text)
+    }
+}
+
+// The last rule that matches decides, as in git.
+fn ignored(rules: &[Rule], path: &Path, is_dir: bool) -> bool {
+    rules
+        .iter()
+        .rev()
+        .find(|r| r.matches(path, is_dir))
+        .is_some_and(|r| !r.negate)
+}
+
+// What is in a folder, read in one go so it can be given up on.
+struct Listing {
+    // Where the folder really is, through any symbolic links
+    canonical: PathBuf,
+    // By path, links last so that a folder is reached by its own path before any link to it
+    entries: Vec<Entry>,
+    // Contents of its ignore files
+    ignore_files: Vec<String>,
+}
+
+struct Entry {
+    path: PathBuf,
+    // Of what a symbolic link points to
+    is_dir: bool,
+    symlink: bool,
+}
+
+fn read_listing(dir: &Path, ignore_files: bool) -> io::Result<Listing> {
+    let canonical = fs::canonicalize(dir)?;
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)?.filter_map(|e| e.ok()) {
+        let Ok(file_type) = entry.file_type() else { continue };
+        let path = entry.path();
+        let symlink = file_type.is_symlink();
+        let is_dir = if symlink {
+            // A broken link is left out
+            let Ok(target) = fs::metadata(&path) else { continue };
+            target.is_dir()
+        } else {
+            file_type.is_dir()
+        };
+        entries.push(Entry { path, is_dir, symlink });
+    }
+    entries.sort_by(|a, b| (a.symlink, &a.path).cmp(&(b.symlink, &b.path)));
+    let ignore_files = if ignore_files {
+        IGNORE_FILES
+            .iter()
+            .filter_map(|name| fs::read_to_string(dir.join(name)).ok())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Ok(Listing {
+        canonical,
+        entries,
+        ignore_files,
+    })
+}
+
+// `read` on a thread of its own, so that a mount that stopped answering is given up on after
+// `limit` instead of holding up the scan. The thread is left to finish or hang.
+fn timed<T: Send + 'static>(limit: Duration, read: impl FnOnce() -> T + Send + 'static) -> Result<T, String> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(read());
+    });
+    match rx.recv_timeout(limit) {
+        Ok(value) => Ok(value),
+        Err(mpsc::RecvTimeoutError::Timeout) => Err(format!("no answer within {} s", limit.as_secs())),
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err("the reading thread stopped".to_string()),
+    }
+}
+
+fn list(dir: &Path, ignore_files: bool) -> Result<Listing, String> {
+    let path = dir.to_path_buf();
+    timed(DIR_TIMEOUT, move || read_listing(&path, ignore_files))?.map_err(|e| e.to_string())
+}
+
+// Counts shown while a folder is opened, and the Cancel button's flag
+#[derive(Default)]
+pub(crate) struct ScanProgress {
+    // Cancelling also stops reading the file being loaded
+    read: ReadProgress,
+    found: AtomicUsize,
+    // Once every folder has been looked in
+    scanned: AtomicBool,
+    loaded: AtomicUsize,
+}
+
+#[derive(Default)]
+pub(crate) struct Scanned {
+    // Log files in path order
+    pub files: Vec<PathBuf>,
+    // Folders that could not be read, with why
+    pub skipped: Vec<String>,
+}
+
+struct Scan<'a> {
+    settings: &'a FolderSettings,
+    extensions: &'a [&'a str],
+    progress: &'a ScanProgress,
+    rules: Vec<Rule>,
+    // Folders looked in, by where they really are; a link back up is not followed round
+    visited: HashSet<PathBuf>,
+    out: Scanned,
+}
+
+impl Scan<'_> {
+    fn walk(&mut self, dir: &Path, depth: usize) {
+        if self.progress.read.is_cancelled() {
+            return;
+        }
+        let listing = match list(dir, self.settings.ignore_files) {
+            Ok(listing) => listing,
+            Err(e) => {
+                self.out.skipped.push(format!("{}: {e}", dir.display()));
+                return;
+            }
+        };
+        if !self.visited.insert(listing.canonical) {
+            return;
+        }
+        let kept = self.rules.len();
+        for text in &listing.ignore_files {
+            self.rules.extend(text.lines().filter_map(|line| rule(line, dir)));
+        }
+        for entry in listing.entries {
+            if ignored(&self.rules, &entry.path, entry.is_dir) {
+                continue;
+            }
+            if entry.is_dir {
+                let hidden = entry
+                    .path
+                    .file_name()
+                    .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+                let followed = !entry.symlink || self.settings.follow_symlinks;
+                if !hidden && followed && depth < MAX_DEPTH {
+                    self.walk(&entry.path, depth + 1);
+                }
+            } else if entry
+                .path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| self.extensions.contains(&e.to_lowercase().as_str()))
+            {
+                self.progress.found.fetch_add(1, Ordering::Relaxed);
+                self.out.files.push(entry.path);
+            }
+        }
+        self.rules.truncate(kept);
+    }
+}
+
+// Log files in `root` and its subfolders that are not excluded. Fails only if `root` itself
+// cannot be read; subfolders that cannot be are skipped.
+pub(crate) fn scan(
+    root: &Path,
+    settings: &FolderSettings,
+    extensions: &[&str],
+    progress: &ScanProgress,
+) -> Result<Scanned, String> {
+    let mut scan = Scan {
+        settings,
+        extensions,
+        progress,
+        rules: settings.excludes.iter().filter_map(|line| rule(line, root)).collect(),
+        visited: HashSet::new(),
+        out: Scanned::default(),
+    };
+    scan.walk(root, 0);
+    scan.out.files.sort();
+    match scan.out.skipped.first() {
+        Some(e) if scan.visited.is_empty() => Err(e.clone()),
+        _ => Ok(scan.out),
+    }
+}
+
+// A file found in a folder, loaded without the parse() hooks. When there are hooks, its text is
+// kept for them to have the first look, on the UI thread.
+struct FolderFile {
+    path: PathBuf,
+    loaded: Result<Loaded>,
+    text: Option<String>,
+}
+
+struct FolderContents {
+    files: Vec<FolderFile>,
+    skipped: Vec<String>,
+}
+
+// Files of a loaded folder going through the parse() hooks, a few each frame
+struct Parsing {
+    pending: std::vec::IntoIter<FolderFile>,
+    done: Vec<(PathBuf, Result<Loaded>)>,
+    skipped: Vec<String>,
+}
+
+// A folder being scanned and loaded on a thread; dropping it cancels.
+pub(crate) struct FolderLoad {
+    rx: mpsc::Receiver<Result<FolderContents, String>>,
+    progress: Arc<ScanProgress>,
+    dir: PathBuf,
+    // Once the thread is done
+    parsing: Option<Parsing>,
+}
+
+impl Drop for FolderLoad {
+    fn drop(&mut self) {
+        self.progress.read.cancel();
+    }
+}
+
+fn load_folder(
+    dir: &Path,
+    settings: &FolderSettings,
+    mappings: Arc<Vec<FormatMapping>>,
+    // Whether to keep the text of files for parse() hooks
+    keep_text: bool,
+    options: ParseOptions,
+    progress: &Arc<ScanProgress>,
+) -> Result<FolderContents, String> {
+    let mut scanned = scan(dir, settings, FOLDER_EXTENSIONS, progress)?;
+    progress.scanned.store(true, Ordering::Relaxed);
+    let mut files = Vec::new();
+    for path in scanned.files {
+        if progress.read.is_cancelled() {
+            return Err("cancelled".to_string());
+        }
+        let (file, mappings, file_progress) = (path.clone(), mappings.clone(), progress.clone());
+        let read = timed(FILE_TIMEOUT, move || {
+            let text = if keep_text { read_text(&file).ok().flatten() } else { None };
+            (load_mapped(&mappings, &file, Some(&file_progress.read), options), text)
+        });
+        progress.loaded.fetch_add(1, Ordering::Relaxed);
+        match read {
+            Ok((loaded, text)) => files.push(FolderFile { path, loaded, text }),
+            Err(e) => scanned.skipped.push(format!("{}: {e}", path.display())),
+        }
+    }
+    Ok(FolderContents {
+        files,
+        skipped: scanned.skipped,
+    })
+}
+
+impl AppState {
+    // Every log file in `dir` and its subfolders becomes an entry in the Sessions pane (see
+    // `show_folder`). The conversation shown stays until the folder is loaded.
+    pub(crate) fn open_folder(&mut self, dir: &Path, ctx: &egui::Context) {
+        let (tx, rx) = mpsc::channel();
+        let progress = Arc::new(ScanProgress::default());
+        self.folder = Some(FolderLoad {
+            rx,
+            progress: progress.clone(),
+            dir: dir.to_path_buf(),
+            parsing: None,
+        });
+        let settings = self.settings.folders.clone();
+        let mappings = Arc::new(self.settings.format_mappings.clone());
+        let keep_text = self.scripts.has_parse();
+        let options = self.parse_options();
+        let dir = dir.to_path_buf();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let contents = load_folder(&dir, &settings, mappings, keep_text, options, &progress);
+            // Fails if the load was cancelled or replaced in the meantime
+            let _ = tx.send(contents);
+            ctx.request_repaint();
+        });
+    }
+
+    // Show the folder once it is loaded and through the parse() hooks; called every frame.
+    pub(crate) fn poll_folder(&mut self, ctx: &egui::Context) {
+        let Some(load) = &mut self.folder else { return };
+        if load.parsing.is_none() {
+            let result = match load.rx.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => Err("the scanning thread stopped".to_string()),
+            };
+            match result {
+                Ok(contents) => {
+                    load.parsing = Some(Parsing {
+                        pending: contents.files.into_iter(),
+                        done: Vec::new(),
+                        skipped: contents.skipped,
+                    })
+                }
+                Err(e) => {
+                    let dir = load.dir.clone();
+                    self.folder = None;
+                    self.errors.push(format!("Failed to read {}: {e}", dir.display()));
+                    return;
+                }
+            }
+        }
+        let started = Instant::now();
+        while let Some(parsing) = self.folder.as_mut().and_then(|load| load.parsing.as_mut()) {
+            if started.elapsed() > PARSE_PER_FRAME && !parsing.pending.as_slice().is_empty() {
+                // The rest next frame, so that the window and Cancel keep answering
+                ctx.request_repaint();
+                return;
+            }
+            let Some(file) = parsing.pending.next() else { break };
+            let loaded = match file.text.and_then(|text| self.scripts.parse(&text)) {
+                Some(parsed) => parsed.and_then(|loaded| with_file_name(loaded, &file.path)),
+                None => file.loaded,
+            };
+            parsing.done.push((file.path, loaded));
+        }
+        let Some(mut load) = self.folder.take() else { return };
+        let Some(parsing) = load.parsing.take() else { return };
+        let dir = load.dir.clone();
+        let skipped = parsing.skipped.iter().map(|e| format!("Skipped {e}")).collect();
+        self.show_folder(&dir, parsing.done, skipped);
+    }
+
+    // Progress in the top bar while a folder is opened.
+    pub(crate) fn folder_indicator(&mut self, ui: &mut egui::Ui) {
+        let Some(load) = &self.folder else { return };
+        let progress = &load.progress;
+        let found = progress.found.load(Ordering::Relaxed);
+        repaint::busy(ui);
+        let bar = if let Some(parsing) = &load.parsing {
+            let parsed = parsing.done.len();
+            let total = parsed + parsing.pending.len();
+            egui::ProgressBar::new(parsed as f32 / total.max(1) as f32).text(format!(
+                "Running parse() on {} of {}",
+                self.fmt.count(parsed),
+                self.fmt.count(total)
+            ))
+        } else if progress.scanned.load(Ordering::Relaxed) {
+            let loaded = progress.loaded.load(Ordering::Relaxed);
+            egui::ProgressBar::new(loaded as f32 / found.max(1) as f32).text(format!(
+                "Loading {} of {}",
+                self.fmt.count(loaded),
+                self.fmt.count(found)
+            ))
+        } else {
+            // How many folders there are is not known until the end
+            egui::ProgressBar::new(0.0).text(format!("Scanning: {} files", self.fmt.count(found)))
+        };
+        ui.add(bar.desired_width(200.0));
+        if ui
+            .small_button("Cancel")
+            .on_hover_text("Stop opening the folder and keep showing the current conversation")
+            .clicked()
+        {
+            self.folder = None;
+        }
+    }
+
+    // Right-click menu of "Open folder…": the exclude globs, one per line.
+    pub(crate) fn folder_excludes_ui(&mut self, ui: &mut egui::Ui) {
+        let folders = &mut self.settings.folders;
+        let mut changed = ui
+            .checkbox(&mut folders.ignore_files, "Honor .gitignore files")
+            .on_hover_text("Leave out what .gitignore and .ignore files in the folder and its subfolders ignore")
+            .changed();
+        changed |= ui
+            .checkbox(&mut folders.follow_symlinks, "Follow symbolic links")
+            .on_hover_text("Look in folders that subfolders link to")
+            .changed();
+        ui.label("Excluded paths, one pattern per line:");
+        let mut text = folders.excludes.join("\n");
+        if ui
+            .add(egui::TextEdit::multiline(&mut text).code_editor().desired_rows(6))
+            .changed()
+        {
+            folders.excludes = text.split('\n').map(String::from).collect();
+            changed = true;
+        }
+        if ui.button("Reset to defaults").clicked() {
+            *folders = FolderSettings::default();
+            changed = true;
+        }
+        if changed {
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+// Opening folders: the glob subset of `.gitignore`, and which files a scan picks up.
+
+use crate::folder_scan::{glob_match, scan, FolderSettings, ScanProgress};
+use std::fs;
+use std::path::Path;
+
+fn matches(glob: &str, path: &str) -> bool {
+    glob_match(&glob.chars().collect::<Vec<_>>(), &path.chars().collect::<Vec<_>>())
+}
+
+#[test]
+fn globs_match_like_gitignore() {
+    assert!(matches("checkpoint*", "checkpoint-500"));
+    assert!(!matches("*.json", "runs/a.json"));
+    assert!(matches("runs/*.json", "runs/a.json"));
+    assert!(matches("**/logs", "logs"));
+    assert!(matches("**/logs", "a/b/logs"));
+    assert!(!matches("**/logs", "a/blogs"));
+    assert!(matches("data/**", "data/x/y.jsonl"));
+    assert!(matches("a/**/b", "a/b"));
+    assert!(matches("a/**/b", "a/x/y/b"));
+    assert!(matches("run-?.log", "run-1.log"));
+    assert!(!matches("run-?.log", "run-/.log"));
+}
+
+// Files found under `root`, relative to it
+fn found(root: &Path, settings: &FolderSettings) -> Vec<String> {
+    scan(root, settings, &["json", "jsonl"], &ScanProgress::default())
+        .unwrap()
+        .files
+        .iter()
+        .map(|p| p.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+        .collect()
+}
+
+#[test]
+fn folders_skip_ignored_paths() {
+    let root = std::env::temp_dir().join(format!("llm_log_viewer_scan_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for dir in ["runs/day1", "node_modules/pkg", "checkpoint-500", ".git", "out"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    let files = [
+        "chat.jsonl",
+        "runs/day1/agent.json",
+        "node_modules/pkg/package.json",
+        "checkpoint-500/trainer_state.json",
+        ".git/config.json",
+        "out/big.jsonl",
+        "out/keep.jsonl",
+        "notes.rs",
+    ];
+    for file in files {
+        fs::write(root.join(file), "{}").unwrap();
+    }
+    fs::write(root.join(".gitignore"), "# build output\nout/*\n!out/keep.jsonl\n").unwrap();
+
+    let relative = |settings: &FolderSettings| found(&root, settings);
+    let settings = FolderSettings::default();
+    assert_eq!(
+        relative(&settings),
+        ["chat.jsonl", "out/keep.jsonl", "runs/day1/agent.json"]
+    );
+    let settings = FolderSettings {
+        excludes: vec!["/runs".to_string()],
+        ignore_files: false,
+        ..Default::default()
+    };
+    assert_eq!(
+        relative(&settings),
+        [
+            "chat.jsonl",
+            "checkpoint-500/trainer_state.json",
+            "node_modules/pkg/package.json",
+            "out/big.jsonl",
+            "out/keep.jsonl"
+        ]
+    );
+    assert!(scan(&root.join("missing"), &settings, &["json"], &ScanProgress::default()).is_err());
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn linked_folders_are_followed_once() {
+    use std::os::unix::fs::symlink;
+    let root = std::env::temp_dir().join(format!("llm_log_viewer_links_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("runs/day1")).unwrap();
+    fs::write(root.join("runs/day1/agent.json"), "{}").unwrap();
+    // A second way into `runs`, and a link back up that would go round forever
+    symlink(root.join("runs"), root.join("a_runs")).unwrap();
+    symlink(&root, root.join("runs/day1/up")).unwrap();
+
+    assert_eq!(found(&root, &FolderSettings::default()), ["runs/day1/agent.json"]);
+    let settings = FolderSettings {
+        follow_symlinks: false,
+        ..Default::default()
+    };
+    assert_eq!(found(&root, &settings), ["runs/day1/agent.json"]);
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,84 @@
+// Any message can be folded to its first sentence with the chevron under its avatar (beside the
+// speaker in the document layout), and "Collapse all" / "Expand all" in the View menu fold or
+// unfold every message, so that a long agent transcript can be skimmed and only the turns of
+// interest opened. Tool cards fold on their own and are left alone.
+
+use crate::AppState;
+use eframe::egui::{self, collapsing_header::paint_default_icon, Sense};
+use std::collections::HashSet;
+
+// Off-screen folded rows reserve this much; a preview line in a bubble, or the avatar and chevron
+pub(crate) const FOLDED_ROW_HEIGHT: f32 = 48.0;
+const CHEVRON_SIZE: f32 = 16.0;
+
+#[derive(Default)]
+pub(crate) struct Folds {
+    // Set by "Collapse all"
+    all: bool,
+    // Messages folded or unfolded against `all`
+    toggled: HashSet<usize>,
+}
+
+impl Folds {
+    pub(crate) fn is_folded(&self, index: usize) -> bool {
+        self.all != self.toggled.contains(&index)
+    }
+
+    pub(crate) fn set(&mut self, index: usize, folded: bool) {
+        if folded == self.all {
+            self.toggled.remove(&index);
+        } else {
+            self.toggled.insert(index);
+        }
+    }
+
+    pub(crate) fn set_all(&mut self, folded: bool) {
+        self.all = folded;
+        self.toggled.clear();
+    }
+
+    pub(crate) fn any_folded(&self) -> bool {
+        self.all || !self.toggled.is_empty()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.set_all(false);
+    }
+}
+
+// The chevron that folds a message: pointing right while folded, down while open.
+pub(crate) fn chevron(ui: &mut egui::Ui, folded: &mut bool) {
+    let (rect, resp) = ui.allocate_exact_size(egui::vec2(CHEVRON_SIZE, CHEVRON_SIZE), Sense::click());
+    let resp = resp.on_hover_text(if *folded {
+        "Unfold this message"
+    } else {
+        "Fold this message to one line"
+    });
+    if resp.clicked() {
+        *folded = !*folded;
+    }
+    if ui.is_rect_visible(rect) {
+        paint_default_icon(ui, if *folded { 0.0 } else { 1.0 }, &resp);
+    }
+}
+
+impl AppState {
+    // "Collapse all" and "Expand all" in the View menu.
+    pub(crate) fn folds_menu(&mut self, ui: &mut egui::Ui) {
+        if ui
+            .button("Collapse all")
+            .on_hover_text("Fold every message to a one-line preview")
+            .clicked()
+        {
+            self.folds.set_all(true);
+            ui.close_menu();
+        }
+        if ui
+            .add_enabled(self.folds.any_folded(), egui::Button::new("Expand all"))
+            .clicked()
+        {
+            self.folds.set_all(false);
+            ui.close_menu();
+        }
+    }
+}
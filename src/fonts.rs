@@ -0,0 +1,311 @@
+// Fonts for text and code, picked from the installed fonts or from a font file. egui's built-in
+// fonts are plain and have no real code face, which makes dense transcripts hard to read. A chosen
+// font goes first in its family; the built-in fonts stay behind it for emoji and missing glyphs.
+//
+// Line and paragraph spacing of message text are set here too. egui has no line height setting
+// that reaches the Markdown renderer, so messages use copies of the two families whose glyphs are
+// scaled down: drawn at a larger size, they keep their size and get taller rows.
+
+use crate::{save_settings, AppState};
+use ab_glyph::{Font, FontRef};
+use anyhow::{anyhow, bail, Result};
+use eframe::egui::{self, FontData, FontDefinitions, FontFamily, FontId, Id, RichText, ScrollArea};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const FONT_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc"];
+// Subfolders of a font directory searched (Linux keeps fonts a few levels down)
+const MAX_DEPTH: usize = 4;
+// Families for message text and code with the line spacing applied
+const TEXT_FAMILY: &str = "message-text";
+const CODE_FAMILY: &str = "message-code";
+const SPACING_ID: &str = "text_spacing";
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub(crate) struct FontSettings {
+    // Message text and the rest of the interface; None for the built-in font
+    pub proportional: Option<PathBuf>,
+    // Code blocks and inline code
+    pub monospace: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub(crate) struct TextSpacing {
+    // Line height of message text and of code, as a multiple of the font's own
+    pub line: f32,
+    pub code_line: f32,
+    // Points between paragraphs, list items, headings and code blocks in a message
+    pub paragraph: f32,
+}
+
+impl Default for TextSpacing {
+    fn default() -> Self {
+        Self {
+            line: 1.0,
+            code_line: 1.0,
+            paragraph: 0.0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct FontsUi {
+    // Installed font files, searched for the first time the menu is opened
+    installed: Option<Vec<PathBuf>>,
+    filter: String,
+    // Which font the list sets
+    code: bool,
+}
+
+fn font_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let mut dirs = Vec::new();
+    if cfg!(windows) {
+        let windir = std::env::var_os("WINDIR").map_or_else(|| PathBuf::from(r"C:\Windows"), PathBuf::from);
+        dirs.push(windir.join("Fonts"));
+        // Fonts installed for the current user only
+        if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(local).join(r"Microsoft\Windows\Fonts"));
+        }
+    } else if cfg!(target_os = "macos") {
+        dirs.extend(["/System/Library/Fonts", "/Library/Fonts"].map(PathBuf::from));
+        dirs.extend(home.map(|h| h.join("Library/Fonts")));
+    } else {
+        dirs.extend(["/usr/share/fonts", "/usr/local/share/fonts"].map(PathBuf::from));
+        if let Some(home) = home {
+            dirs.push(home.join(".local/share/fonts"));
+            dirs.push(home.join(".fonts"));
+        }
+    }
+    dirs
+}
+
+fn find_fonts(dir: &Path, depth: usize, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+        if path.is_dir() {
+            if depth < MAX_DEPTH {
+                find_fonts(&path, depth + 1, out);
+            }
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| FONT_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        {
+            out.push(path);
+        }
+    }
+}
+
+fn installed_fonts() -> Vec<PathBuf> {
+    let mut fonts = Vec::new();
+    for dir in font_dirs() {
+        find_fonts(&dir, 0, &mut fonts);
+    }
+    fonts.sort_by_cached_key(|p| font_name(p).to_lowercase());
+    fonts
+}
+
+// Font files are named after the font and style (`JetBrainsMono-Regular.ttf`), which is close
+// enough to the family name to pick from.
+fn font_name(path: &Path) -> String {
+    path.file_stem()
+        .map_or_else(|| path.display().to_string(), |s| s.to_string_lossy().to_string())
+}
+
+// egui panics on a font it cannot use, so fonts are checked before they are handed over.
+fn read_font(path: &Path) -> Result<Vec<u8>> {
+    let bytes = fs::read(path)?;
+    let units_per_em = FontRef::try_from_slice(&bytes)
+        .map_err(|_| anyhow!("not a TrueType or OpenType font"))?
+        .units_per_em();
+    if !units_per_em.is_some_and(|u| (16.0..=16384.0).contains(&u)) {
+        bail!("unsupported font metrics");
+    }
+    Ok(bytes)
+}
+
+// Copies of `base` for `family` with glyphs scaled by 1 / `line`; the row height stays that of the
+// unscaled font.
+fn add_spaced_family(fonts: &mut FontDefinitions, base: FontFamily, family: &str, line: f32) {
+    let names = fonts.families.get(&base).cloned().unwrap_or_default();
+    let mut spaced = Vec::new();
+    for name in names {
+        let Some(data) = fonts.font_data.get(&name) else {
+            continue;
+        };
+        let mut data = data.clone();
+        data.tweak.scale /= line;
+        let spaced_name = format!("{name} ({family})");
+        fonts.font_data.insert(spaced_name.clone(), data);
+        spaced.push(spaced_name);
+    }
+    fonts.families.insert(FontFamily::Name(family.into()), spaced);
+}
+
+// The message renderer runs deep inside the bubble layout, so the spacing travels in egui's memory.
+pub(crate) fn set_spacing(ctx: &egui::Context, spacing: TextSpacing) {
+    ctx.data_mut(|d| d.insert_temp(Id::new(SPACING_ID), spacing));
+}
+
+pub(crate) fn spacing(ctx: &egui::Context) -> TextSpacing {
+    ctx.data(|d| d.get_temp(Id::new(SPACING_ID))).unwrap_or_default()
+}
+
+// `font` as used in messages: with a line spacing other than 1, the same size in the spaced family.
+pub(crate) fn spaced_font(font: &FontId, spacing: &TextSpacing) -> FontId {
+    let (family, line) = match font.family {
+        FontFamily::Monospace => (CODE_FAMILY, spacing.code_line),
+        _ => (TEXT_FAMILY, spacing.line),
+    };
+    if (line - 1.0).abs() < f32::EPSILON {
+        return font.clone();
+    }
+    FontId::new(font.size * line, FontFamily::Name(family.into()))
+}
+
+impl AppState {
+    // Called at startup and whenever a font is changed. A font that can no longer be read is left
+    // out with an error; the setting is kept in case the file comes back.
+    pub(crate) fn apply_fonts(&mut self, ctx: &egui::Context) {
+        let mut fonts = FontDefinitions::default();
+        let chosen = [
+            (FontFamily::Proportional, &self.settings.fonts.proportional),
+            (FontFamily::Monospace, &self.settings.fonts.monospace),
+        ];
+        for (family, path) in chosen {
+            let Some(path) = path else { continue };
+            match read_font(path) {
+                Ok(bytes) => {
+                    let name = format!("user-{family}");
+                    fonts.font_data.insert(name.clone(), FontData::from_owned(bytes));
+                    fonts.families.entry(family).or_default().insert(0, name);
+                }
+                Err(e) => self.errors.push(format!("Failed to load font {}: {e}", path.display())),
+            }
+        }
+        let spacing = self.settings.spacing;
+        add_spaced_family(&mut fonts, FontFamily::Proportional, TEXT_FAMILY, spacing.line);
+        add_spaced_family(&mut fonts, FontFamily::Monospace, CODE_FAMILY, spacing.code_line);
+        ctx.set_fonts(fonts);
+    }
+
+    fn set_font(&mut self, ctx: &egui::Context, path: Option<PathBuf>) {
+        if let Some(path) = &path {
+            // Checked here so a bad file is refused instead of saved
+            if let Err(e) = read_font(path) {
+                self.errors.push(format!("Failed to load font {}: {e}", path.display()));
+                return;
+            }
+        }
+        if self.fonts.code {
+            self.settings.fonts.monospace = path;
+        } else {
+            self.settings.fonts.proportional = path;
+        }
+        self.apply_fonts(ctx);
+        if let Err(e) = save_settings(&self.settings) {
+            self.errors.push(format!("Failed to save settings: {e}"));
+        }
+    }
+
+    pub(crate) fn fonts_menu(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.fonts.code, false, "Text")
+                .on_hover_text("Message text and the rest of the window");
+            ui.selectable_value(&mut self.fonts.code, true, "Code")
+                .on_hover_text("Code blocks and inline code");
+        });
+        let current = if self.fonts.code {
+            &self.settings.fonts.monospace
+        } else {
+            &self.settings.fonts.proportional
+        };
+        let current = current.clone();
+        ui.label(RichText::new(current.as_deref().map_or("Built-in".to_string(), font_name)).strong());
+        let mut picked = None;
+        if ui.button("Font file…").clicked() {
+            if let Some(path) = rfd::FileDialog::new().add_filter("Font", FONT_EXTENSIONS).pick_file() {
+                picked = Some(Some(path));
+            }
+        }
+        ui.separator();
+        ui.add(
+            egui::TextEdit::singleline(&mut self.fonts.filter)
+                .hint_text("Filter installed fonts")
+                .desired_width(220.0),
+        );
+        let installed = self.fonts.installed.get_or_insert_with(installed_fonts);
+        let filter = self.fonts.filter.to_lowercase();
+        ScrollArea::vertical().max_height(260.0).show(ui, |ui| {
+            if ui.selectable_label(current.is_none(), "Built-in").clicked() {
+                picked = Some(None);
+            }
+            for path in installed.iter() {
+                let name = font_name(path);
+                if !name.to_lowercase().contains(&filter) {
+                    continue;
+                }
+                let selected = current.as_ref() == Some(path);
+                if ui
+                    .selectable_label(selected, name)
+                    .on_hover_text(path.display().to_string())
+                    .clicked()
+                {
+                    picked = Some(Some(path.clone()));
+                }
+            }
+            if installed.is_empty() {
+                ui.label(RichText::new("No installed fonts found").italics().weak());
+            }
+        });
+        if let Some(path) = picked {
+            self.set_font(ui.ctx(), path);
+        }
+    }
+
+    pub(crate) fn spacing_ui(&mut self, ui: &mut egui::Ui) {
+        let spacing = &mut self.settings.spacing;
+        let before = *spacing;
+        let mut save = false;
+        egui::Grid::new("text_spacing").num_columns(2).show(ui, |ui| {
+            let rows = [
+                ("Line spacing", &mut spacing.line, "Message text"),
+                (
+                    "Code line spacing",
+                    &mut spacing.code_line,
+                    "Code blocks and inline code in messages",
+                ),
+            ];
+            for (label, value, hover) in rows {
+                ui.label(label).on_hover_text(hover);
+                let resp = ui.add(egui::Slider::new(value, 1.0..=2.5).fixed_decimals(2));
+                save |= resp.drag_stopped() || (resp.changed() && !resp.dragged());
+                ui.end_row();
+            }
+            ui.label("Paragraph spacing")
+                .on_hover_text("Space between paragraphs, list items, headings and code blocks");
+            let resp = ui.add(egui::Slider::new(&mut spacing.paragraph, 0.0..=24.0).suffix(" pt"));
+            save |= resp.drag_stopped() || (resp.changed() && !resp.dragged());
+            ui.end_row();
+        });
+        if ui.button("Reset").clicked() {
+            *spacing = TextSpacing::default();
+            save = true;
+        }
+        let spacing = self.settings.spacing;
+        if spacing.line != before.line || spacing.code_line != before.code_line {
+            self.apply_fonts(ui.ctx());
+        }
+        if save {
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+    }
+}
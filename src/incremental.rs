@@ -0,0 +1,325 @@
+// Large files are read and parsed on a background thread so the window stays responsive. Plain
+// JSONL logs, gzip- or zstd-compressed or not, are streamed line by line and shown while they load:
+// the first messages appear right away, the rest are appended as they are parsed, and the top bar
+// shows how far through the file the parser is. Only the messages are kept in memory, never the
+// whole file, so logs of hundreds of megabytes stay usable. Other formats
+// are parsed at once on the thread while the current conversation stays on screen, with a progress
+// bar and a Cancel button in the top bar.
+
+use crate::{
+    parse::{compression, decoder, is_plain_jsonl, normalize_into, LineErrors, RawMsg, PLAIN_JSONL},
+    repaint, roles,
+    scripting::load_mapped,
+    AppState, Loaded,
+};
+use anyhow::{anyhow, Result};
+use eframe::egui;
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+// Smaller files parse quickly enough to load at once
+const INCREMENTAL_MIN_BYTES: u64 = 4 << 20;
+// Smaller files of other formats load before the next frame anyway
+const BACKGROUND_MIN_BYTES: u64 = 1 << 20;
+// Enough of the file to tell plain JSONL from the other line-based formats
+const HEAD_BYTES: u64 = 64 * 1024;
+// Messages per update sent to the UI thread
+const BATCH_SIZE: usize = 500;
+
+enum Update {
+    Batch { messages: Vec<RawMsg>, bytes_read: u64 },
+    Done { failed: LineErrors },
+    Failed(String),
+}
+
+pub(crate) struct IncrementalLoad {
+    // Dropping the receiver stops the reader thread at its next batch
+    rx: mpsc::Receiver<Update>,
+    total_bytes: u64,
+    bytes_read: u64,
+}
+
+// How far a loader thread has read, and whether the load was cancelled
+#[derive(Default)]
+pub(crate) struct ReadProgress {
+    bytes_read: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+impl ReadProgress {
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    // `inner`, counted as it is read. Reading fails once the load is cancelled.
+    pub(crate) fn reader<'a, R: Read + 'a>(&'a self, inner: R) -> impl Read + 'a {
+        Counted { inner, progress: self }
+    }
+}
+
+struct Counted<'a, R> {
+    inner: R,
+    progress: &'a ReadProgress,
+}
+
+impl<R: Read> Read for Counted<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.progress.is_cancelled() {
+            return Err(io::Error::other("loading cancelled"));
+        }
+        let n = self.inner.read(buf)?;
+        self.progress.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+// A file parsed at once on a loader thread; nothing of it is shown until it is done.
+pub(crate) struct BackgroundLoad {
+    rx: mpsc::Receiver<Result<Loaded>>,
+    progress: Arc<ReadProgress>,
+    total_bytes: u64,
+    file_name: String,
+}
+
+impl Drop for BackgroundLoad {
+    // Stops the thread at its next read. A file already read is still parsed to the end, but the
+    // result goes nowhere.
+    fn drop(&mut self) {
+        self.progress.cancel();
+    }
+}
+
+// Size of the file if it is plain JSONL, compressed or not, large enough to load incrementally.
+fn incremental_size(path: &Path) -> Option<u64> {
+    let len = fs::metadata(path).ok()?.len();
+    if len < INCREMENTAL_MIN_BYTES {
+        return None;
+    }
+    let mut head = Vec::new();
+    let text = open_text(fs::File::open(path).ok()?).ok()?;
+    text.take(HEAD_BYTES).read_to_end(&mut head).ok()?;
+    // SQLite and Parquet files start with binary magic and are not mistaken for JSONL. Files in
+    // other encodings load at once so they can be decoded; the head may end mid-character.
+    let utf8 = std::str::from_utf8(&head).map_or_else(|e| e.error_len().is_none(), |_| true);
+    (utf8 && is_plain_jsonl(&String::from_utf8_lossy(&head))).then_some(len)
+}
+
+// The text of a file, decompressed while it is read if it is a gzip or zstd stream.
+fn open_text<'a>(file: impl Read + 'a) -> io::Result<Box<dyn Read + 'a>> {
+    let mut reader = BufReader::new(file);
+    match compression(reader.fill_buf()?) {
+        Some(format) => decoder(reader, format),
+        None => Ok(Box::new(reader)),
+    }
+}
+
+fn read_jsonl(path: &Path, tx: &mpsc::Sender<Update>, ctx: &egui::Context) -> Result<Option<LineErrors>> {
+    // Progress is counted in bytes of the file, which are compressed bytes for .gz and .zst logs
+    let progress = ReadProgress::default();
+    let mut reader = BufReader::new(open_text(progress.reader(fs::File::open(path)?))?);
+    let (mut line, mut batch, mut failed) = (Vec::new(), Vec::new(), LineErrors::default());
+    for number in 1.. {
+        line.clear();
+        let n = reader.read_until(b'\n', &mut line)?;
+        if n > 0 {
+            // A byte-order mark only starts the first line
+            match std::str::from_utf8(&line).map(|l| l.trim_start_matches('\u{feff}').trim()) {
+                Ok("") => {}
+                Ok(text) => match serde_json::from_str::<RawMsg>(text) {
+                    Ok(m) => batch.push(m),
+                    Err(e) => failed.push(number, e, text),
+                },
+                Err(e) => failed.push(number, e, &String::from_utf8_lossy(&line)),
+            }
+        }
+        if batch.len() >= BATCH_SIZE || (n == 0 && !batch.is_empty()) {
+            let messages = std::mem::take(&mut batch);
+            let bytes_read = progress.bytes_read.load(Ordering::Relaxed);
+            if tx.send(Update::Batch { messages, bytes_read }).is_err() {
+                // Stopped from the UI
+                return Ok(None);
+            }
+            ctx.request_repaint();
+        }
+        if n == 0 {
+            break;
+        }
+    }
+    Ok(Some(failed))
+}
+
+impl AppState {
+    // Open a log file picked or dropped by the user. Large plain JSONL files load incrementally
+    // (except in strict mode, which must fail before showing anything) and other large files in the
+    // background. Small files, and anything a script wants to parse, load at once: scripts run on
+    // the UI thread.
+    pub(crate) fn open_log_file(&mut self, path: &Path, ctx: &egui::Context) -> Result<()> {
+        let len = fs::metadata(path).map_or(0, |m| m.len());
+        if self.scripts.has_parse() || len < BACKGROUND_MIN_BYTES {
+            let loaded = self.load_path(path)?;
+            self.set_loaded(loaded);
+        } else if let Some(total_bytes) = incremental_size(path).filter(|_| !self.parse_options().strict) {
+            self.start_incremental(path.to_path_buf(), total_bytes, ctx);
+        } else {
+            self.start_background(path.to_path_buf(), len, ctx);
+        }
+        Ok(())
+    }
+
+    fn start_background(&mut self, path: PathBuf, total_bytes: u64, ctx: &egui::Context) {
+        let (tx, rx) = mpsc::channel();
+        let progress = Arc::new(ReadProgress::default());
+        self.background = Some(BackgroundLoad {
+            rx,
+            progress: progress.clone(),
+            total_bytes,
+            file_name: path
+                .file_name()
+                .map_or_else(|| path.display().to_string(), |s| s.to_string_lossy().to_string()),
+        });
+        let mappings = self.settings.format_mappings.clone();
+        let options = self.parse_options();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let loaded = load_mapped(&mappings, &path, Some(&progress), options);
+            // Fails if the load was cancelled or replaced in the meantime
+            let _ = tx.send(loaded);
+            ctx.request_repaint();
+        });
+    }
+
+    // Show a file loaded in the background once it is ready; called every frame.
+    pub(crate) fn poll_background(&mut self) {
+        let Some(load) = &self.background else { return };
+        let result = match load.rx.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => Err(anyhow!("the loader thread stopped")),
+        };
+        self.background = None;
+        match result {
+            Ok(loaded) => self.set_loaded(loaded),
+            Err(e) => self.errors.push(format!("Failed to load: {e}")),
+        }
+    }
+
+    fn start_incremental(&mut self, path: PathBuf, total_bytes: u64, ctx: &egui::Context) {
+        self.set_loaded(Loaded {
+            file_name: path.file_name().map(|s| s.to_string_lossy().to_string()),
+            source: Some(path.clone()),
+            format: Some(PLAIN_JSONL),
+            ..Default::default()
+        });
+        let (tx, rx) = mpsc::channel();
+        self.loading = Some(IncrementalLoad {
+            rx,
+            total_bytes,
+            bytes_read: 0,
+        });
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let update = match read_jsonl(&path, &tx, &ctx) {
+                Ok(Some(failed)) => Update::Done { failed },
+                Ok(None) => return,
+                Err(e) => Update::Failed(e.to_string()),
+            };
+            let _ = tx.send(update);
+            ctx.request_repaint();
+        });
+    }
+
+    // Append what the reader thread has parsed since the last frame; called every frame.
+    pub(crate) fn poll_incremental(&mut self) {
+        let Some(load) = &mut self.loading else { return };
+        let start = self.messages.len();
+        let mut finished = None;
+        while let Ok(update) = load.rx.try_recv() {
+            match update {
+                Update::Batch { messages, bytes_read } => {
+                    load.bytes_read = bytes_read;
+                    normalize_into(messages, &mut self.system, &mut self.messages);
+                }
+                Update::Done { failed } => finished = Some(Ok(failed)),
+                Update::Failed(e) => finished = Some(Err(e)),
+            }
+        }
+        if self.messages.len() > start {
+            roles::apply_aliases(&mut self.messages[start..], &self.settings.role_aliases);
+        }
+        match finished {
+            Some(Ok(failed)) => self.finish_incremental(Vec::new(), failed),
+            Some(Err(e)) => self.finish_incremental(vec![format!("Failed to load: {e}")], LineErrors::default()),
+            None => {}
+        }
+    }
+
+    // Hand the messages read so far to set_loaded, which runs scripts, judge scores and badges.
+    fn finish_incremental(&mut self, errors: Vec<String>, failed_lines: LineErrors) {
+        self.loading = None;
+        let loaded = Loaded {
+            file_name: self.file_name.take(),
+            source: self.source.take(),
+            format: self.format,
+            system: self.system.take(),
+            messages: std::mem::take(&mut self.messages),
+            errors,
+            failed_lines,
+            ..Default::default()
+        };
+        self.set_loaded(loaded);
+    }
+
+    // Progress shown in the top bar while a file is loading.
+    pub(crate) fn loading_indicator(&mut self, ui: &mut egui::Ui) {
+        if let Some(load) = &self.background {
+            let bytes_read = load.progress.bytes_read.load(Ordering::Relaxed);
+            repaint::busy(ui);
+            // Parsing has no progress of its own; the busy indicator keeps turning until it is done
+            let bar = if bytes_read < load.total_bytes {
+                let fraction = bytes_read as f32 / load.total_bytes.max(1) as f32;
+                egui::ProgressBar::new(fraction).text(format!("Reading {}", load.file_name))
+            } else {
+                egui::ProgressBar::new(1.0).text(format!("Parsing {}", load.file_name))
+            };
+            ui.add(bar.desired_width(200.0));
+            if ui
+                .small_button("Cancel")
+                .on_hover_text("Keep showing the current conversation")
+                .clicked()
+            {
+                self.background = None;
+            }
+        }
+        let Some(load) = &self.loading else { return };
+        let fraction = load.bytes_read as f32 / load.total_bytes.max(1) as f32;
+        repaint::busy(ui);
+        ui.add(
+            egui::ProgressBar::new(fraction)
+                .desired_width(160.0)
+                .text(format!("{} messages", self.fmt.count(self.messages.len()))),
+        );
+        if ui
+            .small_button("Stop")
+            .on_hover_text("Keep the messages loaded so far")
+            .clicked()
+        {
+            let percent = (fraction * 100.0).round() as u32;
+            self.finish_incremental(
+                vec![format!("Loading stopped {percent}% into the file")],
+                LineErrors::default(),
+            );
+        }
+    }
+}
@@ -0,0 +1,452 @@
+// Optional "LLM judge": sends each user → assistant exchange to an OpenAI-compatible
+// chat completions endpoint with a rubric prompt and keeps the returned score/rationale
+// as an annotation on the assistant message.
+
+use crate::{
+    config_dir,
+    llm::{self, LlmClient, NetworkSettings},
+    providers::{default_providers, find_provider, ProviderProfile},
+    role_label, save_settings, short_hash, write_file_creating_dirs, AppState, Msg, Role,
+};
+use anyhow::{anyhow, Context, Result};
+use eframe::egui::{self, RichText};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+const JUDGE_WINDOW: &str = "LLM judge";
+
+const DEFAULT_RUBRIC: &str = "You are a strict evaluator of AI assistant replies. \
+Score the assistant's reply to the user's last message on a 1-10 scale for helpfulness, \
+correctness and clarity. Respond with JSON only: {\"score\": <number>, \"rationale\": \"<one or two sentences>\"}";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub(crate) struct JudgeSettings {
+    // Provider profile id (see providers.rs); names can be edited and repeated
+    pub provider: String,
+    // Empty means the provider's default model
+    pub model: String,
+    pub rubric: String,
+    pub requests_per_minute: u32,
+}
+
+impl Default for JudgeSettings {
+    fn default() -> Self {
+        Self {
+            provider: default_providers()[0].id.clone(),
+            model: String::new(),
+            rubric: DEFAULT_RUBRIC.to_string(),
+            requests_per_minute: 20,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct JudgeAnnotation {
+    pub score: f32,
+    pub rationale: String,
+}
+
+// One scored unit: an assistant reply and the closest preceding user message.
+#[derive(Clone, Debug)]
+struct Exchange {
+    assistant_index: usize,
+    prompt: String,
+}
+
+enum JudgeEvent {
+    Scored { index: usize, annotation: JudgeAnnotation, cached: bool },
+    Failed { index: usize, error: String },
+    Finished,
+}
+
+struct JudgeRun {
+    rx: mpsc::Receiver<JudgeEvent>,
+    cancel: Arc<AtomicBool>,
+    total: usize,
+    done: usize,
+    cached: usize,
+}
+
+impl Drop for JudgeRun {
+    fn drop(&mut self) {
+        // Stop the worker when the run is discarded (Clear, new file, Stop)
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct JudgeState {
+    pub show_window: bool,
+    pub annotations: BTreeMap<usize, JudgeAnnotation>,
+    conversation_key: u64,
+    run: Option<JudgeRun>,
+    errors: Vec<String>,
+    // Refreshed lazily; listing the cache directory every frame would be wasteful
+    cache_usage: Option<(usize, u64)>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct StoredAnnotations {
+    annotations: BTreeMap<usize, JudgeAnnotation>,
+}
+
+impl JudgeState {
+    // Reset for a newly loaded conversation and pick up scores from a previous (possibly interrupted) run.
+    pub fn reset_for(&mut self, system: Option<&str>, messages: &[Msg]) {
+        let show_window = self.show_window;
+        *self = JudgeState { show_window, ..Default::default() };
+        self.conversation_key = conversation_key(system, messages);
+        if let Some(path) = self.store_path() {
+            if let Ok(bytes) = fs::read(&path) {
+                if let Ok(stored) = serde_json::from_slice::<StoredAnnotations>(&bytes) {
+                    self.annotations = stored
+                        .annotations
+                        .into_iter()
+                        .filter(|(idx, _)| matches!(messages.get(*idx).map(|m| &m.role), Some(Role::Assistant)))
+                        .collect();
+                }
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.run.is_some()
+    }
+
+    // (mean, min, max, count)
+    pub fn aggregate(&self) -> Option<(f32, f32, f32, usize)> {
+        if self.annotations.is_empty() {
+            return None;
+        }
+        let scores: Vec<f32> = self.annotations.values().map(|a| a.score).collect();
+        let sum: f32 = scores.iter().sum();
+        let min = scores.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max = scores.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        Some((sum / scores.len() as f32, min, max, scores.len()))
+    }
+
+    fn store_path(&self) -> Option<PathBuf> {
+        if self.conversation_key == 0 {
+            return None;
+        }
+        config_dir().map(|d| d.join("judge").join(format!("{:016x}.json", self.conversation_key)))
+    }
+
+    fn persist(&mut self) {
+        let Some(path) = self.store_path() else { return };
+        let stored = StoredAnnotations { annotations: self.annotations.clone() };
+        let res = serde_json::to_vec_pretty(&stored)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| write_file_creating_dirs(&path, &bytes));
+        if let Err(e) = res {
+            self.errors.push(format!("Failed to save judge scores: {e}"));
+        }
+    }
+
+    // Drain worker events; called every frame.
+    pub fn poll(&mut self) {
+        let mut changed = false;
+        let mut finished = false;
+        if let Some(run) = &mut self.run {
+            while let Ok(ev) = run.rx.try_recv() {
+                match ev {
+                    JudgeEvent::Scored { index, annotation, cached } => {
+                        run.done += 1;
+                        run.cached += usize::from(cached);
+                        self.annotations.insert(index, annotation);
+                        changed = true;
+                    }
+                    JudgeEvent::Failed { index, error } => {
+                        run.done += 1;
+                        self.errors.push(format!("Message #{}: {error}", index + 1));
+                    }
+                    JudgeEvent::Finished => finished = true,
+                }
+            }
+        }
+        if changed {
+            self.persist();
+        }
+        if finished {
+            self.run = None;
+            self.cache_usage = None;
+        }
+    }
+
+    fn start(
+        &mut self,
+        settings: &JudgeSettings,
+        providers: &[ProviderProfile],
+        network: &NetworkSettings,
+        system: Option<&str>,
+        messages: &[Msg],
+        ctx: &egui::Context,
+    ) {
+        self.errors.clear();
+        let Some(provider) = find_provider(providers, &settings.provider) else {
+            self.errors.push(format!("Unknown provider \"{}\"", settings.provider));
+            return;
+        };
+        // Without a key (or offline) only cached replies can be used; misses are reported per message
+        let api_key = if network.offline {
+            self.errors.push("Offline mode: using cached results only".to_string());
+            None
+        } else {
+            match provider.api_key() {
+                Ok(key) => key,
+                Err(e) => {
+                    self.errors.push(format!("{e}; using cached results only"));
+                    None
+                }
+            }
+        };
+        let client = match LlmClient::new(&provider.base_url, api_key, network) {
+            Ok(c) => c,
+            Err(e) => {
+                self.errors.push(format!("{e:#}"));
+                return;
+            }
+        };
+        let mut settings = settings.clone();
+        if settings.model.trim().is_empty() {
+            settings.model = provider.default_model.clone();
+        }
+        // Resumable: exchanges that already have a score are skipped
+        let pending: Vec<Exchange> = exchanges(system, messages)
+            .into_iter()
+            .filter(|ex| !self.annotations.contains_key(&ex.assistant_index))
+            .collect();
+        if pending.is_empty() {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.run = Some(JudgeRun { rx, cancel: cancel.clone(), total: pending.len(), done: 0, cached: 0 });
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            run_worker(&settings, &client, pending, &cancel, &tx, &ctx);
+            let _ = tx.send(JudgeEvent::Finished);
+            ctx.request_repaint();
+        });
+    }
+}
+
+fn conversation_key(system: Option<&str>, messages: &[Msg]) -> u64 {
+    let mut buf = String::new();
+    buf.push_str(system.unwrap_or(""));
+    for m in messages {
+        buf.push('\u{1e}');
+        buf.push_str(&role_label(&m.role));
+        buf.push('\u{1f}');
+        buf.push_str(&m.content);
+    }
+    short_hash(&buf)
+}
+
+fn exchanges(system: Option<&str>, messages: &[Msg]) -> Vec<Exchange> {
+    let mut out = Vec::new();
+    let mut last_user: Option<&str> = None;
+    for (idx, m) in messages.iter().enumerate() {
+        match m.role {
+            Role::User => last_user = Some(&m.content),
+            Role::Assistant => {
+                let mut prompt = String::new();
+                if let Some(sys) = system {
+                    prompt.push_str(&format!("System prompt:\n{sys}\n\n"));
+                }
+                prompt.push_str(&format!("User:\n{}\n\nAssistant:\n{}", last_user.unwrap_or("(none)"), m.content));
+                out.push(Exchange { assistant_index: idx, prompt });
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn run_worker(
+    settings: &JudgeSettings,
+    client: &LlmClient,
+    pending: Vec<Exchange>,
+    cancel: &AtomicBool,
+    tx: &mpsc::Sender<JudgeEvent>,
+    ctx: &egui::Context,
+) {
+    let min_interval = Duration::from_secs_f32(60.0 / settings.requests_per_minute.max(1) as f32);
+    let mut last_request: Option<Instant> = None;
+    for ex in pending {
+        if cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        let body = request_body(settings, &ex.prompt);
+        let (reply, cached) = match client.cached(&body) {
+            Some(text) => (Ok(text), true),
+            None => {
+                // Rate limit: keep at least `min_interval` between network requests, staying responsive to Stop
+                if let Some(last) = last_request {
+                    while last.elapsed() < min_interval {
+                        if cancel.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                }
+                last_request = Some(Instant::now());
+                (client.fetch(&body, cancel), false)
+            }
+        };
+        if cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        let ev = match reply.and_then(|text| parse_verdict(&text)) {
+            Ok(annotation) => JudgeEvent::Scored { index: ex.assistant_index, annotation, cached },
+            Err(e) => JudgeEvent::Failed { index: ex.assistant_index, error: format!("{e:#}") },
+        };
+        if tx.send(ev).is_err() {
+            return;
+        }
+        ctx.request_repaint();
+    }
+}
+
+fn request_body(settings: &JudgeSettings, prompt: &str) -> serde_json::Value {
+    serde_json::json!({
+        "model": settings.model,
+        "temperature": 0,
+        "messages": [
+            { "role": "system", "content": settings.rubric },
+            { "role": "user", "content": prompt },
+        ],
+    })
+}
+
+// Accept the JSON verdict even when the model wraps it in prose or a code fence.
+fn parse_verdict(text: &str) -> Result<JudgeAnnotation> {
+    let start = text.find('{').ok_or_else(|| anyhow!("No JSON object in judge reply"))?;
+    let end = text.rfind('}').filter(|e| *e > start).ok_or_else(|| anyhow!("No JSON object in judge reply"))?;
+    let v: serde_json::Value = serde_json::from_str(&text[start..=end]).context("Judge reply is not valid JSON")?;
+    let score = match &v["score"] {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| anyhow!("Judge reply has no numeric score"))?;
+    let rationale = v["rationale"].as_str().unwrap_or("").trim().to_string();
+    Ok(JudgeAnnotation { score: score as f32, rationale })
+}
+
+impl AppState {
+    pub(crate) fn judge_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.judge.show_window;
+        let resp = self
+            .tool_window(JUDGE_WINDOW)
+            .open(&mut open)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                let running = self.judge.is_running();
+                ui.add_enabled_ui(!running, |ui| {
+                    let s = &mut self.settings.judge;
+                    let providers = &self.settings.providers;
+                    egui::Grid::new("judge_settings").num_columns(2).show(ui, |ui| {
+                        ui.label("Provider");
+                        let selected = find_provider(providers, &s.provider).map_or(s.provider.as_str(), |p| p.name.as_str());
+                        egui::ComboBox::from_id_source("judge_provider")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                for p in providers {
+                                    ui.selectable_value(&mut s.provider, p.id.clone(), &p.name);
+                                }
+                            });
+                        ui.end_row();
+                        ui.label("Model");
+                        let default_model = find_provider(providers, &s.provider).map(|p| p.default_model.as_str()).unwrap_or("");
+                        ui.add(egui::TextEdit::singleline(&mut s.model).hint_text(default_model));
+                        ui.end_row();
+                        ui.label("Requests / min");
+                        ui.add(egui::DragValue::new(&mut s.requests_per_minute).clamp_range(1..=600));
+                        ui.end_row();
+                    });
+                    ui.label("Rubric prompt");
+                    ui.add(egui::TextEdit::multiline(&mut s.rubric).desired_rows(5).desired_width(f32::INFINITY));
+                    if ui.small_button("Reset rubric").clicked() {
+                        s.rubric = DEFAULT_RUBRIC.to_string();
+                    }
+                });
+                ui.separator();
+
+                let total = exchanges(self.system.as_deref(), &self.messages).len();
+                ui.horizontal(|ui| {
+                    if running {
+                        if ui.button("Stop").clicked() {
+                            self.judge.run = None;
+                        }
+                    } else {
+                        let label = if self.judge.annotations.is_empty() { "Run" } else { "Resume" };
+                        let editable = !self.is_read_only();
+                        let disabled_hint = if editable { "No exchanges to score" } else { "The conversation is read-only" };
+                        if ui.add_enabled(total > 0 && editable, egui::Button::new(label)).on_disabled_hover_text(disabled_hint).clicked() {
+                            if let Err(e) = save_settings(&self.settings) {
+                                self.errors.push(format!("Failed to save settings: {e}"));
+                            }
+                            let s = &self.settings;
+                            self.judge.start(&s.judge, &s.providers, &s.network, self.system.as_deref(), &self.messages, ctx);
+                        }
+                        if ui.add_enabled(!self.judge.annotations.is_empty() && editable, egui::Button::new("Clear scores")).clicked() {
+                            self.judge.annotations.clear();
+                            self.judge.persist();
+                        }
+                    }
+                    let fmt = self.fmt;
+                    ui.label(format!("Scored {}/{} exchanges", fmt.count(self.judge.annotations.len()), fmt.count(total)));
+                });
+                let fmt = self.fmt;
+                if let Some(run) = &self.judge.run {
+                    let frac = run.done as f32 / run.total.max(1) as f32;
+                    let text = format!("{}/{} ({} cached)", fmt.count(run.done), fmt.count(run.total), fmt.count(run.cached));
+                    ui.add(egui::ProgressBar::new(frac).text(text));
+                }
+                if let Some((mean, min, max, n)) = self.judge.aggregate() {
+                    let (mean, min, max) = (fmt.float(mean.into(), 2), fmt.float(min.into(), 1), fmt.float(max.into(), 1));
+                    ui.label(format!("Mean {mean} • Min {min} • Max {max} • n={}", fmt.count(n)));
+                }
+                ui.horizontal(|ui| {
+                    let (entries, bytes) = *self.judge.cache_usage.get_or_insert_with(llm::cache_usage);
+                    ui.label(RichText::new(format!("Response cache: {} entries, {}", fmt.count(entries), fmt.bytes(bytes))).small());
+                    if ui.add_enabled(!running && entries > 0, egui::Button::new("Clear cache").small()).clicked() {
+                        if let Err(e) = llm::clear_cache() {
+                            self.judge.errors.push(format!("Failed to clear cache: {e}"));
+                        }
+                        self.judge.cache_usage = None;
+                    }
+                });
+                if !self.judge.errors.is_empty() {
+                    ui.separator();
+                    egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+                        for e in &self.judge.errors {
+                            ui.colored_label(egui::Color32::from_rgb(183, 28, 28), e);
+                        }
+                    });
+                }
+            });
+        if let Some(r) = resp {
+            self.remember_window(JUDGE_WINDOW, r.response.rect);
+        }
+        if !open {
+            // Keep edits made in the window even if no run was started
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+        self.judge.show_window = open;
+    }
+}
@@ -0,0 +1,47 @@
+// Message text is prepared for the Markdown renderer (chat artifacts that look like code fences
+// tidied up, raw HTML handled per the HTML policy) once, and reused for as long as the message
+// stays on screen; redoing it every frame for every visible bubble was most of the cost of an idle
+// frame on long conversations. What is cached does not depend on the width or text size: egui keeps
+// the laid-out lines of last frame's text keyed by text, font and wrap width, so a resize or zoom
+// lays the text out again on its own. Entries for messages not drawn in a frame are dropped.
+
+use crate::{
+    code_folds::{folds, Fold},
+    html::HtmlPolicy,
+    sanitize_chat_markdown, short_hash,
+};
+use eframe::egui::{
+    self,
+    util::cache::{ComputerMut, FrameCache},
+};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub(crate) struct Prepared {
+    pub text: Arc<str>,
+    // Of the original text; tells the renderer's per-message state apart
+    pub hash: u64,
+    // Long code blocks in `text`
+    pub folds: Arc<[Fold]>,
+}
+
+#[derive(Default)]
+struct Prepare;
+
+impl ComputerMut<(&str, HtmlPolicy), Prepared> for Prepare {
+    fn compute(&mut self, (text, policy): (&str, HtmlPolicy)) -> Prepared {
+        let sanitized = sanitize_chat_markdown(text);
+        let shown = crate::html::display_text(&sanitized, policy);
+        Prepared {
+            folds: folds(&shown).into(),
+            text: shown.into(),
+            hash: short_hash(text),
+        }
+    }
+}
+
+type PreparedCache = FrameCache<Prepared, Prepare>;
+
+pub(crate) fn prepared(ctx: &egui::Context, text: &str, policy: HtmlPolicy) -> Prepared {
+    ctx.memory_mut(|m| m.caches.cache::<PreparedCache>().get((text, policy)))
+}
@@ -0,0 +1,73 @@
+// JSONL lines skipped while loading, listed under the error bar: the line number, the parser's
+// message and the start of the line, each copyable so the line can be found and fixed.
+
+use crate::{parse::LineError, AppState};
+use eframe::egui::{self, Color32, RichText, ScrollArea};
+
+// Characters of the line shown in the list; hover or copy for the rest
+const PREVIEW_CHARS: usize = 60;
+// The error bar keeps its light background in both themes
+const ERROR_RED: Color32 = Color32::from_rgb(183, 28, 28);
+const TEXT_GRAY: Color32 = Color32::from_gray(60);
+
+fn report(e: &LineError) -> String {
+    format!("line {}: {}\n{}", e.line, e.error, e.text)
+}
+
+impl AppState {
+    pub(crate) fn line_errors_panel(&self, ui: &mut egui::Ui) {
+        let failed = &self.failed_lines;
+        if failed.is_empty() {
+            return;
+        }
+        let title = format!("{} JSONL line(s) failed to parse", self.fmt.count(failed.count));
+        let mut copied = None;
+        egui::CollapsingHeader::new(RichText::new(title).color(ERROR_RED))
+            .id_source("line_errors")
+            .show(ui, |ui| {
+                ScrollArea::vertical().max_height(180.0).show(ui, |ui| {
+                    egui::Grid::new("line_errors_grid")
+                        .num_columns(4)
+                        .spacing([12.0, 4.0])
+                        .show(ui, |ui| {
+                            for e in &failed.lines {
+                                ui.label(
+                                    RichText::new(format!("Line {}", self.fmt.count(e.line)))
+                                        .monospace()
+                                        .color(ERROR_RED),
+                                );
+                                ui.label(RichText::new(&e.error).color(TEXT_GRAY));
+                                let mut preview: String = e.text.chars().take(PREVIEW_CHARS).collect();
+                                if preview.len() < e.text.len() {
+                                    preview.push('…');
+                                }
+                                ui.label(RichText::new(preview).monospace().small().color(TEXT_GRAY))
+                                    .on_hover_text(&e.text);
+                                if ui
+                                    .small_button("Copy")
+                                    .on_hover_text("Copy the line number, error and line")
+                                    .clicked()
+                                {
+                                    copied = Some(report(e));
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    let more = failed.count - failed.lines.len();
+                    if more > 0 {
+                        ui.label(
+                            RichText::new(format!("… and {} more", self.fmt.count(more)))
+                                .italics()
+                                .color(TEXT_GRAY),
+                        );
+                    }
+                });
+                if ui.small_button("Copy all").clicked() {
+                    copied = Some(failed.lines.iter().map(report).collect::<Vec<_>>().join("\n\n"));
+                }
+            });
+        if let Some(text) = copied {
+            ui.output_mut(|o| o.copied_text = text);
+        }
+    }
+}
@@ -0,0 +1,230 @@
+// The Lint pane lists problems found in the open conversation, each over the messages it spans:
+// agents going round in a loop (see loops.rs), replies that were cut off, and tool results that
+// report an error. Clicking one jumps to it, and the spans are marked on a strip along the right
+// edge of the conversation, an overview of the whole conversation top to bottom.
+
+use crate::{
+    health, is_tool_card,
+    loops::{find_loops, Loop},
+    AppState, Msg, Role,
+};
+use eframe::egui::{self, Color32, Id, RichText, ScrollArea, Sense};
+use serde::Serialize;
+
+const STRIP_WIDTH: f32 = 4.0;
+// Text in tool results that means the call failed
+const ERROR_MARKERS: &[&str] = &[
+    "Traceback (most recent call last)",
+    "Error:",
+    "error:",
+    "error[",
+    "ERROR",
+    "Exception",
+    "panicked at",
+    "FAILED",
+    "command not found",
+    "No such file or directory",
+    "Permission denied",
+];
+// A last reply shorter than this that stops mid-sentence is more likely terse than cut off
+const MID_SENTENCE_MIN_CHARS: usize = 200;
+// Characters of the error line quoted in a finding
+const QUOTE_CHARS: usize = 80;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Kind {
+    Loop,
+    Truncated,
+    ToolError,
+}
+
+impl Kind {
+    fn color(self) -> Color32 {
+        match self {
+            Kind::Loop => Color32::from_rgb(230, 145, 30),
+            Kind::Truncated => Color32::from_rgb(200, 170, 40),
+            Kind::ToolError => Color32::from_rgb(211, 47, 47),
+        }
+    }
+}
+
+pub(crate) struct Finding {
+    pub kind: Kind,
+    // First and last message
+    pub start: usize,
+    pub end: usize,
+    pub title: String,
+}
+
+fn loop_finding(l: &Loop) -> Finding {
+    let title = match l.tools.as_slice() {
+        [tool] => format!(
+            "Possible loop: {tool} called {} times with the same arguments",
+            l.cycles
+        ),
+        tools => format!("Possible loop: {} repeated {} times", tools.join(" → "), l.cycles),
+    };
+    Finding {
+        kind: Kind::Loop,
+        start: l.start,
+        end: l.end,
+        title,
+    }
+}
+
+fn is_reply(msg: &Msg) -> bool {
+    matches!(msg.role, Role::Assistant) && !msg.content.trim_start().starts_with("**Tool call:")
+}
+
+// A code block left open, or for the last reply, text that stops mid-sentence (usually the
+// model running out of tokens).
+fn truncation(msg: &Msg, last_reply: bool) -> Option<&'static str> {
+    let fences = msg
+        .content
+        .lines()
+        .filter(|l| l.trim_start().starts_with("```"))
+        .count();
+    if fences % 2 == 1 {
+        return Some("code block left open");
+    }
+    let text = msg.content.trim_end();
+    let last_line = text.lines().last().unwrap_or_default().trim_start();
+    // Lists, headings and tables end without punctuation
+    let structured =
+        last_line.starts_with(['-', '*', '#', '|', '>']) || last_line.starts_with(|c: char| c.is_ascii_digit());
+    let cut = text.ends_with(|c: char| c.is_alphanumeric() || c == ',');
+    (last_reply && cut && !structured && text.chars().count() >= MID_SENTENCE_MIN_CHARS).then_some("ends mid-sentence")
+}
+
+// The first line of a tool result that reports an error.
+fn tool_error(msg: &Msg) -> Option<String> {
+    let line = msg
+        .content
+        .lines()
+        .find(|l| ERROR_MARKERS.iter().any(|m| l.contains(m)))?;
+    let line = line.trim();
+    let mut quote: String = line.chars().take(QUOTE_CHARS).collect();
+    if quote.len() < line.len() {
+        quote.push('…');
+    }
+    Some(quote)
+}
+
+pub(crate) fn lint(messages: &[Msg]) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = find_loops(messages).iter().map(loop_finding).collect();
+    let last_reply = messages.iter().rposition(is_reply);
+    for (i, msg) in messages.iter().enumerate() {
+        let found = if is_reply(msg) {
+            truncation(msg, Some(i) == last_reply).map(|why| (Kind::Truncated, format!("Reply cut off: {why}")))
+        } else if is_tool_card(msg) {
+            tool_error(msg).map(|line| (Kind::ToolError, format!("Tool error: {line}")))
+        } else {
+            None
+        };
+        if let Some((kind, title)) = found {
+            findings.push(Finding {
+                kind,
+                start: i,
+                end: i,
+                title,
+            });
+        }
+    }
+    findings.sort_by_key(|f| f.start);
+    findings
+}
+
+// Findings for the open conversation, found again when it changes. Keyed by message count and
+// total length like the reading-time cache.
+#[derive(Default)]
+pub(crate) struct Lint {
+    key: Option<(usize, usize)>,
+    findings: Vec<Finding>,
+}
+
+impl Lint {
+    fn update(&mut self, messages: &[Msg]) {
+        let key = (messages.len(), messages.iter().map(|m| m.content.len()).sum());
+        if self.key != Some(key) {
+            self.key = Some(key);
+            self.findings = lint(messages);
+        }
+    }
+}
+
+impl AppState {
+    pub(crate) fn lint_ui(&mut self, ui: &mut egui::Ui) {
+        self.lint.update(&self.messages);
+        if self.messages.is_empty() {
+            return;
+        }
+        let summary = health::Summary::of(&self.lint.findings);
+        ui.horizontal(|ui| {
+            ui.label("Health");
+            health::badge(ui, &summary);
+        });
+        if self.lint.findings.is_empty() {
+            ui.label(RichText::new("No problems found").italics().weak());
+            return;
+        }
+        ui.separator();
+        let mut jump = None;
+        ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+            for finding in &self.lint.findings {
+                ui.horizontal_wrapped(|ui| {
+                    ui.colored_label(finding.kind.color(), "⚠");
+                    ui.label(&finding.title);
+                    let span = if finding.start == finding.end {
+                        format!("Message {}", self.fmt.count(finding.start + 1))
+                    } else {
+                        format!(
+                            "Messages {}–{}",
+                            self.fmt.count(finding.start + 1),
+                            self.fmt.count(finding.end + 1)
+                        )
+                    };
+                    if ui.link(RichText::new(span).small()).clicked() {
+                        jump = Some(finding.start);
+                    }
+                });
+            }
+        });
+        if jump.is_some() {
+            self.scroll_to_message = jump;
+        }
+    }
+
+    // The findings' spans on a strip along the right edge of the conversation in `rect`.
+    pub(crate) fn lint_strip(&mut self, ui: &mut egui::Ui, rect: egui::Rect) {
+        self.lint.update(&self.messages);
+        let len = self.messages.len();
+        if self.lint.findings.is_empty() || len == 0 {
+            return;
+        }
+        // Left of the scroll bar
+        let scroll = &ui.spacing().scroll;
+        let right = rect.right() - scroll.bar_width - scroll.bar_inner_margin - scroll.bar_outer_margin;
+        let y = |i: usize| rect.top() + rect.height() * i as f32 / len as f32;
+        let mut jump = None;
+        for (n, finding) in self.lint.findings.iter().enumerate() {
+            let mark = egui::Rect::from_x_y_ranges(
+                right - STRIP_WIDTH..=right,
+                y(finding.start)..=y(finding.end + 1).max(y(finding.start) + 3.0),
+            );
+            let resp = ui.interact(mark, Id::new(("lint_strip", n)), Sense::click());
+            let color = if resp.hovered() {
+                finding.kind.color()
+            } else {
+                finding.kind.color().gamma_multiply(0.7)
+            };
+            ui.painter().rect_filled(mark, 1.0, color);
+            if resp.on_hover_text(&finding.title).clicked() {
+                jump = Some(finding.start);
+            }
+        }
+        if jump.is_some() {
+            self.scroll_to_message = jump;
+        }
+    }
+}
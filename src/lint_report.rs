@@ -0,0 +1,256 @@
+// `llm_log_viewer lint [--min-score N] [--junit] FILE…` runs the Lint pane's checks and health
+// score over every conversation in the files without opening a window, so a dataset regression
+// (agents looping, replies cut off) can fail a CI job. The report on stdout is JSON, or JUnit XML
+// with `--junit` for CI systems that show test results, with one test case per conversation. A
+// conversation scoring below the minimum (50 unless given) makes the exit status 1; a file that
+// cannot be read or recognized at all makes it 2. A folder stands for the log files in it, minus
+// the excluded ones, as when it is opened. The saved format mappings, role aliases and strict
+// parsing setting apply; scripts are not run.
+
+use crate::{
+    folder_scan::{scan, ScanProgress, FOLDER_EXTENSIONS},
+    health::Summary,
+    html_escape,
+    lint::{lint, Finding, Kind},
+    load_settings,
+    parse::ParseOptions,
+    roles, scripting, Msg, Settings,
+};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_MIN_SCORE: u32 = 50;
+const USAGE: &str = "usage: llm_log_viewer lint [--min-score N] [--junit] FILE|FOLDER…
+Folders are searched like File > Open folder. The saved format mappings, role aliases and
+strict parsing setting apply; scripts are not run.";
+
+#[derive(Serialize)]
+struct FindingReport {
+    kind: Kind,
+    // 1-based first and last message
+    messages: [usize; 2],
+    detail: String,
+}
+
+#[derive(Serialize)]
+struct ConversationReport {
+    // 1-based
+    conversation: usize,
+    title: String,
+    ok: bool,
+    score: u32,
+    loops: u32,
+    truncated: u32,
+    tool_errors: u32,
+    findings: Vec<FindingReport>,
+}
+
+#[derive(Serialize, Default)]
+struct FileReport {
+    file: String,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    conversations: Vec<ConversationReport>,
+}
+
+#[derive(Serialize)]
+struct Report {
+    ok: bool,
+    min_score: u32,
+    files: Vec<FileReport>,
+}
+
+fn finding_report(f: &Finding) -> FindingReport {
+    FindingReport {
+        kind: f.kind,
+        messages: [f.start + 1, f.end + 1],
+        detail: f.title.clone(),
+    }
+}
+
+fn conversation_report(index: usize, title: String, messages: &[Msg], min_score: u32) -> ConversationReport {
+    let findings = lint(messages);
+    let summary = Summary::of(&findings);
+    let score = summary.score();
+    ConversationReport {
+        conversation: index + 1,
+        title,
+        ok: score >= min_score,
+        score,
+        loops: summary.loops,
+        truncated: summary.truncated,
+        tool_errors: summary.tool_errors,
+        findings: findings.iter().map(finding_report).collect(),
+    }
+}
+
+fn lint_file(path: &Path, settings: &Settings, min_score: u32) -> FileReport {
+    let file = path.display().to_string();
+    let options = ParseOptions {
+        strict: settings.strict_parsing,
+    };
+    let mut loaded = match scripting::load_mapped(&settings.format_mappings, path, None, options) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            return FileReport {
+                file,
+                error: Some(format!("{e:#}")),
+                ..Default::default()
+            }
+        }
+    };
+    roles::apply_aliases(&mut loaded.messages, &settings.role_aliases);
+    for session in &mut loaded.sessions {
+        roles::apply_aliases(&mut session.messages, &settings.role_aliases);
+    }
+    let conversations: Vec<ConversationReport> = if loaded.sessions.is_empty() {
+        vec![conversation_report(0, file.clone(), &loaded.messages, min_score)]
+    } else {
+        loaded
+            .sessions
+            .iter()
+            .enumerate()
+            .map(|(i, s)| conversation_report(i, s.title.clone(), &s.messages, min_score))
+            .collect()
+    };
+    FileReport {
+        ok: conversations.iter().all(|c| c.ok),
+        file,
+        format: loaded.format,
+        error: None,
+        conversations,
+    }
+}
+
+// A folder argument becomes the log files found in it; a folder that cannot be read is an error,
+// and subfolders that cannot be are reported on stderr.
+fn expand(path: &Path, settings: &Settings) -> Result<Vec<PathBuf>, String> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let scanned = scan(path, &settings.folders, FOLDER_EXTENSIONS, &ScanProgress::default())?;
+    for skipped in &scanned.skipped {
+        eprintln!("Skipped {skipped}");
+    }
+    Ok(scanned.files)
+}
+
+// XML text and attributes; characters XML does not allow (ANSI escapes in tool output) are left out.
+fn xml(s: &str) -> String {
+    let allowed: String = s
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .collect();
+    html_escape(&allowed)
+}
+
+fn junit(report: &Report) -> String {
+    let cases = |f: &FileReport| f.conversations.len().max(1);
+    let failures = |f: &FileReport| f.conversations.iter().filter(|c| !c.ok).count() + usize::from(f.error.is_some());
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<testsuites name=\"llm_log_viewer lint\" tests=\"{}\" failures=\"{}\">\n",
+        report.files.iter().map(cases).sum::<usize>(),
+        report.files.iter().map(failures).sum::<usize>()
+    ));
+    for file in &report.files {
+        let name = xml(&file.file);
+        out.push_str(&format!(
+            "  <testsuite name=\"{name}\" tests=\"{}\" failures=\"{}\">\n",
+            cases(file),
+            failures(file)
+        ));
+        if let Some(error) = &file.error {
+            out.push_str(&format!(
+                "    <testcase classname=\"{name}\" name=\"read\">\n      <failure message=\"{}\"/>\n    </testcase>\n",
+                xml(error)
+            ));
+        }
+        for c in &file.conversations {
+            out.push_str(&format!(
+                "    <testcase classname=\"{name}\" name=\"{}. {}\">\n",
+                c.conversation,
+                xml(&c.title)
+            ));
+            if !c.ok {
+                let details: Vec<String> = c
+                    .findings
+                    .iter()
+                    .map(|f| format!("messages {}–{}: {}", f.messages[0], f.messages[1], f.detail))
+                    .collect();
+                out.push_str(&format!(
+                    "      <failure message=\"health {} is below {}\">{}</failure>\n",
+                    c.score,
+                    report.min_score,
+                    xml(&details.join("\n"))
+                ));
+            }
+            out.push_str("    </testcase>\n");
+        }
+        out.push_str("  </testsuite>\n");
+    }
+    out.push_str("</testsuites>");
+    out
+}
+
+// Prints the report and returns the exit status.
+pub(crate) fn run(args: &[String]) -> i32 {
+    let mut min_score = DEFAULT_MIN_SCORE;
+    let mut junit_output = false;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--junit" => junit_output = true,
+            "--min-score" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => min_score = n,
+                None => {
+                    eprintln!("{USAGE}");
+                    return 2;
+                }
+            },
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("{USAGE}");
+        return 2;
+    }
+    let settings = load_settings();
+    let mut files = Vec::new();
+    for path in paths {
+        match expand(Path::new(path), &settings) {
+            Ok(expanded) => files.extend(expanded.iter().map(|p| lint_file(p, &settings, min_score))),
+            Err(e) => files.push(FileReport {
+                file: path.clone(),
+                error: Some(e),
+                ..Default::default()
+            }),
+        }
+    }
+    let unreadable = files.iter().any(|f| f.error.is_some());
+    let report = Report {
+        ok: files.iter().all(|f| f.ok),
+        min_score,
+        files,
+    };
+    if junit_output {
+        println!("{}", junit(&report));
+    } else {
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{json}"),
+            Err(e) => {
+                eprintln!("Failed to write the report: {e}");
+                return 2;
+            }
+        }
+    }
+    match (unreadable, report.ok) {
+        (true, _) => 2,
+        (false, false) => 1,
+        (false, true) => 0,
+    }
+}
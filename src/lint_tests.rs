@@ -0,0 +1,38 @@
+// `lint`: loops, cut-off replies and tool errors are found in a conversation and scored.
+
+use crate::{health::Summary, lint::lint, parse::{load_from_bytes, ParseOptions}};
+
+#[test]
+fn agent_problems_are_found_and_scored() {
+    let mut text = String::from("{\"role\":\"user\",\"content\":\"wait for the build\"}\n");
+    for i in 0..4 {
+        text.push_str(&format!(
+            "{{\"role\":\"assistant\",\"content\":null,\"tool_calls\":[{{\"function\":{{\"name\":\"status\",\"arguments\":\"{{\\\"build\\\": {i}}}\"}}}}]}}\n"
+        ));
+        text.push_str("{\"role\":\"tool\",\"content\":\"running\"}\n");
+    }
+    text.push_str("{\"role\":\"tool\",\"content\":\"Traceback (most recent call last):\\nValueError: x\"}\n");
+    text.push_str("{\"role\":\"assistant\",\"content\":\"Fixed:\\n```python\\nprint(1)\"}\n");
+    let loaded = load_from_bytes(text.as_bytes(), ParseOptions::default()).unwrap();
+    let findings = lint(&loaded.messages);
+    let summary: Vec<String> = findings
+        .iter()
+        .map(|f| format!("{:?} {}-{} {}", f.kind, f.start + 1, f.end + 1, f.title))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            "Loop 2-10 Possible loop: status called 4 times with the same arguments",
+            "ToolError 10-10 Tool error: Traceback (most recent call last):",
+            "Truncated 11-11 Reply cut off: code block left open",
+        ]
+    );
+    assert_eq!(Summary::of(&findings).score(), 100 - 25 - 15 - 5);
+
+    let clean = load_from_bytes(
+        b"[{\"role\":\"user\",\"content\":\"hi\"},{\"role\":\"assistant\",\"content\":\"Hello!\"}]",
+        ParseOptions::default(),
+    )
+    .unwrap();
+    assert!(lint(&clean.messages).is_empty());
+}
@@ -0,0 +1,143 @@
+// Shared client for LLM-assisted features (judge, ...). Every completion is cached on disk
+// keyed by a hash of the request body, so re-running on the same content costs nothing
+// and keeps working offline once the cache is warm.
+
+use crate::{config_dir, short_hash, write_file_creating_dirs};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+// Longest wait a `Retry-After` header is followed for before the request is given up on
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+pub(crate) struct LlmClient {
+    agent: ureq::Agent,
+    base_url: String,
+    api_key: Option<String>,
+    offline: bool,
+}
+
+// Global network controls; every feature that talks to the network goes through LlmClient.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub(crate) struct NetworkSettings {
+    // Disables all outbound requests; cached replies keep working
+    pub offline: bool,
+    // Explicit HTTP(S) proxy URL; empty means HTTPS_PROXY / ALL_PROXY from the environment
+    pub proxy: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    model: String,
+    response: String,
+}
+
+impl LlmClient {
+    pub fn new(base_url: &str, api_key: Option<String>, network: &NetworkSettings) -> Result<Self> {
+        let mut builder = ureq::AgentBuilder::new().timeout(Duration::from_secs(120));
+        let proxy = network.proxy.trim();
+        if proxy.is_empty() {
+            builder = builder.try_proxy_from_env(true);
+        } else {
+            builder = builder.proxy(ureq::Proxy::new(proxy).with_context(|| format!("Invalid proxy URL {proxy}"))?);
+        }
+        Ok(Self {
+            agent: builder.build(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            offline: network.offline,
+        })
+    }
+
+    fn url(&self) -> String {
+        format!("{}/chat/completions", self.base_url)
+    }
+
+    // Cached assistant text for this exact request, if any.
+    pub fn cached(&self, body: &serde_json::Value) -> Option<String> {
+        let path = cache_path(&self.url(), body)?;
+        let bytes = fs::read(path).ok()?;
+        serde_json::from_slice::<CacheEntry>(&bytes).ok().map(|e| e.response)
+    }
+
+    // Send a chat completions request and store the reply in the cache. Setting `cancel` ends a
+    // back-off after a rate-limit response without sending the request again.
+    pub fn fetch(&self, body: &serde_json::Value, cancel: &AtomicBool) -> Result<String> {
+        if self.offline {
+            return Err(anyhow!("Not cached and offline mode is on"));
+        }
+        let api_key = self.api_key.as_deref().ok_or_else(|| anyhow!("Not cached and no API key is available"))?;
+        let url = self.url();
+        let mut attempt = 0;
+        let resp: serde_json::Value = loop {
+            match self.agent.post(&url).set("Authorization", &format!("Bearer {api_key}")).send_json(body.clone()) {
+                Ok(r) => break r.into_json().context("Invalid JSON response")?,
+                // Back off on rate-limit responses before giving up, for as long as the server asks
+                Err(ureq::Error::Status(429, r)) if attempt < 3 => {
+                    attempt += 1;
+                    let wait = r
+                        .header("Retry-After")
+                        .and_then(|s| s.trim().parse::<u64>().ok())
+                        .map_or(Duration::from_secs(5 * attempt), |s| Duration::from_secs(s).min(MAX_RETRY_AFTER));
+                    let start = Instant::now();
+                    while start.elapsed() < wait {
+                        if cancel.load(Ordering::Relaxed) {
+                            return Err(anyhow!("Cancelled"));
+                        }
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                }
+                Err(ureq::Error::Status(code, r)) => {
+                    let detail = r.into_string().unwrap_or_default();
+                    return Err(anyhow!("HTTP {code}: {}", detail.chars().take(200).collect::<String>()));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        };
+        let text = resp["choices"][0]["message"]["content"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Response has no message content"))?
+            .to_string();
+        if let Some(path) = cache_path(&url, body) {
+            let entry = CacheEntry { model: body["model"].as_str().unwrap_or("").to_string(), response: text.clone() };
+            // A failed cache write only costs a repeated call later
+            let _ = serde_json::to_vec(&entry).map(|bytes| write_file_creating_dirs(&path, &bytes));
+        }
+        Ok(text)
+    }
+}
+
+fn cache_dir() -> Option<PathBuf> {
+    config_dir().map(|d| d.join("cache").join("llm"))
+}
+
+fn cache_path(url: &str, body: &serde_json::Value) -> Option<PathBuf> {
+    // The API key is not part of the key: cached replies are shared across keys
+    let key = short_hash(&format!("{url}\n{body}"));
+    cache_dir().map(|d| d.join(format!("{key:016x}.json")))
+}
+
+// (entries, bytes)
+pub(crate) fn cache_usage() -> (usize, u64) {
+    let Some(dir) = cache_dir() else { return (0, 0) };
+    let Ok(entries) = fs::read_dir(dir) else { return (0, 0) };
+    entries
+        .flatten()
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .fold((0, 0), |(n, bytes), m| (n + 1, bytes + m.len()))
+}
+
+pub(crate) fn clear_cache() -> Result<()> {
+    match cache_dir() {
+        Some(dir) if dir.exists() => fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display())),
+        _ => Ok(()),
+    }
+}
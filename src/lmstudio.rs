@@ -0,0 +1,217 @@
+// "Import from LM Studio": lists the chats LM Studio keeps as JSON files in its data folder
+// and opens the selected one through the normal loading pipeline. The folder is scanned on a
+// thread, reading only the title, date and message count of each chat.
+
+use crate::{repaint, AppState};
+use eframe::egui::{self, RichText, ScrollArea};
+use serde::{de::IgnoredAny, Deserialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+const LMSTUDIO_WINDOW: &str = "Import from LM Studio";
+
+#[derive(Default)]
+pub(crate) struct LmStudioUi {
+    pub show_window: bool,
+    // None until the first scan picks one of the default locations
+    folder: Option<PathBuf>,
+    entries: Vec<ConversationEntry>,
+    scanned: bool,
+    // Scan in progress; dropping it discards the result
+    scanning: Option<mpsc::Receiver<Vec<ConversationEntry>>>,
+    filter: String,
+}
+
+struct ConversationEntry {
+    path: PathBuf,
+    title: String,
+    // Milliseconds since the Unix epoch
+    created: Option<i64>,
+    messages: usize,
+}
+
+// The parts of a conversation file the list shows; message contents are skipped, not parsed.
+#[derive(Deserialize)]
+struct ConversationHead {
+    name: Option<String>,
+    #[serde(rename = "createdAt")]
+    created_at: Option<IgnoredAny>,
+    #[serde(default)]
+    messages: Vec<MessageHead>,
+}
+
+#[derive(Deserialize)]
+struct MessageHead {
+    versions: Option<IgnoredAny>,
+}
+
+// Same test as `parse::is_lmstudio_conversation`, on the skimmed file
+fn is_conversation(head: &ConversationHead) -> bool {
+    head.messages.iter().any(|m| m.versions.is_some()) || (head.created_at.is_some() && head.name.is_some())
+}
+
+// `createdAt` is only needed as a number, so it is read on its own
+fn created_at(bytes: &[u8]) -> Option<i64> {
+    #[derive(Deserialize)]
+    struct Created {
+        #[serde(rename = "createdAt")]
+        created_at: Option<serde_json::Number>,
+    }
+    serde_json::from_slice::<Created>(bytes).ok()?.created_at?.as_i64()
+}
+
+// Where LM Studio stores conversations (0.3+ first, then the older cache location).
+fn default_folders() -> Vec<PathBuf> {
+    let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")).map(PathBuf::from)
+    else {
+        return Vec::new();
+    };
+    vec![home.join(".lmstudio").join("conversations"), home.join(".cache").join("lm-studio").join("conversations")]
+}
+
+fn scan(folder: &Path) -> Vec<ConversationEntry> {
+    let mut entries = Vec::new();
+    collect(folder, 0, &mut entries);
+    entries.sort_by(|a, b| b.created.cmp(&a.created).then_with(|| a.title.cmp(&b.title)));
+    entries
+}
+
+// Conversations may be grouped in user-created subfolders.
+fn collect(dir: &Path, depth: usize, out: &mut Vec<ConversationEntry>) {
+    let Ok(read_dir) = fs::read_dir(dir) else { return };
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth < 4 {
+                collect(&path, depth + 1, out);
+            }
+            continue;
+        }
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Ok(bytes) = fs::read(&path) else { continue };
+        let Ok(head) = serde_json::from_slice::<ConversationHead>(&bytes) else { continue };
+        if !is_conversation(&head) {
+            continue;
+        }
+        let title = head
+            .name
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or_else(|| path.file_name().unwrap_or_default().to_string_lossy().to_string());
+        out.push(ConversationEntry {
+            title,
+            created: head.created_at.and_then(|_| created_at(&bytes)),
+            messages: head.messages.len(),
+            path,
+        });
+    }
+}
+
+impl AppState {
+    pub(crate) fn lmstudio_window(&mut self, ctx: &egui::Context) {
+        let ui_state = &mut self.lmstudio;
+        if !ui_state.scanned {
+            if ui_state.folder.is_none() {
+                let folders = default_folders();
+                ui_state.folder = folders.iter().find(|f| f.is_dir()).or(folders.first()).cloned();
+            }
+            ui_state.entries.clear();
+            ui_state.scanning = ui_state.folder.clone().map(|folder| {
+                let (tx, rx) = mpsc::channel();
+                let ctx = ctx.clone();
+                thread::spawn(move || {
+                    let _ = tx.send(scan(&folder));
+                    ctx.request_repaint();
+                });
+                rx
+            });
+            ui_state.scanned = true;
+        }
+        if let Some(rx) = &ui_state.scanning {
+            match rx.try_recv() {
+                Ok(entries) => {
+                    ui_state.entries = entries;
+                    ui_state.scanning = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => ui_state.scanning = None,
+            }
+        }
+
+        let mut open = ui_state.show_window;
+        let mut picked = None;
+        let window = self.tool_window(LMSTUDIO_WINDOW).default_width(520.0).default_height(420.0).open(&mut open);
+        let fmt = self.fmt;
+        let ui_state = &mut self.lmstudio;
+        let resp = window.show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let folder = ui_state.folder.as_ref().map_or("(no folder)".to_string(), |f| f.display().to_string());
+                ui.label(RichText::new(folder).monospace().small());
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Choose folder…").clicked() {
+                    if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                        ui_state.folder = Some(dir);
+                        ui_state.scanned = false;
+                    }
+                }
+                if ui.button("Refresh").clicked() {
+                    ui_state.scanned = false;
+                }
+                ui.add(egui::TextEdit::singleline(&mut ui_state.filter).hint_text("Filter by title"));
+            });
+            ui.separator();
+
+            if ui_state.scanning.is_some() {
+                ui.horizontal(|ui| {
+                    repaint::busy(ui);
+                    ui.label(RichText::new("Looking for conversations…").italics().weak());
+                });
+                return;
+            }
+            if ui_state.entries.is_empty() {
+                ui.label(RichText::new("No LM Studio conversations found in this folder").italics().weak());
+                return;
+            }
+            let filter = ui_state.filter.to_lowercase();
+            ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                egui::Grid::new("lmstudio_conversations").num_columns(3).striped(true).show(ui, |ui| {
+                    for entry in &ui_state.entries {
+                        if !filter.is_empty() && !entry.title.to_lowercase().contains(&filter) {
+                            continue;
+                        }
+                        if ui.link(&entry.title).on_hover_text(entry.path.display().to_string()).clicked() {
+                            picked = Some(entry.path.clone());
+                        }
+                        ui.label(RichText::new(entry.created.map(|ms| fmt.datetime_ms(ms)).unwrap_or_default()).weak());
+                        ui.label(RichText::new(format!("{} messages", fmt.count(entry.messages))).weak());
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+        if let Some(r) = resp {
+            self.remember_window(LMSTUDIO_WINDOW, r.response.rect);
+        }
+        if let Some(path) = picked {
+            match self.load_path(&path) {
+                Ok(loaded) => {
+                    self.set_loaded(loaded);
+                    open = false;
+                }
+                Err(e) => self.errors.push(format!("Failed to load: {e}")),
+            }
+        }
+        self.lmstudio.show_window = open;
+        if !open {
+            // Rescan next time so new chats show up
+            self.lmstudio.scanned = false;
+            self.lmstudio.scanning = None;
+        }
+    }
+}
@@ -0,0 +1,190 @@
+// Locale-aware formatting of numbers and timestamps, shared by the UI and exports.
+// Only the conventions the viewer needs are modelled: separators, date order and clock.
+
+use crate::{save_settings, AppState};
+use chrono::{Local, TimeZone};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub(crate) struct LocaleSettings {
+    // BCP 47 tag such as "de-DE"; empty follows the system locale
+    pub locale: String,
+    pub clock: ClockFormat,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum ClockFormat {
+    // Whatever the locale uses
+    #[default]
+    Locale,
+    H12,
+    H24,
+}
+
+#[derive(Clone, Copy)]
+enum DateOrder {
+    Ymd,
+    Dmy,
+    Mdy,
+}
+
+#[derive(Clone, Copy)]
+struct Conventions {
+    decimal: char,
+    group: &'static str,
+    order: DateOrder,
+    date_sep: char,
+    h12: bool,
+}
+
+// (tag, display name, conventions). The first entry is the fallback.
+const LOCALES: &[(&str, &str, Conventions)] = &[
+    ("en-US", "English (US)", Conventions { decimal: '.', group: ",", order: DateOrder::Mdy, date_sep: '/', h12: true }),
+    ("en-GB", "English (UK)", Conventions { decimal: '.', group: ",", order: DateOrder::Dmy, date_sep: '/', h12: false }),
+    ("de-DE", "Deutsch", Conventions { decimal: ',', group: ".", order: DateOrder::Dmy, date_sep: '.', h12: false }),
+    ("fr-FR", "Français", Conventions { decimal: ',', group: "\u{202f}", order: DateOrder::Dmy, date_sep: '/', h12: false }),
+    ("es-ES", "Español", Conventions { decimal: ',', group: ".", order: DateOrder::Dmy, date_sep: '/', h12: false }),
+    ("it-IT", "Italiano", Conventions { decimal: ',', group: ".", order: DateOrder::Dmy, date_sep: '/', h12: false }),
+    ("pt-BR", "Português (Brasil)", Conventions { decimal: ',', group: ".", order: DateOrder::Dmy, date_sep: '/', h12: false }),
+    ("ru-RU", "Русский", Conventions { decimal: ',', group: "\u{a0}", order: DateOrder::Dmy, date_sep: '.', h12: false }),
+    ("ja-JP", "日本語", Conventions { decimal: '.', group: ",", order: DateOrder::Ymd, date_sep: '/', h12: false }),
+    ("zh-CN", "中文 (简体)", Conventions { decimal: '.', group: ",", order: DateOrder::Ymd, date_sep: '/', h12: false }),
+    ("ko-KR", "한국어", Conventions { decimal: '.', group: ",", order: DateOrder::Ymd, date_sep: '.', h12: true }),
+    ("sv-SE", "Svenska (ISO dates)", Conventions { decimal: ',', group: "\u{a0}", order: DateOrder::Ymd, date_sep: '-', h12: false }),
+];
+
+// Normalize "de_DE.UTF-8" / "de-DE" / "de" and find the closest known locale.
+fn conventions_for(tag: &str) -> Conventions {
+    let tag = tag.split('.').next().unwrap_or_default().replace('_', "-");
+    let lang = tag.split('-').next().unwrap_or_default();
+    LOCALES
+        .iter()
+        .find(|(t, _, _)| t.eq_ignore_ascii_case(&tag))
+        .or_else(|| LOCALES.iter().find(|(t, _, _)| t.split('-').next().is_some_and(|l| l.eq_ignore_ascii_case(lang))))
+        .unwrap_or(&LOCALES[0])
+        .2
+}
+
+// Resolved formatting rules; rebuilt whenever the locale settings change.
+#[derive(Clone, Copy)]
+pub(crate) struct Formatter {
+    conv: Conventions,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self::new(&LocaleSettings::default())
+    }
+}
+
+impl Formatter {
+    pub fn new(settings: &LocaleSettings) -> Self {
+        let mut conv = if settings.locale.is_empty() {
+            conventions_for(&sys_locale::get_locale().unwrap_or_default())
+        } else {
+            conventions_for(&settings.locale)
+        };
+        match settings.clock {
+            ClockFormat::Locale => {}
+            ClockFormat::H12 => conv.h12 = true,
+            ClockFormat::H24 => conv.h12 = false,
+        }
+        Self { conv }
+    }
+
+    // Integer with thousands separators: 12,345 / 12.345 / 12 345
+    pub fn int(&self, n: impl Into<i64>) -> String {
+        let n: i64 = n.into();
+        let digits = n.unsigned_abs().to_string();
+        let mut out = String::new();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push_str(self.conv.group);
+            }
+            out.push(c);
+        }
+        if n < 0 {
+            out.insert(0, '-');
+        }
+        out
+    }
+
+    pub fn count(&self, n: usize) -> String {
+        self.int(i64::try_from(n).unwrap_or(i64::MAX))
+    }
+
+    pub fn float(&self, x: f64, decimals: usize) -> String {
+        let s = format!("{:.*}", decimals, x.abs());
+        let (whole, frac) = s.split_once('.').unwrap_or((&s, ""));
+        let mut out = self.int(whole.parse::<i64>().unwrap_or(0));
+        if x < 0.0 && s.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            out.insert(0, '-');
+        }
+        if !frac.is_empty() {
+            out.push(self.conv.decimal);
+            out.push_str(frac);
+        }
+        out
+    }
+
+    pub fn bytes(&self, n: u64) -> String {
+        match n {
+            0..=1023 => format!("{} B", self.int(n as i64)),
+            1024..=1_048_575 => format!("{} KB", self.float(n as f64 / 1024.0, 1)),
+            _ => format!("{} MB", self.float(n as f64 / 1_048_576.0, 1)),
+        }
+    }
+
+    pub fn date(&self, d: impl chrono::Datelike) -> String {
+        let sep = self.conv.date_sep;
+        let (y, m, day) = (d.year(), d.month(), d.day());
+        match self.conv.order {
+            DateOrder::Ymd => format!("{y:04}{sep}{m:02}{sep}{day:02}"),
+            DateOrder::Dmy => format!("{day:02}{sep}{m:02}{sep}{y:04}"),
+            DateOrder::Mdy => format!("{m:02}{sep}{day:02}{sep}{y:04}"),
+        }
+    }
+
+    pub fn time(&self, t: impl chrono::Timelike) -> String {
+        if self.conv.h12 {
+            let (pm, h) = t.hour12();
+            format!("{h}:{:02} {}", t.minute(), if pm { "PM" } else { "AM" })
+        } else {
+            format!("{:02}:{:02}", t.hour(), t.minute())
+        }
+    }
+
+    // Date and time in the local time zone from Unix milliseconds.
+    pub fn datetime_ms(&self, ms: i64) -> String {
+        match Local.timestamp_millis_opt(ms).single() {
+            Some(t) => format!("{} {}", self.date(t), self.time(t)),
+            None => String::new(),
+        }
+    }
+}
+
+impl AppState {
+    pub(crate) fn locale_menu(&mut self, ui: &mut egui::Ui) {
+        let locale = &mut self.settings.locale;
+        let mut changed = false;
+        changed |= ui.radio_value(&mut locale.locale, String::new(), "System").changed();
+        for (tag, name, _) in LOCALES {
+            changed |= ui.radio_value(&mut locale.locale, tag.to_string(), *name).changed();
+        }
+        ui.separator();
+        changed |= ui.radio_value(&mut locale.clock, ClockFormat::Locale, "Locale clock").changed();
+        changed |= ui.radio_value(&mut locale.clock, ClockFormat::H12, "12-hour").changed();
+        changed |= ui.radio_value(&mut locale.clock, ClockFormat::H24, "24-hour").changed();
+        ui.separator();
+        let example = format!("{} • {}", self.fmt.float(1234567.891, 2), self.fmt.datetime_ms(1_700_000_000_000));
+        ui.label(egui::RichText::new(example).weak());
+        if changed {
+            self.fmt = Formatter::new(&self.settings.locale);
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+    }
+}
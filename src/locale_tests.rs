@@ -0,0 +1,50 @@
+// `locale`: separators, byte units, date order and clock of the formatter, as they end up in
+// the UI and in exports.
+
+use crate::locale::{ClockFormat, Formatter, LocaleSettings};
+use chrono::{NaiveDate, NaiveTime};
+
+fn formatter(locale: &str) -> Formatter {
+    Formatter::new(&LocaleSettings { locale: locale.to_string(), clock: ClockFormat::Locale })
+}
+
+#[test]
+fn numbers_bytes_and_dates_follow_the_locale() {
+    let date = NaiveDate::from_ymd_opt(2024, 3, 9).unwrap();
+    let time = NaiveTime::from_hms_opt(14, 5, 0).unwrap();
+    let summary = |f: Formatter| {
+        [
+            f.int(1_234_567),
+            f.int(-999),
+            f.count(0),
+            f.float(-1234.5, 2),
+            f.float(-0.001, 2),
+            f.bytes(1000),
+            f.bytes(1536),
+            f.bytes(5 * 1_048_576),
+            f.date(date),
+            f.time(time),
+        ]
+    };
+    assert_eq!(
+        summary(formatter("en-US")),
+        ["1,234,567", "-999", "0", "-1,234.50", "0.00", "1,000 B", "1.5 KB", "5.0 MB", "03/09/2024", "2:05 PM"]
+    );
+    assert_eq!(
+        summary(formatter("ja-JP")),
+        ["1,234,567", "-999", "0", "-1,234.50", "0.00", "1,000 B", "1.5 KB", "5.0 MB", "2024/03/09", "14:05"]
+    );
+    assert_eq!(
+        summary(formatter("de-DE")),
+        ["1.234.567", "-999", "0", "-1.234,50", "0,00", "1.000 B", "1,5 KB", "5,0 MB", "09.03.2024", "14:05"]
+    );
+
+    // POSIX-style tags and bare languages find the same conventions; unknown ones fall back to en-US
+    assert_eq!(formatter("de_DE.UTF-8").float(1234.5, 1), "1.234,5");
+    assert_eq!(formatter("ja").date(date), "2024/03/09");
+    assert_eq!(formatter("xx-YY").int(12_345), "12,345");
+    assert_eq!(formatter("fr-FR").int(12_345), "12\u{202f}345");
+
+    let h12 = Formatter::new(&LocaleSettings { locale: "de-DE".to_string(), clock: ClockFormat::H12 });
+    assert_eq!(h12.time(time), "2:05 PM");
+}
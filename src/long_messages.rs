@@ -0,0 +1,66 @@
+// Messages of tens of thousands of characters (pasted files, dumped tool output) were laid out in
+// full whenever they were on screen, and scrolling past one stuttered. Past LONG_CHARS only about
+// the first PREVIEW_CHARS are shown, up to a line break, with "Show full message" below them.
+// Copying and exporting always use the whole text.
+
+use eframe::egui::{self, Id, RichText};
+use std::borrow::Cow;
+
+const LONG_CHARS: usize = 10_000;
+const PREVIEW_CHARS: usize = 2_000;
+
+fn expanded_id(viewer_key: &str) -> Id {
+    Id::new(("show-full", viewer_key))
+}
+
+fn expanded(ctx: &egui::Context, viewer_key: &str) -> bool {
+    ctx.data(|d| d.get_temp(expanded_id(viewer_key))).unwrap_or(false)
+}
+
+fn is_long(text: &str) -> bool {
+    text.len() > LONG_CHARS && text.char_indices().nth(LONG_CHARS).is_some()
+}
+
+// The start of a long `text`, cut at a line break near PREVIEW_CHARS; a code block cut in two is
+// closed again.
+pub(crate) fn preview(text: &str) -> Option<Cow<'_, str>> {
+    if !is_long(text) {
+        return None;
+    }
+    let (cut, _) = text.char_indices().nth(PREVIEW_CHARS)?;
+    let cut = text[..cut].rfind('\n').filter(|&n| n >= cut * 3 / 4).unwrap_or(cut);
+    let head = text[..cut].trim_end();
+    let fences = head.lines().filter(|l| l.trim_start().starts_with("```")).count();
+    Some(if fences % 2 == 1 {
+        Cow::Owned(format!("{head}\n```"))
+    } else {
+        Cow::Borrowed(head)
+    })
+}
+
+// What to render of `text` for the message shown with `viewer_key`: the preview until it is expanded.
+pub(crate) fn shown<'a>(ctx: &egui::Context, viewer_key: &str, text: &'a str) -> Cow<'a, str> {
+    if expanded(ctx, viewer_key) {
+        return Cow::Borrowed(text);
+    }
+    preview(text).unwrap_or(Cow::Borrowed(text))
+}
+
+// "Show full message" under a preview, "Show less" under an expanded long message.
+pub(crate) fn toggle_ui(ui: &mut egui::Ui, viewer_key: &str, text: &str) {
+    if !is_long(text) {
+        return;
+    }
+    let open = expanded(ui.ctx(), viewer_key);
+    let label = if open { "Show less" } else { "… Show full message" };
+    ui.add_space(4.0);
+    let resp = ui.link(RichText::new(label).small());
+    let resp = if open {
+        resp
+    } else {
+        resp.on_hover_text(format!("{} characters in all", text.chars().count()))
+    };
+    if resp.clicked() {
+        ui.data_mut(|d| d.insert_temp(expanded_id(viewer_key), !open));
+    }
+}
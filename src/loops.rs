@@ -0,0 +1,113 @@
+// Agents stuck in a loop call the same tool, or the same few tools in turn, with the same
+// arguments over and over. Tool calls are compared by name and by their arguments with numbers
+// and ids ignored, and a cycle of up to three calls repeated at least MIN_CYCLES times in a row
+// is reported as a probable loop. Polling that eventually succeeds is reported too; the Lint pane
+// says "possible".
+
+use crate::{is_tool_card, repeats::hash_words, Msg, Role};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+const MIN_CYCLES: usize = 4;
+// Longest cycle looked for, in calls
+const MAX_PERIOD: usize = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Loop {
+    // Messages from the first call to the last call's result
+    pub start: usize,
+    pub end: usize,
+    // Tools called in one cycle, in order
+    pub tools: Vec<String>,
+    pub cycles: usize,
+}
+
+struct Call {
+    message: usize,
+    name: String,
+    key: u64,
+}
+
+// Calls written by `tool_call_markdown`: a `**Tool call: name**` heading and a code block with
+// the arguments.
+fn calls_in(content: &str) -> Vec<(String, &str)> {
+    let mut calls = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("**Tool call: ") {
+        rest = &rest[start + "**Tool call: ".len()..];
+        let Some(end) = rest.find("**") else { break };
+        let name = rest[..end].trim().to_string();
+        rest = &rest[end + 2..];
+        let body = rest.trim_start();
+        let arguments = match body.strip_prefix("```").and_then(|b| b.find("```").map(|e| &b[..e])) {
+            Some(arguments) => {
+                rest = &body[arguments.len() + 6..];
+                arguments
+            }
+            None => "",
+        };
+        calls.push((name, arguments));
+    }
+    calls
+}
+
+fn calls(messages: &[Msg]) -> Vec<Call> {
+    let mut calls = Vec::new();
+    for (i, msg) in messages.iter().enumerate() {
+        if !matches!(msg.role, Role::Assistant) {
+            continue;
+        }
+        for (name, arguments) in calls_in(&msg.content) {
+            let mut hasher = DefaultHasher::new();
+            name.hash(&mut hasher);
+            hash_words(arguments, &mut hasher);
+            calls.push(Call {
+                message: i,
+                name,
+                key: hasher.finish(),
+            });
+        }
+    }
+    calls
+}
+
+pub(crate) fn find_loops(messages: &[Msg]) -> Vec<Loop> {
+    let calls = calls(messages);
+    let mut covered = vec![false; calls.len()];
+    let mut loops = Vec::new();
+    // Shortest cycles first, so `a a a a …` is not also reported as `a a` repeated
+    for period in 1..=MAX_PERIOD {
+        let mut i = period;
+        while i < calls.len() {
+            if calls[i].key != calls[i - period].key {
+                i += 1;
+                continue;
+            }
+            let first = i - period;
+            while i < calls.len() && calls[i].key == calls[i - period].key {
+                i += 1;
+            }
+            let cycles = (i - first) / period;
+            let last = first + cycles * period - 1;
+            if cycles < MIN_CYCLES || covered[first..=last].iter().any(|c| *c) {
+                continue;
+            }
+            covered[first..=last].iter_mut().for_each(|c| *c = true);
+            // Through the results of the last call
+            let mut end = calls[last].message;
+            while messages.get(end + 1).is_some_and(is_tool_card) {
+                end += 1;
+            }
+            loops.push(Loop {
+                start: calls[first].message,
+                end,
+                tools: calls[first..first + period].iter().map(|c| c.name.clone()).collect(),
+                cycles,
+            });
+        }
+    }
+    loops.sort_by_key(|l| l.start);
+    loops
+}
@@ -1,8 +1,117 @@
 use anyhow::{anyhow, Context, Result};
 use eframe::{egui, egui::{Align, Align2, Color32, Frame, Id, Label, Layout, RichText, Rounding, ScrollArea, Vec2}};
-use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
+use egui_commonmark::CommonMarkCache;
 use serde::{Deserialize, Serialize};
-use std::{fs, path::Path};
+use std::{collections::BTreeMap, fs, ops::Range, path::{Path, PathBuf}};
+
+mod archive;
+mod backup;
+mod branches;
+mod caches;
+mod charset;
+mod clipboard;
+mod code_folds;
+mod csv_import;
+#[cfg(test)]
+mod csv_import_tests;
+mod display;
+mod dock;
+mod escapes;
+mod folder_scan;
+#[cfg(test)]
+mod folder_scan_tests;
+mod folds;
+mod fonts;
+mod health;
+mod html;
+#[cfg(test)]
+mod html_tests;
+#[cfg(test)]
+mod export_tests;
+mod incremental;
+mod judge;
+mod layout_cache;
+mod line_errors;
+mod lint;
+mod lint_report;
+#[cfg(test)]
+mod lint_tests;
+mod llm;
+mod lmstudio;
+mod locale;
+#[cfg(test)]
+mod locale_tests;
+mod long_messages;
+mod loops;
+mod mapping;
+#[cfg(test)]
+mod mapping_tests;
+mod parse;
+#[cfg(test)]
+mod parse_tests;
+mod paste_prompt;
+mod paging;
+mod patch;
+#[cfg(test)]
+mod patch_tests;
+mod present;
+mod provenance;
+mod providers;
+mod read_only;
+#[cfg(test)]
+mod read_only_tests;
+mod reading;
+mod reasoning;
+mod repaint;
+mod repeats;
+mod roles;
+mod row_heights;
+mod scripting;
+mod snapshot;
+mod start_position;
+mod tools;
+mod uri;
+#[cfg(test)]
+mod uri_tests;
+mod validate;
+#[cfg(test)]
+mod validate_tests;
+
+use archive::ArchiveUi;
+use backup::Backup;
+use branches::MessageTree;
+use clipboard::ClipboardHistory;
+use csv_import::CsvImportUi;
+use display::{DisplayOptions, MessageLayout};
+use dock::Pane;
+use egui_dock::DockState;
+use escapes::Escapes;
+use folder_scan::{FolderLoad, FolderSettings};
+use folds::Folds;
+use fonts::{FontSettings, FontsUi, TextSpacing};
+use html::HtmlPolicy;
+use incremental::{BackgroundLoad, IncrementalLoad};
+use judge::{JudgeAnnotation, JudgeSettings, JudgeState};
+use llm::NetworkSettings;
+use lmstudio::LmStudioUi;
+use mapping::FormatMapping;
+use paste_prompt::PastePromptUi;
+use patch::PatchUi;
+use parse::{load_from_bytes, LineErrors, ParseOptions};
+use present::Presentation;
+use provenance::Checksum;
+use scripting::Scripts;
+use locale::{Formatter, LocaleSettings};
+use providers::{ProviderProfile, ProvidersUi};
+use reading::ReadingCache;
+use roles::{RoleAlias, RoleScales};
+use caches::Caches;
+use health::HealthCache;
+use lint::Lint;
+use repeats::Repeats;
+use row_heights::RowHeights;
+use start_position::StartPosition;
+use tools::{ExportHooks, ExternalTool, ToolsUi};
 
 fn app_icon() -> egui::IconData {
     // assets/icon.png は 256px 以上推奨（透過PNG）
@@ -14,6 +123,15 @@ fn app_icon() -> egui::IconData {
 }
 
 fn main() -> Result<()> {
+    // `llm_log_viewer validate FILE…` prints a report and exits without opening a window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|a| a == "validate") {
+        std::process::exit(validate::run(&args[1..]));
+    }
+    // `llm_log_viewer lint FILE…` does the same with the Lint pane's checks and health scores
+    if args.first().is_some_and(|a| a == "lint") {
+        std::process::exit(lint_report::run(&args[1..]));
+    }
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size(Vec2::new(900.0, 700.0))
@@ -26,8 +144,27 @@ fn main() -> Result<()> {
         native_options,
         Box::new(|cc| {
             // Default visuals
-            let app = AppState::default();
+            let settings = load_settings();
+            let mut app = AppState {
+                saved_layout: layout_json(&settings.layout),
+                fmt: Formatter::new(&settings.locale),
+                settings,
+                ..Default::default()
+            };
+            app.scripts.reload();
+            // `--strict` turns strict parsing on for this run without saving it
+            let (flags, args): (Vec<String>, Vec<String>) = std::env::args().skip(1).partition(|a| a.starts_with("--"));
+            app.strict_session = flags.iter().any(|f| f == "--strict");
+            // `--read-only` opens everything read-only, whatever its location
+            app.read_only_session = flags.iter().any(|f| f == "--read-only");
+            // `llm_log_viewer chat.jsonl#msg-42` or an llmlog:// link opens the file at that message
+            if let Some(arg) = args.first() {
+                app.open_reference(arg);
+            }
             app.apply_theme(cc.egui_ctx.clone());
+            app.apply_fonts(&cc.egui_ctx);
+            // Inline images in messages are decoded from `bytes://` URIs
+            egui_extras::install_image_loaders(&cc.egui_ctx);
             Box::new(app)
         }),
     ) {
@@ -36,24 +173,126 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+// Content width in focus mode, in points at text scale 1.0
+const FOCUS_MEASURE: f32 = 760.0;
+// Pauses at least this long between turns get a "— 2h 14m later —" separator
+const IDLE_GAP_MS: i64 = 30 * 60 * 1000;
+// Lines of a user message measured to size its bubble
+const MEASURED_LINES: usize = 8;
+
 struct AppState {
     theme_dark: bool,
     text_scale: f32,
     file_name: Option<String>,
+    // File the conversation was read from; None for folders, archives, drops and pastes
+    source: Option<PathBuf>,
+    // SHA-256 of `source`, shown in the export menus and written in export footers
+    checksum: Checksum,
+    // Why the shown conversation is read-only (see read_only.rs); None when it is not
+    read_only: Option<String>,
+    // `--read-only` was given: everything opened in this run is read-only
+    read_only_session: bool,
+    // `--strict` was given: strict parsing for this run, whatever the setting says
+    strict_session: bool,
+    // Log format the file was read as
+    format: Option<&'static str>,
     system: Option<String>,
     messages: Vec<Msg>,
+    // Pass/fail of the shown conversation when it comes from an eval run
+    outcome: Option<EvalOutcome>,
     errors: Vec<String>,
+    // JSONL lines skipped while loading, listed under the error bar
+    failed_lines: LineErrors,
+    // All conversations of a multi-conversation file (e.g. ChatGPT export); empty otherwise
+    sessions: Vec<Session>,
+    active_session: usize,
+    // Sessions were produced by "Split at gaps" rather than by the file itself
+    split_applied: bool,
+
+    settings: Settings,
+    // Number/date formatting for the selected locale
+    fmt: Formatter,
+    judge: JudgeState,
+    providers_ui: ProvidersUi,
+    lmstudio: LmStudioUi,
+    paste_prompt: PastePromptUi,
+    patch: PatchUi,
+    csv: CsvImportUi,
+    archive: ArchiveUi,
+    present: Presentation,
+    scripts: Scripts,
+    tools: ToolsUi,
+    // Double-encoded text detection and the per-file decode toggle
+    escapes: Escapes,
+    // Large JSONL file still being read
+    loading: Option<IncrementalLoad>,
+    // Large file of another format being parsed; the conversation shown stays until it is done
+    background: Option<BackgroundLoad>,
+    // Folder being scanned and loaded
+    folder: Option<FolderLoad>,
+    // Alternative branches of the shown conversation
+    tree: Option<MessageTree>,
 
     // UI helpers
+    // Distraction-free reading: everything but the conversation is hidden
+    focus_mode: bool,
+    // 1-based index of the message at the top of the conversation viewport
+    reading_position: Option<usize>,
+    // Message to bring to the top of the viewport on the next frame (from a reference)
+    scroll_to_message: Option<usize>,
+    // Scroll to the last message on the next frame (opening at the latest message)
+    scroll_to_end: bool,
+    // Word counts behind the reading-time estimates
+    reading: ReadingCache,
+    // Recently copied text, shown in the Clipboard pane
+    clipboard: ClipboardHistory,
+    // Measured message heights, so only messages on screen are drawn
+    row_heights: RowHeights,
+    repeats: Repeats,
+    // Messages folded to one line
+    folds: Folds,
+    // Messages shown of a conversation longer than a page; empty for the first page
+    page: Range<usize>,
+    // Installed fonts listed in the Fonts menu
+    fonts: FontsUi,
+    // Result of "Register llmlog:// links"
+    links_status: Option<Result<String, String>>,
+    // Bumped by "Reset layout" so egui forgets remembered panel/window geometry
+    layout_epoch: u32,
+    // Serialized layout as last saved, to detect changes
+    saved_layout: String,
+    // Set when a pane or window edit may have changed the layout; checked once the pointer is up
+    layout_dirty: bool,
     scroll_area_key: String,
     show_drop_overlay: bool,
     md_cache: CommonMarkCache,
+    caches: Caches,
+    lint: Lint,
+    health: HealthCache,
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
-struct RawMsg {
-    role: String,
-    content: String,
+#[derive(Clone, Debug)]
+struct Attachment {
+    uri: String,
+    bytes: std::sync::Arc<[u8]>,
+}
+
+// Decode a base64 image into an attachment plus the Markdown that displays it.
+// Non-image data only gets a placeholder.
+fn inline_attachment(mime: &str, data: &str) -> (String, Option<Attachment>) {
+    use base64::Engine as _;
+    let ext = match mime {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpeg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        _ => return (format!("[attachment: {mime}]"), None),
+    };
+    let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(data.trim()) else {
+        return (format!("[image: {mime}, invalid data]"), None);
+    };
+    let uri = format!("bytes://attachment/{:016x}.{ext}", short_hash(data));
+    (format!("![{mime}]({uri})"), Some(Attachment { uri, bytes: bytes.into() }))
 }
 
 #[derive(Clone, Debug)]
@@ -68,14 +307,73 @@ enum Role {
 struct Msg {
     role: Role,
     content: String,
+    name: Option<String>,
+    // Unix milliseconds
+    timestamp: Option<i64>,
+    depth: usize,
+    // Palette slot of the named speaker (multi-agent logs), in order of first appearance
+    agent: Option<usize>,
+    detail: Option<String>,
+    attachments: Vec<Attachment>,
+}
+
+// Result of an evaluation run (promptfoo), shown above the conversation.
+#[derive(Clone, Debug)]
+struct EvalOutcome {
+    pass: bool,
+    summary: String,
+}
+
+// A message within a file, as written by "Copy reference": `chat.jsonl#msg-42`, or
+// `export.json#s3-msg-7` for the 7th message of the 3rd conversation. Numbers are 1-based.
+#[derive(Clone, Copy, Debug)]
+struct MessageRef {
+    session: Option<usize>,
+    message: usize,
+}
+
+impl MessageRef {
+    fn parse(fragment: &str) -> Option<Self> {
+        let (session, message) = match fragment.split_once("-msg-") {
+            Some((session, message)) => (Some(session.strip_prefix('s')?.parse().ok()?), message),
+            None => (None, fragment.strip_prefix("msg-")?),
+        };
+        Some(Self { session, message: message.parse().ok()? })
+    }
+}
+
+impl std::fmt::Display for MessageRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.session {
+            Some(s) => write!(f, "s{s}-msg-{}", self.message),
+            None => write!(f, "msg-{}", self.message),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Session {
+    title: String,
+    system: Option<String>,
+    messages: Vec<Msg>,
+    outcome: Option<EvalOutcome>,
+    tree: Option<MessageTree>,
 }
 
 #[derive(Default, Clone)]
 struct Loaded {
     file_name: Option<String>,
+    // File the conversation was read from, when it was read from a file of its own
+    source: Option<PathBuf>,
     system: Option<String>,
     messages: Vec<Msg>,
     errors: Vec<String>,
+    sessions: Vec<Session>,
+    outcome: Option<EvalOutcome>,
+    tree: Option<MessageTree>,
+    // Name of the detected log format
+    format: Option<&'static str>,
+    failed_lines: LineErrors,
 }
 
 impl Default for AppState {
@@ -84,12 +382,58 @@ impl Default for AppState {
             theme_dark: true,
             text_scale: 1.0,
             file_name: None,
+            source: None,
+            checksum: Checksum::default(),
+            read_only: None,
+            read_only_session: false,
+            strict_session: false,
+            format: None,
             system: None,
             messages: vec![],
+            outcome: None,
             errors: vec![],
+            failed_lines: LineErrors::default(),
+            sessions: vec![],
+            active_session: 0,
+            split_applied: false,
+            settings: Settings::default(),
+            fmt: Formatter::default(),
+            judge: JudgeState::default(),
+            providers_ui: ProvidersUi::default(),
+            lmstudio: LmStudioUi::default(),
+            paste_prompt: PastePromptUi::default(),
+            patch: PatchUi::default(),
+            csv: CsvImportUi::default(),
+            archive: ArchiveUi::default(),
+            present: Presentation::default(),
+            scripts: Scripts::default(),
+            tools: ToolsUi::default(),
+            escapes: Escapes::default(),
+            loading: None,
+            background: None,
+            folder: None,
+            tree: None,
+            focus_mode: false,
+            reading_position: None,
+            scroll_to_message: None,
+            scroll_to_end: false,
+            reading: ReadingCache::default(),
+            clipboard: ClipboardHistory::default(),
+            row_heights: RowHeights::default(),
+            repeats: Repeats::default(),
+            folds: Folds::default(),
+            page: 0..0,
+            fonts: FontsUi::default(),
+            links_status: None,
+            layout_epoch: 0,
+            saved_layout: String::new(),
+            layout_dirty: false,
             scroll_area_key: String::new(),
             show_drop_overlay: false,
             md_cache: CommonMarkCache::default(),
+            caches: Caches::default(),
+            lint: Lint::default(),
+            health: HealthCache::default(),
         }
     }
 }
@@ -103,39 +447,731 @@ impl AppState {
         }
     }
 
-    fn set_loaded(&mut self, loaded: Loaded) {
+    fn set_loaded(&mut self, mut loaded: Loaded) {
+        self.remember_position();
+        self.reading_position = None;
+        // Replaces whatever was still loading
+        self.loading = None;
+        self.background = None;
+        self.folder = None;
+        self.scripts.transform_loaded(&mut loaded);
+        roles::apply_aliases(&mut loaded.messages, &self.settings.role_aliases);
+        for session in &mut loaded.sessions {
+            roles::apply_aliases(&mut session.messages, &self.settings.role_aliases);
+        }
         self.file_name = loaded.file_name;
+        self.read_only = self.read_only_reason(loaded.source.as_deref());
+        self.source = loaded.source;
+        self.format = loaded.format;
         self.system = loaded.system;
         self.messages = loaded.messages;
+        self.outcome = loaded.outcome;
+        self.tree = loaded.tree;
         self.errors = loaded.errors;
+        self.failed_lines = loaded.failed_lines;
+        self.sessions = loaded.sessions;
+        self.reading.clear();
+        self.health.clear();
+        self.row_heights.clear();
+        self.repeats.clear();
+        self.folds.clear();
+        self.caches.mark_stale();
+        self.page = 0..0;
+        self.active_session = 0;
+        self.split_applied = false;
+        if !self.sessions.is_empty() {
+            self.open_pane(Pane::Sessions);
+        }
+        self.reset_escapes(true);
+        self.judge.reset_for(self.system.as_deref(), &self.messages);
+        self.reset_badges();
         // Reset scroll position by changing the scroll area id key
         self.scroll_area_key = self
             .file_name
             .clone()
             .unwrap_or_else(|| "__empty__".to_string());
+        self.apply_start_position();
+    }
+
+    // Floating tool window, restored at its saved position/size (see LayoutSettings).
+    fn tool_window<'open>(&self, title: &str) -> egui::Window<'open> {
+        let mut window = egui::Window::new(title).id(Id::new(title).with(self.layout_epoch)).collapsible(true);
+        if let Some([x, y, w, h]) = self.settings.layout.windows.get(title) {
+            window = window.default_pos(egui::pos2(*x, *y)).default_size(egui::vec2(*w, *h));
+        }
+        window
+    }
+
+    fn remember_window(&mut self, title: &str, rect: egui::Rect) {
+        let r = [rect.min.x, rect.min.y, rect.width(), rect.height()];
+        if self.settings.layout.windows.get(title) != Some(&r) {
+            self.settings.layout.windows.insert(title.to_string(), r);
+            self.layout_dirty = true;
+        }
+    }
+
+    // Save panel/window geometry once a drag/resize has finished.
+    fn persist_layout_if_changed(&mut self, ctx: &egui::Context) {
+        if !self.layout_dirty || ctx.input(|i| i.pointer.any_down()) {
+            return;
+        }
+        self.layout_dirty = false;
+        let layout = layout_json(&self.settings.layout);
+        if layout == self.saved_layout {
+            return;
+        }
+        self.saved_layout = layout;
+        if let Err(e) = save_settings(&self.settings) {
+            self.errors.push(format!("Failed to save settings: {e}"));
+        }
+    }
+
+    fn select_session(&mut self, index: usize) {
+        if index >= self.sessions.len() {
+            return;
+        }
+        self.remember_position();
+        self.reading_position = None;
+        self.page = 0..0;
+        let session = &self.sessions[index];
+        self.system = session.system.clone();
+        self.messages = session.messages.clone();
+        self.outcome = session.outcome.clone();
+        self.tree = session.tree.clone();
+        self.active_session = index;
+        self.folds.clear();
+        self.row_heights.clear();
+        self.caches.mark_stale();
+        self.reset_escapes(false);
+        self.judge.reset_for(self.system.as_deref(), &self.messages);
+        self.reset_badges();
+        self.scroll_area_key = format!("{}#{}", self.file_name.as_deref().unwrap_or("__empty__"), index);
+        self.apply_start_position();
+    }
+
+    // Each conversation in the files found in a folder becomes an entry in the Sessions pane, titled
+    // by the file's path in the folder. Ignored and excluded paths were left out (see folder_scan.rs);
+    // spreadsheets and archives need their own dialogs and are skipped.
+    fn show_folder(&mut self, dir: &Path, files: Vec<(PathBuf, Result<Loaded>)>, mut errors: Vec<String>) {
+        let mut sessions: Vec<Session> = Vec::new();
+        for (path, loaded) in files {
+            // Relative, so files of the same name in different subfolders can be told apart
+            let name = path.strip_prefix(dir).unwrap_or(&path).to_string_lossy().to_string();
+            let loaded = match loaded {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    errors.push(format!("{name}: {e}"));
+                    continue;
+                }
+            };
+            // Text files that are not logs come back empty; leave them out quietly
+            if loaded.messages.is_empty() && loaded.sessions.is_empty() {
+                continue;
+            }
+            errors.extend(loaded.errors.iter().map(|e| format!("{name}: {e}")));
+            if !loaded.failed_lines.is_empty() {
+                errors.push(format!("{name}: {} JSONL line(s) failed to parse", loaded.failed_lines.count));
+            }
+            if loaded.sessions.is_empty() {
+                sessions.push(Session { title: name, system: loaded.system, messages: loaded.messages, outcome: loaded.outcome, tree: loaded.tree });
+            } else {
+                sessions.extend(loaded.sessions.into_iter().map(|s| Session { title: format!("{name} · {}", s.title), ..s }));
+            }
+        }
+        if sessions.is_empty() {
+            errors.push("No conversations in this folder".to_string());
+        }
+        let first = sessions.first().cloned();
+        if sessions.len() == 1 {
+            sessions.clear();
+        }
+        let (system, messages, outcome, tree) =
+            first.map(|s| (s.system, s.messages, s.outcome, s.tree)).unwrap_or_default();
+        self.set_loaded(Loaded {
+            file_name: dir.file_name().map(|s| s.to_string_lossy().to_string()),
+            source: None,
+            system,
+            messages,
+            errors,
+            sessions,
+            outcome,
+            tree,
+            // Files in a folder may each be in a different format
+            format: None,
+            failed_lines: LineErrors::default(),
+        });
+        self.read_only = self.read_only_reason(Some(dir));
+    }
+
+    // Leading developer messages folded into the system card ("Merge developer into system").
+    fn merged_developer_count(&self) -> usize {
+        if !self.settings.merge_developer {
+            return 0;
+        }
+        self.messages.iter().take_while(|m| is_developer(m)).count()
+    }
+
+    // Title and text of the card above the conversation, with the first `developer` messages appended.
+    fn system_card(&self, developer: usize) -> Option<(String, String)> {
+        let mut parts: Vec<&str> = self.system.iter().map(String::as_str).collect();
+        parts.extend(self.messages[..developer].iter().map(|m| m.content.as_str()));
+        let title = match (self.system.is_some(), developer > 0) {
+            (true, true) => "System · Developer",
+            (false, true) => "Developer",
+            _ => "System",
+        };
+        (!parts.is_empty()).then(|| (title.to_string(), parts.join("\n\n---\n\n")))
+    }
+
+    // "Copy reference" text for a message of the shown conversation. After "Split at gaps" the
+    // number counts from the start of the unsplit file, which is what opening it again shows.
+    fn message_reference(&self, index: usize) -> String {
+        let file = self.file_name.as_deref().unwrap_or("conversation");
+        let reference = if self.sessions.is_empty() {
+            MessageRef { session: None, message: index + 1 }
+        } else if self.split_applied {
+            let offset: usize = self.sessions[..self.active_session].iter().map(|s| s.messages.len()).sum();
+            MessageRef { session: None, message: offset + index + 1 }
+        } else {
+            MessageRef { session: Some(self.active_session + 1), message: index + 1 }
+        };
+        format!("{file}#{reference}")
+    }
+
+    // Strict parsing from the saved setting or `--strict`; passed to every load.
+    fn parse_options(&self) -> ParseOptions {
+        ParseOptions { strict: self.settings.strict_parsing || self.strict_session }
+    }
+
+    // Open `path`, `path#msg-42` / `path#s3-msg-7` or `llmlog://open?path=…&msg=42` and
+    // scroll to the referenced message.
+    fn open_reference(&mut self, arg: &str) {
+        let (path, reference) = if arg.starts_with(&format!("{}:", uri::URI_SCHEME)) {
+            match uri::parse_open_uri(arg) {
+                Ok(target) => target,
+                Err(e) => {
+                    self.errors.push(e.to_string());
+                    return;
+                }
+            }
+        } else {
+            match arg.rsplit_once('#') {
+                Some((path, fragment)) if !Path::new(arg).exists() => (PathBuf::from(path), MessageRef::parse(fragment)),
+                _ => (PathBuf::from(arg), None),
+            }
+        };
+        match self.load_path(&path) {
+            Ok(loaded) => self.set_loaded(loaded),
+            Err(e) => {
+                self.errors.push(format!("Failed to load: {e}"));
+                return;
+            }
+        }
+        if let Some(r) = reference {
+            if let Some(session) = r.session.filter(|_| !self.sessions.is_empty()) {
+                self.select_session(session.saturating_sub(1));
+            }
+            if r.message == 0 || r.message > self.messages.len() {
+                self.errors.push(format!("No message {} in this conversation", r.message));
+            } else {
+                self.scroll_to_message = Some(r.message - 1);
+                self.scroll_to_end = false;
+            }
+        }
+    }
+
+    // Break a single long log into sessions wherever the pause between turns exceeds the threshold.
+    fn split_at_gaps(&mut self) {
+        // The pieces keep the text as loaded; select_session decodes again
+        self.restore_escapes();
+        let gap_ms = i64::from(self.settings.split_gap_minutes) * 60_000;
+        let mut pieces: Vec<Vec<Msg>> = vec![Vec::new()];
+        let mut last_time = None;
+        for msg in &self.messages {
+            if let Some(t) = msg.timestamp {
+                let current = pieces.last().map_or(0, Vec::len);
+                if last_time.is_some_and(|prev| t - prev >= gap_ms) && current > 0 {
+                    pieces.push(Vec::new());
+                }
+                last_time = Some(t);
+            }
+            pieces.last_mut().expect("pieces is never empty").push(msg.clone());
+        }
+        if pieces.len() < 2 {
+            self.errors.push(format!("No pauses of {} minutes or more in this conversation", self.settings.split_gap_minutes));
+            return;
+        }
+        self.sessions = pieces
+            .into_iter()
+            .enumerate()
+            .map(|(i, messages)| {
+                let started = messages.iter().find_map(|m| m.timestamp).map(|t| self.fmt.datetime_ms(t));
+                let title = match started {
+                    Some(started) => format!("Part {} · {started}", i + 1),
+                    None => format!("Part {}", i + 1),
+                };
+                Session { title, system: self.system.clone(), messages, outcome: self.outcome.clone(), tree: None }
+            })
+            .collect();
+        self.split_applied = true;
+        self.open_pane(Pane::Sessions);
+        self.select_session(0);
+    }
+
+    fn undo_split(&mut self) {
+        self.restore_escapes();
+        let sessions = std::mem::take(&mut self.sessions);
+        self.messages = sessions.into_iter().flat_map(|s| s.messages).collect();
+        self.split_applied = false;
+        self.active_session = 0;
+        self.reset_escapes(false);
+        self.judge.reset_for(self.system.as_deref(), &self.messages);
+        self.reset_badges();
+        self.scroll_area_key = self.file_name.clone().unwrap_or_else(|| "__empty__".to_string());
+    }
+
+    fn split_menu(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Split where the pause is at least");
+            let drag = egui::DragValue::new(&mut self.settings.split_gap_minutes).clamp_range(1..=10_080).suffix(" min");
+            if ui.add(drag).lost_focus() {
+                if let Err(e) = save_settings(&self.settings) {
+                    self.errors.push(format!("Failed to save settings: {e}"));
+                }
+            }
+        });
+        if self.split_applied {
+            if ui.button("Undo split").clicked() {
+                self.undo_split();
+                ui.close_menu();
+            }
+        } else if ui
+            .add_enabled(self.sessions.is_empty(), egui::Button::new("Split conversation"))
+            .on_disabled_hover_text("This file already contains several conversations")
+            .clicked()
+        {
+            self.split_at_gaps();
+            ui.close_menu();
+        }
+    }
+
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        // Handle file drops without any overlay, to avoid interfering with text selection
+        let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
+        if !dropped_files.is_empty() {
+            // Try loading first valid path or bytes
+            for f in dropped_files {
+                if let Some(path) = f.path {
+                    if path.is_dir() {
+                        self.open_folder(&path, ctx);
+                        break;
+                    }
+                    if csv_import::is_table_file(&path) {
+                        self.open_table_file(&path);
+                        break;
+                    }
+                    if archive::is_zip_file(&path) {
+                        self.open_zip_file(&path);
+                        break;
+                    }
+                    match self.open_log_file(&path, ctx) {
+                        Ok(()) => break,
+                        Err(e) => self.errors.push(format!("Failed to load dropped file: {e}")),
+                    }
+                } else if let Some(bytes) = f.bytes {
+                    match load_from_bytes(&bytes, self.parse_options()) {
+                        Ok(mut loaded) => {
+                            loaded.file_name = Some("(dropped)".to_string());
+                            self.set_loaded(loaded);
+                            // Only a full path says where the file came from
+                            let from = Some(Path::new(&f.name)).filter(|p| p.is_absolute());
+                            self.read_only = self.read_only_reason(from);
+                            break;
+                        }
+                        Err(e) => self.errors.push(format!("Failed to parse dropped bytes: {e}")),
+                    }
+                }
+            }
+        }
+    }
+
+    fn conversation_ui(&mut self, ui: &mut egui::Ui) {
+        // Conversation rendering
+        let scroll_id = Id::new("scroll_conversation").with(self.scroll_area_key.clone());
+        let mut top_message = None;
+        let mut page_end = self.messages.len();
+        let output = ScrollArea::vertical()
+            .id_source(scroll_id)
+            // Do not shrink horizontally (keep full width), but allow vertical to fit content
+            .auto_shrink([false, true])
+            .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysVisible)
+            // Follow a large file while it loads, when opening at the latest message
+            .stick_to_bottom(self.loading.is_some() && self.settings.start_position == StartPosition::Bottom)
+            // Rows measured on the way would move the target of an animated jump
+            .animated(self.scroll_to_message.is_none())
+            .show(ui, |ui| {
+                ui.add_space(6.0);
+
+                if let Some(outcome) = &self.outcome {
+                    render_outcome_banner(ui, outcome, self.theme_dark);
+                }
+                self.escapes_banner(ui);
+
+                // System card
+                let merged = self.merged_developer_count();
+                if let Some((title, text)) = self.system_card(merged) {
+                    let scale = self.text_scale * self.settings.role_scales.system;
+                    render_system_card(ui, &title, &text, &mut self.md_cache, scale);
+                    ui.add_space(6.0);
+                }
+
+                // Messages
+                let content_width = ui.available_width();
+                let viewport_top = ui.clip_rect().top();
+                // Child runs stay hidden while an enclosing tool card is collapsed
+                let mut collapsed_depth: Option<usize> = None;
+                let mut last_time: Option<i64> = None;
+                let mut apply_patch = None;
+                let mut copy_reference = None;
+                let mut run_tool = None;
+                let forks = self.branch_forks();
+                let mut switch_branch = None;
+                let shown = self.page_range();
+                page_end = shown.end;
+                self.load_previous_ui(ui, &shown);
+                let runs = self.repeats.runs(&self.messages, shown.clone());
+                for (idx, msg) in self.messages.iter().enumerate().take(shown.end).skip(shown.start) {
+                    let run = runs.get(idx);
+                    if self.scroll_to_message == Some(idx) {
+                        // Jumping into a collapsed run of similar results opens it
+                        if let Some(run) = run.filter(|r| r.hides(ui.ctx(), idx)) {
+                            run.open(ui.ctx());
+                        }
+                        ui.scroll_to_cursor(Some(Align::TOP));
+                        self.scroll_to_message = None;
+                    }
+                    if idx < merged || collapsed_depth.is_some_and(|d| msg.depth > d) || run.is_some_and(|r| r.hides(ui.ctx(), idx)) {
+                        continue;
+                    }
+                    let gap = msg.timestamp.and_then(|t| {
+                        let gap = last_time.map(|prev| t - prev).filter(|gap| *gap >= IDLE_GAP_MS);
+                        last_time = Some(t);
+                        gap.map(|gap| (gap, t))
+                    });
+                    collapsed_depth = (is_tool_card(msg) && !tool_card_state(ui.ctx(), idx).is_open()).then_some(msg.depth);
+                    // Rows away from the viewport only take up their space
+                    let row_top = ui.cursor().top();
+                    let scale = self.text_scale * self.settings.role_scales.for_msg(msg);
+                    let mut folded = is_foldable(msg) && self.folds.is_folded(idx);
+                    let height = if folded { folds::FOLDED_ROW_HEIGHT } else { self.row_heights.height(idx, msg, content_width, scale) };
+                    if !row_heights::is_visible(ui, row_top, height) {
+                        ui.allocate_space(egui::vec2(0.0, height));
+                        if top_message.is_none() && ui.min_rect().bottom() > viewport_top {
+                            top_message = Some(idx);
+                        }
+                        ui.add_space(6.0);
+                        continue;
+                    }
+                    if let Some((gap, t)) = gap {
+                        render_gap_marker(ui, gap, &self.fmt.datetime_ms(t));
+                    }
+                    if let Some(run) = run.filter(|r| r.marker_at(ui.ctx(), idx)) {
+                        run.marker_ui(ui, &self.fmt);
+                    }
+                    for att in &msg.attachments {
+                        ui.ctx().include_bytes(att.uri.clone(), att.bytes.clone());
+                    }
+                    let annotation = self.judge.annotations.get(&idx);
+                    let was_folded = folded;
+                    if render_message_bubble(ui, msg, idx, content_width, self.theme_dark, &mut self.md_cache, scale, annotation, &self.fmt, self.settings.display, &mut folded) {
+                        copy_reference = Some(idx);
+                    }
+                    if folded != was_folded {
+                        self.folds.set(idx, folded);
+                    }
+                    // Diffs are not applied and tools not run from read-only conversations
+                    let editable = !self.is_read_only();
+                    let has_patch = editable && msg.content.contains("@@") && !is_tool_card(msg) && patch::message_patch(msg).is_some();
+                    let commands = if may_contain_commands(msg) { message_commands(&msg.content) } else { Vec::new() };
+                    let badge = self.scripts.badges.get(&idx);
+                    let tools: &[_] = if editable { &self.settings.external_tools } else { &[] };
+                    let fork = forks.get(idx).copied().flatten();
+                    // Folded messages keep to their one line
+                    if !was_folded && (has_patch || !commands.is_empty() || badge.is_some() || !tools.is_empty() || fork.is_some()) {
+                        ui.horizontal(|ui| {
+                            ui.add_space(36.0);
+                            if let Some(fork) = fork {
+                                if let Some(index) = branches::branch_switcher(ui, fork) {
+                                    switch_branch = Some((fork, index));
+                                }
+                            }
+                            if let Some(badge) = badge {
+                                ui.label(RichText::new(badge).small().strong()).on_hover_text("From a user script");
+                            }
+                            if has_patch && ui.small_button("Apply diff to folder…").on_hover_text("Preview and apply this diff to a working directory").clicked() {
+                                apply_patch = Some(idx);
+                            }
+                            if !commands.is_empty() {
+                                let text = commands.join("\n");
+                                let resp = ui
+                                    .small_button(format!("Copy commands ({})", commands.len()))
+                                    .on_hover_text(RichText::new(&text).monospace());
+                                if resp.clicked() {
+                                    ui.output_mut(|o| o.copied_text = text);
+                                }
+                            }
+                            if !tools.is_empty() {
+                                if let Some(tool) = tools::run_tool_menu(ui, tools) {
+                                    run_tool = Some((tool, idx));
+                                }
+                            }
+                        });
+                    }
+                    // The cursor is already past the spacing after the last widget, which
+                    // allocate_space adds again
+                    if !was_folded {
+                        self.row_heights.set(idx, msg, ui.cursor().top() - row_top - ui.spacing().item_spacing.y);
+                    }
+                    // First message still visible at the top of the viewport
+                    if top_message.is_none() && ui.min_rect().bottom() > viewport_top {
+                        top_message = Some(idx);
+                    }
+                    ui.add_space(6.0);
+                }
+
+                self.load_next_ui(ui, &shown);
+                if let Some(idx) = apply_patch {
+                    self.open_patch_window(idx);
+                }
+                if let Some((fork, index)) = switch_branch {
+                    self.switch_branch(fork, index);
+                }
+                if let Some((tool, idx)) = run_tool {
+                    self.run_external_tool(tool, idx, &ui.ctx().clone());
+                }
+                if let Some(idx) = copy_reference {
+                    let reference = self.message_reference(idx);
+                    ui.output_mut(|o| o.copied_text = reference);
+                }
+
+                // Ensure the last Copy bar isn't clipped at the bottom
+                ui.add_space(18.0);
+                if std::mem::take(&mut self.scroll_to_end) {
+                    ui.scroll_to_cursor(Some(Align::BOTTOM));
+                }
+            });
+
+        // Reading progress: thin bar along the top edge, "Message i / n" in the status line
+        let scrollable = (output.content_size.y - output.inner_rect.height()).max(0.0);
+        let fraction = if scrollable > 0.0 { (output.state.offset.y / scrollable).clamp(0.0, 1.0) } else { 1.0 };
+        self.reading_position = top_message.map(|i| {
+            // At the very end, the last message counts as read even if it starts above the viewport
+            if fraction >= 0.999 { page_end } else { i + 1 }
+        });
+        if scrollable > 0.0 {
+            let top = output.inner_rect.left_top();
+            let bar = egui::Rect::from_min_size(top, egui::vec2(output.inner_rect.width() * fraction, 2.0));
+            ui.painter().rect_filled(bar, 0.0, ui.visuals().selection.bg_fill);
+        }
+        self.lint_strip(ui, output.inner_rect);
+    }
+
+    // Conversation list for multi-conversation files; returns the clicked session.
+    fn sessions_ui(&mut self, ui: &mut egui::Ui) -> Option<usize> {
+        if self.sessions.is_empty() {
+            ui.label(RichText::new("This file contains a single conversation").italics().weak());
+            return None;
+        }
+        let mut clicked = None;
+        let dark = ui.visuals().dark_mode;
+        let max_len = self.sessions.iter().flat_map(|s| &s.messages).map(|m| m.content.len()).max().unwrap_or(0);
+        // Review budget for the whole file or folder
+        let words: Vec<f64> = self.sessions.iter().map(|s| self.reading.words(s.system.as_deref(), &s.messages)).collect();
+        let total: f64 = words.iter().sum();
+        ui.label(
+            RichText::new(format!("{} conversations · {} to read", self.fmt.count(self.sessions.len()), reading::reading_time(total)))
+                .small()
+                .weak(),
+        )
+        .on_hover_text(reading::describe(total, &self.fmt));
+        let health: Vec<health::Summary> = self.sessions.iter().map(|s| self.health.summary(&s.messages)).collect();
+        ui.checkbox(&mut self.health.worst_first, "Worst first")
+            .on_hover_text("Order conversations by health score, lowest first");
+        let mut order: Vec<usize> = (0..self.sessions.len()).collect();
+        if self.health.worst_first {
+            order.sort_by_key(|&i| health[i].score());
+        }
+        ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+            for i in order {
+                let session = &self.sessions[i];
+                let chars: usize = session.messages.iter().map(|m| m.content.len()).sum();
+                let hover = format!(
+                    "{} messages, {} characters\n{}",
+                    self.fmt.count(session.messages.len()),
+                    self.fmt.count(chars),
+                    reading::describe(words[i], &self.fmt)
+                );
+                let label = ui
+                    .horizontal(|ui| {
+                        health::badge(ui, &health[i]);
+                        ui.selectable_label(i == self.active_session, &session.title).on_hover_text(&hover)
+                    })
+                    .inner;
+                // Turn count and the start of the first user message, which tells untitled conversations apart
+                let turns = session.messages.iter().filter(|m| matches!(m.role, Role::User)).count();
+                let first_user = session.messages.iter().find(|m| matches!(m.role, Role::User));
+                let opening = first_user.and_then(|m| m.content.lines().map(str::trim).find(|l| !l.is_empty()));
+                let minutes = reading::reading_time(words[i]);
+                let summary = match opening {
+                    Some(line) => format!("{} turns · {minutes} · {line}", self.fmt.count(turns)),
+                    None => format!("{} turns · {minutes}", self.fmt.count(turns)),
+                };
+                let preview = ui
+                    .add(Label::new(RichText::new(summary).small().weak()).truncate(true).sense(egui::Sense::click()))
+                    .on_hover_text(&hover);
+                let strip = render_size_strip(ui, &session.messages, max_len, dark).on_hover_text(&hover);
+                if label.clicked() || preview.clicked() || strip.clicked() {
+                    clicked = Some(i);
+                }
+                ui.add_space(4.0);
+            }
+        });
+        clicked
     }
 }
 
 impl eframe::App for AppState {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.frame_ui(ctx);
+        self.record_copied(ctx);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.remember_position();
+    }
+}
+
+impl AppState {
+    fn frame_ui(&mut self, ctx: &egui::Context) {
+        self.judge.poll();
+        let failed_exports = self.tools.poll();
+        self.errors.extend(failed_exports);
+        self.poll_incremental();
+        self.poll_background();
+        self.poll_folder(ctx);
+        self.poll_snapshot(ctx);
+        self.evict_stale_caches(ctx);
+        repaint::heartbeat(ctx);
+        html::set_policy(ctx, self.settings.html_policy);
+        fonts::set_spacing(ctx, self.settings.spacing);
+
+        if self.present.active {
+            self.presentation_ui(ctx);
+            return;
+        }
+        if !self.messages.is_empty() && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F5)) {
+            self.start_presentation();
+        }
+
+        // F11 toggles focus mode; Esc only leaves it
+        let toggle_focus = ctx.input_mut(|i| {
+            i.consume_key(egui::Modifiers::NONE, egui::Key::F11)
+                || (self.focus_mode && i.consume_key(egui::Modifiers::NONE, egui::Key::Escape))
+        });
+        if toggle_focus {
+            self.focus_mode = !self.focus_mode;
+        }
+        if self.focus_mode {
+            self.handle_dropped_files(ctx);
+            // Only the conversation, centered at a readable line length
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let side = ((ui.available_width() - FOCUS_MEASURE * self.text_scale) / 2.0).max(0.0);
+                Frame::none().inner_margin(egui::Margin::symmetric(side, 0.0)).show(ui, |ui| self.conversation_ui(ui));
+            });
+            return;
+        }
+
         // Top menu bar
         egui::TopBottomPanel::top("top_bar").show(ctx, |ui| {
             ui.horizontal_wrapped(|ui| {
                 if ui.button("Open file…").clicked() {
                     if let Some(path) = rfd::FileDialog::new()
-                        .add_filter("Log", &["json", "jsonl"]) // not exclusive
+                        .add_filter("Log", &["json", "jsonl", "yaml", "yml", "md", "txt", "vscdb", "csv", "tsv", "parquet", "gz", "zst", "zip"]) // not exclusive
                         .pick_file()
                     {
-                        match load_from_path(&path) {
-                            Ok(loaded) => self.set_loaded(loaded),
-                            Err(e) => self.errors.push(format!("Failed to load: {e}")),
+                        if csv_import::is_table_file(&path) {
+                            self.open_table_file(&path);
+                        } else if archive::is_zip_file(&path) {
+                            self.open_zip_file(&path);
+                        } else {
+                            if let Err(e) = self.open_log_file(&path, ctx) {
+                                self.errors.push(format!("Failed to load: {e}"));
+                            }
                         }
                     }
                 }
 
+                let open_folder = ui
+                    .button("Open folder…")
+                    .on_hover_text("List every conversation in a folder and its subfolders in the Sessions pane. Right-click for excluded paths and links.");
+                if open_folder.clicked() {
+                    if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                        self.open_folder(&dir, ctx);
+                    }
+                }
+                open_folder.context_menu(|ui| self.folder_excludes_ui(ui));
+
+                if ui
+                    .selectable_label(self.lmstudio.show_window, "LM Studio…")
+                    .on_hover_text("Import a conversation from LM Studio")
+                    .clicked()
+                {
+                    self.lmstudio.show_window = !self.lmstudio.show_window;
+                }
+
+                if ui
+                    .selectable_label(self.paste_prompt.show_window, "Paste prompt…")
+                    .on_hover_text("Split a flattened prompt string (ChatML, Llama, Gemma, …) back into messages")
+                    .clicked()
+                {
+                    self.paste_prompt.show_window = !self.paste_prompt.show_window;
+                }
+
+                let mut strict = self.parse_options().strict;
+                if ui
+                    .checkbox(&mut strict, "Strict")
+                    .on_hover_text("Fail on the first malformed JSONL line and report where, instead of skipping it with a warning")
+                    .changed()
+                {
+                    // Unchecking also ends `--strict` for this run
+                    self.strict_session = false;
+                    self.settings.strict_parsing = strict;
+                    if let Err(e) = save_settings(&self.settings) {
+                        self.errors.push(format!("Failed to save settings: {e}"));
+                    }
+                }
+
+                self.loading_indicator(ui);
+                self.folder_indicator(ui);
+                self.export_indicator(ui);
+                self.read_only_indicator(ui);
+
                 if ui.button("Clear").clicked() {
                     let keep_scale = self.text_scale;
-                    *self = AppState { theme_dark: self.theme_dark, text_scale: keep_scale, ..Default::default() };
+                    let settings = std::mem::take(&mut self.settings);
+                    let saved_layout = layout_json(&settings.layout);
+                    let layout_epoch = self.layout_epoch;
+                    *self = AppState {
+                        theme_dark: self.theme_dark,
+                        text_scale: keep_scale,
+                        settings,
+                        fmt: self.fmt,
+                        saved_layout,
+                        layout_epoch,
+                        read_only_session: self.read_only_session,
+                        strict_session: self.strict_session,
+                        ..Default::default()
+                    };
                     self.apply_theme(ctx.clone());
                 }
 
@@ -145,24 +1181,203 @@ impl eframe::App for AppState {
                     self.apply_theme(ctx.clone());
                 }
 
+                if ui.button("Focus").on_hover_text("Hide everything but the conversation (F11, Esc to leave)").clicked() {
+                    self.focus_mode = true;
+                }
+
+                if ui
+                    .add_enabled(!self.messages.is_empty(), egui::Button::new("Present"))
+                    .on_hover_text("Reveal the conversation one message at a time (F5, Esc to leave)")
+                    .clicked()
+                {
+                    self.start_presentation();
+                }
+
                 if ui.button("Copy as Markdown").clicked() {
-                    let md = to_markdown(self);
+                    let md = to_markdown(&self.transcript());
                     ui.output_mut(|o| o.copied_text = md);
                 }
 
-                if ui.button("Export HTML…").clicked() {
+                if ui.button("Copy commands").on_hover_text("Copy the shell commands suggested by the assistant, without prompts or comments").clicked() {
+                    let commands: Vec<String> = self
+                        .messages
+                        .iter()
+                        .filter(|m| matches!(m.role, Role::Assistant) && may_contain_commands(m))
+                        .flat_map(|m| message_commands(&m.content))
+                        .collect();
+                    if commands.is_empty() {
+                        self.errors.push("No shell commands found in assistant messages".to_string());
+                    } else {
+                        ui.output_mut(|o| o.copied_text = commands.join("\n"));
+                    }
+                }
+
+                ui.menu_button("Export Markdown", |ui| {
+                    for flavor in [MarkdownFlavor::Plain, MarkdownFlavor::Obsidian, MarkdownFlavor::Notion] {
+                        if !ui.button(flavor.label()).clicked() {
+                            continue;
+                        }
+                        ui.close_menu();
+                        let stem = self.file_name.as_deref().and_then(|f| Path::new(f).file_stem()).and_then(|s| s.to_str());
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("Markdown", &["md"])
+                            .set_file_name(format!("{}.md", stem.unwrap_or("chat")))
+                            .save_file()
+                        {
+                            let conv = Transcript { provenance: self.provenance(), ..self.transcript() };
+                            let markdown = to_markdown_flavor(&conv, flavor);
+                            let hook = self.settings.export_hooks.markdown.clone();
+                            self.save_export(ui.ctx(), "Markdown", path, markdown, hook);
+                        }
+                    }
+                    ui.separator();
+                    self.provenance_ui(ui);
+                });
+
+                let export_html = ui.button("Export HTML…").on_hover_text("Right-click for the provenance footer");
+                if export_html.clicked() {
                     if let Some(path) = rfd::FileDialog::new()
                         .add_filter("HTML", &["html", "htm"]) // not exclusive
                         .set_file_name("chat.html")
                         .save_file()
                     {
-                        let html = to_html(self);
-                        if let Err(e) = fs::write(&path, html) {
-                            self.errors.push(format!("Failed to export HTML: {e}"));
+                        let conv = Transcript { provenance: self.provenance(), ..self.transcript() };
+                        let html = to_html(&conv);
+                        let hook = self.settings.export_hooks.html.clone();
+                        self.save_export(ui.ctx(), "HTML", path, html, hook);
+                    }
+                }
+                export_html.context_menu(|ui| self.provenance_ui(ui));
+
+                if ui
+                    .button("Export notebook…")
+                    .on_hover_text("Jupyter notebook: code blocks from the assistant become runnable cells")
+                    .clicked()
+                {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("Jupyter notebook", &["ipynb"])
+                        .set_file_name("chat.ipynb")
+                        .save_file()
+                    {
+                        let hook = self.settings.export_hooks.notebook.clone();
+                        self.save_export(ui.ctx(), "notebook", path, to_ipynb(&self.transcript()), hook);
+                    }
+                }
+
+                if ui
+                    .button("Extract code…")
+                    .on_hover_text("Write every fenced code block to its own file in a folder")
+                    .clicked()
+                {
+                    if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                        let extracted = self
+                            .check_writable(&dir)
+                            .and_then(|()| extract_code_blocks(&self.transcript(), &dir, self.settings.backups));
+                        if let Err(e) = extracted {
+                            self.errors.push(format!("Failed to extract code: {e}"));
                         }
                     }
                 }
 
+                ui.menu_button("Outline", |ui| {
+                    if ui.checkbox(&mut self.settings.outline_tokens, "With token counts").changed() {
+                        if let Err(e) = save_settings(&self.settings) {
+                            self.errors.push(format!("Failed to save settings: {e}"));
+                        }
+                    }
+                    if ui.button("Copy outline").on_hover_text("One line per turn: speaker and first sentence").clicked() {
+                        ui.close_menu();
+                        let outline = to_outline(&self.transcript(), self.settings.outline_tokens);
+                        ui.output_mut(|o| o.copied_text = outline);
+                    }
+                    if ui.button("Save outline…").clicked() {
+                        ui.close_menu();
+                        let stem = self.file_name.as_deref().and_then(|f| Path::new(f).file_stem()).and_then(|s| s.to_str());
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("Markdown", &["md"])
+                            .set_file_name(format!("{}.outline.md", stem.unwrap_or("chat")))
+                            .save_file()
+                        {
+                            let outline = to_outline(&self.transcript(), self.settings.outline_tokens);
+                            let hook = self.settings.export_hooks.outline.clone();
+                            self.save_export(ui.ctx(), "outline", path, outline, hook);
+                        }
+                    }
+                });
+
+                if ui
+                    .button("Export skeleton…")
+                    .on_hover_text("Roles, approximate token counts and timestamps, without any message text")
+                    .clicked()
+                {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("JSON", &["json"])
+                        .set_file_name("chat.skeleton.json")
+                        .save_file()
+                    {
+                        let skeleton = to_skeleton_json(&self.transcript());
+                        let hook = self.settings.export_hooks.skeleton.clone();
+                        self.save_export(ui.ctx(), "skeleton", path, skeleton, hook);
+                    }
+                }
+
+                if ui.selectable_label(self.judge.show_window, "Judge…").clicked() {
+                    self.judge.show_window = !self.judge.show_window;
+                }
+                if ui.selectable_label(self.providers_ui.show_window, "Providers…").clicked() {
+                    self.providers_ui.show_window = !self.providers_ui.show_window;
+                }
+                if ui
+                    .toggle_value(&mut self.settings.network.offline, "Offline")
+                    .on_hover_text("Disable all network features")
+                    .changed()
+                {
+                    if let Err(e) = save_settings(&self.settings) {
+                        self.errors.push(format!("Failed to save settings: {e}"));
+                    }
+                }
+
+                if self.split_applied || self.messages.iter().any(|m| m.timestamp.is_some()) {
+                    ui.menu_button("Split", |ui| self.split_menu(ui));
+                }
+                ui.menu_button("View", |ui| {
+                    self.display_menu(ui);
+                    ui.separator();
+                    self.folds_menu(ui);
+                    ui.separator();
+                    self.page_size_ui(ui);
+                });
+                ui.menu_button("Fonts", |ui| self.fonts_menu(ui));
+                ui.menu_button("Spacing", |ui| self.spacing_ui(ui));
+                ui.menu_button("Open at", |ui| self.start_position_menu(ui));
+                ui.menu_button("Locale", |ui| self.locale_menu(ui));
+                ui.menu_button("Roles", |ui| self.roles_menu(ui));
+                ui.menu_button("HTML", |ui| self.html_menu(ui));
+                ui.menu_button("Links", |ui| self.links_menu(ui));
+                ui.menu_button("Scripts", |ui| self.scripts_menu(ui));
+                ui.menu_button("Tools", |ui| self.tools_menu(ui));
+                ui.menu_button("Panes", |ui| {
+                    for pane in Pane::OPTIONAL {
+                        let mut open = self.pane_open(pane);
+                        let label = match pane {
+                            Pane::Sessions if !self.sessions.is_empty() => format!("Sessions ({})", self.sessions.len()),
+                            _ => pane.title().to_string(),
+                        };
+                        if ui.checkbox(&mut open, label).changed() {
+                            if open {
+                                self.open_pane(pane);
+                            } else {
+                                self.close_pane(pane);
+                            }
+                        }
+                    }
+                });
+                if ui.button("Reset layout").on_hover_text("Restore default panes and window positions").clicked() {
+                    self.settings.layout = LayoutSettings::default();
+                    self.layout_epoch += 1;
+                    self.layout_dirty = true;
+                }
+
                 ui.separator();
                 ui.label("Text size");
                 let mut scale = self.text_scale;
@@ -171,20 +1386,54 @@ impl eframe::App for AppState {
                 if (scale - before).abs() > f32::EPSILON {
                     self.text_scale = scale;
                 }
+                ui.menu_button("By role", |ui| self.role_scale_menu(ui))
+                    .response
+                    .on_hover_text("Smaller text for system and tool messages");
 
                 ui.separator();
                 if let Some(name) = &self.file_name {
                     ui.label(RichText::new(name).italics());
+                    if let Some(format) = self.format {
+                        ui.label(RichText::new(format).small().weak()).on_hover_text("Detected format");
+                    }
                 } else {
                     ui.label(RichText::new("No file loaded").italics());
                 }
                 ui.separator();
-                ui.label(format!("Messages: {}", self.messages.len()));
+                ui.label(format!("Messages: {}", self.fmt.count(self.messages.len())));
+                if let Some((start, end)) = session_span(&self.messages) {
+                    ui.separator();
+                    ui.label(format!("Duration: {}", format_duration(end - start)))
+                        .on_hover_text(format!("{} – {}", self.fmt.datetime_ms(start), self.fmt.datetime_ms(end)));
+                }
+                self.reading_ui(ui);
             });
         });
 
+        if self.judge.show_window {
+            self.judge_window(ctx);
+        }
+        if self.providers_ui.show_window {
+            self.providers_window(ctx);
+        }
+        if self.lmstudio.show_window {
+            self.lmstudio_window(ctx);
+        }
+        if self.paste_prompt.show_window {
+            self.paste_prompt_window(ctx);
+        }
+        if self.patch.show_window {
+            self.patch_window(ctx);
+        }
+        if self.csv.show_window {
+            self.csv_window(ctx);
+        }
+        if self.archive.show_window {
+            self.archive_window(ctx);
+        }
+
         // Error banner (non-blocking)
-        if !self.errors.is_empty() {
+        if !self.errors.is_empty() || !self.failed_lines.is_empty() {
             egui::TopBottomPanel::top("error_bar").show(ctx, |ui| {
                 Frame::none()
                     .fill(Color32::from_rgb(255, 235, 238))
@@ -195,69 +1444,21 @@ impl eframe::App for AppState {
                             ui.colored_label(Color32::from_rgb(183, 28, 28), msg);
                             if ui.button("Dismiss").clicked() {
                                 self.errors.clear();
+                                self.failed_lines = LineErrors::default();
                             }
                         });
+                        self.line_errors_panel(ui);
                     });
             });
         }
 
-        // Central content with drag&drop handling
-        egui::CentralPanel::default().show(ctx, |ui| {
-            // Handle file drops without any overlay, to avoid interfering with text selection
-            let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
-            if !dropped_files.is_empty() {
-                // Try loading first valid path or bytes
-                for f in dropped_files {
-                    if let Some(path) = f.path {
-                        match load_from_path(&path) {
-                            Ok(loaded) => {
-                                self.set_loaded(loaded);
-                                break;
-                            }
-                            Err(e) => self.errors.push(format!("Failed to load dropped file: {e}")),
-                        }
-                    } else if let Some(bytes) = f.bytes {
-                        match load_from_bytes(&bytes) {
-                            Ok(mut loaded) => {
-                                loaded.file_name = Some("(dropped)".to_string());
-                                self.set_loaded(loaded);
-                                break;
-                            }
-                            Err(e) => self.errors.push(format!("Failed to parse dropped bytes: {e}")),
-                        }
-                    }
-                }
-            }
+        self.handle_dropped_files(ctx);
+        // No drag & drop overlay; prioritize text selection UX
 
-            // No drag & drop overlay; prioritize text selection UX
+        // Docked panes (conversation, sessions, ...)
+        egui::CentralPanel::default().frame(Frame::none()).show(ctx, |ui| self.dock_area(ui));
 
-            // Conversation rendering
-            let scroll_id = Id::new("scroll_conversation").with(self.scroll_area_key.clone());
-            ScrollArea::vertical()
-                .id_source(scroll_id)
-                // Do not shrink horizontally (keep full width), but allow vertical to fit content
-                .auto_shrink([false, true])
-                .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysVisible)
-                .show(ui, |ui| {
-                    ui.add_space(6.0);
-
-                    // System card
-                    if let Some(sys) = &self.system {
-                        render_system_card(ui, sys, &mut self.md_cache, self.text_scale);
-                        ui.add_space(6.0);
-                    }
-
-                    // Messages
-                    let content_width = ui.available_width();
-                    for (idx, msg) in self.messages.iter().enumerate() {
-                        render_message_bubble(ui, msg, idx, content_width, self.theme_dark, &mut self.md_cache, self.text_scale);
-                        ui.add_space(6.0);
-                    }
-
-                    // Ensure the last Copy bar isn't clipped at the bottom
-                    ui.add_space(18.0);
-                });
-        });
+        self.persist_layout_if_changed(ctx);
 
         // Bottom status line
         egui::TopBottomPanel::bottom("status_line").show(ctx, |ui| {
@@ -268,128 +1469,197 @@ impl eframe::App for AppState {
                     .unwrap_or_else(|| "(no file)".to_string());
                 ui.label(format!("File: {}", fname));
                 ui.separator();
-                ui.label(format!("Turns: {}", self.messages.len()));
-                if !self.errors.is_empty() {
+                ui.label(format!("Turns: {}", self.fmt.count(self.messages.len())));
+                if let Some(pos) = self.reading_position {
                     ui.separator();
-                    ui.colored_label(Color32::from_rgb(183, 28, 28), format!("Warnings: {}", self.errors.len()));
+                    ui.label(format!("Message {} / {}", self.fmt.count(pos), self.fmt.count(self.messages.len())));
                 }
+                if let Some((mean, _, _, n)) = self.judge.aggregate() {
+                    ui.separator();
+                    ui.label(format!("Judge avg: {} (n={})", self.fmt.float(mean.into(), 2), self.fmt.count(n)));
+                }
+                // All skipped lines together count as one warning
+                let warnings = self.errors.len() + usize::from(!self.failed_lines.is_empty());
+                if warnings > 0 {
+                    ui.separator();
+                    ui.colored_label(Color32::from_rgb(183, 28, 28), format!("Warnings: {warnings}"));
+                }
+                ui.separator();
+                self.cache_status_ui(ui);
             });
         });
     }
 }
 
-// ---------------- Parsing & Loading ----------------
+// ---------------- Settings ----------------
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+struct Settings {
+    layout: LayoutSettings,
+    network: NetworkSettings,
+    providers: Vec<ProviderProfile>,
+    judge: JudgeSettings,
+    locale: LocaleSettings,
+    // Threshold for "Split at gaps"
+    split_gap_minutes: u32,
+    // Presentation mode types out assistant turns
+    typewriter: bool,
+    // Outline export lists estimated token counts
+    outline_tokens: bool,
+    // Show leading `developer` messages inside the system card instead of as separate cards
+    merge_developer: bool,
+    // User-defined role names on top of the built-in aliases
+    role_aliases: Vec<RoleAlias>,
+    // Commands that can be run on a message ("Run tool")
+    external_tools: Vec<ExternalTool>,
+    // Commands exports are piped through before they are written
+    export_hooks: ExportHooks,
+    // How raw HTML in messages is shown and exported
+    html_policy: HtmlPolicy,
+    // Fail on malformed JSONL lines instead of skipping them
+    strict_parsing: bool,
+    // In-house log schemas mapped onto messages with JSONPath-style paths (edited in the file)
+    format_mappings: Vec<FormatMapping>,
+    // Entries kept in the Clipboard pane
+    clipboard_history: usize,
+    // Where a conversation opens
+    start_position: StartPosition,
+    // 1-based message at the top of the view when each file was last left, most recent first
+    last_read: Vec<(String, usize)>,
+    // Avatars, role badges and Copy bars shown with messages
+    display: DisplayOptions,
+    // Messages shown at a time in long conversations; 0 shows all
+    page_size: usize,
+    // Font files for text and code
+    fonts: FontSettings,
+    // Line and paragraph spacing in messages
+    spacing: TextSpacing,
+    // Smaller text for system and tool turns
+    role_scales: RoleScales,
+    // Copy files to the backup folder before overwriting or deleting them
+    backups: bool,
+    // Paths "Open folder…" leaves out
+    folders: FolderSettings,
+    // End HTML and Markdown exports with the source file's checksum, the export time and the app version
+    provenance_footer: bool,
+    // Paths whose files and folders are opened read-only
+    protected_locations: Vec<String>,
+}
 
-fn load_from_path(path: &Path) -> Result<Loaded> {
-    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
-    let mut loaded = load_from_bytes(&bytes)?;
-    loaded.file_name = Some(
-        path.file_name()
-            .map(|s| s.to_string_lossy().to_string())
-            .unwrap_or_else(|| path.display().to_string()),
-    );
-    Ok(loaded)
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            layout: LayoutSettings::default(),
+            network: NetworkSettings::default(),
+            providers: providers::default_providers(),
+            judge: JudgeSettings::default(),
+            locale: LocaleSettings::default(),
+            split_gap_minutes: 60,
+            typewriter: true,
+            outline_tokens: false,
+            merge_developer: false,
+            role_aliases: Vec::new(),
+            external_tools: Vec::new(),
+            export_hooks: ExportHooks::default(),
+            html_policy: HtmlPolicy::default(),
+            strict_parsing: false,
+            format_mappings: Vec::new(),
+            clipboard_history: clipboard::DEFAULT_HISTORY_LEN,
+            start_position: StartPosition::default(),
+            last_read: Vec::new(),
+            display: DisplayOptions::default(),
+            page_size: paging::DEFAULT_PAGE_SIZE,
+            fonts: FontSettings::default(),
+            spacing: TextSpacing::default(),
+            role_scales: RoleScales::default(),
+            backups: true,
+            folders: FolderSettings::default(),
+            provenance_footer: true,
+            protected_locations: Vec::new(),
+        }
+    }
 }
 
-fn load_from_bytes(bytes: &[u8]) -> Result<Loaded> {
-    if bytes.len() > 20 * 1024 * 1024 {
-        // ~20MB warning
-        // allocate after checking encoding; no extra temp needed
-        let text = std::str::from_utf8(bytes).map_err(|_| anyhow!("Non-UTF8 file"))?;
-        let (raws, mut warnings) = detect_and_parse(text)?;
-        let mut l = normalize(raws);
-        l.errors.append(&mut warnings);
-        l.errors.push("File larger than ~20MB".to_string());
-        return Ok(l);
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+struct LayoutSettings {
+    // Arrangement of the docked panes
+    dock: DockState<Pane>,
+    // Floating windows by title: [x, y, width, height]
+    windows: BTreeMap<String, [f32; 4]>,
+}
+
+impl Default for LayoutSettings {
+    fn default() -> Self {
+        Self { dock: dock::default_dock(), windows: BTreeMap::new() }
     }
+}
 
-    let text = std::str::from_utf8(bytes).map_err(|_| anyhow!("Non-UTF8 file"))?;
-    let (raws, warnings) = detect_and_parse(text)?;
-    let mut l = normalize(raws);
-    l.errors.extend(warnings);
-    Ok(l)
+fn layout_json(layout: &LayoutSettings) -> String {
+    serde_json::to_string(layout).unwrap_or_default()
 }
 
-fn detect_and_parse(text: &str) -> Result<(Vec<RawMsg>, Vec<String>)> {
-    let first_non_ws = text.chars().find(|c| !c.is_whitespace());
-    let mut warnings = Vec::new();
-    let raws = match first_non_ws {
-        Some('[') => parse_json(text.as_bytes())?,
-        _ => {
-            let (msgs, failed) = parse_jsonl_with_errors(text.as_bytes())?;
-            if failed > 0 {
-                warnings.push(format!("{} JSONL line(s) failed to parse", failed));
-            }
-            msgs
-        }
+// Per-user config directory (settings, judge scores), following platform conventions.
+fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|h| PathBuf::from(h).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
     };
-    Ok((raws, warnings))
+    base.map(|b| b.join("llm_log_viewer"))
 }
 
-fn parse_json(bytes: &[u8]) -> Result<Vec<RawMsg>> {
-    let v: Vec<RawMsg> = serde_json::from_slice(bytes).context("JSON array parse error")?;
-    Ok(v)
+fn write_file_creating_dirs(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))
 }
 
-fn parse_jsonl(bytes: &[u8]) -> Result<Vec<RawMsg>> {
-    // Basic version without exposing failures
-    let text = std::str::from_utf8(bytes).map_err(|_| anyhow!("Non-UTF8 file"))?;
-    let mut out = Vec::new();
-    for (idx, line) in text.lines().enumerate() {
-        let line = line.trim();
-        if line.is_empty() { continue; }
-        match serde_json::from_str::<RawMsg>(line) {
-            Ok(m) => out.push(m),
-            Err(_e) => {
-                // Skip silently here; load_from_bytes uses the variant with errors.
-                eprintln!("Warning: failed to parse JSONL line {}", idx + 1);
-            }
-        }
-    }
-    Ok(out)
+fn load_settings() -> Settings {
+    let mut settings: Settings = config_dir()
+        .and_then(|d| fs::read(d.join("settings.json")).ok())
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default();
+    providers::ensure_ids(&mut settings.providers, &mut settings.judge.provider);
+    settings
 }
 
-fn parse_jsonl_with_errors(bytes: &[u8]) -> Result<(Vec<RawMsg>, usize)> {
-    let text = std::str::from_utf8(bytes).map_err(|_| anyhow!("Non-UTF8 file"))?;
-    let mut out = Vec::new();
-    let mut failed = 0usize;
-    for line in text.lines() {
-        let line = line.trim();
-        if line.is_empty() { continue; }
-        match serde_json::from_str::<RawMsg>(line) {
-            Ok(m) => out.push(m),
-            Err(_) => failed += 1,
-        }
-    }
-    Ok((out, failed))
-}
-
-fn normalize(raw: Vec<RawMsg>) -> Loaded {
-    let mut system: Option<String> = None;
-    let mut messages: Vec<Msg> = Vec::new();
-    for rm in raw {
-        let cleaned = trim_chat_whitespace(&rm.content);
-        let content = if cleaned.trim().is_empty() { "(empty)".to_string() } else { cleaned };
-        let role_lower = rm.role.to_lowercase();
-        match role_lower.as_str() {
-            "system" => {
-                if system.is_none() {
-                    system = Some(content);
-                } else {
-                    messages.push(Msg { role: Role::Other("System (extra)".into()), content });
-                }
-            }
-            "user" => messages.push(Msg { role: Role::User, content }),
-            "assistant" => messages.push(Msg { role: Role::Assistant, content }),
-            other => messages.push(Msg { role: Role::Other(other.to_string()), content }),
-        }
-    }
-    Loaded { file_name: None, system, messages, errors: Vec::new() }
+fn save_settings(settings: &Settings) -> Result<()> {
+    let dir = config_dir().ok_or_else(|| anyhow!("No config directory"))?;
+    write_file_creating_dirs(&dir.join("settings.json"), &serde_json::to_vec_pretty(settings)?)
 }
 
 // ---------------- Rendering helpers ----------------
 
-fn render_system_card(ui: &mut egui::Ui, text: &str, cache: &mut CommonMarkCache, scale: f32) {
+fn render_outcome_banner(ui: &mut egui::Ui, outcome: &EvalOutcome, dark: bool) {
+    let (fill, accent, verdict) = match (outcome.pass, dark) {
+        (true, true) => (Color32::from_rgb(20, 60, 35), Color32::from_rgb(102, 187, 106), "PASS"),
+        (true, false) => (Color32::from_rgb(232, 245, 233), Color32::from_rgb(46, 125, 50), "PASS"),
+        (false, true) => (Color32::from_rgb(70, 25, 25), Color32::from_rgb(239, 83, 80), "FAIL"),
+        (false, false) => (Color32::from_rgb(255, 235, 238), Color32::from_rgb(183, 28, 28), "FAIL"),
+    };
+    Frame::none()
+        .fill(fill)
+        .stroke(egui::Stroke::new(1.0, accent))
+        .rounding(Rounding::same(6.0))
+        .inner_margin(egui::Margin::symmetric(10.0, 6.0))
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width() - 20.0);
+            ui.horizontal_wrapped(|ui| {
+                ui.label(RichText::new(verdict).strong().color(accent));
+                ui.label(&outcome.summary);
+            });
+        });
+    ui.add_space(8.0);
+}
+
+fn render_system_card(ui: &mut egui::Ui, title: &str, text: &str, cache: &mut CommonMarkCache, scale: f32) {
     let fill = ui.visuals().extreme_bg_color.linear_multiply(0.9);
     // Allocate a column with a right gutter so the card doesn't sit under the scrollbar
     let full = ui.available_width();
@@ -410,7 +1680,7 @@ fn render_system_card(ui: &mut egui::Ui, text: &str, cache: &mut CommonMarkCache
                 // Force the frame to take the conversation lane width (minus gutter and inset)
                 ui.set_min_width(sys_w);
                 ui.set_max_width(sys_w);
-                ui.label(RichText::new("System").strong());
+                ui.label(RichText::new(title).strong());
                 ui.add_space(6.0);
                 // Use the full message lane width so it aligns with chat lanes
                 let w = sys_w;
@@ -419,14 +1689,87 @@ fn render_system_card(ui: &mut egui::Ui, text: &str, cache: &mut CommonMarkCache
     });
 }
 
-fn render_message_bubble(ui: &mut egui::Ui, msg: &Msg, index: usize, content_width: f32, dark: bool, cache: &mut CommonMarkCache, scale: f32) {
+// Avatar color for a role; also used by the sidebar heat strips.
+fn role_accent(role: &Role, dark: bool) -> Color32 {
+    match role {
+        Role::User => if dark { Color32::from_rgb(48, 200, 120) } else { Color32::from_rgb(16, 163, 127) },
+        Role::Assistant => if dark { Color32::from_rgb(100, 100, 100) } else { Color32::from_rgb(200, 200, 200) },
+        Role::Other(_) => if dark { Color32::from_rgb(120, 120, 120) } else { Color32::from_rgb(180, 180, 180) },
+        Role::System => if dark { Color32::from_rgb(160, 130, 20) } else { Color32::from_rgb(230, 200, 80) },
+    }
+}
+
+// Distinct, evenly spread hues for named agents.
+fn agent_color(slot: usize, dark: bool) -> Color32 {
+    const PALETTE: [(u8, u8, u8); 8] = [
+        (33, 150, 243),
+        (233, 30, 99),
+        (255, 152, 0),
+        (156, 39, 176),
+        (0, 150, 136),
+        (121, 85, 72),
+        (63, 81, 181),
+        (205, 220, 57),
+    ];
+    let (r, g, b) = PALETTE[slot % PALETTE.len()];
+    let color = Color32::from_rgb(r, g, b);
+    if dark { color.linear_multiply(0.8) } else { color }
+}
+
+// One bar per message, colored by role (or agent). Heights share a scale across all sessions
+// (`max_len`) so oversized or lopsided conversations stand out in the list.
+fn render_size_strip(ui: &mut egui::Ui, messages: &[Msg], max_len: usize, dark: bool) -> egui::Response {
+    let (rect, resp) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 14.0), egui::Sense::click());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().faint_bg_color);
+    if messages.is_empty() || max_len == 0 {
+        return resp;
+    }
+    let bar_w = rect.width() / messages.len() as f32;
+    for (i, msg) in messages.iter().enumerate() {
+        // Square root keeps short turns visible next to huge pastes
+        let h = ((msg.content.len() as f32 / max_len as f32).sqrt() * rect.height()).max(1.0);
+        let x = rect.left() + bar_w * i as f32;
+        let bar = egui::Rect::from_min_max(egui::pos2(x, rect.bottom() - h), egui::pos2(x + bar_w.max(1.0), rect.bottom()));
+        let color = msg.agent.map_or_else(|| role_accent(&msg.role, dark), |slot| agent_color(slot, dark));
+        painter.rect_filled(bar, 0.0, color);
+    }
+    resp
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_message_bubble(
+    ui: &mut egui::Ui,
+    msg: &Msg,
+    index: usize,
+    content_width: f32,
+    dark: bool,
+    cache: &mut CommonMarkCache,
+    scale: f32,
+    annotation: Option<&JudgeAnnotation>,
+    fmt: &Formatter,
+    display: DisplayOptions,
+    folded: &mut bool,
+) -> bool {
+    // Returns whether "Copy reference" was clicked; the caller knows the file name
+    if is_tool_card(msg) {
+        render_tool_card(ui, msg, index, content_width, cache, scale);
+        return false;
+    }
+    if is_developer(msg) {
+        render_system_card(ui, "Developer", &msg.content, cache, scale);
+        return false;
+    }
+    if display.layout == MessageLayout::Document {
+        return render_document_message(ui, msg, index, content_width, dark, cache, scale, annotation, fmt, display, folded);
+    }
     // Palette close to ChatGPT: assistant neutral gray, user green-tinted
     let (bg, align_right, role_badge, avatar_bg, avatar_fg, avatar_initial) = match &msg.role {
         Role::User => (
             if dark { Color32::from_rgb(20, 80, 60) } else { Color32::from_rgb(219, 247, 230) },
             true,
             None,
-            if dark { Color32::from_rgb(48, 200, 120) } else { Color32::from_rgb(16, 163, 127) },
+            role_accent(&msg.role, dark),
             Color32::WHITE,
             "U".to_string(),
         ),
@@ -434,7 +1777,7 @@ fn render_message_bubble(ui: &mut egui::Ui, msg: &Msg, index: usize, content_wid
             if dark { Color32::from_rgb(45, 45, 45) } else { Color32::from_rgb(246, 246, 246) },
             false,
             None,
-            if dark { Color32::from_rgb(100, 100, 100) } else { Color32::from_rgb(200, 200, 200) },
+            role_accent(&msg.role, dark),
             if dark { Color32::WHITE } else { Color32::BLACK },
             "A".to_string(),
         ),
@@ -442,7 +1785,7 @@ fn render_message_bubble(ui: &mut egui::Ui, msg: &Msg, index: usize, content_wid
             if dark { Color32::from_rgb(60, 60, 60) } else { Color32::from_rgb(232, 232, 232) },
             false,
             Some(r.clone()),
-            if dark { Color32::from_rgb(120, 120, 120) } else { Color32::from_rgb(180, 180, 180) },
+            role_accent(&msg.role, dark),
             Color32::WHITE,
             r.chars().next().unwrap_or('?').to_ascii_uppercase().to_string(),
         ),
@@ -450,11 +1793,23 @@ fn render_message_bubble(ui: &mut egui::Ui, msg: &Msg, index: usize, content_wid
             if dark { Color32::from_rgb(90, 90, 20) } else { Color32::from_rgb(255, 250, 220) },
             false,
             Some("System".into()),
-            if dark { Color32::from_rgb(160, 130, 20) } else { Color32::from_rgb(230, 200, 80) },
+            role_accent(&msg.role, dark),
             Color32::BLACK,
             "S".to_string(),
         ),
     };
+    let (avatar_bg, avatar_fg) = match msg.agent {
+        Some(slot) => (agent_color(slot, dark), Color32::WHITE),
+        None => (avatar_bg, avatar_fg),
+    };
+    // A speaker name (character chats, named participants) replaces the generic badge and initial
+    let (role_badge, avatar_initial) = match &msg.name {
+        Some(name) => (Some(name.clone()), name.chars().next().map(|c| c.to_uppercase().collect()).unwrap_or(avatar_initial)),
+        None => (role_badge.map(|b| title_case(&b)), avatar_initial),
+    };
+    let role_badge = role_badge.filter(|_| display.role_badges);
+    // A folded message shows its first sentence
+    let preview = folded.then(|| first_sentence(&msg.content));
 
     let layout = if align_right {
         Layout::right_to_left(Align::TOP)
@@ -462,6 +1817,7 @@ fn render_message_bubble(ui: &mut egui::Ui, msg: &Msg, index: usize, content_wid
         Layout::left_to_right(Align::TOP)
     };
 
+    let mut copy_reference = false;
     ui.vertical(|ui| {
         ui.scope(|ui| {
             ui.spacing_mut().item_spacing.y = 0.0; // eliminate default vertical gaps inside a message
@@ -484,7 +1840,7 @@ fn render_message_bubble(ui: &mut egui::Ui, msg: &Msg, index: usize, content_wid
                     .max(160.0);
                 // Let bubbles shrink based on content for a more natural width (user only)
                 let bubble_width = if align_right {
-                    preferred_bubble_width(ui, &msg.content, user_max_width, scale)
+                    preferred_bubble_width(ui, preview.as_deref().unwrap_or(&msg.content), user_max_width, scale)
                 } else {
                     assist_max_width
                 };
@@ -494,40 +1850,73 @@ fn render_message_bubble(ui: &mut egui::Ui, msg: &Msg, index: usize, content_wid
                     // Avatar at the far right, then bubble to its left
                     // Move avatar further right: smaller pre-gutter inside the row.
                     ui.add_space(8.0);
-                    draw_avatar(ui, &avatar_initial, avatar_bg, avatar_fg);
+                    // The fold chevron goes under the avatar
+                    let avatar = ui
+                        .vertical(|ui| {
+                            let avatar = display.avatars.then(|| {
+                                draw_avatar(ui, &avatar_initial, avatar_bg, avatar_fg)
+                                    .on_hover_ui(|ui| {
+                                        ui.label(reading::describe(reading::words(&msg.content), fmt));
+                                    })
+                                    .rect
+                            });
+                            ui.add_space(2.0);
+                            folds::chevron(ui, folded);
+                            avatar
+                        })
+                        .inner;
                     ui.add_space(gap);
-                    let role_label = match &msg.role {
-                        Role::User => "User".to_string(),
-                        Role::Assistant => "Assistant".to_string(),
-                        Role::System => "System".to_string(),
-                        Role::Other(r) => title_case(r),
-                    };
+                    let role_label = role_label(&msg.role);
                     bubble_w_for_copy = bubble_width;
                     let key = format!("msg-{}", index);
                     // Constrain bubble and copy bar to the same fixed-width column sized to bubble.
                     // Align RIGHT inside the column so the bubble's右端 is constant next to the avatar.
                     ui.allocate_ui_with_layout(egui::vec2(bubble_width, 0.0), Layout::top_down(Align::RIGHT), |col| {
-                        render_bubble(col, bg, bubble_width, role_badge.as_ref(), &msg.content, cache, scale, &role_label, false, &key);
-                        col.add_space(2.0);
-                        render_copy_bar(col, bubble_width, &role_label, &msg.content, true);
+                        let bubble = render_bubble(col, bg, bubble_width, role_badge.as_ref(), &msg.content, preview.as_deref(), cache, scale, &role_label, false, &key);
+                        if display.copy_bars && !*folded {
+                            col.add_space(2.0);
+                            match render_copy_bar(col, bubble_width, &role_label, &msg.content, msg.detail.as_deref(), true) {
+                                CopyRequest::Image => snapshot::request_snapshot(col.ctx(), avatar.map_or(bubble, |a| a.union(bubble)).intersect(col.clip_rect())),
+                                CopyRequest::Reference => copy_reference = true,
+                                CopyRequest::None => {}
+                            }
+                        }
                     });
                 } else {
                     // Avatar left, then bubble
-                    draw_avatar(ui, &avatar_initial, avatar_bg, avatar_fg);
+                    // The fold chevron goes under the avatar
+                    let avatar = ui
+                        .vertical(|ui| {
+                            let avatar = display.avatars.then(|| {
+                                draw_avatar(ui, &avatar_initial, avatar_bg, avatar_fg)
+                                    .on_hover_ui(|ui| {
+                                        ui.label(reading::describe(reading::words(&msg.content), fmt));
+                                    })
+                                    .rect
+                            });
+                            ui.add_space(2.0);
+                            folds::chevron(ui, folded);
+                            avatar
+                        })
+                        .inner;
                     ui.add_space(gap);
-                    let role_label = match &msg.role {
-                        Role::User => "User".to_string(),
-                        Role::Assistant => "Assistant".to_string(),
-                        Role::System => "System".to_string(),
-                        Role::Other(r) => title_case(r),
-                    };
+                    let role_label = role_label(&msg.role);
                     // Assistant: bubble and copy bar in the same fixed-width column
                     bubble_w_for_copy = assist_max_width;
                     let key = format!("msg-{}", index);
                     ui.allocate_ui_with_layout(egui::vec2(assist_max_width, 0.0), Layout::top_down(Align::LEFT), |col| {
-                        render_bubble(col, bg, assist_max_width, role_badge.as_ref(), &msg.content, cache, scale, &role_label, false, &key);
-                        col.add_space(2.0);
-                        render_copy_bar(col, assist_max_width, &role_label, &msg.content, false);
+                        let bubble = render_bubble(col, bg, assist_max_width, role_badge.as_ref(), &msg.content, preview.as_deref(), cache, scale, &role_label, false, &key);
+                        if let Some(a) = annotation.filter(|_| !*folded) {
+                            render_judge_note(col, assist_max_width, a);
+                        }
+                        if display.copy_bars && !*folded {
+                            col.add_space(2.0);
+                            match render_copy_bar(col, assist_max_width, &role_label, &msg.content, msg.detail.as_deref(), false) {
+                                CopyRequest::Image => snapshot::request_snapshot(col.ctx(), avatar.map_or(bubble, |a| a.union(bubble)).intersect(col.clip_rect())),
+                                CopyRequest::Reference => copy_reference = true,
+                                CopyRequest::None => {}
+                            }
+                        }
                     });
                 }
             });
@@ -536,11 +1925,139 @@ fn render_message_bubble(ui: &mut egui::Ui, msg: &Msg, index: usize, content_wid
 
         // Leave inter-message spacing to the outer loop for consistency
     });
+    copy_reference
+}
+
+// Document layout: the speaker as a small heading over full-width text, messages divided by a thin
+// rule. Long technical answers read like a page instead of a narrow bubble.
+#[allow(clippy::too_many_arguments)]
+fn render_document_message(
+    ui: &mut egui::Ui,
+    msg: &Msg,
+    index: usize,
+    content_width: f32,
+    dark: bool,
+    cache: &mut CommonMarkCache,
+    scale: f32,
+    annotation: Option<&JudgeAnnotation>,
+    fmt: &Formatter,
+    display: DisplayOptions,
+    folded: &mut bool,
+) -> bool {
+    // Same right-side gutter as the bubbles, clear of the scrollbar
+    let width = (content_width - 20.0).max(160.0);
+    let accent = msg.agent.map_or_else(|| role_accent(&msg.role, dark), |slot| agent_color(slot, dark));
+    let heading = match &msg.name {
+        Some(name) if display.role_badges => name.clone(),
+        _ => role_label(&msg.role),
+    };
+    let role_label = role_label(&msg.role);
+    let mut copy_reference = false;
+    ui.allocate_ui_with_layout(egui::vec2(width, 0.0), Layout::top_down(Align::LEFT), |col| {
+        let top = col.cursor().min;
+        col.horizontal(|ui| {
+            folds::chevron(ui, folded);
+            let (dot, _) = ui.allocate_exact_size(egui::vec2(8.0, 8.0), egui::Sense::hover());
+            ui.painter().circle_filled(dot.center(), 4.0, accent);
+            ui.label(RichText::new(heading).small().strong()).on_hover_ui(|ui| {
+                ui.label(reading::describe(reading::words(&msg.content), fmt));
+            });
+        });
+        col.add_space(4.0);
+        if *folded {
+            col.add(Label::new(RichText::new(first_sentence(&msg.content)).weak()).truncate(true));
+        } else {
+            render_markdown_with_width(col, &msg.content, width, cache, Some(scale), &format!("msg-{index}"));
+        }
+        let text = egui::Rect::from_min_max(top, egui::pos2(top.x + width, col.min_rect().bottom()));
+        if let Some(a) = annotation.filter(|_| !*folded) {
+            render_judge_note(col, width, a);
+        }
+        if display.copy_bars && !*folded {
+            col.add_space(4.0);
+            match render_copy_bar(col, width, &role_label, &msg.content, msg.detail.as_deref(), false) {
+                CopyRequest::Image => snapshot::request_snapshot(col.ctx(), text.intersect(col.clip_rect())),
+                CopyRequest::Reference => copy_reference = true,
+                CopyRequest::None => {}
+            }
+        }
+        col.add(egui::Separator::default().spacing(12.0));
+    });
+    copy_reference
+}
+
+fn is_tool_card(msg: &Msg) -> bool {
+    matches!(&msg.role, Role::Other(r) if r == "tool")
+}
+
+// OpenAI's `developer` role (system instructions in newer APIs); drawn like the system prompt.
+fn is_developer(msg: &Msg) -> bool {
+    matches!(&msg.role, Role::Other(r) if r == "developer")
+}
+
+// Messages drawn as bubbles; tool cards collapse on their own.
+fn is_foldable(msg: &Msg) -> bool {
+    !is_tool_card(msg) && !is_developer(msg)
+}
+
+fn tool_card_state(ctx: &egui::Context, index: usize) -> egui::collapsing_header::CollapsingState {
+    egui::collapsing_header::CollapsingState::load_with_default_open(ctx, Id::new(("tool-card", index)), false)
+}
+
+// Tool invocations (agent traces, tool results) are collapsed by default to keep the dialogue readable.
+// Child runs of a tool are indented under it.
+fn render_tool_card(ui: &mut egui::Ui, msg: &Msg, index: usize, content_width: f32, cache: &mut CommonMarkCache, scale: f32) {
+    let avatar_w = 28.0;
+    let gap = 8.0;
+    let indent = avatar_w + gap + 16.0 * msg.depth as f32;
+    let width = (content_width - indent).clamp(160.0, 800.0);
+    let title = match &msg.name {
+        Some(name) => format!("Tool · {name}"),
+        None => "Tool".to_string(),
+    };
+    ui.horizontal(|ui| {
+        ui.add_space(indent);
+        ui.allocate_ui_with_layout(egui::vec2(width, 0.0), Layout::top_down(Align::LEFT), |col| {
+            Frame::none()
+                .stroke(egui::Stroke::new(1.0, col.visuals().widgets.noninteractive.bg_stroke.color))
+                .rounding(Rounding::same(8.0))
+                .inner_margin(egui::Margin::symmetric(10.0, 6.0))
+                .show(col, |ui| {
+                    ui.set_width(width - 20.0);
+                    tool_card_state(ui.ctx(), index)
+                        .show_header(ui, |ui| {
+                            ui.label(RichText::new(title).small().strong());
+                        })
+                        .body(|ui| {
+                            render_markdown_with_width(ui, &msg.content, width - 20.0, cache, Some(scale), &format!("msg-{index}"));
+                        });
+                });
+        });
+    });
+}
+
+fn render_gap_marker(ui: &mut egui::Ui, gap_ms: i64, resumed_at: &str) {
+    ui.add_space(2.0);
+    ui.vertical_centered(|ui| {
+        ui.label(RichText::new(format!("— {} later —", format_duration(gap_ms))).small().weak())
+            .on_hover_text(format!("Resumed {resumed_at}"));
+    });
+    ui.add_space(8.0);
+}
+
+fn render_judge_note(ui: &mut egui::Ui, max_width: f32, a: &JudgeAnnotation) {
+    ui.add_space(4.0);
+    let text = if a.rationale.is_empty() {
+        format!("Judge: {:.1}", a.score)
+    } else {
+        format!("Judge: {:.1} — {}", a.score, a.rationale)
+    };
+    ui.add_sized(egui::vec2(max_width, 0.0), Label::new(RichText::new(text).small().italics()).wrap(true));
 }
 
-fn draw_avatar(ui: &mut egui::Ui, initial: &str, bg: Color32, fg: Color32) {
+fn draw_avatar(ui: &mut egui::Ui, initial: &str, bg: Color32, fg: Color32) -> egui::Response {
     let size = egui::vec2(28.0, 28.0);
-    let (rect, _resp) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let (rect, resp) = ui.allocate_exact_size(size, egui::Sense::hover());
     let radius = size.x.min(size.y) * 0.5;
     let painter = ui.painter();
     painter.circle_filled(rect.center(), radius, bg);
@@ -551,6 +2068,7 @@ fn draw_avatar(ui: &mut egui::Ui, initial: &str, bg: Color32, fg: Color32) {
         egui::FontId::new(14.0, egui::FontFamily::Proportional),
         fg,
     );
+    resp
 }
 
 fn render_bubble(
@@ -559,27 +2077,33 @@ fn render_bubble(
     max_width: f32,
     role_badge: Option<&String>,
     content: &str,
+    // One line shown instead of the content while the message is folded
+    preview: Option<&str>,
     cache: &mut CommonMarkCache,
     scale: f32,
     role_label: &str,
     copy_inside_left: bool,
     viewer_key: &str,
-) {
+) -> egui::Rect {
     Frame::none()
         .fill(bg)
         .rounding(Rounding::same(14.0))
         .inner_margin(egui::Margin::symmetric(12.0, 10.0))
         .show(ui, |ui| {
             ui.set_max_width(max_width);
-            // Optional role badge at the top for non-user/assistant roles
+            // Optional badge at the top: speaker name, or the role for non-user/assistant roles
             if let Some(badge) = role_badge {
-                let badge = format!("{}", title_case(badge));
                 ui.add(Label::new(RichText::new(badge).small().italics()).wrap(true));
                 ui.add_space(4.0);
             }
 
             // Main content
-            render_markdown_with_width(ui, content, max_width, cache, Some(scale), viewer_key);
+            match preview {
+                Some(preview) => {
+                    ui.add(Label::new(RichText::new(preview).weak()).truncate(true));
+                }
+                None => render_markdown_with_width(ui, content, max_width, cache, Some(scale), viewer_key),
+            }
             if copy_inside_left {
                 ui.add_space(6.0);
                 // Bottom-right inside bubble for assistant
@@ -590,11 +2114,28 @@ fn render_bubble(
                     }
                 });
             }
-        });
+        })
+        .response
+        .rect
+}
+
+// Copy actions under a bubble that need more than the message text
+enum CopyRequest {
+    None,
+    Image,
+    Reference,
 }
 
-fn render_copy_bar(ui: &mut egui::Ui, max_width: f32, role_label: &str, content: &str, align_right: bool) {
+fn render_copy_bar(
+    ui: &mut egui::Ui,
+    max_width: f32,
+    role_label: &str,
+    content: &str,
+    detail: Option<&str>,
+    align_right: bool,
+) -> CopyRequest {
     // Subtle bar under the bubble with configurable alignment
+    let mut request = CopyRequest::None;
     Frame::none()
         .show(ui, |ui| {
             ui.set_min_width(max_width);
@@ -605,6 +2146,15 @@ fn render_copy_bar(ui: &mut egui::Ui, max_width: f32, role_label: &str, content:
                         let md = format!("**{}**  \n{}\n", role_label, content);
                         ui.output_mut(|o| o.copied_text = md);
                     }
+                    if ui.small_button("Copy as image").on_hover_text("Copy this message as a picture").clicked() {
+                        request = CopyRequest::Image;
+                    }
+                    if ui.small_button("Copy reference").on_hover_text("Copy a link to this message (file#msg-N)").clicked() {
+                        request = CopyRequest::Reference;
+                    }
+                    if let Some(detail) = detail {
+                        ui.label(RichText::new(detail).small().weak());
+                    }
                 });
             } else {
                 ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
@@ -612,9 +2162,19 @@ fn render_copy_bar(ui: &mut egui::Ui, max_width: f32, role_label: &str, content:
                         let md = format!("**{}**  \n{}\n", role_label, content);
                         ui.output_mut(|o| o.copied_text = md);
                     }
+                    if ui.small_button("Copy as image").on_hover_text("Copy this message as a picture").clicked() {
+                        request = CopyRequest::Image;
+                    }
+                    if ui.small_button("Copy reference").on_hover_text("Copy a link to this message (file#msg-N)").clicked() {
+                        request = CopyRequest::Reference;
+                    }
+                    if let Some(detail) = detail {
+                        ui.label(RichText::new(detail).small().weak());
+                    }
                 });
             }
         });
+    request
 }
 
 fn render_markdown_with_width(
@@ -626,11 +2186,37 @@ fn render_markdown_with_width(
     viewer_key: &str,
 ) {
     ui.set_max_width(content_width);
+    // Very long messages show their start until expanded
+    let shown = long_messages::shown(ui.ctx(), viewer_key, text);
+    match reasoning::parts(&shown).as_slice() {
+        [reasoning::Part::Text(text)] => render_markdown_text(ui, text, cache, scale_override, viewer_key),
+        parts => {
+            for (i, part) in parts.iter().enumerate() {
+                let key = format!("{viewer_key}-{i}");
+                match part {
+                    reasoning::Part::Text(text) => render_markdown_text(ui, text, cache, scale_override, &key),
+                    reasoning::Part::Reasoning(text) => reasoning::show(ui, &key, text, |ui| {
+                        render_markdown_text(ui, text, cache, scale_override, &key)
+                    }),
+                }
+            }
+        }
+    }
+    long_messages::toggle_ui(ui, viewer_key, text);
+}
+
+fn render_markdown_text(
+    ui: &mut egui::Ui,
+    text: &str,
+    cache: &mut CommonMarkCache,
+    scale_override: Option<f32>,
+    viewer_key: &str,
+) {
+    // Sanitized once per message, not every frame
+    let prepared = layout_cache::prepared(ui.ctx(), text, html::policy(ui.ctx()));
     // Use a stable-but-unique viewer id per text to avoid layout/cache collisions
-    let id = format!("{}:{}", viewer_key, short_hash(text));
-    let mut viewer = CommonMarkViewer::new(&id);
-    // Sanitize common chat artifacts that look like code fences
-    let sanitized = sanitize_chat_markdown(text);
+    let id = format!("{}:{}", viewer_key, prepared.hash);
+    let sanitized = &*prepared.text;
     // Apply chat-only text scaling by temporarily adjusting text styles
     let content_scale = scale_override.unwrap_or_else(|| {
         // Read from a global-like hint stored via Ui memory? We don't have it here,
@@ -638,10 +2224,11 @@ fn render_markdown_with_width(
         1.0
     });
 
-    if (content_scale - 1.0).abs() < f32::EPSILON {
+    let spacing = fonts::spacing(ui.ctx());
+    if (content_scale - 1.0).abs() < f32::EPSILON && spacing == TextSpacing::default() {
         ui.scope(|ui| {
             ui.spacing_mut().item_spacing.y = 0.0; // minimize intra-markdown vertical gaps
-            viewer.show(ui, cache, &sanitized);
+            code_folds::show(ui, cache, &id, sanitized, &prepared.folds);
         });
         return;
     }
@@ -652,40 +2239,574 @@ fn render_markdown_with_width(
     for ts in [egui::TextStyle::Body, egui::TextStyle::Monospace, egui::TextStyle::Heading] {
         if let Some(font) = style.text_styles.get_mut(&ts) {
             font.size *= content_scale;
+            *font = fonts::spaced_font(font, &spacing);
         }
     }
     ui.set_style(style);
     ui.scope(|ui| {
-        ui.spacing_mut().item_spacing.y = 0.0;
-        viewer.show(ui, cache, &sanitized);
+        ui.spacing_mut().item_spacing.y = spacing.paragraph;
+        code_folds::show(ui, cache, &id, sanitized, &prepared.folds);
     });
     ui.set_style(prev_style);
 }
 
 // Markdown parsing is delegated to egui_commonmark.
 
-fn to_markdown(state: &AppState) -> String {
+// What the exporters see of the app: the shown conversation and nothing else, so exports can be
+// produced (and tested) without a window.
+struct Transcript<'a> {
+    system: Option<&'a str>,
+    messages: &'a [Msg],
+    file_name: Option<&'a str>,
+    // HTML colours follow the viewer's theme
+    dark: bool,
+    html: HtmlPolicy,
+    // Avatars and role badges in the HTML export
+    display: DisplayOptions,
+    // Footer of HTML and Markdown exports; left out of copies
+    provenance: Option<provenance::Provenance>,
+}
+
+impl AppState {
+    fn transcript(&self) -> Transcript<'_> {
+        Transcript {
+            system: self.system.as_deref(),
+            messages: &self.messages,
+            file_name: self.file_name.as_deref(),
+            dark: self.theme_dark,
+            html: self.settings.html_policy,
+            display: self.settings.display,
+            provenance: None,
+        }
+    }
+}
+
+fn to_markdown(conv: &Transcript) -> String {
     let mut out = String::new();
-    if let Some(sys) = &state.system {
+    if let Some(sys) = conv.system {
         out.push_str("# System\n");
         out.push_str(sys);
         out.push_str("\n\n---\n\n");
     }
-    for msg in &state.messages {
-        let role_label = match &msg.role {
-            Role::User => "User".to_string(),
-            Role::Assistant => "Assistant".to_string(),
-            Role::System => "System".to_string(),
-            Role::Other(r) => title_case(r),
+    for msg in conv.messages {
+        out.push_str(&format!("**{}**  \n{}\n\n", speaker_label(msg), msg.content));
+    }
+    out
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MarkdownFlavor {
+    Plain,
+    // Callout block per message, with Obsidian-only syntax escaped
+    Obsidian,
+    // H2/H3 headings only, collapsible toggles for system prompts and tool cards
+    Notion,
+}
+
+impl MarkdownFlavor {
+    fn label(self) -> &'static str {
+        match self {
+            MarkdownFlavor::Plain => "Plain…",
+            MarkdownFlavor::Obsidian => "Obsidian…",
+            MarkdownFlavor::Notion => "Notion…",
+        }
+    }
+}
+
+fn to_markdown_flavor(conv: &Transcript, flavor: MarkdownFlavor) -> String {
+    let mut out = match flavor {
+        MarkdownFlavor::Plain => to_markdown(conv),
+        MarkdownFlavor::Obsidian => to_obsidian_markdown(conv),
+        MarkdownFlavor::Notion => to_notion_markdown(conv),
+    };
+    if let Some(provenance) = &conv.provenance {
+        out.push_str(&provenance.markdown());
+    }
+    out
+}
+
+// Speaker shown in exported headings: the name if there is one, otherwise the role.
+fn speaker_label(msg: &Msg) -> String {
+    match (&msg.name, is_tool_card(msg)) {
+        (Some(name), true) => format!("Tool · {name}"),
+        (Some(name), false) => name.clone(),
+        (None, _) => role_label(&msg.role),
+    }
+}
+
+// Apply `f` to every line outside fenced code blocks.
+fn map_prose_lines(text: &str, mut f: impl FnMut(&str) -> String) -> String {
+    let mut in_fence = false;
+    text.lines()
+        .map(|line| {
+            let t = line.trim_start();
+            if t.starts_with("```") || t.starts_with("~~~") {
+                in_fence = !in_fence;
+                return line.to_string();
+            }
+            if in_fence { line.to_string() } else { f(line) }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Escape syntax Obsidian adds on top of Markdown (wikilinks, embeds, tags, highlights,
+// comments, block references) so chat text is not turned into links or tags. Inline code is left alone.
+fn obsidian_escape_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    for (i, segment) in line.split('`').enumerate() {
+        if i > 0 {
+            out.push('`');
+        }
+        if i % 2 == 1 {
+            out.push_str(segment);
+            continue;
+        }
+        let mut prev: Option<char> = None;
+        let mut chars = segment.chars().peekable();
+        while let Some(c) = chars.next() {
+            let next = chars.peek().copied();
+            let escape = match c {
+                '[' | ']' | '=' | '%' => next == Some(c),
+                '#' => prev.is_none_or(char::is_whitespace) && next.is_some_and(|n| n.is_alphanumeric() || n == '_'),
+                '^' => prev.is_some_and(char::is_whitespace) && next.is_some_and(char::is_alphanumeric),
+                _ => false,
+            };
+            if escape {
+                out.push('\\');
+            }
+            out.push(c);
+            prev = Some(c);
+        }
+    }
+    out
+}
+
+// `folded` adds the "-" marker so the callout starts collapsed.
+fn obsidian_callout(out: &mut String, kind: &str, folded: bool, title: &str, body: &str) {
+    out.push_str(&format!("> [!{kind}]{} {title}\n", if folded { "-" } else { "" }));
+    for line in map_prose_lines(body, obsidian_escape_line).lines() {
+        out.push_str(if line.is_empty() { ">" } else { "> " });
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push('\n');
+}
+
+fn to_obsidian_markdown(conv: &Transcript) -> String {
+    let mut out = String::new();
+    out.push_str("---\n");
+    if let Some(name) = conv.file_name {
+        out.push_str(&format!("source: \"{}\"\n", name.replace('"', "'")));
+    }
+    out.push_str(&format!("messages: {}\n", conv.messages.len()));
+    if let Some((start, _)) = session_span(conv.messages) {
+        if let Some(t) = chrono::DateTime::from_timestamp_millis(start) {
+            out.push_str(&format!("date: {}\n", t.format("%Y-%m-%d")));
+        }
+    }
+    out.push_str("tags: [chat-log]\n---\n\n");
+    if let Some(sys) = conv.system {
+        obsidian_callout(&mut out, "abstract", true, "System", sys);
+    }
+    for msg in conv.messages {
+        let kind = match &msg.role {
+            Role::User => "question",
+            Role::Assistant => "info",
+            Role::System => "abstract",
+            Role::Other(_) if is_tool_card(msg) => "example",
+            Role::Other(_) => "note",
+        };
+        obsidian_callout(&mut out, kind, is_tool_card(msg), &speaker_label(msg), &msg.content);
+    }
+    out
+}
+
+// Headings inside a message would break the page outline; make them bold paragraphs instead.
+fn notion_demote_heading(line: &str) -> String {
+    let t = line.trim_start();
+    let level = t.chars().take_while(|c| *c == '#').count();
+    match t[level..].strip_prefix(' ') {
+        Some(text) if (1..=6).contains(&level) && !text.trim().is_empty() => format!("**{}**", text.trim()),
+        _ => line.to_string(),
+    }
+}
+
+fn notion_toggle(out: &mut String, summary: &str, body: &str) {
+    out.push_str(&format!("<details>\n<summary>{}</summary>\n\n", html_escape(summary)));
+    out.push_str(&map_prose_lines(body, notion_demote_heading));
+    out.push_str("\n\n</details>\n\n");
+}
+
+fn to_notion_markdown(conv: &Transcript) -> String {
+    let mut out = String::new();
+    let title = conv.file_name.and_then(|f| Path::new(f).file_stem()).and_then(|s| s.to_str());
+    out.push_str(&format!("# {}\n\n", title.unwrap_or("Chat")));
+    if let Some(sys) = conv.system {
+        notion_toggle(&mut out, "System prompt", sys);
+    }
+    for (i, msg) in conv.messages.iter().enumerate() {
+        if is_tool_card(msg) {
+            notion_toggle(&mut out, &speaker_label(msg), &msg.content);
+            continue;
+        }
+        out.push_str(&format!("## {} · {}\n\n", i + 1, speaker_label(msg)));
+        out.push_str(&map_prose_lines(&msg.content, notion_demote_heading));
+        out.push_str("\n\n");
+    }
+    out
+}
+
+// Notebook cell source: a list of lines that keep their trailing newline (except the last).
+fn notebook_source(text: &str) -> serde_json::Value {
+    let text = text.trim_matches('\n');
+    let mut lines: Vec<String> = text.split_inclusive('\n').map(str::to_string).collect();
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines.into()
+}
+
+fn markdown_cell(text: &str) -> serde_json::Value {
+    serde_json::json!({ "cell_type": "markdown", "metadata": {}, "source": notebook_source(text) })
+}
+
+fn code_cell(code: &str) -> serde_json::Value {
+    serde_json::json!({
+        "cell_type": "code",
+        "execution_count": null,
+        "metadata": {},
+        "outputs": [],
+        "source": notebook_source(code),
+    })
+}
+
+// User turns and prose become Markdown cells; the assistant's Python and shell code blocks
+// become code cells (shell via `%%bash`). Blocks in other languages stay fenced in Markdown.
+fn to_ipynb(conv: &Transcript) -> String {
+    let mut cells = Vec::new();
+    if let Some(sys) = conv.system {
+        cells.push(markdown_cell(&format!("### System\n\n{sys}")));
+    }
+    for msg in conv.messages {
+        let heading = format!("### {}", speaker_label(msg));
+        if !matches!(msg.role, Role::Assistant) {
+            cells.push(markdown_cell(&format!("{heading}\n\n{}", msg.content)));
+            continue;
+        }
+        let mut prose = heading;
+        for segment in fenced_segments(&msg.content) {
+            let code = match &segment {
+                TextSegment::Code { lang, code } => match lang.to_lowercase().as_str() {
+                    "" | "python" | "py" | "python3" | "ipython" => Some(code.clone()),
+                    "bash" | "sh" | "shell" | "zsh" => Some(format!("%%bash\n{code}")),
+                    _ => None,
+                },
+                TextSegment::Prose(_) => None,
+            };
+            match code {
+                Some(code) => {
+                    if !prose.trim().is_empty() {
+                        cells.push(markdown_cell(&prose));
+                    }
+                    prose.clear();
+                    cells.push(code_cell(&code));
+                }
+                None => {
+                    prose.push_str("\n\n");
+                    prose.push_str(&segment.to_markdown());
+                }
+            }
+        }
+        if !prose.trim().is_empty() {
+            cells.push(markdown_cell(&prose));
+        }
+    }
+    let notebook = serde_json::json!({
+        "cells": cells,
+        "metadata": {
+            "kernelspec": { "display_name": "Python 3", "language": "python", "name": "python3" },
+            "language_info": { "name": "python" },
+        },
+        "nbformat": 4,
+        "nbformat_minor": 4,
+    });
+    serde_json::to_string_pretty(&notebook).unwrap_or_default()
+}
+
+// File extension for a fence language tag, or a guess from the code when the tag is missing.
+fn code_extension(lang: &str, code: &str) -> &'static str {
+    match lang.to_lowercase().as_str() {
+        "python" | "py" | "python3" => "py",
+        "rust" | "rs" => "rs",
+        "javascript" | "js" | "node" => "js",
+        "typescript" | "ts" => "ts",
+        "jsx" => "jsx",
+        "tsx" => "tsx",
+        "bash" | "sh" | "shell" | "zsh" | "console" => "sh",
+        "powershell" | "ps1" => "ps1",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "html" => "html",
+        "css" => "css",
+        "sql" => "sql",
+        "go" | "golang" => "go",
+        "java" => "java",
+        "kotlin" | "kt" => "kt",
+        "swift" => "swift",
+        "c" => "c",
+        "cpp" | "c++" | "cc" => "cpp",
+        "csharp" | "c#" | "cs" => "cs",
+        "ruby" | "rb" => "rb",
+        "php" => "php",
+        "markdown" | "md" => "md",
+        "xml" => "xml",
+        "dockerfile" => "Dockerfile",
+        "diff" | "patch" => "diff",
+        "" => {
+            let head = code.trim_start();
+            if head.starts_with("#!") && head.lines().next().is_some_and(|l| l.contains("python")) {
+                "py"
+            } else if head.starts_with("#!") || head.starts_with("$ ") {
+                "sh"
+            } else if (head.starts_with('{') || head.starts_with('['))
+                && serde_json::from_str::<serde_json::Value>(code).is_ok()
+            {
+                "json"
+            } else if head.starts_with("<!DOCTYPE") || head.starts_with("<html") {
+                "html"
+            } else if head.starts_with("def ") || head.starts_with("import ") || head.starts_with("from ") {
+                "py"
+            } else if head.starts_with("fn ") || head.starts_with("use ") || head.starts_with("pub ") {
+                "rs"
+            } else {
+                "txt"
+            }
+        }
+        _ => "txt",
+    }
+}
+
+// Write each fenced code block to `msg<NNN>_<K>.<ext>` plus an INDEX.md mapping files to messages.
+// Files already there are backed up first when `backups` is on.
+fn extract_code_blocks(conv: &Transcript, dir: &Path, backups: bool) -> Result<usize> {
+    let mut backup = Backup::new(backups);
+    let mut index = String::from("| File | Message | Speaker | Language |\n|---|---|---|---|\n");
+    let mut written = 0;
+    for (i, msg) in conv.messages.iter().enumerate() {
+        // Tool cards wrap their input/output in fences of their own
+        if is_tool_card(msg) {
+            continue;
+        }
+        let blocks = fenced_segments(&msg.content).into_iter().filter_map(|s| match s {
+            TextSegment::Code { lang, code } if !code.trim().is_empty() => Some((lang, code)),
+            _ => None,
+        });
+        for (k, (lang, code)) in blocks.enumerate() {
+            let ext = code_extension(&lang, &code);
+            let file = if ext == "Dockerfile" {
+                format!("msg{:03}_{}.Dockerfile", i + 1, k + 1)
+            } else {
+                format!("msg{:03}_{}.{ext}", i + 1, k + 1)
+            };
+            backup.keep(&dir.join(&file))?;
+            fs::write(dir.join(&file), format!("{}\n", code.trim_end()))
+                .with_context(|| format!("Failed to write {file}"))?;
+            let lang = if lang.is_empty() { "(none)" } else { lang.as_str() };
+            index.push_str(&format!("| [{file}]({file}) | {} | {} | {lang} |\n", i + 1, speaker_label(msg)));
+            written += 1;
+        }
+    }
+    if written == 0 {
+        return Err(anyhow!("No code blocks in this conversation"));
+    }
+    backup.keep(&dir.join("INDEX.md"))?;
+    fs::write(dir.join("INDEX.md"), index).context("Failed to write INDEX.md")?;
+    Ok(written)
+}
+
+fn is_shell_lang(lang: &str) -> bool {
+    matches!(lang.to_lowercase().as_str(), "bash" | "sh" | "shell" | "zsh" | "console" | "shell-session" | "terminal")
+}
+
+// Commands of one shell block. Session-style blocks (`$ cmd` followed by its output) keep only
+// the prompted lines; plain scripts keep every line but comments and blanks. Lines continued
+// with a trailing backslash stay together.
+fn shell_commands(block: &str) -> Vec<String> {
+    let prompted = block.lines().any(|l| l.trim_start().starts_with("$ "));
+    let mut commands: Vec<String> = Vec::new();
+    let mut continued = false;
+    for line in block.lines() {
+        let line = line.trim_end();
+        if continued {
+            if let Some(last) = commands.last_mut() {
+                last.push('\n');
+                last.push_str(line);
+            }
+            continued = line.ends_with('\\');
+            continue;
+        }
+        let command = if prompted { line.trim_start().strip_prefix("$ ").map(str::trim_start) } else { Some(line) };
+        let Some(command) = command.filter(|c| !c.trim().is_empty() && !c.trim_start().starts_with('#')) else {
+            continue;
+        };
+        continued = command.ends_with('\\');
+        commands.push(command.to_string());
+    }
+    commands
+}
+
+// Shell commands suggested in a message: ```bash/sh/console blocks, untagged blocks with `$ `
+// prompts, and `$ `-prefixed lines in the prose.
+fn message_commands(text: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    for segment in fenced_segments(text) {
+        match segment {
+            TextSegment::Code { lang, code } => {
+                let untagged_session = lang.is_empty() && code.lines().any(|l| l.trim_start().starts_with("$ "));
+                if is_shell_lang(&lang) || untagged_session {
+                    commands.extend(shell_commands(&code));
+                }
+            }
+            TextSegment::Prose(prose) => commands.extend(
+                prose.lines().filter_map(|l| l.trim().strip_prefix("$ ")).map(|c| c.trim().to_string()).filter(|c| !c.is_empty()),
+            ),
+        }
+    }
+    commands
+}
+
+// Cheap pre-check so message_commands only runs on messages that can contain commands.
+fn may_contain_commands(msg: &Msg) -> bool {
+    !is_tool_card(msg) && (msg.content.contains("```") || msg.content.contains("$ "))
+}
+
+// Rough token count without a tokenizer: about four characters per token for
+// Latin text, one per character for CJK and other non-ASCII scripts.
+fn estimate_tokens(text: &str) -> usize {
+    let ascii = text.chars().filter(char::is_ascii).count();
+    let other = text.chars().count() - ascii;
+    ascii.div_ceil(4) + other
+}
+
+// The shape of the conversation with all text removed, for sharing when the content is confidential.
+fn to_skeleton_json(conv: &Transcript) -> String {
+    let messages: Vec<serde_json::Value> = conv
+        .messages
+        .iter()
+        .enumerate()
+        .map(|(i, msg)| {
+            let mut entry = serde_json::json!({
+                "index": i,
+                "role": role_label(&msg.role).to_lowercase(),
+                "tokens": estimate_tokens(&msg.content),
+            });
+            if let Some(name) = &msg.name {
+                entry["name"] = name.clone().into();
+            }
+            if let Some(t) = msg.timestamp.and_then(chrono::DateTime::from_timestamp_millis) {
+                entry["timestamp"] = t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true).into();
+            }
+            if msg.depth > 0 {
+                entry["depth"] = msg.depth.into();
+            }
+            entry
+        })
+        .collect();
+    let system_tokens = conv.system.map(estimate_tokens);
+    let total = system_tokens.unwrap_or(0) + conv.messages.iter().map(|m| estimate_tokens(&m.content)).sum::<usize>();
+    let skeleton = serde_json::json!({
+        "system_tokens": system_tokens,
+        "total_tokens": total,
+        "messages": messages,
+    });
+    serde_json::to_string_pretty(&skeleton).unwrap_or_default()
+}
+
+// A skimmable digest of a long transcript: one numbered line per turn with the speaker and the
+// first sentence, optionally with estimated token counts.
+fn to_outline(conv: &Transcript, tokens: bool) -> String {
+    let count = |text: &str| if tokens { format!(" ({} tokens)", estimate_tokens(text)) } else { String::new() };
+    let title = conv.file_name.and_then(|f| Path::new(f).file_stem()).and_then(|s| s.to_str()).unwrap_or("Conversation");
+    let mut out = format!("# {title} — outline\n\n");
+    if let Some(sys) = conv.system {
+        out.push_str(&format!("**System**{}: {}\n\n", count(sys), first_sentence(sys)));
+    }
+    for (i, msg) in conv.messages.iter().enumerate() {
+        let speaker = match &msg.name {
+            Some(name) => format!("{} ({name})", role_label(&msg.role)),
+            None => role_label(&msg.role),
         };
-        out.push_str(&format!("**{}**  \n{}\n\n", role_label, msg.content));
+        out.push_str(&format!("{}. **{speaker}**{}: {}\n", i + 1, count(&msg.content), first_sentence(&msg.content)));
+    }
+    if tokens {
+        let total = conv.system.map_or(0, estimate_tokens) + conv.messages.iter().map(|m| estimate_tokens(&m.content)).sum::<usize>();
+        out.push_str(&format!("\nTotal: {total} tokens\n"));
     }
     out
 }
 
-fn to_html(state: &AppState) -> String {
+// Opening sentence of the first paragraph of prose, without quote and list markers.
+// Messages that are only code are summarized by their fence.
+fn first_sentence(text: &str) -> String {
+    const MAX_CHARS: usize = 160;
+    // Previews start at the answer, not the reasoning
+    let segments = fenced_segments(&reasoning::answer(text));
+    let prose = || segments.iter().filter_map(|s| if let TextSegment::Prose(p) = s { Some(p.as_str()) } else { None });
+    // Headings end a paragraph, and are used only when there is nothing else
+    let paragraph = prose().find_map(|prose| {
+        let lines: Vec<&str> = prose
+            .lines()
+            .map(|l| {
+                let l = l.trim().trim_start_matches('>').trim_start();
+                let l = if l.starts_with('#') { "" } else { l };
+                l.strip_prefix("- ").or_else(|| l.strip_prefix("* ")).unwrap_or(l)
+            })
+            .skip_while(|l| l.is_empty())
+            .take_while(|l| !l.is_empty())
+            .collect();
+        (!lines.is_empty()).then(|| lines.join(" "))
+    });
+    let heading = || prose().flat_map(str::lines).find_map(|l| l.trim().strip_prefix('#')).map(|h| h.trim_start_matches('#').trim().to_string());
+    let Some(paragraph) = paragraph.or_else(heading).filter(|p| !p.is_empty()) else {
+        return match segments.first() {
+            Some(TextSegment::Code { lang, .. }) if !lang.is_empty() => format!("[{lang} code]"),
+            Some(TextSegment::Code { .. }) => "[code]".to_string(),
+            _ => "(empty)".to_string(),
+        };
+    };
+    let chars: Vec<char> = paragraph.chars().collect();
+    let end = (0..chars.len())
+        .find(|&i| {
+            matches!(chars[i], '。' | '！' | '？')
+                || (matches!(chars[i], '.' | '!' | '?') && chars.get(i + 1).is_none_or(|c| c.is_whitespace()))
+        })
+        .map_or(chars.len(), |i| i + 1);
+    if end > MAX_CHARS {
+        chars[..MAX_CHARS].iter().collect::<String>().trim_end().to_string() + "…"
+    } else {
+        chars[..end].iter().collect()
+    }
+}
+
+// Message text as HTML; reasoning becomes a closed `<details>` above the answer.
+fn content_html(content: &str, policy: HtmlPolicy) -> String {
+    reasoning::parts(content)
+        .iter()
+        .map(|part| match part {
+            reasoning::Part::Text(text) => html::export_html(&sanitize_chat_markdown(text), policy),
+            reasoning::Part::Reasoning(text) => format!(
+                "<details class=\"reasoning\"><summary>Reasoning</summary>{}</details>",
+                html::export_html(&sanitize_chat_markdown(text), policy)
+            ),
+        })
+        .collect()
+}
+
+fn to_html(conv: &Transcript) -> String {
     let mut out = String::new();
-    let dark = state.theme_dark;
+    let dark = conv.dark;
     let (bg_body, fg_body, bg_assist, bg_user, avatar_user_bg, avatar_assist_bg, avatar_user_fg, avatar_assist_fg) = if dark {
         (
             "#121212", "#eaeaea",
@@ -724,48 +2845,65 @@ fn to_html(state: &AppState) -> String {
     out.push_str(".content{ }\n");
     out.push_str(".role{ font-weight:600; margin-bottom:6px; opacity:0.8;}\n");
     out.push_str(".bubble pre, .system pre{ background: rgba(127,127,127,0.15); border:1px solid rgba(127,127,127,0.25); border-radius:8px; padding:10px; overflow:auto; white-space:pre; margin:8px 0 0 0; }\n");
+    out.push_str(".fold{ display:flex; flex-direction:column-reverse; }\n.fold summary{ cursor:pointer; font-size:0.85em; opacity:0.8; margin-top:4px; }\n.fold details[open] + pre, .fold details[open] .more, .fold details:not([open]) .less{ display:none; }\n");
+    out.push_str(".reasoning{ opacity:0.75; margin-bottom:8px; }\n.reasoning summary{ cursor:pointer; font-style:italic; }\n");
+    out.push_str(".provenance{ margin-top:24px; padding-top:8px; border-top:1px solid rgba(127,127,127,0.25); font-size:0.8em; opacity:0.7; }\n");
     out.push_str(".bubble code{ font-family: ui-monospace, SFMono-Regular, Menlo, Monaco, Consolas, \"Liberation Mono\", \"Courier New\", monospace; font-size: 0.95em; }\n");
     out.push_str("</style></head><body><div class=\"container\">\n");
 
-    if let Some(sys) = &state.system {
+    if let Some(sys) = conv.system {
         out.push_str("<div class=\"system\">\n<div class=\"role\">System</div>\n");
         let sanitized = sanitize_chat_markdown(sys);
-        out.push_str(&format!("<div class=\"content\">{}</div>\n", text_to_html_with_fences(&sanitized)));
+        out.push_str(&format!("<div class=\"content\">{}</div>\n", html::export_html(&sanitized, conv.html)));
         out.push_str("</div>\n");
     }
 
-    for msg in &state.messages {
+    for msg in conv.messages {
         let (cls, role, initial, show_role_badge) = match &msg.role {
             Role::User => ("user", "User", "U", false),
             Role::Assistant => ("assist", "Assistant", "A", false),
             Role::System => ("assist", "System", "S", true),
             Role::Other(r) => ("assist", &*title_case(r), "?", true),
         };
+        // A speaker name replaces the generic badge and initial, as in the viewer
+        let (role, initial, show_role_badge) = match &msg.name {
+            Some(name) => (
+                speaker_label(msg),
+                name.chars().next().map(|c| c.to_uppercase().collect()).unwrap_or_default(),
+                true,
+            ),
+            None => (role.to_string(), initial.to_string(), show_role_badge),
+        };
         out.push_str(&format!("<div class=\"row {}\">\n", cls));
         if matches!(&msg.role, Role::User) {
             // User: bubble first (right側に気泡、その右にアバター)
             out.push_str("<div class=\"bubble\">\n");
-            if show_role_badge {
-                out.push_str(&format!("<div class=\"role\">{}</div>\n", html_escape(role)));
+            if show_role_badge && conv.display.role_badges {
+                out.push_str(&format!("<div class=\"role\">{}</div>\n", html_escape(&role)));
             }
-            let sanitized = sanitize_chat_markdown(&msg.content);
-            out.push_str(&format!("<div class=\"content\">{}</div>\n", text_to_html_with_fences(&sanitized)));
+            out.push_str(&format!("<div class=\"content\">{}</div>\n", content_html(&msg.content, conv.html)));
             out.push_str("</div>\n");
-            out.push_str(&format!("<div class=\"avatar\">{}</div>\n", html_escape(initial)));
+            if conv.display.avatars {
+                out.push_str(&format!("<div class=\"avatar\">{}</div>\n", html_escape(&initial)));
+            }
         } else {
             // Assistant/Other: avatar first, then bubble
-            out.push_str(&format!("<div class=\"avatar\">{}</div>\n", html_escape(initial)));
+            if conv.display.avatars {
+                out.push_str(&format!("<div class=\"avatar\">{}</div>\n", html_escape(&initial)));
+            }
             out.push_str("<div class=\"bubble\">\n");
-            if show_role_badge {
-                out.push_str(&format!("<div class=\"role\">{}</div>\n", html_escape(role)));
+            if show_role_badge && conv.display.role_badges {
+                out.push_str(&format!("<div class=\"role\">{}</div>\n", html_escape(&role)));
             }
-            let sanitized = sanitize_chat_markdown(&msg.content);
-            out.push_str(&format!("<div class=\"content\">{}</div>\n", text_to_html_with_fences(&sanitized)));
+            out.push_str(&format!("<div class=\"content\">{}</div>\n", content_html(&msg.content, conv.html)));
             out.push_str("</div>\n");
         }
         out.push_str("</div>\n");
     }
 
+    if let Some(provenance) = &conv.provenance {
+        out.push_str(&provenance.html());
+    }
     out.push_str("</div></body></html>\n");
     out
 }
@@ -785,53 +2923,126 @@ fn html_escape(s: &str) -> String {
     out
 }
 
-fn text_to_html_with_fences(s: &str) -> String {
-    // Convert a subset of Markdown-like fences ```lang ... ``` into <pre><code> blocks.
-    // Outside code blocks, escape HTML and keep newlines (white-space: pre-wrap in CSS handles them).
+enum TextSegment {
+    Prose(String),
+    Code { lang: String, code: String },
+}
+
+impl TextSegment {
+    fn to_markdown(&self) -> String {
+        match self {
+            TextSegment::Prose(text) => text.trim_matches('\n').to_string(),
+            TextSegment::Code { lang, code } => format!("```{lang}\n{code}\n```"),
+        }
+    }
+}
+
+// Split message text at ``` fences. An unterminated fence runs to the end of the text.
+fn fenced_segments(text: &str) -> Vec<TextSegment> {
+    let mut segments = Vec::new();
+    let mut prose: Vec<&str> = Vec::new();
+    let mut code: Option<(String, Vec<&str>)> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match code.as_mut() {
+            Some(_) if trimmed.trim_end() == "```" => {
+                let (lang, lines) = code.take().expect("checked above");
+                segments.push(TextSegment::Code { lang, code: lines.join("\n") });
+            }
+            Some((_, lines)) => lines.push(line),
+            None => match trimmed.strip_prefix("```") {
+                Some(rest) => {
+                    if prose.iter().any(|l| !l.trim().is_empty()) {
+                        segments.push(TextSegment::Prose(prose.join("\n")));
+                    }
+                    prose.clear();
+                    code = Some((rest.trim().to_string(), Vec::new()));
+                }
+                None => prose.push(line),
+            },
+        }
+    }
+    if let Some((lang, lines)) = code {
+        segments.push(TextSegment::Code { lang, code: lines.join("\n") });
+    }
+    if prose.iter().any(|l| !l.trim().is_empty()) {
+        segments.push(TextSegment::Prose(prose.join("\n")));
+    }
+    segments
+}
+
+fn text_to_html_with_fences(s: &str, mut line_html: impl FnMut(&str) -> String) -> String {
+    // Convert a subset of Markdown-like fences ```lang ... ``` into <pre><code> blocks, long ones
+    // folded (see code_folds.rs).
+    // Outside code blocks, `line_html` renders each line (usually html_escape) and newlines are kept
+    // (white-space: pre-wrap in CSS handles them).
     let mut out = String::new();
     let mut in_fence = false;
     let mut fence_lang: Option<String> = None;
+    let mut code: Vec<&str> = Vec::new();
     for line in s.lines() {
         let trimmed = line.trim_start();
         if !in_fence {
             if let Some(rest) = trimmed.strip_prefix("```") {
                 // Start of fence
                 let lang = rest.trim();
-                let lang_safe = if lang.is_empty() { None } else { Some(lang.to_string()) };
-                fence_lang = lang_safe;
-                if let Some(lang) = &fence_lang {
-                    out.push_str(&format!("<pre><code class=\"language-{}\">", html_escape(lang)));
-                } else {
-                    out.push_str("<pre><code>");
-                }
+                fence_lang = if lang.is_empty() { None } else { Some(lang.to_string()) };
                 in_fence = true;
             } else {
-                out.push_str(&html_escape(line));
+                out.push_str(&line_html(line));
                 out.push('\n');
             }
         } else {
             // In fence: check for closing fence
             if trimmed.starts_with("```") && trimmed.trim() == "```" {
-                out.push_str("</code></pre>\n");
+                out.push_str(&code_folds::code_html(fence_lang.as_deref(), &code));
+                code.clear();
                 in_fence = false;
                 fence_lang = None;
             } else {
-                out.push_str(&html_escape(line));
-                out.push('\n');
+                code.push(line);
             }
         }
     }
     if in_fence {
-        out.push_str("</code></pre>\n");
+        out.push_str(&code_folds::code_html(fence_lang.as_deref(), &code));
     }
     out
 }
 
+// First and last message time, when the log has timestamps.
+fn session_span(messages: &[Msg]) -> Option<(i64, i64)> {
+    let mut times = messages.iter().filter_map(|m| m.timestamp);
+    let first = times.next()?;
+    Some(times.fold((first, first), |(lo, hi), t| (lo.min(t), hi.max(t))))
+}
+
+// "45s", "12m", "2h 14m", "3d 4h"
+fn format_duration(ms: i64) -> String {
+    let secs = ms.max(0) / 1000;
+    let (d, h, m) = (secs / 86_400, secs % 86_400 / 3600, secs % 3600 / 60);
+    match (d, h, m) {
+        (0, 0, 0) => format!("{secs}s"),
+        (0, 0, m) => format!("{m}m"),
+        (0, h, m) => format!("{h}h {m}m"),
+        (d, h, _) => format!("{d}d {h}h"),
+    }
+}
+
+fn role_label(role: &Role) -> String {
+    match role {
+        Role::User => "User".to_string(),
+        Role::Assistant => "Assistant".to_string(),
+        Role::System => "System".to_string(),
+        Role::Other(r) => title_case(r),
+    }
+}
+
 fn title_case(s: &str) -> String {
     let mut it = s.chars();
     match it.next() {
         None => String::new(),
-        Some(first) => first.to_uppercase().collect::<String>() + &it.as_str().to_lowercase(),
+        Some(first) => first.to_uppercase().collect::<String>() + it.as_str().to_lowercase().as_str(),
     }
 }
 
@@ -910,14 +3121,27 @@ fn trim_chat_whitespace(input: &str) -> String {
 }
 
 fn preferred_bubble_width(ui: &egui::Ui, text: &str, max_width: f32, scale: f32) -> f32 {
-    // Heuristic: estimate width from first non-empty line length and body font size
-    let first_line = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
-    let style = ui.style().clone();
-    let body_size = style.text_styles.get(&egui::TextStyle::Body).map(|f| f.size).unwrap_or(14.0);
-    // Approximate average character width as ~0.55 of font size
-    let char_w = body_size * 0.55 * scale;
-    let len = first_line.chars().take(80).count() as f32; // cap to avoid overly wide single lines
-    let padding = 24.0; // inner margins in bubble frame roughly
-    let desired = padding + len * char_w;
-    desired.clamp(160.0, max_width)
+    // Widest of the first few lines as the fonts lay them out, so CJK text and emoji are sized
+    // right; code block lines are measured in the code font
+    let font = |style: egui::TextStyle| {
+        let mut font = ui.style().text_styles.get(&style).cloned().unwrap_or_default();
+        font.size *= scale;
+        font
+    };
+    let (body, code) = (font(egui::TextStyle::Body), font(egui::TextStyle::Monospace));
+    let mut in_code = false;
+    let mut widest: f32 = 0.0;
+    for line in text.lines().filter(|l| !l.trim().is_empty()).take(MEASURED_LINES) {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        // Lines this long fill the bubble anyway
+        let line: String = line.chars().take(200).collect();
+        let font = if in_code { code.clone() } else { body.clone() };
+        let width = ui.fonts(|f| f.layout_no_wrap(line, font, Color32::PLACEHOLDER).size().x);
+        widest = widest.max(width);
+    }
+    let padding = 24.0; // inner margins in bubble frame
+    (padding + widest).clamp(160.0, max_width)
 }
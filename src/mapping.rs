@@ -0,0 +1,230 @@
+// User-defined formats: in-house log schemas are mapped onto messages with JSONPath-style paths
+// set in settings.json, e.g. messages at `$.turns[*]`, role at `$.speaker`, content at `$.text`.
+// Mappings are tried before the built-in formats, after script parse() hooks.
+//
+// Paths support `$` (the root, or the message for per-message fields), `.key`, `['key']`,
+// `[0]`, `[*]` and `.*`. A JSONL file is treated as an array of its lines.
+
+use crate::{
+    parse::{normalize_sessions, timestamp_ms, RawMsg, RawSession},
+    Loaded,
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Shown as the detected format; the mapping's own name appears in errors
+const MAPPED_FORMAT: &str = "Custom mapping";
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub(crate) struct FormatMapping {
+    pub name: String,
+    // Optional: one conversation per match, e.g. `$.sessions[*]`; the paths below are then
+    // relative to each conversation
+    pub conversations: String,
+    // Optional conversation title
+    pub title: String,
+    pub messages: String,
+    // Relative to each message
+    pub role: String,
+    pub content: String,
+    pub speaker: String,
+    pub timestamp: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Step {
+    Key(String),
+    Index(usize),
+    All,
+}
+
+// `$.turns[*].text` → [Key("turns"), All, Key("text")]. A path without `$` starts at the root too,
+// so `speaker` works as well as `$.speaker`.
+pub(crate) fn compile(path: &str) -> Result<Vec<Step>, String> {
+    let path = path.trim();
+    let rest = match path.strip_prefix('$') {
+        Some(rest) => rest.to_string(),
+        None if path.starts_with(['.', '[']) => path.to_string(),
+        None => format!(".{path}"),
+    };
+    let mut steps = Vec::new();
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' if chars.peek() == Some(&'*') => {
+                chars.next();
+                steps.push(Step::All);
+            }
+            '.' => {
+                let mut key = String::new();
+                while let Some(&c) = chars.peek().filter(|c| !matches!(c, '.' | '[')) {
+                    key.push(c);
+                    chars.next();
+                }
+                if key.is_empty() {
+                    return Err(format!("empty key in `{path}`"));
+                }
+                steps.push(Step::Key(key));
+            }
+            '[' => {
+                let inner: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                let inner = inner.trim();
+                let quoted = ['\'', '"']
+                    .iter()
+                    .find_map(|q| inner.strip_prefix(*q)?.strip_suffix(*q));
+                steps.push(match (inner, quoted) {
+                    (_, Some(key)) => Step::Key(key.to_string()),
+                    ("*", _) => Step::All,
+                    _ => Step::Index(
+                        inner
+                            .parse()
+                            .map_err(|_| format!("unsupported `[{inner}]` in `{path}`"))?,
+                    ),
+                });
+            }
+            other => return Err(format!("unexpected `{other}` in `{path}`")),
+        }
+    }
+    Ok(steps)
+}
+
+pub(crate) fn select<'a>(root: &'a Value, steps: &[Step]) -> Vec<&'a Value> {
+    let mut current = vec![root];
+    for step in steps {
+        current = current
+            .into_iter()
+            .flat_map(|v| match (step, v) {
+                (Step::Key(key), _) => v.get(key).into_iter().collect(),
+                (Step::Index(i), _) => v.get(i).into_iter().collect(),
+                (Step::All, Value::Array(items)) => items.iter().collect(),
+                (Step::All, Value::Object(fields)) => fields.values().collect(),
+                (Step::All, _) => Vec::new(),
+            })
+            .collect();
+    }
+    current
+}
+
+// Strings as they are; numbers and booleans written out (numeric speaker ids)
+fn text(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(_) | Value::Bool(_) => Some(v.to_string()),
+        _ => None,
+    }
+}
+
+struct Compiled {
+    conversations: Option<Vec<Step>>,
+    title: Option<Vec<Step>>,
+    messages: Vec<Step>,
+    role: Vec<Step>,
+    content: Vec<Step>,
+    speaker: Option<Vec<Step>>,
+    timestamp: Option<Vec<Step>>,
+}
+
+impl FormatMapping {
+    fn compile(&self) -> Result<Compiled, String> {
+        let required = |field: &str, path: &str| {
+            if path.trim().is_empty() {
+                return Err(format!("`{field}` is not set"));
+            }
+            compile(path).map_err(|e| format!("`{field}`: {e}"))
+        };
+        let optional = |field: &str, path: &str| {
+            (!path.trim().is_empty())
+                .then(|| compile(path).map_err(|e| format!("`{field}`: {e}")))
+                .transpose()
+        };
+        Ok(Compiled {
+            conversations: optional("conversations", &self.conversations)?,
+            title: optional("title", &self.title)?,
+            messages: required("messages", &self.messages)?,
+            role: required("role", &self.role)?,
+            content: required("content", &self.content)?,
+            speaker: optional("speaker", &self.speaker)?,
+            timestamp: optional("timestamp", &self.timestamp)?,
+        })
+    }
+
+    // Conversations with at least one message that has both a role and content; empty when the
+    // document does not fit this mapping.
+    fn apply(&self, doc: &Value) -> Result<Vec<RawSession>, String> {
+        let paths = self.compile()?;
+        let first = |v: &Value, steps: &[Step]| select(v, steps).into_iter().find_map(text);
+        let conversations = paths
+            .conversations
+            .as_ref()
+            .map_or(vec![doc], |steps| select(doc, steps));
+        let mut sessions = Vec::new();
+        for conversation in conversations {
+            let messages: Vec<RawMsg> = select(conversation, &paths.messages)
+                .into_iter()
+                .filter_map(|m| {
+                    Some(RawMsg {
+                        role: first(m, &paths.role)?,
+                        content: first(m, &paths.content)?,
+                        name: paths.speaker.as_ref().and_then(|s| first(m, s)),
+                        timestamp: paths
+                            .timestamp
+                            .as_ref()
+                            .and_then(|t| select(m, t).into_iter().find_map(timestamp_ms)),
+                        ..Default::default()
+                    })
+                })
+                .collect();
+            if !messages.is_empty() {
+                let title = paths.title.as_ref().and_then(|t| first(conversation, t));
+                sessions.push(RawSession {
+                    title,
+                    messages,
+                    ..Default::default()
+                });
+            }
+        }
+        Ok(sessions)
+    }
+}
+
+// The whole text as one JSON document, or a JSONL file as the array of its lines.
+fn document(text: &str) -> Option<Value> {
+    if let Ok(v) = serde_json::from_str(text) {
+        return Some(v);
+    }
+    let lines: Vec<Value> = text
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect();
+    (!lines.is_empty()).then_some(Value::Array(lines))
+}
+
+// Loaded with the first mapping that finds messages in `text`, or None to go on to the built-in
+// formats. A mapping with a broken path is reported rather than skipped.
+pub(crate) fn parse_mapped(mappings: &[FormatMapping], text: &str) -> Option<Result<Loaded>> {
+    if mappings.is_empty() {
+        return None;
+    }
+    let doc = document(text)?;
+    for mapping in mappings {
+        match mapping.apply(&doc) {
+            Ok(sessions) if sessions.is_empty() => continue,
+            Ok(sessions) => {
+                return Some(Ok(Loaded {
+                    format: Some(MAPPED_FORMAT),
+                    ..normalize_sessions(sessions)
+                }))
+            }
+            Err(e) => {
+                return Some(Err(anyhow!(
+                    "Format mapping \"{}\" in settings.json: {e}",
+                    mapping.name
+                )))
+            }
+        }
+    }
+    None
+}
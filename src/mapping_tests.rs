@@ -0,0 +1,60 @@
+// User-defined format mappings: path syntax, and conversations read from JSON and JSONL logs.
+
+use crate::mapping::{compile, parse_mapped, select, FormatMapping, Step};
+use serde_json::json;
+
+fn mapping(messages: &str) -> FormatMapping {
+    FormatMapping {
+        name: "internal".to_string(),
+        messages: messages.to_string(),
+        role: "$.speaker".to_string(),
+        content: "text".to_string(),
+        timestamp: "$.meta.ts".to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn paths_compile_and_select() {
+    let steps = compile("$.turns[*]['the text']").unwrap();
+    assert_eq!(
+        steps,
+        [Step::Key("turns".into()), Step::All, Step::Key("the text".into())]
+    );
+    assert_eq!(compile("speaker").unwrap(), [Step::Key("speaker".into())]);
+    assert_eq!(compile("$").unwrap(), []);
+    assert!(compile("$.a[x]").is_err());
+    assert!(compile("$..a").is_err());
+
+    let doc = json!({"turns": [{"the text": "a"}, {"the text": "b"}, {"other": 1}], "n": {"x": 1, "y": 2}});
+    let texts: Vec<_> = select(&doc, &steps).into_iter().filter_map(|v| v.as_str()).collect();
+    assert_eq!(texts, ["a", "b"]);
+    assert_eq!(select(&doc, &compile("$.turns[1]['the text']").unwrap()), [&json!("b")]);
+    assert_eq!(select(&doc, &compile("$.n.*").unwrap()).len(), 2);
+}
+
+#[test]
+fn mapped_logs_load() {
+    let doc = r#"{"turns": [
+        {"speaker": "user", "text": "Hi", "meta": {"ts": "2024-05-01T10:00:00Z"}},
+        {"speaker": "assistant", "text": "Hello"},
+        {"speaker": "assistant"}
+    ]}"#;
+    let loaded = parse_mapped(&[mapping("$.turns[*]")], doc).unwrap().unwrap();
+    assert_eq!(loaded.format, Some("Custom mapping"));
+    let contents: Vec<_> = loaded.messages.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(contents, ["Hi", "Hello"]);
+    assert!(loaded.messages[0].timestamp.is_some());
+
+    let jsonl = "{\"speaker\": \"user\", \"text\": \"Hi\"}\n{\"speaker\": \"assistant\", \"text\": \"Hello\"}\n";
+    let loaded = parse_mapped(&[mapping("$[*]")], jsonl).unwrap().unwrap();
+    assert_eq!(loaded.messages.len(), 2);
+
+    // Files the mapping does not fit are left to the built-in formats
+    assert!(parse_mapped(&[mapping("$.turns[*]")], "[{\"role\": \"user\", \"content\": \"hi\"}]").is_none());
+    let broken = FormatMapping {
+        role: String::new(),
+        ..mapping("$.turns[*]")
+    };
+    assert!(parse_mapped(&[broken], doc).unwrap().is_err());
+}
@@ -0,0 +1,89 @@
+// Gigantic conversations are shown a page of messages at a time, with buttons at either end to load
+// the previous or next page as well. Jumps to a message (links, search, "Where I left off") move
+// the page to it. A page size of 0 shows every message.
+
+use crate::{save_settings, start_position::StartPosition, AppState};
+use eframe::egui;
+use std::ops::Range;
+
+pub(crate) const DEFAULT_PAGE_SIZE: usize = 500;
+
+impl AppState {
+    // Messages to draw this frame.
+    pub(crate) fn page_range(&mut self) -> Range<usize> {
+        let len = self.messages.len();
+        let size = self.settings.page_size;
+        if size == 0 || len <= size {
+            return 0..len;
+        }
+        let following = self.loading.is_some() && self.settings.start_position == StartPosition::Bottom;
+        if let Some(i) = self.scroll_to_message.filter(|i| !self.page.contains(i)) {
+            let start = i.saturating_sub(size / 2).min(len - size);
+            self.page = start..start + size;
+        } else if self.scroll_to_end || following {
+            self.page = len - size..len;
+        } else if self.page.is_empty() {
+            self.page = 0..size;
+        }
+        self.page.start.min(len)..self.page.end.min(len)
+    }
+
+    // "Load previous" above the first message shown.
+    pub(crate) fn load_previous_ui(&mut self, ui: &mut egui::Ui, shown: &Range<usize>) {
+        if shown.start == 0 {
+            return;
+        }
+        let count = self.settings.page_size.min(shown.start);
+        ui.vertical_centered(|ui| {
+            if ui
+                .button(format!("Load previous {}", self.fmt.count(count)))
+                .on_hover_text(format!("{} earlier messages", self.fmt.count(shown.start)))
+                .clicked()
+            {
+                self.page = shown.start - count..shown.end;
+                // Keep the message that was first in view instead of jumping to the new top
+                self.scroll_to_message = Some(shown.start);
+            }
+        });
+        ui.add_space(6.0);
+    }
+
+    // "Load next" below the last message shown.
+    pub(crate) fn load_next_ui(&mut self, ui: &mut egui::Ui, shown: &Range<usize>) {
+        let len = self.messages.len();
+        if shown.end >= len {
+            return;
+        }
+        let count = self.settings.page_size.min(len - shown.end);
+        ui.vertical_centered(|ui| {
+            if ui
+                .button(format!("Load next {}", self.fmt.count(count)))
+                .on_hover_text(format!("{} later messages", self.fmt.count(len - shown.end)))
+                .clicked()
+            {
+                self.page = shown.start..shown.end + count;
+            }
+        });
+    }
+
+    pub(crate) fn page_size_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Messages per page");
+            let resp = ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.page_size)
+                        .speed(10)
+                        .clamp_range(0..=100_000),
+                )
+                .on_hover_text("Longer conversations show this many messages at a time; 0 shows all");
+            if resp.changed() {
+                self.page = self.page.start..self.page.start + self.settings.page_size;
+            }
+            if resp.lost_focus() || resp.drag_stopped() {
+                if let Err(e) = save_settings(&self.settings) {
+                    self.errors.push(format!("Failed to save settings: {e}"));
+                }
+            }
+        });
+    }
+}
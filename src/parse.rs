@@ -0,0 +1,2627 @@
+// Loading conversations: format detection and the parsers for every supported log format, down
+// to the normalized messages the viewer shows. Input comes from files users drop onto the window,
+// so nothing here may panic on malformed data.
+
+use crate::{
+    branches::{MessageTree, TreeNode},
+    charset,
+    incremental::ReadProgress,
+    inline_attachment, is_tool_card, roles, trim_chat_whitespace, Attachment, EvalOutcome, Loaded, Msg, Role, Session,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(try_from = "WireMsg")]
+pub(crate) struct RawMsg {
+    pub role: String,
+    pub content: String,
+    // Speaker name (character chats, named participants)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // Unix milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    // Nesting level of tool cards (child runs of a tool in agent traces)
+    #[serde(skip)]
+    pub depth: usize,
+    // One-line generation stats shown under the bubble (e.g. Ollama eval timings)
+    #[serde(skip)]
+    pub detail: Option<String>,
+    // Inline images referenced from `content` as `![..](bytes://..)`
+    #[serde(skip)]
+    pub attachments: Vec<Attachment>,
+}
+
+// A `{role, content}` message as written in files. `content` is a string or, in multimodal
+// requests, an array of typed parts. Assistant turns that only call tools have `content: null`
+// (or none at all) and `tool_calls` instead.
+#[derive(Deserialize)]
+struct WireMsg {
+    role: String,
+    #[serde(default)]
+    content: serde_json::Value,
+    #[serde(default)]
+    tool_calls: Vec<serde_json::Value>,
+    // Pre-`tools` OpenAI API: a single `{name, arguments}`
+    #[serde(default)]
+    function_call: Option<serde_json::Value>,
+    #[serde(default)]
+    name: Option<String>,
+    // Unix milliseconds; accepts ISO 8601 strings and epoch seconds or milliseconds
+    #[serde(default, alias = "created_at", alias = "createdAt", alias = "time", deserialize_with = "de_timestamp")]
+    timestamp: Option<i64>,
+    // Thinking of reasoning models served OpenAI-style (DeepSeek, vLLM; `reasoning` on OpenRouter)
+    #[serde(default, alias = "reasoning")]
+    reasoning_content: serde_json::Value,
+}
+
+// Reasoning kept apart from the answer, in the `<think>` tags reasoning.rs shows collapsed.
+fn think_block(reasoning: &str) -> String {
+    format!("<think>\n{}\n</think>", reasoning.trim())
+}
+
+impl TryFrom<WireMsg> for RawMsg {
+    type Error = String;
+
+    fn try_from(m: WireMsg) -> Result<Self, String> {
+        let calls: Vec<&serde_json::Value> = m.tool_calls.iter().chain(&m.function_call).collect();
+        let (mut content, attachments) = match &m.content {
+            serde_json::Value::String(s) => (s.clone(), Vec::new()),
+            serde_json::Value::Array(parts) => content_parts(parts),
+            serde_json::Value::Null if !calls.is_empty() => (String::new(), Vec::new()),
+            serde_json::Value::Null => return Err("missing content".to_string()),
+            other => return Err(format!("content must be a string or an array of parts, not {other}")),
+        };
+        for call in calls {
+            if !content.trim().is_empty() {
+                content.push_str("\n\n");
+            }
+            content.push_str(&tool_call_markdown(call));
+        }
+        if let Some(reasoning) = m.reasoning_content.as_str().filter(|r| !r.trim().is_empty()) {
+            content = format!("{}\n\n{content}", think_block(reasoning));
+        }
+        Ok(RawMsg { role: m.role, content, name: m.name, timestamp: m.timestamp, attachments, ..Default::default() })
+    }
+}
+
+// Multimodal content (OpenAI chat and Responses APIs, Anthropic Messages): text parts are joined,
+// base64 images become attachments, and other parts (remote images, audio, files) placeholders.
+fn content_parts(parts: &[serde_json::Value]) -> (String, Vec<Attachment>) {
+    let mut texts = Vec::new();
+    let mut attachments = Vec::new();
+    for part in parts {
+        if let Some(s) = part.as_str() {
+            texts.push(s.to_string());
+            continue;
+        }
+        let kind = part["type"].as_str().unwrap_or_default();
+        let text = match kind {
+            "text" | "input_text" | "output_text" => part["text"].as_str().unwrap_or_default().to_string(),
+            "image_url" | "input_image" => {
+                // `{image_url: {url}}` in chat completions, `{image_url: "..."}` in the Responses API
+                let url = part["image_url"]["url"].as_str().or(part["image_url"].as_str()).unwrap_or_default();
+                image_part(url, &mut attachments)
+            }
+            "image" => match (part["source"]["type"].as_str(), part["source"]["data"].as_str()) {
+                (Some("base64"), Some(data)) => {
+                    let (markdown, att) = inline_attachment(part["source"]["media_type"].as_str().unwrap_or("image"), data);
+                    attachments.extend(att);
+                    markdown
+                }
+                _ => image_part(part["source"]["url"].as_str().unwrap_or_default(), &mut attachments),
+            },
+            "thinking" => think_block(part["thinking"].as_str().unwrap_or_default()),
+            "input_audio" => format!("[audio: {}]", part["input_audio"]["format"].as_str().unwrap_or("input")),
+            "file" | "input_file" | "document" => {
+                let file = if part["file"].is_object() { &part["file"] } else { part };
+                let name = file["filename"].as_str().or(file["title"].as_str()).or(file["file_id"].as_str());
+                format!("[file: {}]", name.unwrap_or("attachment"))
+            }
+            "" => part["text"].as_str().unwrap_or_default().to_string(),
+            other => format!("[{other}]"),
+        };
+        if !text.is_empty() {
+            texts.push(text);
+        }
+    }
+    (texts.join("\n\n"), attachments)
+}
+
+// `{id, type: "function", function: {name, arguments}}` or a bare `{name, arguments}`. Arguments
+// arrive as a JSON-encoded string and are pretty-printed when they parse.
+fn tool_call_markdown(call: &serde_json::Value) -> String {
+    let function = if call["function"].is_object() { &call["function"] } else { call };
+    let name = function["name"].as_str().unwrap_or("tool");
+    let arguments = match &function["arguments"] {
+        serde_json::Value::String(s) => serde_json::from_str::<serde_json::Value>(s)
+            .map(|v| json_text(&v))
+            .unwrap_or_else(|_| s.clone()),
+        other => json_text(other),
+    };
+    format!("**Tool call: {name}**\n\n```json\n{arguments}\n```")
+}
+
+// `data:` URLs are decoded into attachments; remote images are only referenced.
+fn image_part(url: &str, attachments: &mut Vec<Attachment>) -> String {
+    let Some((mime, data)) = url.strip_prefix("data:").and_then(|rest| rest.split_once(";base64,")) else {
+        return if url.is_empty() { "[image]".to_string() } else { format!("[image: {url}]") };
+    };
+    let (markdown, att) = inline_attachment(mime, data);
+    attachments.extend(att);
+    markdown
+}
+
+// A conversation before normalization; exports such as ChatGPT's contain many of them.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RawSession {
+    pub title: Option<String>,
+    pub messages: Vec<RawMsg>,
+    pub outcome: Option<EvalOutcome>,
+    // Alternative branches, for exports that keep them
+    pub tree: Option<MessageTree>,
+}
+
+fn de_timestamp<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Option<i64>, D::Error> {
+    Ok(timestamp_ms(&serde_json::Value::deserialize(d)?))
+}
+
+// Message time as Unix milliseconds from the shapes logs use: epoch seconds (possibly
+// fractional), epoch milliseconds, RFC 3339, or naive "YYYY-MM-DD HH:MM:SS" (taken as UTC).
+pub(crate) fn timestamp_ms(v: &serde_json::Value) -> Option<i64> {
+    let from_number = |x: f64| {
+        // Seconds until the year ~5000; anything larger is already milliseconds
+        let ms = if x.abs() < 1e11 { x * 1000.0 } else { x };
+        (ms > 0.0).then_some(ms as i64)
+    };
+    match v {
+        serde_json::Value::Number(n) => n.as_f64().and_then(from_number),
+        serde_json::Value::String(s) => {
+            let s = s.trim();
+            if let Ok(x) = s.parse::<f64>() {
+                return from_number(x);
+            }
+            if let Ok(t) = chrono::DateTime::parse_from_rfc3339(s) {
+                return Some(t.timestamp_millis());
+            }
+            // SillyTavern writes e.g. "March 5, 2024 2:15pm"
+            ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%B %d, %Y %I:%M%p"]
+                .iter()
+                .find_map(|f| chrono::NaiveDateTime::parse_from_str(s, f).ok())
+                .map(|t| t.and_utc().timestamp_millis())
+        }
+        _ => None,
+    }
+}
+
+// Largest input held in memory, after decompression. A few hundred kilobytes of gzip can expand
+// to gigabytes, so reading stops here rather than exhausting memory.
+pub(crate) const MAX_INPUT_BYTES: u64 = 1 << 30;
+// Deepest `{`/`[` nesting accepted. serde_json gives up at 128 levels too, but line by line and
+// without saying why; real logs stay far below this.
+const MAX_JSON_DEPTH: usize = 128;
+
+fn too_large() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("larger than {} MiB", MAX_INPUT_BYTES >> 20),
+    )
+}
+
+// read_to_end that fails instead of growing past MAX_INPUT_BYTES.
+pub(crate) fn read_limited(reader: impl std::io::Read) -> std::io::Result<Vec<u8>> {
+    use std::io::Read;
+    let mut out = Vec::new();
+    reader.take(MAX_INPUT_BYTES + 1).read_to_end(&mut out)?;
+    if out.len() as u64 > MAX_INPUT_BYTES {
+        return Err(too_large());
+    }
+    Ok(out)
+}
+
+// Whether `{`/`[` nesting outside of strings goes deeper than `limit`.
+fn json_depth_exceeds(text: &str, limit: usize) -> bool {
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+    for b in text.bytes() {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                depth += 1;
+                if depth > limit {
+                    return true;
+                }
+            }
+            b'}' | b']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    false
+}
+
+// A file as parsed, before normalization. The viewer normalizes it straight away; `validate`
+// checks the messages as they were written.
+pub(crate) struct RawLoaded {
+    pub format: &'static str,
+    pub sessions: Vec<RawSession>,
+    pub failed: LineErrors,
+    pub warnings: Vec<String>,
+}
+
+impl RawLoaded {
+    fn normalize(self) -> Loaded {
+        let mut l = normalize_sessions(self.sessions);
+        if MULTI_AGENT_FORMATS.contains(&self.format) {
+            assign_agents(&mut l.messages);
+            l.sessions.iter_mut().for_each(|s| assign_agents(&mut s.messages));
+        }
+        l.format = Some(self.format);
+        l.failed_lines = self.failed;
+        l.errors.extend(self.warnings);
+        l
+    }
+}
+
+// `progress`, when given, counts the bytes read and stops reading once the load is cancelled.
+pub(crate) fn parse_path(path: &Path, progress: Option<&ReadProgress>, options: ParseOptions) -> Result<RawLoaded> {
+    use std::io::Read;
+    let read_error = || format!("Failed to read {}", path.display());
+    let mut file = fs::File::open(path).with_context(read_error)?;
+    // The first bytes decide how the rest is read
+    let mut head = Vec::new();
+    file.by_ref().take(16).read_to_end(&mut head).with_context(read_error)?;
+    if head.starts_with(SQLITE_MAGIC) {
+        Ok(RawLoaded {
+            format: "Cursor chat database",
+            sessions: parse_cursor_db(path)?,
+            failed: LineErrors::default(),
+            warnings: Vec::new(),
+        })
+    } else if head.starts_with(PARQUET_MAGIC) {
+        let (sessions, warnings) = parse_parquet(path)?;
+        Ok(RawLoaded { format: "Parquet dataset", sessions, failed: LineErrors::default(), warnings })
+    } else {
+        let file: Box<dyn Read + '_> = match progress {
+            Some(progress) => Box::new(progress.reader(file)),
+            None => Box::new(file),
+        };
+        let rest = std::io::Cursor::new(&head).chain(file);
+        let bytes = match compression(&head) {
+            // Decompressed while reading, so the compressed file is never held in memory as well
+            Some(format) => decompress(rest, format),
+            None => read_limited(rest),
+        };
+        parse_bytes(&bytes.with_context(read_error)?, options)
+    }
+}
+
+pub(crate) fn load_from_path(path: &Path, progress: Option<&ReadProgress>, options: ParseOptions) -> Result<Loaded> {
+    let mut loaded = parse_path(path, progress, options)?.normalize();
+    loaded.file_name = Some(
+        path.file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string()),
+    );
+    loaded.source = Some(path.to_path_buf());
+    Ok(loaded)
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Compression {
+    Gzip,
+    Zstd,
+}
+
+// Recognized by magic bytes, so `.jsonl.gz`, `.log.zst` and renamed files all work
+pub(crate) fn compression(head: &[u8]) -> Option<Compression> {
+    if head.starts_with(&[0x1f, 0x8b]) {
+        Some(Compression::Gzip)
+    } else if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Some(Compression::Zstd)
+    } else {
+        None
+    }
+}
+
+// The decompressed stream, decoded as it is read.
+pub(crate) fn decoder<'a>(
+    reader: impl std::io::Read + 'a,
+    format: Compression,
+) -> std::io::Result<Box<dyn std::io::Read + 'a>> {
+    Ok(match format {
+        // Multi-member, as produced by appending to a .gz log or by rotated concatenation
+        Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(reader)?),
+    })
+}
+
+pub(crate) fn decompress(reader: impl std::io::Read, format: Compression) -> std::io::Result<Vec<u8>> {
+    read_limited(decoder(reader, format)?)
+}
+
+pub(crate) fn parse_bytes(bytes: &[u8], options: ParseOptions) -> Result<RawLoaded> {
+    if let Some(format) = compression(bytes) {
+        return parse_bytes(&decompress(bytes, format).context("Failed to decompress")?, options);
+    }
+    if bytes.starts_with(SQLITE_MAGIC) {
+        return Err(anyhow!("SQLite databases can only be opened from a file"));
+    }
+    if bytes.starts_with(PARQUET_MAGIC) {
+        return Err(anyhow!("Parquet files can only be opened from a file"));
+    }
+    // No extra copy unless the file has to be decoded
+    let (text, warning) = charset::decode(bytes);
+    let (format, (sessions, failed)) = detect_and_parse(&text, options)?;
+    let mut warnings: Vec<String> = warning.into_iter().collect();
+    if bytes.len() > 20 * 1024 * 1024 {
+        warnings.push("File larger than ~20MB".to_string());
+    }
+    Ok(RawLoaded { format, sessions, failed, warnings })
+}
+
+pub(crate) fn load_from_bytes(bytes: &[u8], options: ParseOptions) -> Result<Loaded> {
+    parse_bytes(bytes, options).map(RawLoaded::normalize)
+}
+
+// ---------------- Format registry ----------------
+//
+// Every text format is a `Parser` in PARSERS. Each one scores the input and the highest score
+// wins; on a tie the earlier entry does. To add a format, write its detector and parser and add
+// a `Format` entry at the place where its evidence ranks:
+//
+//   100  the whole document is JSON with the format's own structure
+//    90  message lists in JSON, and JSONL whose lines carry the format's own fields
+//    80  text with markers only this format uses ([INST], LangChain's [chain/start], …)
+//    60  text with looser markers (Markdown exports, YAML, ChatML)
+//    40  speaker-prefixed plain text
+//    20  any JSON array, read as plain messages
+//     1  anything else, read as JSONL messages
+
+// Sessions, and the JSONL lines that were skipped
+type Parsed = (Vec<RawSession>, LineErrors);
+
+pub(crate) trait Parser: Sync {
+    // Shown next to the file name, e.g. "ChatGPT export"
+    fn name(&self) -> &'static str;
+    // 0 when the input is not in this format; higher for more specific evidence (see above)
+    fn score(&self, input: &Input) -> u32;
+    fn parse(&self, input: &Input) -> Result<Parsed>;
+}
+
+// The text with the views detectors share, so a large document is parsed as JSON only once.
+pub(crate) struct Input<'a> {
+    pub text: &'a str,
+    // First non-whitespace character
+    pub first: Option<char>,
+    json: std::cell::OnceCell<Option<serde_json::Value>>,
+}
+
+impl<'a> Input<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, first: text.chars().find(|c| !c.is_whitespace()), json: Default::default() }
+    }
+
+    // The whole text as a single JSON document
+    pub fn json(&self) -> Option<&serde_json::Value> {
+        self.json
+            .get_or_init(|| matches!(self.first, Some('{' | '[')).then(|| serde_json::from_str(self.text).ok()).flatten())
+            .as_ref()
+    }
+
+    pub fn object(&self) -> Option<&serde_json::Value> {
+        self.json().filter(|v| v.is_object())
+    }
+
+    pub fn array(&self) -> Option<&[serde_json::Value]> {
+        self.json()?.as_array().map(Vec::as_slice)
+    }
+
+    // Formats that are neither JSON nor JSONL
+    fn is_text(&self) -> bool {
+        !matches!(self.first, Some('{' | '['))
+    }
+}
+
+// A format given by a detector and a parser function.
+struct Format {
+    name: &'static str,
+    score: fn(&Input) -> u32,
+    parse: fn(&Input) -> Result<Parsed>,
+}
+
+impl Parser for Format {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn score(&self, input: &Input) -> u32 {
+        (self.score)(input)
+    }
+
+    fn parse(&self, input: &Input) -> Result<Parsed> {
+        (self.parse)(input)
+    }
+}
+
+pub(crate) const PLAIN_JSONL: &str = "JSONL messages";
+
+static PARSERS: &[&dyn Parser] = &[
+    &Format {
+        name: "OpenTelemetry traces",
+        score: |i| if i.first == Some('{') && is_otlp_json(i.text) { 100 } else { 0 },
+        parse: |i| Ok((parse_otlp_traces(i.text)?, LineErrors::default())),
+    },
+    &Format {
+        name: "promptfoo results",
+        score: |i| if i.object().and_then(promptfoo_results).is_some() { 100 } else { 0 },
+        parse: |i| Ok((i.object().and_then(promptfoo_results).map(parse_promptfoo_results).unwrap_or_default(), LineErrors::default())),
+    },
+    &Format {
+        name: "LangSmith runs",
+        score: |i| {
+            if i.array().is_some_and(is_langsmith_run_list) {
+                100
+            } else if i.first != Some('[') && is_langsmith_jsonl(i.text) {
+                90
+            } else {
+                0
+            }
+        },
+        parse: |i| match i.array() {
+            Some(runs) => Ok((parse_langsmith_runs(runs), LineErrors::default())),
+            None => {
+                let (runs, failed) = jsonl_values(i.text);
+                Ok((parse_langsmith_runs(&runs), failed))
+            }
+        },
+    },
+    &Format {
+        name: "Gemini generateContent",
+        // streamGenerateContent returns a JSON array of response chunks
+        score: |i| {
+            let chunks = i.array().and_then(<[_]>::first).is_some_and(|c| c.get("candidates").is_some());
+            if chunks || i.object().is_some_and(is_gemini_conversation) { 100 } else { 0 }
+        },
+        parse: |i| {
+            let chunks = i.array().or_else(|| i.object().map(std::slice::from_ref)).unwrap_or_default();
+            Ok((vec![parse_gemini_conversation(chunks)], LineErrors::default()))
+        },
+    },
+    &Format { name: "ChatGPT export", score: |i| export_score(i, is_chatgpt_conversation), parse: parse_exports },
+    &Format { name: "Claude.ai export", score: |i| export_score(i, is_claude_conversation), parse: parse_exports },
+    &Format {
+        name: "LM Studio conversation",
+        score: |i| if i.object().is_some_and(is_lmstudio_conversation) { 100 } else { 0 },
+        parse: parse_exports,
+    },
+    &Format {
+        name: "Continue session",
+        score: |i| if i.object().is_some_and(is_continue_session) { 100 } else { 0 },
+        parse: parse_exports,
+    },
+    &Format { name: "LangChain run tree", score: |i| export_score(i, is_langchain_run), parse: parse_exports },
+    &Format { name: "Bedrock Converse", score: |i| export_score(i, is_bedrock_converse), parse: parse_exports },
+    &Format {
+        name: "Ollama chat",
+        score: |i| if i.object().is_some_and(is_ollama_chat) { 100 } else { 0 },
+        parse: parse_exports,
+    },
+    &Format {
+        name: "Wrapped message list",
+        score: |i| {
+            let items = i.array().filter(|items| !items.is_empty());
+            let wrapped = match items {
+                Some(items) => items.iter().all(|v| parse_wrapped_messages(v).is_some()),
+                None => i.object().and_then(parse_wrapped_messages).is_some(),
+            };
+            if wrapped { 90 } else { 0 }
+        },
+        // Several wrapped conversations, e.g. `[{"id": 1, "messages": [...]}, ...]`
+        parse: |i| {
+            let sessions = match i.array() {
+                Some(items) => items.iter().filter_map(parse_wrapped_messages).collect(),
+                None => i.object().and_then(parse_wrapped_messages).into_iter().collect(),
+            };
+            Ok((sessions, LineErrors::default()))
+        },
+    },
+    &Format {
+        name: "Llama 3 prompt",
+        score: |i| if i.is_text() && i.text.contains(LLAMA3.start) { 80 } else { 0 },
+        parse: |i| Ok(single(parse_templated_prompt(i.text, &LLAMA3))),
+    },
+    &Format {
+        name: "Gemma prompt",
+        score: |i| if i.is_text() && i.text.contains(GEMMA.start) { 80 } else { 0 },
+        parse: |i| Ok(single(parse_templated_prompt(i.text, &GEMMA))),
+    },
+    &Format {
+        name: "Llama [INST] prompt",
+        score: |i| if is_llama_inst_prompt(i.text) { 80 } else { 0 },
+        parse: |i| Ok(single(parse_llama_inst(i.text))),
+    },
+    &Format {
+        name: "LangChain debug output",
+        score: |i| if is_langchain_debug(i.text) { 80 } else { 0 },
+        parse: |i| Ok(single(parse_langchain_debug(i.text))),
+    },
+    &Format {
+        name: "AutoGen console log",
+        score: |i| if i.first != Some('{') && is_autogen_console(i.text) { 80 } else { 0 },
+        parse: |i| Ok(single(parse_autogen_console(i.text))),
+    },
+    &Format {
+        name: "CrewAI verbose log",
+        score: |i| if i.first != Some('{') && is_crewai_verbose(i.text) { 80 } else { 0 },
+        parse: |i| Ok(single(parse_crewai_verbose(i.text))),
+    },
+    &Format {
+        name: "Ollama chat log",
+        score: |i| if i.first != Some('[') && is_ollama_jsonl(i.text) { 90 } else { 0 },
+        parse: |i| {
+            let (values, failed) = jsonl_values(i.text);
+            Ok((vec![parse_ollama_chat(&values)], failed))
+        },
+    },
+    &Format {
+        name: "Bedrock Converse log",
+        score: |i| if i.first != Some('[') && is_bedrock_jsonl(i.text) { 90 } else { 0 },
+        parse: |i| {
+            let (mut sessions, mut failed) = (Vec::new(), LineErrors::default());
+            for (n, line) in i.text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+                match serde_json::from_str::<serde_json::Value>(line) {
+                    Ok(v) if is_bedrock_converse(&v) => sessions.push(parse_bedrock_converse(&v)),
+                    Ok(_) => failed.push(n + 1, "not a Converse request or response", line),
+                    Err(e) => failed.push(n + 1, e, line),
+                }
+            }
+            Ok((sessions, failed))
+        },
+    },
+    &Format {
+        name: "SillyTavern chat",
+        score: |i| if i.first != Some('[') && is_sillytavern_jsonl(i.text) { 90 } else { 0 },
+        parse: |i| {
+            let (messages, failed) = parse_sillytavern_jsonl(i.text);
+            Ok((single(messages).0, failed))
+        },
+    },
+    &Format {
+        name: "Markdown export",
+        score: |i| if i.is_text() && is_markdown_export(i.text) { 60 } else { 0 },
+        parse: |i| Ok(single(parse_markdown_export(i.text))),
+    },
+    &Format {
+        name: "YAML prompt",
+        score: |i| if i.first != Some('[') && is_yaml_conversation(i.text) { 60 } else { 0 },
+        parse: |i| Ok(single(parse_yaml_conversation(i.text)?)),
+    },
+    &Format {
+        name: "ChatML prompt",
+        score: |i| if i.is_text() && i.text.contains(CHATML.start) { 60 } else { 0 },
+        parse: |i| Ok(single(parse_templated_prompt(i.text, &CHATML))),
+    },
+    &Format {
+        name: "Plain-text transcript",
+        score: |i| if i.is_text() && i.text.lines().any(|l| transcript_speaker(l).is_some()) { 40 } else { 0 },
+        parse: |i| Ok(single(parse_plain_transcript(i.text))),
+    },
+    &Format {
+        name: "JSON messages",
+        score: |i| if i.first == Some('[') { 20 } else { 0 },
+        parse: |i| {
+            let messages = match i.json() {
+                Some(v) => Vec::<RawMsg>::deserialize(v),
+                // Parsed again for the position of the syntax error
+                None => serde_json::from_str(i.text),
+            };
+            Ok(single(messages.context("JSON array parse error")?))
+        },
+    },
+    &Format {
+        name: PLAIN_JSONL,
+        // A pretty-printed JSON object that nothing recognized is not JSONL either
+        score: |i| if i.object().is_some() && i.text.trim().lines().nth(1).is_some() { 0 } else { 1 },
+        parse: |i| {
+            let (messages, failed) = parse_jsonl_with_errors(i.text.as_bytes())?;
+            Ok((single(messages).0, failed))
+        },
+    },
+];
+
+fn single(messages: Vec<RawMsg>) -> Parsed {
+    (vec![RawSession { title: None, messages, ..Default::default() }], LineErrors::default())
+}
+
+// How a load treats damaged input. Passed to each load, so files read on loader threads and in
+// tests do not depend on what the top bar was set to at the time.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct ParseOptions {
+    // A malformed JSONL line fails the whole file instead of being skipped with a warning
+    pub strict: bool,
+}
+
+// Details are kept for this many failed lines; a text file read as JSONL can fail on every line
+const MAX_LINE_ERRORS: usize = 500;
+// Characters kept of each failed line
+const LINE_PREVIEW_CHARS: usize = 300;
+
+// A JSONL line that did not parse.
+#[derive(Clone, Debug)]
+pub(crate) struct LineError {
+    // 1-based
+    pub line: usize,
+    pub error: String,
+    // The start of the line
+    pub text: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LineErrors {
+    pub count: usize,
+    // The first MAX_LINE_ERRORS of them
+    pub lines: Vec<LineError>,
+}
+
+impl LineErrors {
+    pub(crate) fn push(&mut self, line: usize, error: impl ToString, text: &str) {
+        self.count += 1;
+        if self.lines.len() < MAX_LINE_ERRORS {
+            let text = text.trim().chars().take(LINE_PREVIEW_CHARS).collect();
+            self.lines.push(LineError { line, error: error.to_string(), text });
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+// Every non-empty line as JSON, and the lines that are not.
+fn jsonl_values(text: &str) -> (Vec<serde_json::Value>, LineErrors) {
+    let (mut values, mut failed) = (Vec::new(), LineErrors::default());
+    for (i, line) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(v) => values.push(v),
+            Err(e) => failed.push(i + 1, e, line),
+        }
+    }
+    (values, failed)
+}
+
+// One conversation object from a chat product's data export, or an array of them.
+fn export_score(input: &Input, is_format: fn(&serde_json::Value) -> bool) -> u32 {
+    let first_item = input.array().and_then(<[_]>::first);
+    if input.object().is_some_and(is_format) || first_item.is_some_and(is_format) { 100 } else { 0 }
+}
+
+fn parse_exports(input: &Input) -> Result<Parsed> {
+    let sessions = match input.array() {
+        Some(items) => items.iter().filter_map(parse_export_conversation).collect(),
+        None => input.object().and_then(parse_export_conversation).into_iter().collect(),
+    };
+    Ok((sessions, LineErrors::default()))
+}
+
+// The best-scoring format for `text`, with the sessions it parsed and the lines it skipped.
+fn detect_and_parse(text: &str, options: ParseOptions) -> Result<(&'static str, Parsed)> {
+    let input = Input::new(text);
+    if matches!(input.first, Some('{' | '[')) && !is_llama_inst_prompt(text) && json_depth_exceeds(text, MAX_JSON_DEPTH) {
+        bail!("JSON nested more than {MAX_JSON_DEPTH} levels deep");
+    }
+    let mut best: Option<(u32, &dyn Parser)> = None;
+    for parser in PARSERS {
+        let score = parser.score(&input);
+        if score > 0 && best.is_none_or(|(top, _)| score > top) {
+            best = Some((score, *parser));
+        }
+    }
+    let Some((_, parser)) = best else {
+        let object = input.object().and_then(serde_json::Value::as_object);
+        let keys: Vec<&str> = object.into_iter().flat_map(|o| o.keys().map(String::as_str)).take(12).collect();
+        bail!("Unrecognized JSON object; no message list under its top-level keys ({})", keys.join(", "));
+    };
+    let (sessions, failed) = parser.parse(&input)?;
+    if let Some(first) = failed.lines.first().filter(|_| options.strict) {
+        bail!("JSONL line {} failed to parse: {} (strict parsing is on)", first.line, first.error);
+    }
+    Ok((parser.name(), (sessions, failed)))
+}
+
+// Template grammars offered for a pasted prompt string, by registry name.
+pub(crate) const PROMPT_FORMATS: &[&str] =
+    &["ChatML prompt", "Llama 3 prompt", "Gemma prompt", "Llama [INST] prompt", "Plain-text transcript"];
+
+// The prompt grammar that matches `text` best, if any; ties go to the earlier registry entry.
+pub(crate) fn detect_prompt_format(text: &str) -> Option<&'static str> {
+    let text = unescaped_prompt(text);
+    let input = Input::new(&text);
+    let mut best: Option<(u32, &'static str)> = None;
+    for parser in PARSERS.iter().filter(|p| PROMPT_FORMATS.contains(&p.name())) {
+        let score = parser.score(&input);
+        if score > 0 && best.is_none_or(|(top, _)| score > top) {
+            best = Some((score, parser.name()));
+        }
+    }
+    best.map(|(_, name)| name)
+}
+
+// Split a prompt string with the given grammar, whether or not it was detected.
+pub(crate) fn parse_prompt(text: &str, format: &str) -> Vec<RawMsg> {
+    let text = unescaped_prompt(text);
+    let input = Input::new(&text);
+    let Some(parser) = PARSERS.iter().find(|p| p.name() == format) else { return Vec::new() };
+    parser.parse(&input).map_or(Vec::new(), |(sessions, _)| sessions.into_iter().flat_map(|s| s.messages).collect())
+}
+
+// Keys tools put a plain message list under, e.g. `{"messages": [...]}` or `{"history": [...]}`
+const MESSAGE_LIST_KEYS: &[&str] =
+    &["messages", "conversation", "conversations", "dialog", "dialogue", "history", "chat", "turns"];
+// Keys the object holding the list may itself sit under, e.g. `{"data": {"messages": [...]}}`
+const WRAPPER_KEYS: &[&str] = &["data", "result", "session", "chat"];
+
+// The first non-empty list of turns under one of MESSAGE_LIST_KEYS
+fn wrapped_list(o: &serde_json::Value) -> Option<&Vec<serde_json::Value>> {
+    let is_turn = |t: &serde_json::Value| t["role"].is_string() || t["from"].is_string();
+    MESSAGE_LIST_KEYS.iter().find_map(|k| o[*k].as_array().filter(|l| !l.is_empty() && l.iter().all(is_turn)))
+}
+
+// A JSON object wrapping a plain message list under one of MESSAGE_LIST_KEYS, directly or one
+// level down. Every item needs a `role` (or ShareGPT's `from`), so unrelated arrays under the same
+// keys are not taken for conversations. `title`/`name` and a `system` string are picked up.
+fn parse_wrapped_messages(v: &serde_json::Value) -> Option<RawSession> {
+    let (outer, list) = match wrapped_list(v) {
+        Some(list) => (v, list),
+        None => WRAPPER_KEYS.iter().filter_map(|k| v.get(*k)).find_map(|w| Some((w, wrapped_list(w)?)))?,
+    };
+    let text = |key: &str| [outer, v].iter().find_map(|o| o[key].as_str()).filter(|s| !s.trim().is_empty());
+    let title = text("title").or(text("name")).map(str::to_string);
+    let messages = match serde_json::from_value::<Vec<RawMsg>>(serde_json::Value::Array(list.clone())) {
+        Ok(mut messages) => {
+            if let Some(system) = text("system").filter(|_| !messages.iter().any(|m| m.role == "system")) {
+                messages.insert(0, RawMsg { role: "system".into(), content: system.to_string(), ..Default::default() });
+            }
+            messages
+        }
+        // ShareGPT-style `{from, value}` turns
+        Err(_) => {
+            let row = serde_json::json!({ "messages": list, "system": text("system") });
+            dataset_row_session(&row, 0)?.messages
+        }
+    };
+    Some(RawSession { title, messages, ..Default::default() })
+}
+
+// One conversation object from a chat product's data export (ChatGPT, Claude.ai, LM Studio, Continue)
+// or a LangChain run tree.
+fn parse_export_conversation(v: &serde_json::Value) -> Option<RawSession> {
+    if is_chatgpt_conversation(v) {
+        Some(parse_chatgpt_conversation(v))
+    } else if is_claude_conversation(v) {
+        Some(parse_claude_conversation(v))
+    } else if is_lmstudio_conversation(v) {
+        Some(parse_lmstudio_conversation(v))
+    } else if is_continue_session(v) {
+        Some(parse_continue_session(v))
+    } else if is_langchain_run(v) {
+        Some(parse_langchain_run(v))
+    } else if is_bedrock_converse(v) {
+        Some(parse_bedrock_converse(v))
+    } else if is_gemini_conversation(v) {
+        Some(parse_gemini_conversation(std::slice::from_ref(v)))
+    } else if is_ollama_chat(v) {
+        Some(parse_ollama_chat(std::slice::from_ref(v)))
+    } else {
+        None
+    }
+}
+
+fn is_chatgpt_conversation(v: &serde_json::Value) -> bool {
+    v.get("mapping").is_some_and(serde_json::Value::is_object)
+}
+
+// ChatGPT exports store each conversation as a tree of nodes (`mapping`) with parent/children links.
+// The visible conversation is the branch ending at `current_node`; walk it leaf → root and reverse.
+fn parse_chatgpt_conversation(conv: &serde_json::Value) -> RawSession {
+    let title = conv["title"].as_str().map(str::to_string);
+    let empty = serde_json::Map::new();
+    let mapping = conv["mapping"].as_object().unwrap_or(&empty);
+    let leaf = conv["current_node"].as_str().or_else(|| {
+        // Older exports may lack current_node: fall back to the last leaf
+        mapping
+            .iter()
+            .filter(|(_, n)| n["children"].as_array().map_or(true, |c| c.is_empty()))
+            .map(|(id, _)| id.as_str())
+            .last()
+    });
+    let index: HashMap<&str, usize> = mapping.keys().enumerate().map(|(i, id)| (id.as_str(), i)).collect();
+    let nodes = mapping
+        .values()
+        .map(|node| TreeNode {
+            parent: node["parent"].as_str().and_then(|p| index.get(p).copied()),
+            children: node["children"]
+                .as_array()
+                .map(|c| c.iter().filter_map(|id| id.as_str().and_then(|id| index.get(id).copied())).collect())
+                .unwrap_or_default(),
+            message: chatgpt_message(&node["message"]),
+        })
+        .collect::<Vec<_>>();
+    let mut path = Vec::new();
+    let mut seen = HashSet::new();
+    let mut node = leaf.and_then(|id| index.get(id).copied());
+    while let Some(i) = node {
+        // Guard against malformed exports with cyclic parent links
+        if !seen.insert(i) {
+            break;
+        }
+        path.push(i);
+        node = nodes[i].parent;
+    }
+    path.reverse();
+    let messages = path.iter().filter_map(|&i| nodes[i].message.clone()).collect();
+    // Regenerated answers and edited prompts are siblings in the mapping
+    let tree = MessageTree::new(nodes, path);
+    RawSession { title, messages, tree, ..Default::default() }
+}
+
+fn chatgpt_message(m: &serde_json::Value) -> Option<RawMsg> {
+    let role = m["author"]["role"].as_str()?;
+    // Scaffolding such as the empty root system message is hidden in ChatGPT itself
+    if m["metadata"]["is_visually_hidden_from_conversation"].as_bool() == Some(true) {
+        return None;
+    }
+    let content = chatgpt_content(&m["content"]);
+    if content.trim().is_empty() {
+        return None;
+    }
+    Some(RawMsg { role: role.to_string(), content, timestamp: timestamp_ms(&m["create_time"]), ..Default::default() })
+}
+
+fn chatgpt_content(c: &serde_json::Value) -> String {
+    if c["content_type"].as_str() == Some("code") {
+        let lang = c["language"].as_str().filter(|l| *l != "unknown").unwrap_or("");
+        return format!("```{}\n{}\n```", lang, c["text"].as_str().unwrap_or(""));
+    }
+    if let Some(parts) = c["parts"].as_array() {
+        return parts
+            .iter()
+            .filter_map(|p| match p {
+                serde_json::Value::String(s) => Some(s.clone()),
+                _ if p["content_type"].as_str() == Some("image_asset_pointer") => Some("[image]".to_string()),
+                _ => p["text"].as_str().map(str::to_string),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+    c["text"].as_str().unwrap_or("").to_string()
+}
+
+fn is_claude_conversation(v: &serde_json::Value) -> bool {
+    v.get("chat_messages").is_some_and(serde_json::Value::is_array)
+}
+
+// Claude.ai exports: `{ name, chat_messages: [{ sender: "human" | "assistant", text, content, attachments, files }] }`.
+fn parse_claude_conversation(conv: &serde_json::Value) -> RawSession {
+    let title = conv["name"].as_str().map(str::to_string);
+    let messages = conv["chat_messages"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|m| {
+            let role = match m["sender"].as_str().unwrap_or("unknown") {
+                "human" => "user".to_string(),
+                other => other.to_string(),
+            };
+            RawMsg { role, content: claude_content(m), timestamp: timestamp_ms(&m["created_at"]), ..Default::default() }
+        })
+        .collect();
+    RawSession { title, messages, ..Default::default() }
+}
+
+fn claude_content(m: &serde_json::Value) -> String {
+    let mut text = m["text"].as_str().unwrap_or("").to_string();
+    if text.trim().is_empty() {
+        // Newer exports keep the text in typed content blocks
+        text = m["content"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter(|b| b["type"].as_str() == Some("text"))
+            .filter_map(|b| b["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+    }
+    for a in m["attachments"].as_array().map(Vec::as_slice).unwrap_or_default() {
+        let name = a["file_name"].as_str().unwrap_or("attachment");
+        text.push_str(&format!("\n\n**Attachment: {name}**"));
+        if let Some(extracted) = a["extracted_content"].as_str().filter(|c| !c.trim().is_empty()) {
+            text.push_str(&format!("\n\n```\n{}\n```", extracted.trim_end()));
+        }
+    }
+    for f in m["files"].as_array().map(Vec::as_slice).unwrap_or_default() {
+        if let Some(name) = f["file_name"].as_str() {
+            text.push_str(&format!("\n\n[file: {name}]"));
+        }
+    }
+    text
+}
+
+// LM Studio keeps one `*.conversation.json` per chat: `{ name, createdAt, systemPrompt, messages }`.
+// Newer versions store every regenerated reply under `versions` with a `currentlySelected` index.
+pub(crate) fn is_lmstudio_conversation(v: &serde_json::Value) -> bool {
+    let Some(messages) = v.get("messages").and_then(serde_json::Value::as_array) else { return false };
+    messages.iter().any(|m| m.get("versions").is_some_and(serde_json::Value::is_array))
+        || (v.get("createdAt").is_some() && v.get("name").is_some())
+}
+
+fn parse_lmstudio_conversation(conv: &serde_json::Value) -> RawSession {
+    let title = conv["name"].as_str().map(str::to_string);
+    let mut messages = Vec::new();
+    if let Some(system) = conv["systemPrompt"].as_str().filter(|s| !s.trim().is_empty()) {
+        messages.push(RawMsg { role: "system".to_string(), content: system.to_string(), ..Default::default() });
+    }
+    for m in conv["messages"].as_array().map(Vec::as_slice).unwrap_or_default() {
+        // Show the version that was selected in LM Studio
+        let version = match m["versions"].as_array() {
+            Some(versions) => {
+                let selected = m["currentlySelected"].as_u64().unwrap_or(0) as usize;
+                match versions.get(selected).or(versions.last()) {
+                    Some(v) => v,
+                    None => continue,
+                }
+            }
+            None => m,
+        };
+        let content = match version["steps"].as_array() {
+            // Assistant replies made of several steps (reasoning, tool calls, text)
+            Some(steps) => steps
+                .iter()
+                .filter(|s| s["type"].as_str() == Some("contentBlock"))
+                .map(|s| text_parts_content(&s["content"]))
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+            None => text_parts_content(&version["content"]),
+        };
+        let role = version["role"].as_str().unwrap_or("unknown").to_string();
+        messages.push(RawMsg { role, content, ..Default::default() });
+    }
+    RawSession { title, messages, ..Default::default() }
+}
+
+// A plain string or an array of typed parts (`{type: "text", text}`, `{type: "file", name}`).
+fn text_parts_content(c: &serde_json::Value) -> String {
+    if let Some(s) = c.as_str() {
+        return s.to_string();
+    }
+    c.as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|part| match part["type"].as_str() {
+            Some("text") => part["text"].as_str().map(str::to_string),
+            Some("file") => {
+                let name = part["name"].as_str().or(part["fileIdentifier"].as_str()).unwrap_or("file");
+                Some(format!("[file: {name}]"))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+// Continue.dev sessions (`~/.continue/sessions/<id>.json`):
+// `{ title, history: [{ message: { role, content, toolCalls }, contextItems: [{ name, content }] }] }`.
+fn is_continue_session(v: &serde_json::Value) -> bool {
+    v.get("history")
+        .and_then(serde_json::Value::as_array)
+        .is_some_and(|h| h.first().map_or(v.get("sessionId").is_some(), |item| item.get("message").is_some()))
+}
+
+fn parse_continue_session(session: &serde_json::Value) -> RawSession {
+    let title = session["title"].as_str().map(str::to_string);
+    let messages = session["history"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|item| {
+            let m = &item["message"];
+            let mut content = text_parts_content(&m["content"]);
+            for ctx in item["contextItems"].as_array().map(Vec::as_slice).unwrap_or_default() {
+                let name = ctx["name"].as_str().unwrap_or("context");
+                content.push_str(&format!("\n\n**Context: {name}**"));
+                if let Some(text) = ctx["content"].as_str().filter(|c| !c.trim().is_empty()) {
+                    content.push_str(&format!("\n\n```\n{}\n```", text.trim_end()));
+                }
+            }
+            for call in m["toolCalls"].as_array().map(Vec::as_slice).unwrap_or_default() {
+                let name = call["function"]["name"].as_str().unwrap_or("tool");
+                let args = call["function"]["arguments"].as_str().unwrap_or("");
+                content.push_str(&format!("\n\n**Tool call: {name}**\n\n```json\n{args}\n```"));
+            }
+            RawMsg { role: m["role"].as_str().unwrap_or("unknown").to_string(), content, ..Default::default() }
+        })
+        .collect();
+    RawSession { title, messages, ..Default::default() }
+}
+
+// One model call or tool invocation from an agent/chain trace (LangChain, LangSmith).
+// `depth` > 0 marks runs nested inside a tool, shown as child cards.
+enum TraceStep {
+    // `response` is the assistant message (content plus any timing or timestamp)
+    Llm { prompt: Vec<RawMsg>, response: RawMsg },
+    Tool { name: String, input: String, output: String, depth: usize },
+}
+
+fn assistant_reply(content: String) -> RawMsg {
+    RawMsg { role: "assistant".to_string(), content, ..Default::default() }
+}
+
+// Flatten trace steps into a conversation: the first prompt gives the context, later prompts
+// only contribute new user turns, and tool invocations become collapsible tool cards.
+fn trace_to_messages(steps: Vec<TraceStep>, root: Option<(String, String)>) -> Vec<RawMsg> {
+    let mut out = Vec::new();
+    let mut previous_prompt: Option<Vec<RawMsg>> = None;
+    for step in steps {
+        match step {
+            TraceStep::Llm { prompt, response } => {
+                let shared = previous_prompt.as_ref().map_or(0, |prev| {
+                    prompt.iter().zip(prev).take_while(|(a, b)| a.role == b.role && a.content == b.content).count()
+                });
+                for m in &prompt[shared..] {
+                    // Earlier replies and tool results are already in the conversation
+                    if previous_prompt.is_none() || m.role == "user" {
+                        out.push(m.clone());
+                    }
+                }
+                if !response.content.trim().is_empty() {
+                    out.push(response);
+                }
+                previous_prompt = Some(prompt);
+            }
+            TraceStep::Tool { name, input, output, depth } => {
+                let content = tool_card_content(&input, &output);
+                out.push(RawMsg { role: "tool".to_string(), content, name: Some(name), depth, ..Default::default() });
+            }
+        }
+    }
+    // A chain without model calls (or whose calls were not captured): show its input and output
+    if previous_prompt.is_none() {
+        if let Some((input, output)) = root {
+            out.insert(0, RawMsg { role: "user".to_string(), content: input, ..Default::default() });
+            out.push(RawMsg { role: "assistant".to_string(), content: output, ..Default::default() });
+        }
+    }
+    out
+}
+
+// Body of a tool card: the input and output as code blocks, omitting empty sections.
+fn tool_card_content(input: &str, output: &str) -> String {
+    [("Input", input), ("Output", output)]
+        .iter()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(label, text)| format!("**{label}**\n\n```\n{}\n```", text.trim_end()))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn json_text(v: &serde_json::Value) -> String {
+    match v {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        other => serde_json::to_string_pretty(other).unwrap_or_default(),
+    }
+}
+
+// Chain inputs/outputs are dicts; show the single value when there is only one key.
+fn chain_io_text(v: &serde_json::Value) -> String {
+    match v.as_object() {
+        Some(map) if map.len() == 1 => map.values().next().map(json_text).unwrap_or_default(),
+        _ => json_text(v),
+    }
+}
+
+// Text prompts ("System: ...\nHuman: ...") are split into turns when they look like a transcript.
+fn prompt_messages(prompt: &str) -> Vec<RawMsg> {
+    let msgs = parse_plain_transcript(prompt);
+    if msgs.is_empty() {
+        vec![RawMsg { role: "user".to_string(), content: prompt.to_string(), ..Default::default() }]
+    } else {
+        msgs
+    }
+}
+
+// A serialized LangChain message: `{lc, type: "constructor", id: [.., "HumanMessage"], kwargs}`,
+// `{type: "human", data: {content}}`, `{type: "human", content}` or OpenAI-style `{role, content}`.
+fn langchain_message(m: &serde_json::Value) -> Option<RawMsg> {
+    let body = if m["kwargs"].is_object() { &m["kwargs"] } else if m["data"].is_object() { &m["data"] } else { m };
+    let kind = body["type"]
+        .as_str()
+        .or(m["type"].as_str().filter(|t| *t != "constructor"))
+        .or(m["id"].as_array().and_then(|id| id.last()).and_then(|c| c.as_str()))
+        .or(m["role"].as_str())?;
+    let role = match kind.trim_end_matches("MessageChunk").trim_end_matches("Message").to_lowercase().as_str() {
+        "human" => "user".to_string(),
+        "ai" => "assistant".to_string(),
+        "tool" | "function" => "tool".to_string(),
+        other => other.to_string(),
+    };
+    Some(RawMsg { role, content: text_parts_content(&body["content"]), ..Default::default() })
+}
+
+// `generations[0][0]` of an LLM result (plain `text`, or the chat `message`); runs traced around
+// a raw OpenAI client carry `choices` instead.
+fn langchain_generation_text(output: &serde_json::Value) -> String {
+    let g = &output["generations"][0][0];
+    if let Some(text) = g["text"].as_str().filter(|t| !t.is_empty()) {
+        return text.to_string();
+    }
+    let message = if g["message"].is_object() { &g["message"] } else { &output["choices"][0]["message"] };
+    langchain_message(message).map(|m| m.content).unwrap_or_else(|| chain_io_text(output))
+}
+
+fn langchain_prompt(inputs: &serde_json::Value) -> Vec<RawMsg> {
+    // LangChain batches: `messages: [[...]]`; plain chat APIs: `messages: [...]`
+    let messages = inputs["messages"][0].as_array().or(inputs["messages"].as_array());
+    if let Some(messages) = messages {
+        return messages.iter().filter_map(langchain_message).collect();
+    }
+    inputs["prompts"][0].as_str().map(prompt_messages).unwrap_or_default()
+}
+
+const LANGCHAIN_DEBUG_KINDS: &[&str] = &["chain", "llm", "tool", "retriever"];
+
+// `[llm/start] [1:chain:AgentExecutor > 2:llm:ChatOpenAI] Entering LLM run with input:`
+// -> ("llm", "start", "1:chain:AgentExecutor > 2:llm:ChatOpenAI")
+fn langchain_debug_header(line: &str) -> Option<(&str, &str, &str)> {
+    let rest = line.strip_prefix('[')?;
+    let (tag, rest) = rest.split_once("] [")?;
+    let (kind, phase) = tag.split_once('/')?;
+    if !LANGCHAIN_DEBUG_KINDS.contains(&kind) || !matches!(phase, "start" | "end" | "error") {
+        return None;
+    }
+    let (path, _) = rest.split_once(']')?;
+    Some((kind, phase, path))
+}
+
+fn is_langchain_debug(text: &str) -> bool {
+    text.lines().take(50).any(|l| langchain_debug_header(l).is_some())
+}
+
+// `langchain.debug = True` console output: a header line per run event followed by its JSON payload.
+fn parse_langchain_debug(text: &str) -> Vec<RawMsg> {
+    // (kind, phase, path, body)
+    let mut events: Vec<(&str, &str, &str, String)> = Vec::new();
+    for line in text.lines() {
+        if let Some((kind, phase, path)) = langchain_debug_header(line) {
+            events.push((kind, phase, path, String::new()));
+        } else if let Some(last) = events.last_mut() {
+            last.3.push_str(line);
+            last.3.push('\n');
+        }
+    }
+
+    let mut steps = Vec::new();
+    let mut pending: BTreeMap<&str, serde_json::Value> = BTreeMap::new();
+    let (mut root_input, mut root_output) = (None, None);
+    for (kind, phase, path, body) in events {
+        let body = body.trim();
+        let value = serde_json::from_str::<serde_json::Value>(body).unwrap_or_else(|_| serde_json::Value::String(body.to_string()));
+        let top_level = !path.contains(" > ");
+        match (kind, phase) {
+            ("chain", "start") if top_level => root_input = Some(chain_io_text(&value)),
+            ("chain", "end") if top_level => root_output = Some(chain_io_text(&value)),
+            (_, "start") => {
+                pending.insert(path, value);
+            }
+            ("llm", _) => {
+                let prompt = pending.remove(path).map(|v| langchain_prompt(&v)).unwrap_or_default();
+                let response = if phase == "error" { format!("Error: {}", json_text(&value)) } else { langchain_generation_text(&value) };
+                steps.push(TraceStep::Llm { prompt, response: assistant_reply(response) });
+            }
+            ("tool", _) | ("retriever", _) => {
+                let name = path.rsplit(':').next().unwrap_or(kind).trim().to_string();
+                let name = if kind == "tool" { name } else { format!("{name} ({kind})") };
+                let input = pending.remove(path).map(|v| json_text(&v)).unwrap_or_default();
+                let output = if phase == "error" { format!("Error: {}", json_text(&value)) } else { json_text(&value) };
+                steps.push(TraceStep::Tool { name, input, output, depth: 0 });
+            }
+            _ => {}
+        }
+    }
+    trace_to_messages(steps, root_input.zip(root_output))
+}
+
+// A LangChain `Run` (tracer callbacks, `run.dict()`): `{ name, run_type, inputs, outputs, child_runs }`.
+fn is_langchain_run(v: &serde_json::Value) -> bool {
+    v.get("run_type").is_some_and(serde_json::Value::is_string) && v.get("inputs").is_some()
+}
+
+fn collect_langchain_steps(run: &serde_json::Value, depth: usize, steps: &mut Vec<TraceStep>) {
+    let name = run["name"].as_str().unwrap_or("run").to_string();
+    let run_type = run["run_type"].as_str().unwrap_or_default();
+    let error = run["error"].as_str().filter(|e| !e.is_empty()).map(|e| format!("Error: {e}"));
+    let children = run["child_runs"].as_array().map(Vec::as_slice).unwrap_or_default();
+    match run_type {
+        "llm" | "chat_model" | "prompt_llm" if depth == 0 => {
+            let response = error.unwrap_or_else(|| langchain_generation_text(&run["outputs"]));
+            steps.push(TraceStep::Llm { prompt: langchain_prompt(&run["inputs"]), response: assistant_reply(response) });
+        }
+        // Model calls made inside a tool are part of the tool's work, not of the dialogue
+        "llm" | "chat_model" | "prompt_llm" => {
+            let input = langchain_prompt(&run["inputs"])
+                .iter()
+                .map(|m| format!("{}: {}", m.role, m.content))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            let output = error.unwrap_or_else(|| langchain_generation_text(&run["outputs"]));
+            steps.push(TraceStep::Tool { name: format!("{name} ({run_type})"), input, output, depth });
+        }
+        "tool" | "retriever" => {
+            let output = error.unwrap_or_else(|| chain_io_text(&run["outputs"]));
+            let name = if run_type == "tool" { name } else { format!("{name} ({run_type})") };
+            steps.push(TraceStep::Tool { name, input: chain_io_text(&run["inputs"]), output, depth });
+            for child in children {
+                collect_langchain_steps(child, depth + 1, steps);
+            }
+        }
+        _ => {
+            for child in children {
+                collect_langchain_steps(child, depth, steps);
+            }
+        }
+    }
+}
+
+fn parse_langchain_run(run: &serde_json::Value) -> RawSession {
+    let mut steps = Vec::new();
+    collect_langchain_steps(run, 0, &mut steps);
+    let root = Some((chain_io_text(&run["inputs"]), chain_io_text(&run["outputs"])));
+    RawSession {
+        title: run["name"].as_str().map(str::to_string),
+        messages: trace_to_messages(steps, root),
+        ..Default::default()
+    }
+}
+
+// Whether a file starting with `head` is generic `{role, content}` JSONL, which can be parsed line
+// by line while it is read. The other JSONL formats are told apart by their first lines.
+pub(crate) fn is_plain_jsonl(head: &str) -> bool {
+    let Some(first) = head.lines().map(str::trim).find(|l| !l.is_empty()) else { return false };
+    first.starts_with('{')
+        && !is_otlp_json(head)
+        && !is_langsmith_jsonl(head)
+        && !is_ollama_jsonl(head)
+        && !is_bedrock_jsonl(head)
+        && !is_sillytavern_jsonl(head)
+        && serde_json::from_str::<RawMsg>(first).is_ok()
+}
+
+// OTLP-JSON trace exports (collector file exporter, `ExportTraceServiceRequest` dumps), one document
+// or one per line. Spans following the GenAI semantic conventions become turns, one session per trace.
+fn is_otlp_json(text: &str) -> bool {
+    // Inside a JSON string the quotes would be escaped, so this only matches a real key
+    text.contains("\"resourceSpans\"")
+}
+
+fn parse_otlp_traces(text: &str) -> Result<Vec<RawSession>> {
+    let docs: Vec<serde_json::Value> = match serde_json::from_str(text) {
+        Ok(v) => vec![v],
+        Err(_) => text.lines().filter(|l| !l.trim().is_empty()).filter_map(|l| serde_json::from_str(l).ok()).collect(),
+    };
+    // Spans grouped by trace, in order of first appearance
+    let mut traces: Vec<(String, Vec<&serde_json::Value>)> = Vec::new();
+    for doc in &docs {
+        for rs in doc["resourceSpans"].as_array().map(Vec::as_slice).unwrap_or_default() {
+            for ss in rs["scopeSpans"].as_array().or(rs["instrumentationLibrarySpans"].as_array()).map(Vec::as_slice).unwrap_or_default() {
+                for span in ss["spans"].as_array().map(Vec::as_slice).unwrap_or_default() {
+                    let trace_id = span["traceId"].as_str().unwrap_or_default().to_string();
+                    match traces.iter_mut().find(|(id, _)| *id == trace_id) {
+                        Some((_, spans)) => spans.push(span),
+                        None => traces.push((trace_id, vec![span])),
+                    }
+                }
+            }
+        }
+    }
+    // Nanosecond timestamps are strings in OTLP-JSON (numbers in some exporters)
+    let start_ns = |s: &&serde_json::Value| {
+        let t = &s["startTimeUnixNano"];
+        t.as_str().and_then(|v| v.parse::<u64>().ok()).or(t.as_u64()).unwrap_or(0)
+    };
+    let mut sessions = Vec::new();
+    for (trace_id, mut spans) in traces {
+        spans.sort_by_key(start_ns);
+        let steps: Vec<TraceStep> = spans.iter().filter_map(|s| otel_span_step(s)).collect();
+        if steps.is_empty() {
+            continue;
+        }
+        let root = spans.iter().find(|s| s["parentSpanId"].as_str().is_none_or(str::is_empty));
+        let title = root.and_then(|s| s["name"].as_str()).map(str::to_string).or_else(|| {
+            (!trace_id.is_empty()).then(|| format!("Trace {}", trace_id.chars().take(8).collect::<String>()))
+        });
+        sessions.push(RawSession { title, messages: trace_to_messages(steps, None), ..Default::default() });
+    }
+    if sessions.is_empty() {
+        return Err(anyhow!("No GenAI spans (gen_ai.prompt / gen_ai.completion) found in this OTLP export"));
+    }
+    Ok(sessions)
+}
+
+// OTLP `AnyValue` (`{stringValue}`, `{intValue: "12"}`, `{kvlistValue}`, ...) as plain JSON.
+fn otel_value(v: &serde_json::Value) -> serde_json::Value {
+    if let Some(i) = v.get("intValue") {
+        // int64 values are encoded as strings in OTLP-JSON
+        return i.as_str().and_then(|s| s.parse::<i64>().ok()).map(serde_json::Value::from).unwrap_or_else(|| i.clone());
+    }
+    if let Some(x) = ["stringValue", "boolValue", "doubleValue"].iter().find_map(|k| v.get(*k)) {
+        return x.clone();
+    }
+    if let Some(values) = v["arrayValue"]["values"].as_array() {
+        return values.iter().map(otel_value).collect();
+    }
+    if v.get("kvlistValue").is_some() {
+        return serde_json::Value::Object(otel_attributes(&v["kvlistValue"]["values"]));
+    }
+    serde_json::Value::Null
+}
+
+fn otel_attributes(list: &serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+    list.as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|kv| Some((kv["key"].as_str()?.to_string(), otel_value(&kv["value"]))))
+        .collect()
+}
+
+// Message payloads are usually JSON encoded into a string attribute; a bare string is one message.
+fn otel_messages(v: &serde_json::Value, default_role: &str) -> Vec<RawMsg> {
+    let decoded = v.as_str().and_then(|s| serde_json::from_str::<serde_json::Value>(s).ok());
+    match decoded.as_ref().unwrap_or(v) {
+        serde_json::Value::Array(items) => items.iter().map(|m| otel_message(m, default_role)).collect(),
+        m @ serde_json::Value::Object(_) => vec![otel_message(m, default_role)],
+        serde_json::Value::String(s) => vec![RawMsg { role: default_role.to_string(), content: s.clone(), ..Default::default() }],
+        _ => Vec::new(),
+    }
+}
+
+// `{role, content}`, a completion choice `{message: {...}}`, or the newer `{role, parts: [{type, content}]}`.
+fn otel_message(m: &serde_json::Value, default_role: &str) -> RawMsg {
+    let m = if m["message"].is_object() { &m["message"] } else { m };
+    let role = m["role"].as_str().unwrap_or(default_role).to_string();
+    let content = match m["parts"].as_array() {
+        Some(parts) => parts
+            .iter()
+            .filter_map(|p| match p["type"].as_str() {
+                Some("tool_call") => Some(format!(
+                    "**Tool call: {}**\n\n```json\n{}\n```",
+                    p["name"].as_str().unwrap_or("tool"),
+                    json_text(&p["arguments"])
+                )),
+                Some("tool_call_response") => Some(json_text(&p["response"])),
+                _ => p["content"].as_str().or(p["text"].as_str()).map(str::to_string),
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        None => text_parts_content(&m["content"]),
+    };
+    RawMsg { role, content, ..Default::default() }
+}
+
+// OpenLLMetry-style flattened attributes: `gen_ai.prompt.0.role`, `gen_ai.prompt.0.content`, ...
+fn otel_indexed_messages(attrs: &serde_json::Map<String, serde_json::Value>, prefix: &str, default_role: &str) -> Vec<RawMsg> {
+    (0..)
+        .map_while(|i| {
+            let role = attrs.get(&format!("{prefix}.{i}.role"));
+            let content = attrs.get(&format!("{prefix}.{i}.content"));
+            (role.is_some() || content.is_some()).then(|| RawMsg {
+                role: role.and_then(|r| r.as_str()).unwrap_or(default_role).to_string(),
+                content: content.map(json_text).unwrap_or_default(),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+fn otel_span_step(span: &serde_json::Value) -> Option<TraceStep> {
+    let attrs = otel_attributes(&span["attributes"]);
+    let attr_str = |k: &str| attrs.get(k).map(json_text).unwrap_or_default();
+    if attrs.get("gen_ai.operation.name").and_then(|v| v.as_str()) == Some("execute_tool") {
+        let name = attrs.get("gen_ai.tool.name").and_then(|v| v.as_str()).unwrap_or("tool").to_string();
+        return Some(TraceStep::Tool {
+            name,
+            input: attr_str("gen_ai.tool.call.arguments"),
+            output: attr_str("gen_ai.tool.call.result"),
+            depth: 0,
+        });
+    }
+
+    let mut prompt = otel_indexed_messages(&attrs, "gen_ai.prompt", "user");
+    let mut completion = otel_indexed_messages(&attrs, "gen_ai.completion", "assistant");
+    for key in ["gen_ai.input.messages", "gen_ai.prompt"] {
+        if let Some(v) = attrs.get(key) {
+            prompt.extend(otel_messages(v, "user"));
+        }
+    }
+    for key in ["gen_ai.output.messages", "gen_ai.completion"] {
+        if let Some(v) = attrs.get(key) {
+            completion.extend(otel_messages(v, "assistant"));
+        }
+    }
+    // Event-based conventions: content in span events instead of attributes
+    for event in span["events"].as_array().map(Vec::as_slice).unwrap_or_default() {
+        let ea = otel_attributes(&event["attributes"]);
+        let body = otel_value(&event["body"]);
+        let payload = |key: &str| ea.get(key).cloned().unwrap_or_else(|| body.clone());
+        match event["name"].as_str().unwrap_or_default() {
+            "gen_ai.content.prompt" => prompt.extend(otel_messages(&payload("gen_ai.prompt"), "user")),
+            "gen_ai.content.completion" => completion.extend(otel_messages(&payload("gen_ai.completion"), "assistant")),
+            "gen_ai.choice" => completion.extend(otel_messages(&payload("message"), "assistant")),
+            name @ ("gen_ai.system.message" | "gen_ai.user.message" | "gen_ai.assistant.message" | "gen_ai.tool.message") => {
+                let role = name.split('.').nth(1).unwrap_or("user");
+                let content = payload("content");
+                let content = if content.is_object() { content["content"].clone() } else { content };
+                prompt.push(RawMsg { role: role.to_string(), content: json_text(&content), ..Default::default() });
+            }
+            _ => {}
+        }
+    }
+    if prompt.is_empty() && completion.is_empty() {
+        return None;
+    }
+    let response = completion.into_iter().map(|m| m.content).collect::<Vec<_>>().join("\n\n");
+    Some(TraceStep::Llm { prompt, response: assistant_reply(response) })
+}
+
+// LangSmith exports list runs flat, linked by `parent_run_id`; rebuild one tree per root run.
+fn is_langsmith_run_list(runs: &[serde_json::Value]) -> bool {
+    runs.first().is_some_and(|r| is_langchain_run(r) && (r.get("parent_run_id").is_some() || r.get("trace_id").is_some()))
+}
+
+fn parse_langsmith_runs(runs: &[serde_json::Value]) -> Vec<RawSession> {
+    let id_of = |r: &serde_json::Value| r["id"].as_str().map(str::to_string);
+    let ids: HashSet<String> = runs.iter().filter_map(id_of).collect();
+    let mut children: BTreeMap<String, Vec<&serde_json::Value>> = BTreeMap::new();
+    let mut roots = Vec::new();
+    for run in runs {
+        match run["parent_run_id"].as_str() {
+            Some(parent) if ids.contains(parent) => children.entry(parent.to_string()).or_default().push(run),
+            // Runs whose parent is missing from the export are treated as roots
+            _ => roots.push(run),
+        }
+    }
+    // `dotted_order` encodes start time and position in the tree; older exports only have `start_time`
+    let order_key = |r: &&serde_json::Value| {
+        r["dotted_order"].as_str().or(r["start_time"].as_str()).unwrap_or_default().to_string()
+    };
+    for list in children.values_mut() {
+        list.sort_by_key(order_key);
+    }
+    roots.sort_by_key(order_key);
+
+    fn build(run: &serde_json::Value, children: &BTreeMap<String, Vec<&serde_json::Value>>, depth: usize) -> serde_json::Value {
+        let mut tree = run.clone();
+        let kids = run["id"].as_str().and_then(|id| children.get(id)).map(Vec::as_slice).unwrap_or_default();
+        // Guard against cycles in malformed exports
+        if depth < 64 {
+            tree["child_runs"] = kids.iter().map(|c| build(c, children, depth + 1)).collect();
+        }
+        tree
+    }
+    roots.iter().map(|root| parse_langchain_run(&build(root, &children, 0))).collect()
+}
+
+fn is_langsmith_jsonl(text: &str) -> bool {
+    text.lines()
+        .find(|l| !l.trim().is_empty())
+        .and_then(|l| serde_json::from_str::<serde_json::Value>(l).ok())
+        .is_some_and(|v| is_langsmith_run_list(std::slice::from_ref(&v)))
+}
+
+// ---------------- AWS Bedrock Converse ----------------
+
+// Request and response halves of a Converse call. Accepts a bare request or response,
+// `{request, response}` pairs, and model invocation log records (`input.inputBodyJson`).
+fn bedrock_parts(v: &serde_json::Value) -> (&serde_json::Value, &serde_json::Value) {
+    if v["input"].get("inputBodyJson").is_some() {
+        (&v["input"]["inputBodyJson"], &v["output"]["outputBodyJson"])
+    } else if v.get("request").is_some() {
+        (&v["request"], &v["response"])
+    } else {
+        (v, v)
+    }
+}
+
+// Converse content blocks are keyed by kind (`{"text": ..}`, `{"toolUse": ..}`) rather than
+// tagged with `type` like the Anthropic Messages API.
+fn is_bedrock_block_list(content: &serde_json::Value) -> bool {
+    content.as_array().is_some_and(|blocks| {
+        blocks.first().is_some_and(|b| {
+            b.get("type").is_none()
+                && ["text", "toolUse", "toolResult", "image", "document", "reasoningContent"]
+                    .iter()
+                    .any(|k| b.get(*k).is_some())
+        })
+    })
+}
+
+fn is_bedrock_converse(v: &serde_json::Value) -> bool {
+    let (request, response) = bedrock_parts(v);
+    request["messages"].as_array().is_some_and(|m| m.iter().any(|m| is_bedrock_block_list(&m["content"])))
+        || is_bedrock_block_list(&response["output"]["message"]["content"])
+}
+
+fn is_bedrock_jsonl(text: &str) -> bool {
+    text.lines()
+        .find(|l| !l.trim().is_empty())
+        .and_then(|l| serde_json::from_str::<serde_json::Value>(l).ok())
+        .is_some_and(|v| is_bedrock_converse(&v))
+}
+
+fn bedrock_text_blocks(blocks: &serde_json::Value) -> String {
+    blocks
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|b| {
+            if let Some(text) = b["text"].as_str() {
+                Some(text.to_string())
+            } else if b.get("json").is_some() {
+                Some(json_text(&b["json"]))
+            } else if let Some(reasoning) = b["reasoningContent"]["reasoningText"]["text"].as_str() {
+                Some(think_block(reasoning))
+            } else if let Some(format) = b["image"]["format"].as_str() {
+                Some(format!("[image: {format}]"))
+            } else {
+                b["document"]["name"].as_str().map(|name| format!("[document: {name}]"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn parse_bedrock_converse(v: &serde_json::Value) -> RawSession {
+    let (request, response) = bedrock_parts(v);
+    let mut messages = Vec::new();
+    let system = bedrock_text_blocks(&request["system"]);
+    if !system.is_empty() {
+        messages.push(RawMsg { role: "system".to_string(), content: system, ..Default::default() });
+    }
+    // Tool cards by toolUseId; the result arrives in a later user message
+    let mut tool_cards: Vec<(String, usize, String)> = Vec::new();
+    let turns = request["messages"].as_array().into_iter().flatten().chain(response["output"].get("message"));
+    for turn in turns {
+        let role = turn["role"].as_str().unwrap_or("assistant");
+        for block in turn["content"].as_array().into_iter().flatten() {
+            if let Some(tool_use) = block.get("toolUse") {
+                let id = tool_use["toolUseId"].as_str().unwrap_or_default().to_string();
+                let input = json_text(&tool_use["input"]);
+                tool_cards.push((id, messages.len(), input.clone()));
+                messages.push(RawMsg {
+                    role: "tool".to_string(),
+                    content: tool_card_content(&input, ""),
+                    name: tool_use["name"].as_str().map(str::to_string),
+                    ..Default::default()
+                });
+            } else if let Some(result) = block.get("toolResult") {
+                let mut output = bedrock_text_blocks(&result["content"]);
+                if result["status"].as_str() == Some("error") {
+                    output.insert_str(0, "(error)\n");
+                }
+                let id = result["toolUseId"].as_str().unwrap_or_default();
+                match tool_cards.iter().find(|(card_id, _, _)| card_id == id) {
+                    Some((_, index, input)) => messages[*index].content = tool_card_content(input, &output),
+                    None => messages.push(RawMsg {
+                        role: "tool".to_string(),
+                        content: tool_card_content("", &output),
+                        ..Default::default()
+                    }),
+                }
+            } else {
+                let text = bedrock_text_blocks(&serde_json::Value::Array(vec![block.clone()]));
+                if text.is_empty() {
+                    continue;
+                }
+                // Consecutive blocks of one turn form a single message
+                match messages.last_mut() {
+                    Some(last) if last.role == role && last.name.is_none() => {
+                        last.content.push_str("\n\n");
+                        last.content.push_str(&text);
+                    }
+                    _ => messages.push(RawMsg { role: role.to_string(), content: text, ..Default::default() }),
+                }
+            }
+        }
+    }
+    let title = v["modelId"].as_str().or_else(|| request["modelId"].as_str()).map(str::to_string);
+    RawSession { title, messages, ..Default::default() }
+}
+
+// ---------------- Google Gemini generateContent ----------------
+
+// Request (`contents` of `{role: "user" | "model", parts}`, `systemInstruction`), response
+// (`candidates[].content`), or a `{request, response}` pair. REST and SDK logs differ in casing.
+fn is_gemini_conversation(v: &serde_json::Value) -> bool {
+    let (request, response) = bedrock_parts(v);
+    request["contents"].as_array().is_some_and(|c| c.first().is_some_and(|t| t["parts"].is_array()))
+        || response["candidates"].as_array().is_some_and(|c| c.first().is_some_and(|c| c["content"].is_object()))
+}
+
+fn gemini_field<'a>(v: &'a serde_json::Value, camel: &str, snake: &str) -> &'a serde_json::Value {
+    v.get(camel).unwrap_or(&v[snake])
+}
+
+// Response chunks are concatenated: streamed text arrives in pieces.
+fn parse_gemini_conversation(values: &[serde_json::Value]) -> RawSession {
+    let mut messages: Vec<RawMsg> = Vec::new();
+    // (function name, message index, input) of calls still waiting for their response
+    let mut calls: Vec<(String, usize, String)> = Vec::new();
+    let mut turns: Vec<(&serde_json::Value, bool)> = Vec::new();
+    for v in values {
+        let (request, response) = bedrock_parts(v);
+        if turns.is_empty() {
+            let system = gemini_field(request, "systemInstruction", "system_instruction");
+            let text = system["parts"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|p| p["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            if !text.is_empty() {
+                messages.push(RawMsg { role: "system".to_string(), content: text, ..Default::default() });
+            }
+            turns.extend(request["contents"].as_array().into_iter().flatten().map(|t| (t, false)));
+        }
+        // Only the first candidate is shown
+        turns.extend(response["candidates"][0].get("content").map(|t| (t, true)));
+    }
+    // Message that text parts are appended to: parts of one turn, and streamed chunks of one reply,
+    // form a single message
+    let mut open: Option<usize> = None;
+    let mut previous_streamed = false;
+    for (turn, streamed) in turns {
+        if !(streamed && previous_streamed) {
+            open = None;
+        }
+        previous_streamed = streamed;
+        let role = match turn["role"].as_str() {
+            Some("model") => "assistant",
+            Some("user") | None if !streamed => "user",
+            Some(other) if !streamed => other,
+            _ => "assistant",
+        };
+        for part in turn["parts"].as_array().into_iter().flatten() {
+            if let Some(call) = gemini_field(part, "functionCall", "function_call").as_object() {
+                let name = call.get("name").and_then(|n| n.as_str()).unwrap_or("function").to_string();
+                let input = call.get("args").map(json_text).unwrap_or_default();
+                calls.push((name.clone(), messages.len(), input.clone()));
+                open = None;
+                messages.push(RawMsg {
+                    role: "tool".to_string(),
+                    content: tool_card_content(&input, ""),
+                    name: Some(name),
+                    ..Default::default()
+                });
+                continue;
+            }
+            if let Some(result) = gemini_field(part, "functionResponse", "function_response").as_object() {
+                let name = result.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+                let output = result.get("response").map(json_text).unwrap_or_default();
+                match calls.iter().position(|(n, _, _)| n == name) {
+                    Some(pos) => {
+                        let (_, index, input) = calls.remove(pos);
+                        messages[index].content = tool_card_content(&input, &output);
+                    }
+                    None => messages.push(RawMsg {
+                        role: "tool".to_string(),
+                        content: tool_card_content("", &output),
+                        name: Some(name.to_string()),
+                        ..Default::default()
+                    }),
+                }
+                open = None;
+                continue;
+            }
+            let mut attachment = None;
+            let inline = gemini_field(part, "inlineData", "inline_data");
+            let file = gemini_field(part, "fileData", "file_data");
+            let code = gemini_field(part, "executableCode", "executable_code");
+            let code_result = gemini_field(part, "codeExecutionResult", "code_execution_result");
+            let text = if let Some(text) = part["text"].as_str() {
+                if part["thought"].as_bool() == Some(true) {
+                    format!("> {}\n\n", text.trim().replace('\n', "\n> "))
+                } else {
+                    text.to_string()
+                }
+            } else if inline.is_object() {
+                let mime = gemini_field(inline, "mimeType", "mime_type").as_str().unwrap_or("application/octet-stream");
+                let (markdown, att) = inline_attachment(mime, inline["data"].as_str().unwrap_or_default());
+                attachment = att;
+                format!("\n\n{markdown}\n\n")
+            } else if file.is_object() {
+                let uri = gemini_field(file, "fileUri", "file_uri").as_str().unwrap_or("file");
+                format!("\n\n[file: {uri}]\n\n")
+            } else if code.is_object() {
+                let lang = code["language"].as_str().unwrap_or_default().to_lowercase();
+                format!("\n\n```{lang}\n{}\n```\n\n", code["code"].as_str().unwrap_or_default().trim_end())
+            } else if code_result.is_object() {
+                format!("\n\n```\n{}\n```\n\n", code_result["output"].as_str().unwrap_or_default().trim_end())
+            } else {
+                continue;
+            };
+            let index = *open.get_or_insert_with(|| {
+                messages.push(RawMsg { role: role.to_string(), ..Default::default() });
+                messages.len() - 1
+            });
+            messages[index].content.push_str(&text);
+            messages[index].attachments.extend(attachment);
+        }
+    }
+    let title = values.iter().find_map(|v| {
+        let (request, response) = bedrock_parts(v);
+        request["model"].as_str().or_else(|| gemini_field(response, "modelVersion", "model_version").as_str())
+    });
+    RawSession { title: title.map(str::to_string), messages, ..Default::default() }
+}
+
+// ---------------- Ollama /api/chat ----------------
+
+// Request bodies (`{model, messages}`) and responses, either whole or streamed as chunks
+// (`{model, created_at, message: {role, content}, done}`; the last chunk carries the timings).
+fn is_ollama_chat(v: &serde_json::Value) -> bool {
+    v["model"].is_string()
+        && (v["messages"].is_array() || (v["message"]["role"].is_string() && v["done"].is_boolean()))
+}
+
+fn is_ollama_jsonl(text: &str) -> bool {
+    text.lines()
+        .find(|l| !l.trim().is_empty())
+        .and_then(|l| serde_json::from_str::<serde_json::Value>(l).ok())
+        .is_some_and(|v| is_ollama_chat(&v))
+}
+
+// "298 tokens · 41.2 tok/s · prompt 26 tokens in 0.13 s · total 7.31 s" from the final chunk.
+fn ollama_timing(v: &serde_json::Value) -> Option<String> {
+    let secs = |key: &str| v[key].as_f64().map(|ns| ns / 1e9);
+    let mut parts = Vec::new();
+    if let Some(tokens) = v["eval_count"].as_u64() {
+        parts.push(format!("{tokens} tokens"));
+        if let Some(duration) = secs("eval_duration").filter(|d| *d > 0.0) {
+            parts.push(format!("{:.1} tok/s", tokens as f64 / duration));
+        }
+    }
+    if let Some(tokens) = v["prompt_eval_count"].as_u64() {
+        match secs("prompt_eval_duration") {
+            Some(d) => parts.push(format!("prompt {tokens} tokens in {d:.2} s")),
+            None => parts.push(format!("prompt {tokens} tokens")),
+        }
+    }
+    if let Some(load) = secs("load_duration").filter(|d| *d >= 0.5) {
+        parts.push(format!("load {load:.2} s"));
+    }
+    if let Some(total) = secs("total_duration") {
+        parts.push(format!("total {total:.2} s"));
+    }
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+fn parse_ollama_chat(values: &[serde_json::Value]) -> RawSession {
+    let mut steps = Vec::new();
+    let mut prompt: Vec<RawMsg> = Vec::new();
+    // Whether the current request already has a reply
+    let mut answered = true;
+    // Reply being reassembled from stream chunks
+    let mut reply: Option<RawMsg> = None;
+    let mut thinking = String::new();
+    for v in values.iter().filter(|v| is_ollama_chat(v)) {
+        if let Some(messages) = v["messages"].as_array() {
+            if !answered {
+                // The previous request's reply was not logged (or never finished)
+                let response = reply.take().unwrap_or_else(|| assistant_reply(String::new()));
+                steps.push(TraceStep::Llm { prompt: prompt.clone(), response });
+            }
+            prompt = messages.iter().filter_map(|m| serde_json::from_value(m.clone()).ok()).collect();
+            answered = false;
+            continue;
+        }
+        let r = reply.get_or_insert_with(|| RawMsg {
+            timestamp: timestamp_ms(&v["created_at"]),
+            ..assistant_reply(String::new())
+        });
+        r.content.push_str(v["message"]["content"].as_str().unwrap_or_default());
+        thinking.push_str(v["message"]["thinking"].as_str().unwrap_or_default());
+        if v["done"].as_bool() == Some(true) {
+            let mut r = reply.take().expect("inserted above");
+            // Reasoning models stream their thinking separately
+            if !thinking.trim().is_empty() {
+                r.content.insert_str(0, &format!("{}\n\n", think_block(&thinking)));
+                thinking.clear();
+            }
+            r.detail = ollama_timing(v);
+            steps.push(TraceStep::Llm { prompt: prompt.clone(), response: r });
+            answered = true;
+        }
+    }
+    if !answered || reply.is_some() {
+        let response = reply.unwrap_or_else(|| assistant_reply(String::new()));
+        steps.push(TraceStep::Llm { prompt, response });
+    }
+    let title = values.iter().find_map(|v| v["model"].as_str()).map(str::to_string);
+    RawSession { title, messages: trace_to_messages(steps, None), ..Default::default() }
+}
+
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+
+// Cursor keeps chats in its VS Code state database (`state.vscdb`): classic chat tabs in
+// `ItemTable`, Composer sessions in `cursorDiskKV` as `composerData:<id>` (+ `bubbleId:<id>:<bubble>`).
+fn parse_cursor_db(path: &Path) -> Result<Vec<RawSession>> {
+    let conn = rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open database {}", path.display()))?;
+    let mut sessions = Vec::new();
+
+    let chat_data: Option<String> = conn
+        .query_row(
+            "SELECT CAST(value AS TEXT) FROM ItemTable WHERE key = 'workbench.panel.aichat.view.aichat.chatdata'",
+            [],
+            |r| r.get(0),
+        )
+        .ok();
+    if let Some(v) = chat_data.and_then(|d| serde_json::from_str::<serde_json::Value>(&d).ok()) {
+        for tab in v["tabs"].as_array().map(Vec::as_slice).unwrap_or_default() {
+            let messages: Vec<RawMsg> = tab["bubbles"]
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .map(|b| {
+                    let role = match b["type"].as_str().unwrap_or("unknown") {
+                        "ai" => "assistant",
+                        other => other,
+                    };
+                    let text = b["text"].as_str().filter(|t| !t.is_empty()).or(b["rawText"].as_str()).unwrap_or("");
+                    RawMsg { role: role.to_string(), content: text.to_string(), ..Default::default() }
+                })
+                .collect();
+            if !messages.is_empty() {
+                sessions.push(RawSession { title: tab["chatTitle"].as_str().map(str::to_string), messages, ..Default::default() });
+            }
+        }
+    }
+
+    // Older databases have no key-value table; that is not an error
+    if let Ok(mut stmt) = conn.prepare("SELECT CAST(value AS TEXT) FROM cursorDiskKV WHERE key LIKE 'composerData:%'") {
+        let rows: Vec<String> = stmt.query_map([], |r| r.get(0))?.flatten().collect();
+        let mut bubble_stmt = conn.prepare("SELECT CAST(value AS TEXT) FROM cursorDiskKV WHERE key = ?1")?;
+        for row in rows {
+            let Ok(composer) = serde_json::from_str::<serde_json::Value>(&row) else { continue };
+            let bubbles: Vec<serde_json::Value> = match composer["conversation"].as_array() {
+                Some(c) if !c.is_empty() => c.clone(),
+                // Newer versions store only headers and keep each bubble under its own key
+                _ => {
+                    let id = composer["composerId"].as_str().unwrap_or_default();
+                    composer["fullConversationHeadersOnly"]
+                        .as_array()
+                        .map(Vec::as_slice)
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(|h| h["bubbleId"].as_str())
+                        .filter_map(|bubble| {
+                            let key = format!("bubbleId:{id}:{bubble}");
+                            bubble_stmt.query_row([key], |r| r.get::<_, String>(0)).ok()
+                        })
+                        .filter_map(|b| serde_json::from_str(&b).ok())
+                        .collect()
+                }
+            };
+            let messages: Vec<RawMsg> = bubbles
+                .iter()
+                .filter_map(|b| {
+                    // 1 = user, 2 = assistant
+                    let role = match b["type"].as_i64() {
+                        Some(1) => "user",
+                        Some(2) => "assistant",
+                        _ => return None,
+                    };
+                    let text = b["text"].as_str().unwrap_or("");
+                    (!text.trim().is_empty())
+                        .then(|| RawMsg { role: role.to_string(), content: text.to_string(), ..Default::default() })
+                })
+                .collect();
+            if !messages.is_empty() {
+                sessions.push(RawSession { title: composer["name"].as_str().map(str::to_string), messages, ..Default::default() });
+            }
+        }
+    }
+
+    if sessions.is_empty() {
+        return Err(anyhow!("No Cursor chats found in {}", path.display()));
+    }
+    Ok(sessions)
+}
+
+const PARQUET_MAGIC: &[u8] = b"PAR1";
+// Large datasets are cut off here so the sessions list stays responsive
+const MAX_DATASET_ROWS: usize = 10_000;
+
+// Chat datasets (e.g. from Hugging Face) in Parquet: one conversation per row.
+fn parse_parquet(path: &Path) -> Result<(Vec<RawSession>, Vec<String>)> {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    let file = fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let reader = SerializedFileReader::new(file).context("Parquet read error")?;
+    let total = usize::try_from(reader.metadata().file_metadata().num_rows()).unwrap_or(0);
+    let mut sessions = Vec::new();
+    let mut skipped = 0;
+    for (i, row) in reader.get_row_iter(None)?.take(MAX_DATASET_ROWS).enumerate() {
+        match dataset_row_session(&row?.to_json_value(), i) {
+            Some(session) => sessions.push(session),
+            None => skipped += 1,
+        }
+    }
+    if sessions.is_empty() {
+        return Err(anyhow!("No chat columns found (expected messages, conversations or prompt/response)"));
+    }
+    let mut warnings = Vec::new();
+    if skipped > 0 {
+        warnings.push(format!("Skipped {skipped} rows without chat columns"));
+    }
+    if total > MAX_DATASET_ROWS {
+        warnings.push(format!("Showing the first {MAX_DATASET_ROWS} of {total} rows"));
+    }
+    Ok((sessions, warnings))
+}
+
+// One dataset row as a conversation. Recognized columns: `messages` / `conversations` with
+// OpenAI-style `{role, content}` or ShareGPT-style `{from, value}` turns, or a single exchange
+// from `prompt`/`instruction`/`question` (+ `input`) and `response`/`output`/`answer`/`completion`.
+// An optional `system` column becomes the system prompt.
+fn dataset_row_session(row: &serde_json::Value, index: usize) -> Option<RawSession> {
+    let text = |key: &str| row[key].as_str().map(str::trim).filter(|s| !s.is_empty());
+    let mut messages = Vec::new();
+    if let Some(system) = text("system").or(text("system_prompt")) {
+        messages.push(RawMsg { role: "system".into(), content: system.to_string(), ..Default::default() });
+    }
+    let turns = ["messages", "conversations", "conversation"].iter().find_map(|k| row[*k].as_array());
+    if let Some(turns) = turns {
+        messages.extend(turns.iter().filter_map(|t| {
+            let role = t["role"].as_str().or(t["from"].as_str())?;
+            let role = match role.to_lowercase().as_str() {
+                "human" => "user".to_string(),
+                "gpt" | "bot" | "model" => "assistant".to_string(),
+                other => other.to_string(),
+            };
+            let content = if t["content"].is_null() { text_parts_content(&t["value"]) } else { text_parts_content(&t["content"]) };
+            Some(RawMsg { role, content, ..Default::default() })
+        }));
+    } else {
+        let prompt = text("prompt").or(text("instruction")).or(text("question"))?;
+        let prompt = match text("input") {
+            Some(input) if text("prompt").is_none() => format!("{prompt}\n\n{input}"),
+            _ => prompt.to_string(),
+        };
+        messages.push(RawMsg { role: "user".into(), content: prompt, ..Default::default() });
+        if let Some(response) = text("response").or(text("output")).or(text("answer")).or(text("completion")) {
+            messages.push(RawMsg { role: "assistant".into(), content: response.to_string(), ..Default::default() });
+        }
+    }
+    let id = ["id", "conversation_id", "uuid", "prompt_id"].iter().find_map(|k| match &row[*k] {
+        serde_json::Value::String(s) if !s.is_empty() => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    });
+    let title = id.unwrap_or_else(|| format!("Row {}", index + 1));
+    Some(RawSession { title: Some(title), messages, ..Default::default() })
+}
+
+// Hand-authored prompt files in YAML: a list of `{role, content}` maps, or a map with a
+// `messages` list (and optionally a `system` prompt), e.g. `- role: user\n  content: |`.
+fn is_yaml_conversation(text: &str) -> bool {
+    let mut lines = text.lines().map(str::trim_end).filter(|l| !l.trim().is_empty() && !l.starts_with('#') && *l != "---");
+    let Some(first) = lines.next() else { return false };
+    let is_item = |l: &str| l.strip_prefix("- ").is_some_and(|rest| ["role:", "content:"].iter().any(|k| rest.trim_start().starts_with(k)));
+    if is_item(first) {
+        return true;
+    }
+    // A bare `messages:` line also occurs in transcripts, so the document has to parse as well
+    text.lines().any(|l| l.trim_end() == "messages:")
+        && serde_yaml::from_str::<serde_yaml::Value>(text).is_ok_and(|v| v.get("messages").is_some_and(serde_yaml::Value::is_sequence))
+}
+
+fn parse_yaml_conversation(text: &str) -> Result<Vec<RawMsg>> {
+    let yaml: serde_yaml::Value = serde_yaml::from_str(text).context("YAML parse error")?;
+    let v = serde_json::to_value(yaml).context("YAML parse error")?;
+    let mut messages = Vec::new();
+    if let Some(system) = v["system"].as_str() {
+        messages.push(RawMsg { role: "system".into(), content: system.to_string(), ..Default::default() });
+    }
+    let list = if v.is_array() { v } else { v["messages"].clone() };
+    messages.extend(serde_json::from_value::<Vec<RawMsg>>(list).context("YAML messages must be a list of role/content maps")?);
+    Ok(messages)
+}
+
+// SillyTavern chat files: an optional header line (`user_name`, `character_name`, `chat_metadata`)
+// followed by one `{"name", "is_user", "is_system", "mes"}` object per message.
+#[derive(Deserialize)]
+struct TavernLine {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    is_user: bool,
+    #[serde(default)]
+    is_system: bool,
+    mes: String,
+    #[serde(default)]
+    send_date: serde_json::Value,
+}
+
+fn is_sillytavern_jsonl(text: &str) -> bool {
+    text.lines().filter(|l| !l.trim().is_empty()).take(2).any(|line| {
+        serde_json::from_str::<serde_json::Value>(line).is_ok_and(|v| {
+            (v.get("mes").is_some() && v.get("is_user").is_some())
+                || (v.get("character_name").is_some() && v.get("chat_metadata").is_some())
+        })
+    })
+}
+
+fn parse_sillytavern_jsonl(text: &str) -> (Vec<RawMsg>, LineErrors) {
+    let mut out = Vec::new();
+    let mut failed = LineErrors::default();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() { continue; }
+        match serde_json::from_str::<TavernLine>(line) {
+            Ok(t) => {
+                // System notes appear inline in the chat, so keep them out of the System card
+                let role = if t.is_system { "narrator" } else if t.is_user { "user" } else { "assistant" };
+                let timestamp = timestamp_ms(&t.send_date);
+                out.push(RawMsg { role: role.to_string(), content: t.mes, name: t.name, timestamp, ..Default::default() });
+            }
+            // The header line has no `mes`; anything else without it is a real failure
+            Err(_) if line.contains("\"chat_metadata\"") => {}
+            Err(e) => failed.push(i + 1, e, line),
+        }
+    }
+    (out, failed)
+}
+
+fn parse_jsonl(bytes: &[u8]) -> Result<Vec<RawMsg>> {
+    // Basic version without exposing failures
+    let text = std::str::from_utf8(bytes).map_err(|_| anyhow!("Non-UTF8 file"))?;
+    let mut out = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() { continue; }
+        match serde_json::from_str::<RawMsg>(line) {
+            Ok(m) => out.push(m),
+            Err(_e) => {
+                // Skip silently here; load_from_bytes uses the variant with errors.
+                eprintln!("Warning: failed to parse JSONL line {}", idx + 1);
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn parse_jsonl_with_errors(bytes: &[u8]) -> Result<(Vec<RawMsg>, LineErrors)> {
+    let text = std::str::from_utf8(bytes).map_err(|_| anyhow!("Non-UTF8 file"))?;
+    let mut out = Vec::new();
+    let mut failed = LineErrors::default();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() { continue; }
+        match serde_json::from_str::<RawMsg>(line) {
+            Ok(m) => out.push(m),
+            Err(e) => failed.push(i + 1, e, line),
+        }
+    }
+    Ok((out, failed))
+}
+
+// Turn markers of a chat template, for splitting a rendered prompt back into turns.
+struct TurnTags {
+    start: &'static str,
+    // Ends the role name; the turn text follows
+    header_end: &'static str,
+    // Any of these ends the turn text
+    ends: &'static [&'static str],
+    // Other special tokens, dropped from the text
+    noise: &'static [&'static str],
+}
+
+// `<|im_start|>role\n...<|im_end|>`
+const CHATML: TurnTags =
+    TurnTags { start: "<|im_start|>", header_end: "\n", ends: &["<|im_end|>"], noise: &["<|endoftext|>"] };
+// Llama 3: `<|start_header_id|>role<|end_header_id|>\n\n...<|eot_id|>`; `<|eom_id|>` ends tool calls
+const LLAMA3: TurnTags = TurnTags {
+    start: "<|start_header_id|>",
+    header_end: "<|end_header_id|>",
+    ends: &["<|eot_id|>", "<|eom_id|>"],
+    noise: &["<|begin_of_text|>", "<|end_of_text|>"],
+};
+// Gemma: `<start_of_turn>user\n...<end_of_turn>`, with `model` for the assistant
+const GEMMA: TurnTags =
+    TurnTags { start: "<start_of_turn>", header_end: "\n", ends: &["<end_of_turn>"], noise: &["<bos>", "<eos>"] };
+
+// Prompts copied out of server logs are often still escaped: a quoted JSON string, or one log
+// line with literal `\n` for its line breaks.
+fn unescaped_prompt(text: &str) -> Cow<'_, str> {
+    let t = text.trim();
+    if t.starts_with('"') || (!t.contains('\n') && t.contains("\\n")) {
+        Cow::Owned(crate::escapes::unescape(t))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+// Split a rendered prompt back into turns. Text before the first turn is dropped; a trailing
+// `<|im_start|>assistant` without content (the generation slot) becomes an empty turn.
+fn parse_templated_prompt(text: &str, tags: &TurnTags) -> Vec<RawMsg> {
+    let text = unescaped_prompt(text);
+    let mut out = Vec::new();
+    for segment in text.split(tags.start).skip(1) {
+        let (header, body) = segment.split_once(tags.header_end).unwrap_or((segment, ""));
+        let role = header.split_whitespace().next().unwrap_or("").to_string();
+        if role.is_empty() { continue; }
+        let end = tags.ends.iter().filter_map(|e| body.find(e)).min().unwrap_or(body.len());
+        let content = tags.noise.iter().fold(body[..end].to_string(), |c, token| c.replace(token, ""));
+        out.push(RawMsg { role, content, ..Default::default() });
+    }
+    out
+}
+
+fn is_llama_inst_prompt(text: &str) -> bool {
+    let t = text.trim_start();
+    let t = t.strip_prefix("<s>").unwrap_or(t).trim_start();
+    t.starts_with("[INST]")
+}
+
+// Split a Llama-2 style prompt (`<s>[INST] <<SYS>>...<</SYS>> user [/INST] assistant </s>`) into turns.
+fn parse_llama_inst(text: &str) -> Vec<RawMsg> {
+    let mut out = Vec::new();
+    for segment in text.split("[INST]").skip(1) {
+        let (user_part, assistant_part) = segment.split_once("[/INST]").unwrap_or((segment, ""));
+        let mut user = user_part.to_string();
+        if let Some(start) = user.find("<<SYS>>") {
+            if let Some(end) = user[start..].find("<</SYS>>").map(|e| start + e) {
+                let sys = user[start + "<<SYS>>".len()..end].trim().to_string();
+                user.replace_range(start..end + "<</SYS>>".len(), "");
+                out.push(RawMsg { role: "system".into(), content: sys, ..Default::default() });
+            }
+        }
+        out.push(RawMsg { role: "user".into(), content: strip_bos_eos(&user), ..Default::default() });
+        let assistant = strip_bos_eos(assistant_part);
+        // Keep an empty final turn: the prompt ends where the model is expected to answer
+        out.push(RawMsg { role: "assistant".into(), content: assistant, ..Default::default() });
+    }
+    out
+}
+
+fn strip_bos_eos(s: &str) -> String {
+    s.replace("</s>", "").replace("<s>", "").trim().to_string()
+}
+
+// Speaker prefixes recognized in plain-text transcripts, mapped to canonical roles.
+const TRANSCRIPT_SPEAKERS: &[(&str, &str)] = &[
+    ("system", "system"),
+    ("user", "user"),
+    ("human", "user"),
+    ("assistant", "assistant"),
+    ("ai", "assistant"),
+];
+
+// Returns (role, rest of line) when the line starts a new turn, e.g. "User: hi" or "**Assistant:** ok".
+fn transcript_speaker(line: &str) -> Option<(&'static str, &str)> {
+    let (head, rest) = line.split_once(':')?;
+    let head = head.trim().trim_matches('*').trim();
+    if head.len() > "assistant".len() {
+        return None;
+    }
+    let head = head.to_lowercase();
+    let (_, role) = TRANSCRIPT_SPEAKERS.iter().find(|(p, _)| *p == head)?;
+    Some((role, rest.trim_start_matches('*')))
+}
+
+// Heuristic parser for copy-pasted chats: a turn starts at a "Role:" line and runs until the next one.
+fn parse_plain_transcript(text: &str) -> Vec<RawMsg> {
+    let mut out: Vec<RawMsg> = Vec::new();
+    let mut in_fence = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        let speaker = if in_fence { None } else { transcript_speaker(line) };
+        if let Some((role, rest)) = speaker {
+            out.push(RawMsg { role: role.to_string(), content: rest.trim_start().to_string(), ..Default::default() });
+        } else if let Some(last) = out.last_mut() {
+            last.content.push('\n');
+            last.content.push_str(line);
+        }
+        // Lines before the first speaker prefix are preamble and dropped
+    }
+    out
+}
+
+// Role labels `to_markdown` writes; other bold lines are taken as part of the message
+const MARKDOWN_EXPORT_ROLES: &[&str] = &["User", "Assistant", "System", "System (extra)", "Tool", "Function", "Developer"];
+
+// Speaker line of a "Copy as Markdown" / plain Markdown export: `**Assistant**  ` (bold label,
+// two-space hard break). Returns the label.
+fn markdown_export_speaker(line: &str) -> Option<&str> {
+    let label = line.strip_suffix("  ")?.strip_prefix("**")?.strip_suffix("**")?;
+    MARKDOWN_EXPORT_ROLES.contains(&label).then_some(label)
+}
+
+fn is_markdown_export(text: &str) -> bool {
+    let first = text.lines().find(|l| !l.trim().is_empty());
+    first.is_some_and(|l| l == "# System" || markdown_export_speaker(l).is_some())
+}
+
+// Inverse of `to_markdown`: an optional `# System` section closed by `---`, then one
+// `**Role**  ` line per message followed by its content. A speaker line only starts a new
+// message after a blank line and outside code fences, so bold text in answers stays put.
+fn parse_markdown_export(text: &str) -> Vec<RawMsg> {
+    let mut out: Vec<RawMsg> = Vec::new();
+    let mut lines = text.lines().skip_while(|l| l.trim().is_empty()).peekable();
+    if lines.peek() == Some(&"# System") {
+        lines.next();
+        let mut system: Vec<&str> = Vec::new();
+        while let Some(line) = lines.next() {
+            // The separator is followed by a blank line and the first speaker
+            if line == "---" && system.last().is_some_and(|l| l.is_empty()) {
+                let mut rest = lines.clone().skip_while(|l| l.trim().is_empty());
+                if rest.next().is_none_or(|l| markdown_export_speaker(l).is_some()) {
+                    break;
+                }
+            }
+            system.push(line);
+        }
+        out.push(RawMsg { role: "system".into(), content: system.join("\n"), ..Default::default() });
+    }
+    // Lines between the system section and the first speaker are dropped
+    let start = out.len();
+    let mut in_fence = false;
+    let mut after_blank = true;
+    for line in lines {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        let speaker = if in_fence || !after_blank { None } else { markdown_export_speaker(line) };
+        after_blank = line.trim().is_empty();
+        match speaker {
+            Some(label) => {
+                // "System (extra)" is how additional system messages are labelled
+                let role = if label.starts_with("System") { "system".to_string() } else { label.to_lowercase() };
+                out.push(RawMsg { role, content: String::new(), ..Default::default() });
+            }
+            None => {
+                if let Some(last) = out[start..].last_mut() {
+                    last.content.push_str(line);
+                    last.content.push('\n');
+                }
+            }
+        }
+    }
+    for m in &mut out {
+        m.content.truncate(m.content.trim_end().len());
+    }
+    if let Some(last) = out.last_mut() {
+        strip_provenance_footer(&mut last.content);
+    }
+    out
+}
+
+// Exports end with a `---` rule and an "*Exported from …*" line (see provenance.rs), which would
+// otherwise be read as the end of the last message.
+fn strip_provenance_footer(content: &mut String) {
+    let Some((before, footer)) = content.rsplit_once('\n') else { return };
+    if !footer.starts_with(crate::provenance::MARKDOWN_MARKER) {
+        return;
+    }
+    if let Some(message) = before.trim_end().strip_suffix("\n---") {
+        let len = message.trim_end().len();
+        content.truncate(len);
+    }
+}
+
+fn normalize(raw: Vec<RawMsg>) -> Loaded {
+    let mut system: Option<String> = None;
+    let mut messages: Vec<Msg> = Vec::new();
+    normalize_into(raw, &mut system, &mut messages);
+    Loaded { system, messages, ..Default::default() }
+}
+
+// Normalize `raw` onto the end of a conversation; the first system message becomes its system
+// prompt. Speaker colors are left to assign_agents, for multi-agent logs only.
+pub(crate) fn normalize_into(raw: Vec<RawMsg>, system: &mut Option<String>, messages: &mut Vec<Msg>) {
+    for rm in raw {
+        let name = rm.name.filter(|n| !n.trim().is_empty());
+        let (timestamp, depth, detail, attachments) = (rm.timestamp, rm.depth, rm.detail, rm.attachments);
+        let cleaned = trim_chat_whitespace(&rm.content);
+        let content = if cleaned.trim().is_empty() { "(empty)".to_string() } else { cleaned };
+        match roles::canonical_role(&rm.role).as_str() {
+            "system" => {
+                if system.is_none() {
+                    *system = Some(content);
+                } else {
+                    messages.push(Msg { role: Role::Other("System (extra)".into()), content, name, timestamp, depth, agent: None, detail, attachments });
+                }
+            }
+            "user" => messages.push(Msg { role: Role::User, content, name, timestamp, depth, agent: None, detail, attachments }),
+            "assistant" => messages.push(Msg { role: Role::Assistant, content, name, timestamp, depth, agent: None, detail, attachments }),
+            other => messages.push(Msg { role: Role::Other(other.to_string()), content, name, timestamp, depth, agent: None, detail, attachments }),
+        }
+    }
+}
+
+// Formats whose named speakers are separate agents. Elsewhere a name (a SillyTavern character, a
+// named ChatGPT user) is only a label, and the bubble keeps its role's colors.
+pub(crate) const MULTI_AGENT_FORMATS: &[&str] = &["AutoGen console log", "CrewAI verbose log"];
+
+// Give every distinct speaker name its own color slot. Tool cards are named after the tool, not a speaker.
+pub(crate) fn assign_agents(messages: &mut [Msg]) {
+    let mut agents: Vec<String> = Vec::new();
+    for msg in messages.iter_mut().filter(|m| !is_tool_card(m)) {
+        let Some(name) = &msg.name else { continue };
+        let slot = agents.iter().position(|a| a == name).unwrap_or_else(|| {
+            agents.push(name.clone());
+            agents.len() - 1
+        });
+        msg.agent = Some(slot);
+    }
+}
+
+// ---------------- promptfoo ----------------
+
+// promptfoo `results.json`: `{"results": {"results": [...]}}` since v3, `{"results": [...]}` before.
+fn promptfoo_results(v: &serde_json::Value) -> Option<&[serde_json::Value]> {
+    let results = v["results"]["results"].as_array().or_else(|| v["results"].as_array())?;
+    let first = results.first()?;
+    (first.get("provider").is_some() && first.get("prompt").is_some() && first.get("response").is_some())
+        .then_some(results.as_slice())
+}
+
+// One session per (test case, prompt, provider) cell, ordered like promptfoo's results table.
+fn parse_promptfoo_results(results: &[serde_json::Value]) -> Vec<RawSession> {
+    let mut results: Vec<&serde_json::Value> = results.iter().collect();
+    results.sort_by_key(|r| (r["testIdx"].as_u64(), r["promptIdx"].as_u64()));
+    let several_prompts = results.iter().any(|r| r["promptIdx"].as_u64().is_some_and(|i| i > 0));
+    results
+        .into_iter()
+        .map(|r| {
+            let provider = r["provider"]["label"]
+                .as_str()
+                .filter(|l| !l.is_empty())
+                .or_else(|| r["provider"]["id"].as_str())
+                .or_else(|| r["provider"].as_str())
+                .unwrap_or("provider");
+            let pass = r["success"].as_bool().or_else(|| r["gradingResult"]["pass"].as_bool()).unwrap_or(false);
+            let test = r["testIdx"].as_u64().map_or_else(|| "Test".to_string(), |i| format!("Test {}", i + 1));
+            let description = r["testCase"]["description"].as_str().or_else(|| r["description"].as_str());
+            let mut title = format!("{} · {test}", if pass { "PASS" } else { "FAIL" });
+            if let Some(d) = description {
+                title.push_str(&format!(": {d}"));
+            }
+            if several_prompts {
+                let label = r["prompt"]["label"].as_str().unwrap_or_default();
+                title.push_str(&format!(" · {}", label.chars().take(40).collect::<String>()));
+            }
+            title.push_str(&format!(" · {provider}"));
+
+            // The rendered prompt is either plain text or a JSON-encoded chat
+            let raw = r["prompt"]["raw"].as_str().unwrap_or_default();
+            let mut messages = serde_json::from_str::<Vec<RawMsg>>(raw)
+                .ok()
+                .filter(|m| !m.is_empty())
+                .unwrap_or_else(|| vec![RawMsg { role: "user".to_string(), content: raw.to_string(), ..Default::default() }]);
+            let output = json_text(&r["response"]["output"]);
+            if !output.trim().is_empty() {
+                messages.push(RawMsg { role: "assistant".to_string(), content: output, ..Default::default() });
+            }
+
+            let mut summary = vec![provider.to_string()];
+            if let Some(score) = r["score"].as_f64().or_else(|| r["gradingResult"]["score"].as_f64()) {
+                summary.push(format!("score {score:.2}"));
+            }
+            if let Some(ms) = r["latencyMs"].as_u64() {
+                summary.push(format!("{ms} ms"));
+            }
+            let reason = r["error"]
+                .as_str()
+                .or_else(|| r["response"]["error"].as_str())
+                .or_else(|| r["gradingResult"]["reason"].as_str());
+            if let Some(reason) = reason.filter(|s| !s.trim().is_empty()) {
+                summary.push(reason.to_string());
+            }
+            RawSession {
+                title: Some(title),
+                messages,
+                outcome: Some(EvalOutcome { pass, summary: summary.join(" · ") }),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+// ---------------- Multi-agent console logs (AutoGen, CrewAI) ----------------
+
+// Both frameworks color their console output; logs captured with `tee` keep the escape codes.
+// Stripped line by line, so detection stops at the first lines that decide and copies only lines
+// that have escapes.
+fn plain_lines(text: &str) -> impl Iterator<Item = Cow<'_, str>> {
+    text.lines().map(strip_ansi)
+}
+
+fn strip_ansi(line: &str) -> Cow<'_, str> {
+    if !line.contains('\u{1b}') {
+        return Cow::Borrowed(line);
+    }
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' && chars.peek() == Some(&'[') {
+            chars.next();
+            // Parameters and intermediates, then one final byte in @..~
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    Cow::Owned(out)
+}
+
+// "Coder (to chat_manager):" starts a message in AutoGen's console output.
+fn autogen_header(line: &str) -> Option<&str> {
+    let rest = line.trim_end().strip_suffix("):")?;
+    let (speaker, _recipient) = rest.rsplit_once(" (to ")?;
+    let speaker = speaker.trim();
+    (!speaker.is_empty() && speaker.len() <= 64).then_some(speaker)
+}
+
+// Messages are separated by a row of dashes.
+fn is_dashed_rule(line: &str) -> bool {
+    let t = line.trim();
+    t.len() >= 40 && t.chars().all(|c| c == '-')
+}
+
+fn is_autogen_console(text: &str) -> bool {
+    plain_lines(text).any(|l| autogen_header(&l).is_some()) && plain_lines(text).any(|l| is_dashed_rule(&l))
+}
+
+// Agents acting for a person (user_proxy, Admin, ...) are shown on the user side.
+fn agent_role(name: &str) -> &'static str {
+    let lower = name.to_lowercase();
+    if ["user", "human", "admin"].iter().any(|k| lower.contains(k)) {
+        "user"
+    } else {
+        "assistant"
+    }
+}
+
+fn parse_autogen_console(text: &str) -> Vec<RawMsg> {
+    let mut out = Vec::new();
+    let mut current: Option<RawMsg> = None;
+    for line in plain_lines(text) {
+        let line = &*line;
+        if let Some(speaker) = autogen_header(line) {
+            out.extend(current.take());
+            current = Some(RawMsg { role: agent_role(speaker).to_string(), name: Some(speaker.to_string()), ..Default::default() });
+        } else if is_dashed_rule(line) {
+            out.extend(current.take());
+        } else if let Some(msg) = current.as_mut() {
+            msg.content.push_str(line);
+            msg.content.push('\n');
+        }
+        // Anything outside a message (startup banners, code execution notices) is dropped
+    }
+    out.extend(current);
+    out
+}
+
+// CrewAI's verbose output: "# Agent: Researcher" followed by "## Task:", "## Thought:",
+// "## Using tool:", "## Tool Input:", "## Tool Output:" and "## Final Answer:" sections.
+fn is_crewai_verbose(text: &str) -> bool {
+    plain_lines(text).any(|l| l.trim_start().starts_with("# Agent:"))
+        && plain_lines(text).any(|l| {
+            let l = l.trim_start();
+            l.starts_with("## Task:") || l.starts_with("## Final Answer:")
+        })
+}
+
+// Other "## ...:" lines are part of the section body (answers are often Markdown).
+const CREWAI_SECTIONS: &[&str] = &["Task", "Thought", "Using tool", "Tool Input", "Tool Output", "Final Answer"];
+
+fn parse_crewai_verbose(text: &str) -> Vec<RawMsg> {
+    let mut out = Vec::new();
+    let mut agent = String::new();
+    let mut tool = String::new();
+    let mut tool_input = String::new();
+    // (section heading, body so far)
+    let mut section: Option<(String, String)> = None;
+    let mut flush = |section: Option<(String, String)>, agent: &str, tool: &mut String, tool_input: &mut String| {
+        let Some((heading, body)) = section else { return };
+        let body = body.trim().to_string();
+        let named = |role: &str, content: String| RawMsg {
+            role: role.to_string(),
+            content,
+            name: (!agent.is_empty()).then(|| agent.to_string()),
+            ..Default::default()
+        };
+        match heading.as_str() {
+            "Task" => out.push(RawMsg { role: "user".to_string(), content: body, ..Default::default() }),
+            "Thought" => out.push(named("assistant", format!("*{body}*"))),
+            "Final Answer" => out.push(named("assistant", body)),
+            "Using tool" => *tool = body,
+            "Tool Input" => *tool_input = body,
+            "Tool Output" => out.push(RawMsg {
+                role: "tool".to_string(),
+                content: tool_card_content(&std::mem::take(tool_input), &body),
+                name: Some(std::mem::take(tool)).filter(|t| !t.is_empty()),
+                ..Default::default()
+            }),
+            _ => {}
+        }
+    };
+    for line in plain_lines(text) {
+        let line = &*line;
+        let trimmed = line.trim_start();
+        if let Some(name) = trimmed.strip_prefix("# Agent:") {
+            flush(section.take(), &agent, &mut tool, &mut tool_input);
+            agent = name.trim().to_string();
+        } else if let Some((heading, rest)) = trimmed
+            .strip_prefix("## ")
+            .and_then(|h| h.split_once(':'))
+            .filter(|(h, _)| CREWAI_SECTIONS.contains(&h.trim()))
+        {
+            flush(section.take(), &agent, &mut tool, &mut tool_input);
+            section = Some((heading.trim().to_string(), format!("{}\n", rest.trim())));
+        } else if let Some((_, body)) = section.as_mut() {
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+    flush(section.take(), &agent, &mut tool, &mut tool_input);
+    out
+}
+
+// Normalize every conversation; the first one is shown initially.
+pub(crate) fn normalize_sessions(raw: Vec<RawSession>) -> Loaded {
+    let mut sessions: Vec<Session> = raw
+        .into_iter()
+        .enumerate()
+        .map(|(i, rs)| {
+            let l = normalize(rs.messages);
+            let title = rs.title.filter(|t| !t.trim().is_empty()).unwrap_or_else(|| format!("Conversation {}", i + 1));
+            Session { title, system: l.system, messages: l.messages, outcome: rs.outcome, tree: rs.tree }
+        })
+        .collect();
+    let first = if sessions.is_empty() { None } else { Some(sessions[0].clone()) };
+    if sessions.len() == 1 {
+        sessions.clear();
+    }
+    let (system, messages, outcome, tree) = first.map(|s| (s.system, s.messages, s.outcome, s.tree)).unwrap_or_default();
+    Loaded { system, messages, sessions, outcome, tree, ..Default::default() }
+}
+
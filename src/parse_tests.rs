@@ -0,0 +1,368 @@
+// Property tests for the loaders: whatever bytes are dropped onto the window, loading returns
+// Ok or Err and never panics, and plain message arrays survive a write/read round trip.
+
+use crate::{
+    parse::{load_from_bytes, load_from_path, ParseOptions, RawMsg},
+    reasoning::{parts, Part},
+    role_label, Role,
+};
+use proptest::prelude::*;
+use std::path::PathBuf;
+
+const LENIENT: ParseOptions = ParseOptions { strict: false };
+
+// Fragments of the formats the detector looks for, so random input reaches the parsers
+// behind detection rather than failing at the first byte.
+#[rustfmt::skip]
+const FRAGMENTS: &[&str] = &[
+    "{", "}", "[", "]", ",", ":", "\"", "\\", "\n", " ", "null", "true", "42", "1.5e3", "\"role\"", "\"content\"",
+    "\"user\"", "\"assistant\"", "\"system\"", "\"tool\"", "\"name\"", "\"timestamp\"", "\"messages\"", "\"mapping\"",
+    "\"chat_messages\"", "\"parts\"", "\"type\"", "\"text\"", "\"image_url\"", "\"tool_calls\"", "\"function\"",
+    "\"arguments\"", "\"candidates\"", "\"resourceSpans\"", "\"run_type\"", "\"child_runs\"", "\"results\"",
+    "\"mes\"", "\"is_user\"", "<|im_start|>", "<|im_end|>", "[INST]", "[/INST]", "<s>", "User:", "Assistant:",
+    "**User**", "- role: ", "content: ", "messages:", "[chain/start]", "[llm/end]", "---", "```", "日本語",
+];
+
+fn fragments() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(FRAGMENTS), 0..200).prop_map(|parts| parts.concat())
+}
+
+// Content that normalization keeps as is: no leading line breaks or trailing whitespace, and
+// nothing that looks like another format's markers.
+fn content() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9`*#(日本]([a-zA-Z0-9 .,!?'`*#|()\\-\n\t\"\\\\/{}日本語中文]{0,80}[a-zA-Z0-9.!?日本])?"
+}
+
+fn raw_msg() -> impl Strategy<Value = RawMsg> {
+    (
+        prop::sample::select(&["user", "assistant", "tool", "critic"][..]),
+        content(),
+        prop::option::of("[a-zA-Z][a-zA-Z0-9_ ]{0,12}[a-zA-Z0-9]"),
+        // Milliseconds, large enough not to be read as seconds
+        prop::option::of(100_000_000_000i64..4_000_000_000_000),
+    )
+        .prop_map(|(role, content, name, timestamp)| RawMsg {
+            role: role.to_string(),
+            content,
+            name,
+            timestamp,
+            ..Default::default()
+        })
+}
+
+fn assert_round_trip(written: &[RawMsg], bytes: &[u8]) -> Result<(), TestCaseError> {
+    let loaded = load_from_bytes(bytes, LENIENT).map_err(|e| TestCaseError::fail(e.to_string()))?;
+    prop_assert!(loaded.errors.is_empty(), "{:?}", loaded.errors);
+    prop_assert_eq!(loaded.messages.len(), written.len());
+    for (raw, msg) in written.iter().zip(&loaded.messages) {
+        let role = match &msg.role {
+            Role::User => "user".to_string(),
+            Role::Assistant => "assistant".to_string(),
+            other => role_label(other).to_lowercase(),
+        };
+        prop_assert_eq!(&role, &raw.role);
+        prop_assert_eq!(&msg.content, &raw.content);
+        prop_assert_eq!(&msg.name, &raw.name);
+        prop_assert_eq!(msg.timestamp, raw.timestamp);
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn arbitrary_bytes_never_panic(bytes in prop::collection::vec(any::<u8>(), 0..2048)) {
+        let _ = load_from_bytes(&bytes, LENIENT);
+    }
+
+    #[test]
+    fn format_fragments_never_panic(text in fragments()) {
+        let _ = load_from_bytes(text.as_bytes(), LENIENT);
+    }
+
+    #[test]
+    fn json_array_round_trips(messages in prop::collection::vec(raw_msg(), 1..20)) {
+        let bytes = serde_json::to_vec(&messages).unwrap();
+        assert_round_trip(&messages, &bytes)?;
+    }
+
+    #[test]
+    fn jsonl_round_trips(messages in prop::collection::vec(raw_msg(), 1..20)) {
+        let text: String = messages.iter().map(|m| serde_json::to_string(m).unwrap() + "\n").collect();
+        assert_round_trip(&messages, text.as_bytes())?;
+    }
+}
+
+#[test]
+fn deeply_nested_json_is_rejected() {
+    for open in ["[", "{\"a\":"] {
+        let text = format!("{}1", open.repeat(100_000));
+        let err = load_from_bytes(text.as_bytes(), LENIENT).err().expect("nesting limit");
+        assert!(err.to_string().contains("nested"), "{err}");
+    }
+}
+
+#[test]
+fn detected_format_is_reported() {
+    let cases: &[(&str, &str)] = &[
+        ("[{\"role\":\"user\",\"content\":\"hi\"}]", "JSON messages"),
+        ("{\"role\":\"user\",\"content\":\"hi\"}\n", "JSONL messages"),
+        ("<|im_start|>user\nhi<|im_end|>\n", "ChatML prompt"),
+        ("<|start_header_id|>user<|end_header_id|>\n\nhi<|eot_id|>", "Llama 3 prompt"),
+        ("{\"name\":\"Bob\",\"is_user\":true,\"mes\":\"hi\"}\n", "SillyTavern chat"),
+        ("{\"data\":{\"messages\":[{\"role\":\"user\",\"content\":\"hi\"}]}}", "Wrapped message list"),
+    ];
+    for (text, format) in cases {
+        let loaded = load_from_bytes(text.as_bytes(), LENIENT).expect(format);
+        assert_eq!(loaded.format, Some(*format), "{text}");
+    }
+}
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("formats").join(name)
+}
+
+// One small file per format in `tests/formats`, with the format it has to be detected as and
+// the messages it holds. Detectors compete on score, so one scoring too high shows up here.
+#[test]
+fn format_fixtures_are_detected() {
+    let cases: &[(&str, &str, usize)] = &[
+        ("autogen_console.txt", "AutoGen console log", 3),
+        ("crewai_verbose.txt", "CrewAI verbose log", 6),
+        ("promptfoo_results.json", "promptfoo results", 2),
+        ("bedrock_invocation.json", "Bedrock Converse", 3),
+        ("bedrock_converse.jsonl", "Bedrock Converse log", 2),
+        ("ollama_stream.jsonl", "Ollama chat log", 2),
+        ("gemini_pair.json", "Gemini generateContent", 3),
+        ("gemini_stream.json", "Gemini generateContent", 1),
+    ];
+    for (file, format, messages) in cases {
+        let loaded = load_from_path(&fixture(file), None, LENIENT).expect(file);
+        assert_eq!((loaded.format, loaded.messages.len()), (Some(*format), *messages), "{file}");
+    }
+}
+
+#[test]
+fn promptfoo_results_give_a_session_per_test() {
+    let loaded = load_from_path(&fixture("promptfoo_results.json"), None, LENIENT).unwrap();
+    let titles: Vec<_> = loaded.sessions.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(titles, ["PASS · Test 1: cheese · openai:gpt-4o-mini", "FAIL · Test 2: bread · openai:gpt-4o-mini"]);
+    let failed = loaded.sessions[1].outcome.as_ref().unwrap();
+    assert_eq!(failed.summary, "openai:gpt-4o-mini · score 0.00 · 380 ms · Expected output \"pain\"");
+}
+
+#[test]
+fn bedrock_tool_results_fill_their_cards() {
+    let loaded = load_from_path(&fixture("bedrock_invocation.json"), None, LENIENT).unwrap();
+    assert_eq!(loaded.system.as_deref(), Some("Be brief."));
+    let speakers: Vec<_> = loaded.messages.iter().map(|m| (role_label(&m.role), m.name.as_deref())).collect();
+    assert_eq!(
+        speakers,
+        [("User".to_string(), None), ("Tool".to_string(), Some("get_weather")), ("Assistant".to_string(), None)]
+    );
+    assert!(loaded.messages[1].content.contains("celsius"));
+    assert_eq!(loaded.messages[2].content, "It is 18 °C.");
+
+    let log = load_from_path(&fixture("bedrock_converse.jsonl"), None, LENIENT).unwrap();
+    assert_eq!(log.sessions.len(), 2);
+}
+
+#[test]
+fn ollama_stream_chunks_form_one_reply() {
+    let loaded = load_from_path(&fixture("ollama_stream.jsonl"), None, LENIENT).unwrap();
+    let reply = &loaded.messages[1];
+    assert_eq!(reply.content, "Rayleigh scattering.");
+    assert_eq!(reply.detail.as_deref(), Some("3 tokens · 3.0 tok/s · total 1.50 s"));
+}
+
+#[test]
+fn gemini_function_calls_and_stream_chunks() {
+    let pair = load_from_path(&fixture("gemini_pair.json"), None, LENIENT).unwrap();
+    assert_eq!(pair.system.as_deref(), Some("Be brief."));
+    assert_eq!(pair.messages[1].name.as_deref(), Some("get_weather"));
+    assert!(pair.messages[1].content.contains("celsius"));
+    assert_eq!(pair.messages[2].content, "It is 18 °C.");
+
+    let stream = load_from_path(&fixture("gemini_stream.json"), None, LENIENT).unwrap();
+    assert_eq!(stream.messages[0].content, "Hello there!");
+}
+
+// Parquet is binary, so the dataset is written here rather than kept as a fixture
+// Trace ids are hex in OTLP, but the file can hold anything; a title made from one takes whole
+// characters.
+#[test]
+fn otlp_traces_without_a_root_span_are_titled_by_id() {
+    let span = |trace: &str| {
+        format!(
+            r#"{{"traceId":"{trace}","parentSpanId":"p1","attributes":[{{"key":"gen_ai.prompt","value":{{"stringValue":"hi"}}}},{{"key":"gen_ai.completion","value":{{"stringValue":"hello"}}}}]}}"#
+        )
+    };
+    let text = format!(
+        r#"{{"resourceSpans":[{{"scopeSpans":[{{"spans":[{},{}]}}]}}]}}"#,
+        span("日本語の追跡番号です"),
+        span("0af7651916cd43dd8448eb211c80319c")
+    );
+    let loaded = load_from_bytes(text.as_bytes(), LENIENT).unwrap();
+    let titles: Vec<_> = loaded.sessions.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(titles, ["Trace 日本語の追跡番号", "Trace 0af76519"]);
+}
+
+#[test]
+fn parquet_rows_become_sessions() {
+    use parquet::{
+        data_type::{ByteArray, ByteArrayType},
+        file::{properties::WriterProperties, writer::SerializedFileWriter},
+        schema::parser::parse_message_type,
+    };
+    use std::sync::Arc;
+
+    let schema = "message chat { REQUIRED BYTE_ARRAY id (UTF8); REQUIRED BYTE_ARRAY prompt (UTF8); REQUIRED BYTE_ARRAY response (UTF8); }";
+    let path = std::env::temp_dir().join(format!("llm_log_viewer_dataset_{}.parquet", std::process::id()));
+    let file = std::fs::File::create(&path).unwrap();
+    let props = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(file, Arc::new(parse_message_type(schema).unwrap()), props).unwrap();
+    let mut row_group = writer.next_row_group().unwrap();
+    for column in [["q1", "q2"], ["What is 2 + 2?", "Name a prime."], ["4", "7"]] {
+        let mut column_writer = row_group.next_column().unwrap().unwrap();
+        let values: Vec<ByteArray> = column.into_iter().map(ByteArray::from).collect();
+        column_writer.typed::<ByteArrayType>().write_batch(&values, None, None).unwrap();
+        column_writer.close().unwrap();
+    }
+    row_group.close().unwrap();
+    writer.close().unwrap();
+
+    let loaded = load_from_path(&path, None, LENIENT);
+    std::fs::remove_file(&path).unwrap();
+    let loaded = loaded.unwrap();
+    assert_eq!(loaded.format, Some("Parquet dataset"));
+    let titles: Vec<_> = loaded.sessions.iter().map(|s| (s.title.as_str(), s.messages.len())).collect();
+    assert_eq!(titles, [("q1", 2), ("q2", 2)]);
+    assert_eq!(loaded.messages[1].content, "4");
+}
+
+#[test]
+fn markdown_exports_read_back() {
+    let golden = |ext: &str| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(format!("tests/golden/code_and_tables.{ext}"));
+    let markdown = load_from_path(&golden("md"), None, LENIENT).unwrap();
+    let original = load_from_path(&golden("jsonl"), None, LENIENT).unwrap();
+    assert_eq!(markdown.format, Some("Markdown export"));
+    assert_eq!(markdown.system, original.system);
+    let turns = |l: &crate::Loaded| l.messages.iter().map(|m| (role_label(&m.role), m.content.clone())).collect::<Vec<_>>();
+    assert_eq!(turns(&markdown), turns(&original));
+}
+
+#[test]
+fn only_multi_agent_logs_color_speakers() {
+    let autogen = load_from_path(&fixture("autogen_console.txt"), None, LENIENT).unwrap();
+    let speakers: Vec<_> =
+        autogen.messages.iter().map(|m| (role_label(&m.role), m.name.as_deref(), m.agent, m.content.as_str())).collect();
+    assert_eq!(
+        speakers,
+        [
+            ("User".to_string(), Some("user_proxy"), Some(0), "Plot NVDA's stock price change this year."),
+            ("Assistant".to_string(), Some("Coder"), Some(1), "First, fetch the prices with yfinance."),
+            ("Assistant".to_string(), Some("Critic"), Some(2), "The plan looks fine."),
+        ]
+    );
+
+    let crewai = load_from_path(&fixture("crewai_verbose.txt"), None, LENIENT).unwrap();
+    let speakers: Vec<_> = crewai.messages.iter().map(|m| (role_label(&m.role), m.name.as_deref(), m.agent)).collect();
+    assert_eq!(
+        speakers,
+        [
+            ("User".to_string(), None, None),
+            ("Assistant".to_string(), Some("Researcher"), Some(0)),
+            ("Tool".to_string(), Some("Search"), None),
+            ("Assistant".to_string(), Some("Researcher"), Some(0)),
+            ("User".to_string(), None, None),
+            ("Assistant".to_string(), Some("Writer"), Some(1)),
+        ]
+    );
+    assert_eq!(crewai.messages[1].content, "*I should search the web.*");
+
+    // A character name is only a label: the bubbles keep their role colors
+    let tavern = "{\"name\":\"Bob\",\"is_user\":true,\"mes\":\"hi\"}\n{\"name\":\"Alice\",\"is_user\":false,\"mes\":\"hello\"}\n";
+    let tavern = load_from_bytes(tavern.as_bytes(), LENIENT).unwrap();
+    assert!(tavern.messages.iter().all(|m| m.name.is_some() && m.agent.is_none()));
+}
+
+#[test]
+fn templated_prompts_split_into_turns() {
+    let prompts = [
+        "<|begin_of_text|><|start_header_id|>system<|end_header_id|>\n\nBe brief.<|eot_id|><|start_header_id|>user<|end_header_id|>\n\nHi there<|eot_id|><|start_header_id|>assistant<|end_header_id|>\n\n",
+        // Copied from a log line, still escaped
+        r#""<|im_start|>system\nBe brief.<|im_end|>\n<|im_start|>user\nHi there<|im_end|>\n<|im_start|>assistant\n""#,
+        "<bos><start_of_turn>system\nBe brief.<end_of_turn>\n<start_of_turn>user\nHi there<end_of_turn>\n<start_of_turn>model\n",
+    ];
+    for prompt in prompts {
+        let loaded = load_from_bytes(prompt.as_bytes(), LENIENT).expect(prompt);
+        assert_eq!(loaded.system.as_deref(), Some("Be brief."), "{prompt}");
+        let turns: Vec<_> = loaded.messages.iter().map(|m| (role_label(&m.role), m.content.as_str())).collect();
+        assert_eq!(turns, [("User".to_string(), "Hi there"), ("Assistant".to_string(), "(empty)")], "{prompt}");
+    }
+}
+
+#[test]
+fn malformed_lines_are_listed_or_fail_in_strict_mode() {
+    let text = "{\"role\":\"user\",\"content\":\"hi\"}\n\n{\"role\": oops}\n";
+    let lenient = load_from_bytes(text.as_bytes(), LENIENT).unwrap();
+    assert_eq!(lenient.messages.len(), 1);
+    let failed = &lenient.failed_lines.lines;
+    assert_eq!((failed.len(), failed[0].line, failed[0].text.as_str()), (1, 3, "{\"role\": oops}"));
+    let err = load_from_bytes(text.as_bytes(), ParseOptions { strict: true }).err().expect("strict mode fails").to_string();
+    assert!(err.starts_with("JSONL line 3 failed to parse: expected value"), "{err}");
+}
+
+#[test]
+fn non_utf8_files_are_decoded() {
+    let (shift_jis, _, _) = encoding_rs::SHIFT_JIS.encode("[{\"role\":\"user\",\"content\":\"こんにちは\"}]");
+    let latin1 = b"[{\"role\":\"user\",\"content\":\"caf\xe9\"}]";
+    // Mostly UTF-8 with one stray byte
+    let dirty = ["[{\"role\":\"user\",\"content\":\"日本語 ".as_bytes(), b"\xff\"}]"].concat();
+    let cases: &[(&[u8], &str, &str)] = &[
+        (&shift_jis, "こんにちは", "Shift_JIS"),
+        (latin1, "café", "Latin-1"),
+        (&dirty, "日本語 \u{fffd}", "Invalid UTF-8 in 1 place"),
+    ];
+    for (bytes, content, warning) in cases {
+        let loaded = load_from_bytes(bytes, LENIENT).expect(warning);
+        assert_eq!(loaded.messages[0].content, *content);
+        assert!(loaded.errors.iter().any(|e| e.contains(warning)), "{:?}", loaded.errors);
+    }
+}
+
+#[test]
+fn bom_and_utf16_files_load() {
+    let text = "{\"role\":\"user\",\"content\":\"héllo\"}\n";
+    let utf16le: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let utf16be: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+    let files = [
+        [b"\xef\xbb\xbf", text.as_bytes()].concat(),
+        [b"\xff\xfe", &utf16le[..]].concat(),
+        [b"\xfe\xff", &utf16be[..]].concat(),
+        utf16le,
+    ];
+    for bytes in &files {
+        let loaded = load_from_bytes(bytes, LENIENT).unwrap();
+        assert_eq!(loaded.messages.len(), 1, "{bytes:?}");
+        assert_eq!(loaded.messages[0].content, "héllo");
+        assert!(loaded.errors.is_empty(), "{:?}", loaded.errors);
+    }
+}
+
+#[test]
+fn reasoning_is_kept_apart_from_the_answer() {
+    let openai = br#"[{"role":"user","content":"2+2?"},{"role":"assistant","content":"4","reasoning_content":"Add them."}]"#;
+    let anthropic = br#"[{"role":"user","content":"2+2?"},{"role":"assistant","content":[
+        {"type":"thinking","thinking":"Add them.","signature":"abc"},{"type":"text","text":"4"}]}]"#;
+    let inline = br#"[{"role":"user","content":"2+2?"},{"role":"assistant","content":"<think>\nAdd them.\n</think>\n\n4"}]"#;
+    for bytes in [&openai[..], &anthropic[..], &inline[..]] {
+        let loaded = load_from_bytes(bytes, LENIENT).expect("messages parse");
+        assert_eq!(parts(&loaded.messages[1].content), vec![Part::Reasoning("Add them."), Part::Text("4")]);
+    }
+    // Cut off while thinking, and tags shown in a code block
+    assert_eq!(parts("<thinking>\nStep one"), vec![Part::Reasoning("Step one")]);
+    let code = "```\n<think>x</think>\n```";
+    assert_eq!(parts(code), vec![Part::Text(code)]);
+}
@@ -0,0 +1,151 @@
+// "Paste raw prompt…": a flattened prompt string from a server log is split back into turns with
+// a chosen template grammar. The turns found are previewed before the conversation is loaded.
+
+use crate::{
+    parse::{detect_prompt_format, normalize_sessions, parse_prompt, RawMsg, RawSession, PROMPT_FORMATS},
+    AppState,
+};
+use eframe::egui::{self, RichText, ScrollArea};
+
+const PASTE_WINDOW: &str = "Paste raw prompt";
+// Characters of each turn shown in the preview
+const SNIPPET_CHARS: usize = 80;
+
+#[derive(Default)]
+pub(crate) struct PastePromptUi {
+    pub show_window: bool,
+    text: String,
+    // None picks the grammar that matches best
+    format: Option<&'static str>,
+    // Recomputed when the text or grammar changes
+    detected: Option<&'static str>,
+    preview: Vec<RawMsg>,
+    stale: bool,
+}
+
+impl PastePromptUi {
+    fn grammar(&self) -> Option<&'static str> {
+        self.format.or(self.detected)
+    }
+
+    fn refresh(&mut self) {
+        self.detected = detect_prompt_format(&self.text);
+        self.preview = self.grammar().map_or(Vec::new(), |f| parse_prompt(&self.text, f));
+        self.stale = false;
+    }
+}
+
+fn snippet(content: &str) -> String {
+    let line = content.trim().lines().next().unwrap_or_default();
+    let mut short: String = line.chars().take(SNIPPET_CHARS).collect();
+    if short.len() < content.trim().len() {
+        short.push('…');
+    }
+    short
+}
+
+impl AppState {
+    pub(crate) fn paste_prompt_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.paste_prompt.show_window;
+        let mut load = false;
+        let window = self.tool_window(PASTE_WINDOW).default_width(600.0).open(&mut open);
+        let fmt = self.fmt;
+        let ui_state = &mut self.paste_prompt;
+        let resp = window.show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Template");
+                let auto = match ui_state.detected {
+                    Some(name) => format!("Auto ({name})"),
+                    None => "Auto".to_string(),
+                };
+                let selected = ui_state.format.map_or(auto.clone(), str::to_string);
+                egui::ComboBox::from_id_source("paste_prompt_format")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        ui_state.stale |= ui.selectable_value(&mut ui_state.format, None, auto).changed();
+                        for &name in PROMPT_FORMATS {
+                            ui_state.stale |= ui.selectable_value(&mut ui_state.format, Some(name), name).changed();
+                        }
+                    });
+            });
+            ScrollArea::vertical()
+                .id_source("paste_prompt_text")
+                .max_height(220.0)
+                .show(ui, |ui| {
+                    let edit = egui::TextEdit::multiline(&mut ui_state.text)
+                        .code_editor()
+                        .desired_rows(10)
+                        .desired_width(f32::INFINITY)
+                        .hint_text("Paste a rendered prompt, e.g. <|im_start|>system …<|im_end|>");
+                    ui_state.stale |= ui.add(edit).changed();
+                });
+            if ui_state.stale {
+                ui_state.refresh();
+            }
+            ui.separator();
+
+            if ui_state.preview.is_empty() {
+                let hint = if ui_state.text.trim().is_empty() {
+                    "No prompt pasted yet"
+                } else {
+                    "No turns found with this template"
+                };
+                ui.label(RichText::new(hint).italics().weak());
+            } else {
+                ui.label(RichText::new(format!("{} turns", fmt.count(ui_state.preview.len()))).weak());
+                ScrollArea::vertical()
+                    .id_source("paste_prompt_preview")
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("paste_prompt_turns")
+                            .num_columns(4)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for (i, msg) in ui_state.preview.iter().enumerate() {
+                                    ui.label(RichText::new(format!("{}", i + 1)).weak());
+                                    ui.label(RichText::new(&msg.role).strong());
+                                    ui.label(
+                                        RichText::new(format!("{} chars", fmt.count(msg.content.chars().count())))
+                                            .weak(),
+                                    );
+                                    let preview: String = msg.content.chars().take(2000).collect();
+                                    ui.label(RichText::new(snippet(&msg.content)).small())
+                                        .on_hover_text(preview);
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!ui_state.preview.is_empty(), egui::Button::new("Load"))
+                    .clicked()
+                {
+                    load = true;
+                }
+                if ui.button("Clear").clicked() {
+                    ui_state.text.clear();
+                    ui_state.refresh();
+                }
+            });
+        });
+        if let Some(r) = resp {
+            self.remember_window(PASTE_WINDOW, r.response.rect);
+        }
+
+        if load {
+            let messages = std::mem::take(&mut self.paste_prompt.preview);
+            let mut loaded = normalize_sessions(vec![RawSession {
+                messages,
+                ..Default::default()
+            }]);
+            loaded.format = self.paste_prompt.grammar();
+            loaded.file_name = Some("(pasted)".to_string());
+            self.set_loaded(loaded);
+            self.paste_prompt = PastePromptUi::default();
+            open = false;
+        }
+        self.paste_prompt.show_window = open;
+    }
+}
@@ -0,0 +1,428 @@
+// "Apply to folder…" for unified diffs in messages: parses the ```diff blocks of a message,
+// previews what each file would become against a chosen working directory (dry run), and
+// only writes once every hunk has been located.
+
+use crate::{backup::Backup, fenced_segments, write_file_creating_dirs, AppState, Msg, TextSegment};
+use anyhow::{anyhow, Context, Result};
+use eframe::egui::{self, Color32, RichText, ScrollArea};
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+const PATCH_WINDOW: &str = "Apply patch";
+
+#[derive(Default)]
+pub(crate) struct PatchUi {
+    pub show_window: bool,
+    // 1-based message number the patch came from
+    message: usize,
+    patch: String,
+    folder: Option<PathBuf>,
+    // Dry-run result for `folder`; None until a folder is chosen
+    plan: Option<Vec<FilePlan>>,
+    status: Option<Result<String, String>>,
+}
+
+pub(crate) struct FilePatch {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+pub(crate) struct Hunk {
+    // 1-based line from the `@@ -l,s +l,s @@` header; agents often get it wrong, so it is only a hint
+    old_start: Option<usize>,
+    // (' ' | '-' | '+', text)
+    lines: Vec<(char, String)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum FileAction {
+    Write(String),
+    Delete,
+}
+
+pub(crate) struct FilePlan {
+    pub path: String,
+    pub summary: String,
+    pub result: Result<FileAction, String>,
+}
+
+// Concatenated ```diff / ```patch blocks of a message, plus untagged blocks that look like a diff.
+pub(crate) fn message_patch(msg: &Msg) -> Option<String> {
+    let blocks: Vec<String> = fenced_segments(&msg.content)
+        .into_iter()
+        .filter_map(|s| match s {
+            TextSegment::Code { lang, code } if is_diff_block(&lang, &code) => Some(code),
+            _ => None,
+        })
+        .collect();
+    (!blocks.is_empty()).then(|| blocks.join("\n"))
+}
+
+fn is_diff_block(lang: &str, code: &str) -> bool {
+    let lang = lang.to_lowercase();
+    let looks_like_diff = code.lines().any(|l| l.starts_with("--- ")) && code.lines().any(|l| l.starts_with("@@"));
+    (matches!(lang.as_str(), "diff" | "patch" | "udiff") || lang.is_empty()) && looks_like_diff
+}
+
+// "a/src/main.rs" -> "src/main.rs"; None for /dev/null.
+fn diff_path(header: &str) -> Option<String> {
+    let path = header.split('\t').next().unwrap_or_default().trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+fn hunk_old_start(header: &str) -> Option<usize> {
+    let old = header.strip_prefix("@@ -")?.split([' ', ',']).next()?;
+    old.parse().ok()
+}
+
+pub(crate) fn parse_unified_diff(text: &str) -> Vec<FilePatch> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        // A "--- " line is a file header only when "+++ " follows; inside a hunk it may be a removed line
+        if let (Some(old), Some(new)) = (
+            line.strip_prefix("--- "),
+            lines.get(i + 1).and_then(|next| next.strip_prefix("+++ ")),
+        ) {
+            files.push(FilePatch {
+                old_path: diff_path(old),
+                new_path: diff_path(new),
+                hunks: Vec::new(),
+            });
+            i += 2;
+            continue;
+        }
+        if line.starts_with("@@") {
+            if let Some(file) = files.last_mut() {
+                file.hunks.push(Hunk {
+                    old_start: hunk_old_start(line),
+                    lines: Vec::new(),
+                });
+            }
+        } else if let Some(hunk) = files.last_mut().and_then(|f| f.hunks.last_mut()) {
+            match line.chars().next() {
+                Some(c @ (' ' | '-' | '+')) => hunk.lines.push((c, line[1..].to_string())),
+                // Some tools drop the leading space of blank context lines
+                None => hunk.lines.push((' ', String::new())),
+                // "\ No newline at end of file", "diff --git", "index ..." and prose between blocks
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    // Blank lines after the last hunk are prose, not context
+    for hunk in files.iter_mut().flat_map(|f| f.hunks.iter_mut()) {
+        while hunk.lines.last().is_some_and(|(c, l)| *c == ' ' && l.is_empty()) {
+            hunk.lines.pop();
+        }
+    }
+    files
+}
+
+// Reject paths that would escape the chosen folder.
+fn safe_relative(path: &str) -> Result<PathBuf, String> {
+    let p = Path::new(path);
+    if p.components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        Ok(p.to_path_buf())
+    } else {
+        Err(format!("refusing path outside the folder: {path}"))
+    }
+}
+
+// Place each hunk where its context and removed lines match, preferring the position closest
+// to the header's line number. Trailing whitespace is ignored when matching.
+pub(crate) fn apply_hunks(original: &str, hunks: &[Hunk]) -> Result<String, String> {
+    let crlf = original.contains("\r\n");
+    let trailing_newline = original.ends_with('\n') || original.is_empty();
+    let mut lines: Vec<String> = original.lines().map(str::to_string).collect();
+    let mut offset: isize = 0;
+    let mut search_from = 0;
+    for (n, hunk) in hunks.iter().enumerate() {
+        let old: Vec<&str> = hunk
+            .lines
+            .iter()
+            .filter(|(c, _)| *c != '+')
+            .map(|(_, l)| l.trim_end())
+            .collect();
+        let new: Vec<String> = hunk
+            .lines
+            .iter()
+            .filter(|(c, _)| *c != '-')
+            .map(|(_, l)| l.clone())
+            .collect();
+        let expected = hunk.old_start.map(|s| (s as isize - 1 + offset).max(0) as usize);
+        let pos = if old.is_empty() {
+            Some(expected.unwrap_or(lines.len()).min(lines.len()))
+        } else {
+            // A hunk longer than the file has no window to match
+            lines
+                .windows(old.len())
+                .enumerate()
+                .skip(search_from)
+                .filter(|(_, window)| window.iter().map(|l| l.trim_end()).eq(old.iter().copied()))
+                .map(|(p, _)| p)
+                .min_by_key(|&p| expected.map_or(p, |e| p.abs_diff(e)))
+        };
+        let Some(pos) = pos else {
+            return Err(format!("hunk {} does not match the file", n + 1));
+        };
+        lines.splice(pos..pos + old.len(), new.iter().cloned());
+        offset += new.len() as isize - old.len() as isize;
+        search_from = pos + new.len();
+    }
+    let mut out = lines.join(if crlf { "\r\n" } else { "\n" });
+    if trailing_newline && !out.is_empty() {
+        out.push_str(if crlf { "\r\n" } else { "\n" });
+    }
+    Ok(out)
+}
+
+pub(crate) fn plan_file(folder: &Path, patch: &FilePatch) -> FilePlan {
+    let display = patch
+        .new_path
+        .clone()
+        .or_else(|| patch.old_path.clone())
+        .unwrap_or_else(|| "(unnamed)".to_string());
+    let added = patch
+        .hunks
+        .iter()
+        .flat_map(|h| &h.lines)
+        .filter(|(c, _)| *c == '+')
+        .count();
+    let removed = patch
+        .hunks
+        .iter()
+        .flat_map(|h| &h.lines)
+        .filter(|(c, _)| *c == '-')
+        .count();
+    let result = (|| match (&patch.old_path, &patch.new_path) {
+        (None, Some(_)) => {
+            let path = inside_folder(folder, &display)?;
+            if path.exists() {
+                return Err("new file already exists".to_string());
+            }
+            apply_hunks("", &patch.hunks).map(FileAction::Write)
+        }
+        (Some(old), None) => {
+            let path = inside_folder(folder, old)?;
+            path.is_file()
+                .then_some(FileAction::Delete)
+                .ok_or_else(|| "file to delete not found".to_string())
+        }
+        (Some(old), Some(_)) => {
+            inside_folder(folder, &display)?;
+            let path = inside_folder(folder, old)?;
+            let original = fs::read_to_string(&path).map_err(|e| format!("cannot read file: {e}"))?;
+            apply_hunks(&original, &patch.hunks).map(FileAction::Write)
+        }
+        (None, None) => Err("no file name in the diff header".to_string()),
+    })();
+    let kind = match (&patch.old_path, &patch.new_path) {
+        (None, _) => "create",
+        (_, None) => "delete",
+        (Some(old), Some(new)) if old != new => "rename",
+        _ => "modify",
+    };
+    let summary = format!("{kind}, {} hunk(s), +{added} −{removed}", patch.hunks.len());
+    FilePlan {
+        path: display,
+        summary,
+        result,
+    }
+}
+
+// `path` under `folder`, unless a symlinked directory (or a link at `path` itself) leads outside
+// it. Checked against the deepest part of the path that exists, since new files may create
+// directories; a dangling link fails to resolve and is refused as well.
+fn inside_folder(folder: &Path, path: &str) -> Result<PathBuf, String> {
+    let target = folder.join(safe_relative(path)?);
+    let root = fs::canonicalize(folder).map_err(|e| format!("cannot open the folder: {e}"))?;
+    let existing = target.ancestors().find(|p| fs::symlink_metadata(p).is_ok()).unwrap_or(folder);
+    match fs::canonicalize(existing) {
+        Ok(real) if real.starts_with(&root) => Ok(target),
+        _ => Err(format!("refusing path that leads outside the folder through a link: {path}")),
+    }
+}
+
+// Files changed or removed are copied to `backup` first. Every path is checked before anything
+// is written, so a rejected file leaves the folder untouched.
+pub(crate) fn apply_plan(folder: &Path, patches: &[FilePatch], plans: &[FilePlan], backup: &mut Backup) -> Result<usize> {
+    let mut steps = Vec::new();
+    for (patch, plan) in patches.iter().zip(plans) {
+        let action = plan.result.as_ref().map_err(|e| anyhow!("{}: {e}", plan.path))?;
+        let path = inside_folder(folder, &plan.path).map_err(|e| anyhow!(e))?;
+        // A rename writes the new path and removes the old one
+        let renamed_from = match (action, patch.old_path.as_ref().filter(|old| **old != plan.path)) {
+            (FileAction::Write(_), Some(old)) => Some(inside_folder(folder, old).map_err(|e| anyhow!(e))?),
+            _ => None,
+        };
+        steps.push((action, path, renamed_from));
+    }
+    for (action, path, renamed_from) in steps {
+        backup.keep(&path)?;
+        match action {
+            FileAction::Write(contents) => {
+                write_file_creating_dirs(&path, contents.as_bytes())?;
+                if let Some(old) = renamed_from {
+                    backup.keep(&old)?;
+                    fs::remove_file(&old).with_context(|| format!("Failed to remove {}", old.display()))?;
+                }
+            }
+            FileAction::Delete => fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?,
+        }
+    }
+    Ok(plans.len())
+}
+
+impl AppState {
+    pub(crate) fn open_patch_window(&mut self, index: usize) {
+        let Some(patch) = self.messages.get(index).and_then(message_patch) else {
+            return;
+        };
+        let ui_state = &mut self.patch;
+        ui_state.message = index + 1;
+        ui_state.patch = patch;
+        ui_state.status = None;
+        ui_state.show_window = true;
+        ui_state.plan = ui_state.folder.as_deref().map(|f| {
+            parse_unified_diff(&ui_state.patch)
+                .iter()
+                .map(|p| plan_file(f, p))
+                .collect()
+        });
+    }
+
+    pub(crate) fn patch_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.patch.show_window;
+        let window = self
+            .tool_window(PATCH_WINDOW)
+            .default_width(560.0)
+            .default_height(420.0)
+            .open(&mut open);
+        let backups = self.settings.backups;
+        let read_only = self.is_read_only();
+        // Files of the last dry run that are in a protected location, renamed ones included
+        let refused = self.patch.folder.as_deref().and_then(|folder| {
+            let renamed = parse_unified_diff(&self.patch.patch).into_iter().filter_map(|p| p.old_path);
+            let planned = self.patch.plan.iter().flatten().map(|p| p.path.clone());
+            renamed
+                .chain(planned)
+                .find_map(|path| self.check_writable(&folder.join(path)).err())
+                .or_else(|| self.check_writable(folder).err())
+        });
+        let ui_state = &mut self.patch;
+        let resp = window.show(ctx, |ui| {
+            ui.label(format!("Diff from message {}", ui_state.message));
+            ui.horizontal(|ui| {
+                if ui.button("Choose folder…").clicked() {
+                    if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                        ui_state.folder = Some(dir);
+                        ui_state.plan = None;
+                        ui_state.status = None;
+                    }
+                }
+                let folder = ui_state
+                    .folder
+                    .as_ref()
+                    .map_or("(no folder)".to_string(), |f| f.display().to_string());
+                ui.label(RichText::new(folder).monospace().small());
+            });
+            let Some(folder) = ui_state.folder.clone() else {
+                ui.label(
+                    RichText::new("Choose the working directory the diff applies to")
+                        .italics()
+                        .weak(),
+                );
+                return;
+            };
+            let patches = parse_unified_diff(&ui_state.patch);
+            if ui_state.plan.is_none() || ui.button("Re-check").clicked() {
+                ui_state.plan = Some(patches.iter().map(|p| plan_file(&folder, p)).collect());
+                ui_state.status = None;
+            }
+            let plans = ui_state.plan.as_deref().unwrap_or_default();
+            if plans.is_empty() {
+                ui.label("No file headers (--- / +++) found in the diff");
+                return;
+            }
+            ui.separator();
+            ui.label(RichText::new("Dry run").strong());
+            egui::Grid::new("patch_plan")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    for plan in plans {
+                        ui.label(RichText::new(&plan.path).monospace());
+                        ui.label(RichText::new(&plan.summary).weak());
+                        match &plan.result {
+                            Ok(_) => ui.colored_label(Color32::from_rgb(46, 125, 50), "ok"),
+                            Err(e) => ui.colored_label(Color32::from_rgb(183, 28, 28), e),
+                        };
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+            ScrollArea::vertical()
+                .max_height(220.0)
+                .auto_shrink([false, true])
+                .show(ui, |ui| {
+                    for line in ui_state.patch.lines() {
+                        let color = match line.chars().next() {
+                            Some('+') if !line.starts_with("+++") => Color32::from_rgb(67, 160, 71),
+                            Some('-') if !line.starts_with("---") => Color32::from_rgb(229, 57, 53),
+                            Some('@') => Color32::from_rgb(30, 136, 229),
+                            _ => ui.visuals().text_color(),
+                        };
+                        ui.label(RichText::new(line).monospace().color(color));
+                    }
+                });
+            ui.separator();
+            let applied = matches!(ui_state.status, Some(Ok(_)));
+            let ready = !applied && !read_only && refused.is_none() && plans.iter().all(|p| p.result.is_ok());
+            let apply = ui
+                .add_enabled(ready, egui::Button::new(format!("Apply to {} file(s)", plans.len())))
+                .on_disabled_hover_text(match &refused {
+                    _ if read_only => "The conversation is read-only".to_string(),
+                    Some(e) => e.to_string(),
+                    None => "Every file must pass the dry run".to_string(),
+                });
+            if apply.clicked() {
+                let mut backup = Backup::new(backups);
+                ui_state.status = Some(match apply_plan(&folder, &patches, plans, &mut backup) {
+                    Ok(n) => Ok(match backup.kept_in() {
+                        Some(dir) => format!("Applied to {n} file(s); the originals are in {}", dir.display()),
+                        None => format!("Applied to {n} file(s)"),
+                    }),
+                    Err(e) => Err(e.to_string()),
+                });
+            }
+            match &ui_state.status {
+                Some(Ok(msg)) => {
+                    ui.label(RichText::new(msg).small());
+                }
+                Some(Err(msg)) => {
+                    ui.colored_label(Color32::from_rgb(183, 28, 28), msg);
+                }
+                None => {}
+            }
+        });
+        if let Some(r) = resp {
+            self.remember_window(PATCH_WINDOW, r.response.rect);
+        }
+        self.patch.show_window = open;
+    }
+}
@@ -0,0 +1,96 @@
+// `patch`: reading unified diffs from agents, placing hunks whose line numbers are off, and
+// keeping writes inside the chosen folder.
+
+use crate::{
+    backup::Backup,
+    patch::{apply_hunks, apply_plan, parse_unified_diff, plan_file, FileAction},
+};
+use std::{fs, path::PathBuf};
+
+fn apply(original: &str, diff: &str) -> Result<String, String> {
+    apply_hunks(original, &parse_unified_diff(diff)[0].hunks)
+}
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("llm_log_viewer_patch_{}_{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn diffs_are_split_into_files_and_hunks() {
+    // Prose around the diff, git's extra headers, and a removed line that starts with "--"
+    let diff = "Here is the fix:\n\ndiff --git a/src/a.rs b/src/a.rs\nindex 1..2 100644\n--- a/src/a.rs\n+++ b/src/a.rs\n@@ -1,3 +1,3 @@\n fn a() {\n--- old comment\n+// new comment\n }\n\n--- /dev/null\n+++ b/notes.md\n@@ -0,0 +1 @@\n+hello\n\nThat's all.\n";
+    let files = parse_unified_diff(diff);
+    let headers: Vec<_> = files.iter().map(|f| (f.old_path.as_deref(), f.new_path.as_deref(), f.hunks.len())).collect();
+    assert_eq!(headers, [(Some("src/a.rs"), Some("src/a.rs"), 1), (None, Some("notes.md"), 1)]);
+    assert_eq!(apply_hunks("fn a() {\n-- old comment\n}\n", &files[0].hunks).unwrap(), "fn a() {\n// new comment\n}\n");
+    assert_eq!(apply_hunks("", &files[1].hunks).unwrap(), "hello\n");
+}
+
+#[test]
+fn hunks_are_placed_by_their_context() {
+    let diff = "--- a/f\n+++ b/f\n@@ -1,3 +1,3 @@\n b\n-c\n+C\n d\n";
+    // The header says line 1, but the context starts on line 2
+    assert_eq!(apply("a\nb\nc\nd\n", diff).unwrap(), "a\nb\nC\nd\n");
+    // CRLF line endings and a missing final newline are kept
+    assert_eq!(apply("a\r\nb\r\nc\r\nd", diff).unwrap(), "a\r\nb\r\nC\r\nd");
+    // Trailing whitespace in the file does not prevent a match
+    assert_eq!(apply("b  \nc\nd\n", diff).unwrap(), "b\nC\nd\n");
+    assert_eq!(apply("a\nb\n", diff), Err("hunk 1 does not match the file".to_string()));
+
+    // A blank context line that lost its leading space
+    let blank = "--- a/f\n+++ b/f\n@@ -1,3 +1,3 @@\n a\n\n-b\n+B\n";
+    assert_eq!(apply("a\n\nb\n", blank).unwrap(), "a\n\nB\n");
+
+    // Of several matches, the one nearest the header's line wins
+    let near = "--- a/f\n+++ b/f\n@@ -4,1 +4,1 @@\n-x\n+y\n";
+    assert_eq!(apply("x\nx\nx\nx\n", near).unwrap(), "x\nx\nx\ny\n");
+}
+
+#[test]
+fn renames_and_deletes_apply_to_the_folder() {
+    let root = scratch("apply");
+    fs::write(root.join("old.txt"), "one\ntwo\n").unwrap();
+    fs::write(root.join("gone.txt"), "bye\n").unwrap();
+    let diff = "--- a/old.txt\n+++ b/new.txt\n@@ -1,2 +1,2 @@\n one\n-two\n+2\n--- a/gone.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-bye\n";
+    let patches = parse_unified_diff(diff);
+    let plans: Vec<_> = patches.iter().map(|p| plan_file(&root, p)).collect();
+    let summaries: Vec<_> = plans.iter().map(|p| (p.path.as_str(), p.summary.as_str())).collect();
+    assert_eq!(summaries, [("new.txt", "rename, 1 hunk(s), +1 −1"), ("gone.txt", "delete, 1 hunk(s), +0 −1")]);
+    assert_eq!(plans[1].result, Ok(FileAction::Delete));
+
+    assert_eq!(apply_plan(&root, &patches, &plans, &mut Backup::new(false)).unwrap(), 2);
+    assert_eq!(fs::read_to_string(root.join("new.txt")).unwrap(), "one\n2\n");
+    assert!(!root.join("old.txt").exists());
+    assert!(!root.join("gone.txt").exists());
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn paths_outside_the_folder_are_refused() {
+    let root = scratch("escape");
+    let diff = "--- /dev/null\n+++ b/../escaped.txt\n@@ -0,0 +1 @@\n+x\n--- /dev/null\n+++ /tmp/escaped.txt\n@@ -0,0 +1 @@\n+x\n";
+    for patch in parse_unified_diff(diff) {
+        let error = plan_file(&root, &patch).result.unwrap_err();
+        assert!(error.starts_with("refusing path outside the folder"), "{error}");
+    }
+
+    // A directory that is a link to somewhere else, created after the dry run
+    #[cfg(unix)]
+    {
+        let outside = scratch("outside");
+        let diff = "--- /dev/null\n+++ b/link/new.txt\n@@ -0,0 +1 @@\n+x\n";
+        let patches = parse_unified_diff(diff);
+        let plans: Vec<_> = patches.iter().map(|p| plan_file(&root, p)).collect();
+        assert!(plans[0].result.is_ok());
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+        let error = plan_file(&root, &patches[0]).result.unwrap_err();
+        assert!(error.contains("through a link"), "{error}");
+        assert!(apply_plan(&root, &patches, &plans, &mut Backup::new(false)).is_err());
+        assert!(!outside.join("new.txt").exists());
+        fs::remove_dir_all(&outside).unwrap();
+    }
+    fs::remove_dir_all(&root).unwrap();
+}
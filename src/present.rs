@@ -0,0 +1,166 @@
+// Presentation mode: the conversation is revealed one message at a time for demos and
+// teaching, optionally typing out assistant turns. Space/→ advances, ← goes back, Esc leaves.
+
+use crate::{render_message_bubble, render_system_card, save_settings, AppState, Role, FOCUS_MEASURE};
+use eframe::egui::{self, Align, Frame, Key, Modifiers, RichText, ScrollArea};
+
+// Typewriter speed in characters per second
+const TYPE_RATE: f64 = 90.0;
+
+#[derive(Default)]
+pub(crate) struct Presentation {
+    pub active: bool,
+    // Number of messages on screen
+    shown: usize,
+    // Time the newest message started typing; None once it is fully shown
+    typing_since: Option<f64>,
+    // Scroll the newest message into view on the next frame
+    follow: bool,
+}
+
+impl AppState {
+    pub(crate) fn start_presentation(&mut self) {
+        self.present = Presentation {
+            active: true,
+            ..Default::default()
+        };
+    }
+
+    fn present_step(&mut self, forward: bool, now: f64) {
+        let p = &mut self.present;
+        if forward {
+            if p.typing_since.take().is_some() {
+                // First press finishes the message being typed
+                return;
+            }
+            if p.shown < self.messages.len() {
+                p.shown += 1;
+                let assistant = matches!(self.messages[p.shown - 1].role, Role::Assistant);
+                if self.settings.typewriter && assistant {
+                    p.typing_since = Some(now);
+                }
+            }
+        } else {
+            p.typing_since = None;
+            p.shown = p.shown.saturating_sub(1);
+        }
+        p.follow = true;
+    }
+
+    pub(crate) fn presentation_ui(&mut self, ctx: &egui::Context) {
+        let (next, back, home, end, exit, now) = ctx.input_mut(|i| {
+            let mut pressed = |keys: &[Key]| keys.iter().any(|k| i.consume_key(Modifiers::NONE, *k));
+            (
+                pressed(&[Key::Space, Key::ArrowRight, Key::ArrowDown, Key::PageDown, Key::Enter]),
+                pressed(&[Key::ArrowLeft, Key::ArrowUp, Key::PageUp, Key::Backspace]),
+                pressed(&[Key::Home]),
+                pressed(&[Key::End]),
+                pressed(&[Key::Escape, Key::F5]),
+                i.time,
+            )
+        });
+        if exit {
+            self.present.active = false;
+            return;
+        }
+        if next {
+            self.present_step(true, now);
+        }
+        if back {
+            self.present_step(false, now);
+        }
+        if home || end {
+            self.present.shown = if end { self.messages.len() } else { 0 };
+            self.present.typing_since = None;
+            self.present.follow = true;
+        }
+
+        egui::TopBottomPanel::bottom("presentation_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let progress = format!(
+                    "{} / {}",
+                    self.fmt.count(self.present.shown),
+                    self.fmt.count(self.messages.len())
+                );
+                ui.label(RichText::new(progress).strong());
+                ui.label(RichText::new("Space/→ next · ← back · Home/End · Esc to leave").weak());
+                ui.with_layout(egui::Layout::right_to_left(Align::Center), |ui| {
+                    if ui.button("Exit").clicked() {
+                        self.present.active = false;
+                    }
+                    if ui.checkbox(&mut self.settings.typewriter, "Typewriter").changed() {
+                        if let Err(e) = save_settings(&self.settings) {
+                            self.errors.push(format!("Failed to save settings: {e}"));
+                        }
+                    }
+                });
+            });
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let side = ((ui.available_width() - FOCUS_MEASURE * self.text_scale) / 2.0).max(0.0);
+            Frame::none()
+                .inner_margin(egui::Margin::symmetric(side, 0.0))
+                .show(ui, |ui| {
+                    ScrollArea::vertical()
+                        .id_source("presentation")
+                        .auto_shrink([false, false])
+                        .show(ui, |ui| {
+                            ui.add_space(6.0);
+                            if let Some(sys) = &self.system {
+                                let scale = self.text_scale * self.settings.role_scales.system;
+                                render_system_card(ui, "System", sys, &mut self.md_cache, scale);
+                                ui.add_space(6.0);
+                            }
+                            if self.present.shown == 0 {
+                                ui.label(RichText::new("Press Space to show the first message").italics().weak());
+                            }
+                            let content_width = ui.available_width();
+                            let shown = self.present.shown.min(self.messages.len());
+                            for (idx, msg) in self.messages[..shown].iter().enumerate() {
+                                // The message being typed is drawn cut off at the characters revealed so far
+                                let mut partial = None;
+                                if let Some(since) = self.present.typing_since.filter(|_| idx + 1 == shown) {
+                                    let chars = ((now - since) * TYPE_RATE) as usize;
+                                    match msg.content.char_indices().nth(chars) {
+                                        Some((end, _)) => {
+                                            let mut cut = msg.clone();
+                                            cut.content.truncate(end);
+                                            partial = Some(cut);
+                                            ctx.request_repaint();
+                                        }
+                                        None => self.present.typing_since = None,
+                                    }
+                                    self.present.follow = true;
+                                }
+                                let msg = partial.as_ref().unwrap_or(msg);
+                                let (dark, scale) = (
+                                    self.theme_dark,
+                                    self.text_scale * self.settings.role_scales.for_msg(msg),
+                                );
+                                let mut folded = self.folds.is_folded(idx);
+                                render_message_bubble(
+                                    ui,
+                                    msg,
+                                    idx,
+                                    content_width,
+                                    dark,
+                                    &mut self.md_cache,
+                                    scale,
+                                    None,
+                                    &self.fmt,
+                                    self.settings.display,
+                                    &mut folded,
+                                );
+                                self.folds.set(idx, folded);
+                                ui.add_space(6.0);
+                            }
+                            ui.add_space(18.0);
+                            if std::mem::take(&mut self.present.follow) {
+                                ui.scroll_to_cursor(Some(Align::BOTTOM));
+                            }
+                        });
+                });
+        });
+    }
+}
@@ -0,0 +1,125 @@
+// HTML and Markdown exports end with a footer saying where they came from: the log file's name and
+// SHA-256, when the export was made and the app version, so a transcript passed around can be
+// traced back to the exact log it was exported from. Conversations that were not read from a file
+// of their own (folders, archives, drops, pastes) get the footer without a checksum. On by default;
+// the setting is in the Export Markdown menu and the Export HTML button's context menu, next to the
+// open file's checksum.
+
+use crate::{html_escape, save_settings, AppState};
+use chrono::Utc;
+use eframe::egui::{self, RichText};
+use sha2::{Digest, Sha256};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+// Starts the last line of a Markdown export's footer; the Markdown importer leaves it out
+pub(crate) const MARKDOWN_MARKER: &str = "*Exported from ";
+
+pub(crate) struct Provenance {
+    pub file: String,
+    pub sha256: Option<String>,
+    // UTC, e.g. "2024-05-01 10:32 UTC"
+    pub exported: String,
+    pub version: &'static str,
+}
+
+impl Provenance {
+    fn parts(&self, code: impl Fn(&str) -> String) -> String {
+        let mut parts = vec![code(&self.file)];
+        if let Some(sha256) = &self.sha256 {
+            parts.push(format!("SHA-256 {}", code(sha256)));
+        }
+        parts.push(self.exported.clone());
+        parts.push(format!("LLM Log Viewer {}", self.version));
+        parts.join(" · ")
+    }
+
+    pub(crate) fn markdown(&self) -> String {
+        let code = |s: &str| format!("`{}`", s.replace('`', "'"));
+        format!("---\n\n{MARKDOWN_MARKER}{}*\n", self.parts(code))
+    }
+
+    pub(crate) fn html(&self) -> String {
+        let code = |s: &str| format!("<code>{}</code>", html_escape(s));
+        format!(
+            "<footer class=\"provenance\">Exported from {}</footer>\n",
+            self.parts(code)
+        )
+    }
+}
+
+pub(crate) fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|b| format!("{b:02x}")).collect())
+}
+
+// Checksum of the open file, computed again when its size or modification time changes.
+#[derive(Default)]
+pub(crate) struct Checksum {
+    key: Option<(PathBuf, u64, SystemTime)>,
+    sha256: Option<String>,
+}
+
+impl Checksum {
+    fn of(&mut self, path: &Path) -> Option<&str> {
+        let meta = fs::metadata(path).ok()?;
+        let key = (path.to_path_buf(), meta.len(), meta.modified().ok()?);
+        if self.key.as_ref() != Some(&key) {
+            self.sha256 = sha256_file(path).ok();
+            self.key = Some(key);
+        }
+        self.sha256.as_deref()
+    }
+}
+
+impl AppState {
+    // The footer for an export of the shown conversation; None when the footer is turned off.
+    pub(crate) fn provenance(&mut self) -> Option<Provenance> {
+        if !self.settings.provenance_footer {
+            return None;
+        }
+        let sha256 = self
+            .source
+            .as_deref()
+            .and_then(|p| self.checksum.of(p))
+            .map(str::to_string);
+        Some(Provenance {
+            file: self.file_name.clone().unwrap_or_else(|| "(unsaved)".to_string()),
+            sha256,
+            exported: Utc::now().format("%Y-%m-%d %H:%M UTC").to_string(),
+            version: env!("CARGO_PKG_VERSION"),
+        })
+    }
+
+    pub(crate) fn provenance_ui(&mut self, ui: &mut egui::Ui) {
+        if ui
+            .checkbox(&mut self.settings.provenance_footer, "Provenance footer")
+            .on_hover_text("End exports with the source file's name and SHA-256, the export time and the app version")
+            .changed()
+        {
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+        let Some(path) = self.source.clone() else { return };
+        match self.checksum.of(&path) {
+            Some(sha256) => {
+                let sha256 = sha256.to_string();
+                if ui
+                    .link(RichText::new(format!("SHA-256 {sha256}")).monospace().small())
+                    .on_hover_text("Copy")
+                    .clicked()
+                {
+                    ui.output_mut(|o| o.copied_text = sha256);
+                }
+            }
+            None => {
+                ui.label(RichText::new("SHA-256: file can no longer be read").small().weak());
+            }
+        }
+    }
+}
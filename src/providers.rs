@@ -0,0 +1,257 @@
+// Named LLM provider profiles shared by every feature that calls an API (judge, ...).
+// Profiles live in settings.json, but API keys never do: a key is either read from an
+// environment variable or stored in the OS keychain.
+
+use crate::{save_settings, AppState};
+use anyhow::{anyhow, Result};
+use eframe::egui::{self, Color32, RichText};
+use serde::{Deserialize, Serialize};
+
+const KEYCHAIN_SERVICE: &str = "llm_log_viewer";
+const PROVIDERS_WINDOW: &str = "Providers";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) enum KeySource {
+    // Name of the environment variable holding the key
+    Env(String),
+    // Stored in the OS keychain under the profile id
+    Keychain,
+    // Local servers that do not need authentication
+    None,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub(crate) struct ProviderProfile {
+    // Stable keychain account; unlike the name it never changes once the profile exists.
+    // Settings written before profiles had ids leave it empty (see `ensure_ids`).
+    pub id: String,
+    pub name: String,
+    pub base_url: String,
+    pub default_model: String,
+    pub key_source: KeySource,
+}
+
+impl Default for ProviderProfile {
+    fn default() -> Self {
+        Self {
+            id: String::new(),
+            name: "OpenAI".to_string(),
+            base_url: "https://api.openai.com/v1".to_string(),
+            default_model: "gpt-4o-mini".to_string(),
+            key_source: KeySource::Env("OPENAI_API_KEY".to_string()),
+        }
+    }
+}
+
+pub(crate) fn default_providers() -> Vec<ProviderProfile> {
+    vec![
+        ProviderProfile { id: "OpenAI".to_string(), ..Default::default() },
+        ProviderProfile {
+            id: "Local (Ollama)".to_string(),
+            name: "Local (Ollama)".to_string(),
+            base_url: "http://localhost:11434/v1".to_string(),
+            default_model: "llama3".to_string(),
+            key_source: KeySource::None,
+        },
+    ]
+}
+
+impl ProviderProfile {
+    // Resolve the API key. `Ok(None)` means the profile does not use one.
+    pub fn api_key(&self) -> Result<Option<String>> {
+        match &self.key_source {
+            KeySource::None => Ok(None),
+            KeySource::Env(var) => match std::env::var(var) {
+                Ok(k) if !k.trim().is_empty() => Ok(Some(k)),
+                _ => Err(anyhow!("Environment variable {var} is not set")),
+            },
+            KeySource::Keychain => keychain_entry(&self.id)?
+                .get_password()
+                .map(Some)
+                .map_err(|e| anyhow!("No key in keychain for \"{}\": {e}", self.name)),
+        }
+    }
+}
+
+fn keychain_entry(id: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, id).map_err(|e| anyhow!("Keychain unavailable: {e}"))
+}
+
+// Give every profile an id. Older settings stored keys under the profile name, so that name
+// becomes the id and existing keychain entries keep working. `judge_provider`, which older
+// settings also kept as a name, is pointed at the id of the profile with that name.
+pub(crate) fn ensure_ids(providers: &mut [ProviderProfile], judge_provider: &mut String) {
+    for i in 0..providers.len() {
+        if providers[i].id.is_empty() {
+            let taken = providers.iter().any(|p| p.id == providers[i].name);
+            let id = if taken { new_profile_id(providers) } else { providers[i].name.clone() };
+            providers[i].id = id;
+        }
+    }
+    if find_provider(providers, judge_provider).is_none() {
+        if let Some(p) = providers.iter().find(|p| p.name == *judge_provider) {
+            *judge_provider = p.id.clone();
+        }
+    }
+}
+
+fn new_profile_id(providers: &[ProviderProfile]) -> String {
+    (1..).map(|n| format!("profile-{n}")).find(|id| providers.iter().all(|p| &p.id != id)).unwrap()
+}
+
+pub(crate) fn find_provider<'a>(providers: &'a [ProviderProfile], id: &str) -> Option<&'a ProviderProfile> {
+    providers.iter().find(|p| p.id == id)
+}
+
+#[derive(Default)]
+pub(crate) struct ProvidersUi {
+    pub show_window: bool,
+    selected: usize,
+    // Typed key waiting to be saved to the keychain; only ever held in memory
+    key_input: String,
+    status: Option<Result<String, String>>,
+}
+
+impl AppState {
+    pub(crate) fn providers_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.providers_ui.show_window;
+        let resp = self
+            .tool_window(PROVIDERS_WINDOW)
+            .open(&mut open)
+            .default_width(440.0)
+            .show(ctx, |ui| {
+                let network = &mut self.settings.network;
+                egui::CollapsingHeader::new("Network").show(ui, |ui| {
+                    ui.checkbox(&mut network.offline, "Offline mode (disable all network features)");
+                    ui.horizontal(|ui| {
+                        ui.label("HTTP(S) proxy");
+                        ui.add(egui::TextEdit::singleline(&mut network.proxy).hint_text("from HTTPS_PROXY / ALL_PROXY"));
+                    });
+                });
+                ui.separator();
+
+                let ui_state = &mut self.providers_ui;
+                let providers = &mut self.settings.providers;
+                ui.horizontal_wrapped(|ui| {
+                    for (i, p) in providers.iter().enumerate() {
+                        if ui.selectable_label(ui_state.selected == i, &p.name).clicked() {
+                            ui_state.selected = i;
+                            ui_state.key_input.clear();
+                            ui_state.status = None;
+                        }
+                    }
+                    if ui.small_button("+ Add").clicked() {
+                        providers.push(ProviderProfile {
+                            id: new_profile_id(providers),
+                            name: format!("Provider {}", providers.len() + 1),
+                            key_source: KeySource::Env(String::new()),
+                            ..Default::default()
+                        });
+                        ui_state.selected = providers.len() - 1;
+                    }
+                });
+                ui.separator();
+
+                let Some(p) = providers.get_mut(ui_state.selected) else {
+                    ui.label("No provider selected");
+                    return;
+                };
+                egui::Grid::new("provider_fields").num_columns(2).show(ui, |ui| {
+                    ui.label("Name");
+                    ui.text_edit_singleline(&mut p.name);
+                    ui.end_row();
+                    ui.label("Base URL");
+                    ui.text_edit_singleline(&mut p.base_url);
+                    ui.end_row();
+                    ui.label("Default model");
+                    ui.text_edit_singleline(&mut p.default_model);
+                    ui.end_row();
+                    ui.label("API key");
+                    ui.horizontal(|ui| {
+                        let is_env = matches!(p.key_source, KeySource::Env(_));
+                        if ui.radio(is_env, "Env var").clicked() && !is_env {
+                            p.key_source = KeySource::Env(String::new());
+                        }
+                        if ui.radio(p.key_source == KeySource::Keychain, "Keychain").clicked() {
+                            p.key_source = KeySource::Keychain;
+                        }
+                        if ui.radio(p.key_source == KeySource::None, "None").clicked() {
+                            p.key_source = KeySource::None;
+                        }
+                    });
+                    ui.end_row();
+                });
+
+                match &mut p.key_source {
+                    KeySource::Env(var) => {
+                        ui.horizontal(|ui| {
+                            ui.label("Variable");
+                            ui.text_edit_singleline(var);
+                        });
+                    }
+                    KeySource::Keychain => {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut ui_state.key_input).password(true).hint_text("Paste API key"));
+                            if ui.add_enabled(!ui_state.key_input.is_empty(), egui::Button::new("Save to keychain")).clicked() {
+                                let res = keychain_entry(&p.id).and_then(|e| {
+                                    e.set_password(ui_state.key_input.trim()).map_err(|e| anyhow!("{e}"))
+                                });
+                                ui_state.key_input.clear();
+                                ui_state.status = Some(res.map(|_| "Key saved".to_string()).map_err(|e| e.to_string()));
+                            }
+                            if ui.button("Remove").clicked() {
+                                let res = keychain_entry(&p.id).and_then(|e| e.delete_password().map_err(|e| anyhow!("{e}")));
+                                ui_state.status = Some(res.map(|_| "Key removed".to_string()).map_err(|e| e.to_string()));
+                            }
+                        });
+                    }
+                    KeySource::None => {}
+                }
+
+                let mut delete = false;
+                ui.horizontal(|ui| {
+                    if ui.button("Test key").clicked() {
+                        ui_state.status = Some(match p.api_key() {
+                            Ok(Some(_)) => Ok("Key available".to_string()),
+                            Ok(None) => Ok("No key needed".to_string()),
+                            Err(e) => Err(e.to_string()),
+                        });
+                    }
+                    delete = ui.button("Delete profile").clicked();
+                });
+                if delete {
+                    let removed = providers.remove(ui_state.selected);
+                    ui_state.selected = ui_state.selected.saturating_sub(1);
+                    ui_state.status = None;
+                    // Do not leave the key behind in the OS keychain
+                    if removed.key_source == KeySource::Keychain {
+                        if let Err(e) = keychain_entry(&removed.id).and_then(|e| match e.delete_password() {
+                            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+                            Err(e) => Err(anyhow!("{e}")),
+                        }) {
+                            ui_state.status = Some(Err(format!("Profile deleted, but its key could not be removed: {e}")));
+                        }
+                    }
+                }
+                match &ui_state.status {
+                    Some(Ok(msg)) => {
+                        ui.label(RichText::new(msg).small());
+                    }
+                    Some(Err(msg)) => {
+                        ui.colored_label(Color32::from_rgb(183, 28, 28), msg);
+                    }
+                    None => {}
+                }
+            });
+        if let Some(r) = resp {
+            self.remember_window(PROVIDERS_WINDOW, r.response.rect);
+        }
+        if !open {
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+        self.providers_ui.show_window = open;
+    }
+}
@@ -0,0 +1,81 @@
+// Canonical logs (production traces, eval results others rely on) can be opened read-only: with
+// `--read-only` on the command line, or when the file or folder is in a protected location listed
+// under Tools. Locations are patterns matched against the absolute path and each folder above it,
+// like `/data/prod` or `/mnt/*/canonical`. While read-only, a lock in the top bar says why, and the
+// features that change files or attach data to the conversation are turned off: judge scoring,
+// applying diffs and running external tools on messages. Viewing and copying still work, and so do
+// exports and extracted code, except into a protected location (or anywhere with `--read-only`).
+// A conversation read from a ZIP archive, a spreadsheet or a folder is protected by where that is.
+
+use crate::{folder_scan::glob_match, save_settings, AppState};
+use anyhow::{bail, Result};
+use eframe::egui::{self, RichText};
+use std::path::Path;
+
+// The first protected location `path` is in.
+pub(crate) fn protected_by<'a>(path: &Path, locations: &'a [String]) -> Option<&'a str> {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let paths: Vec<Vec<char>> = absolute
+        .ancestors()
+        .map(|p| p.to_string_lossy().replace('\\', "/").chars().collect())
+        .collect();
+    locations
+        .iter()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .find(|l| {
+            let glob: Vec<char> = l.trim_end_matches('/').replace('\\', "/").chars().collect();
+            paths.iter().any(|p| glob_match(&glob, p))
+        })
+}
+
+impl AppState {
+    // Why the conversation read from `path` (or the folder opened) is read-only, if it is.
+    pub(crate) fn read_only_reason(&self, path: Option<&Path>) -> Option<String> {
+        if self.read_only_session {
+            return Some("Started with --read-only".to_string());
+        }
+        let location = protected_by(path?, &self.settings.protected_locations)?;
+        Some(format!("In the protected location {location}"))
+    }
+
+    // Refuses writing `target` when it is read-only, whatever conversation is shown.
+    pub(crate) fn check_writable(&self, target: &Path) -> Result<()> {
+        match self.read_only_reason(Some(target)) {
+            Some(reason) => bail!("{} is read-only: {reason}", target.display()),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn is_read_only(&self) -> bool {
+        self.read_only.is_some()
+    }
+
+    // Lock shown in the top bar while read-only.
+    pub(crate) fn read_only_indicator(&self, ui: &mut egui::Ui) {
+        if let Some(reason) = &self.read_only {
+            ui.label(RichText::new("🔒 Read-only").strong()).on_hover_text(format!(
+                "{reason}. Judge scoring, applying diffs and external tools are turned off."
+            ));
+        }
+    }
+
+    pub(crate) fn protected_locations_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Protected locations, opened read-only (one path or pattern per line):");
+        let mut text = self.settings.protected_locations.join("\n");
+        if ui
+            .add(
+                egui::TextEdit::multiline(&mut text)
+                    .code_editor()
+                    .desired_rows(3)
+                    .hint_text("/data/prod"),
+            )
+            .changed()
+        {
+            self.settings.protected_locations = text.split('\n').map(String::from).collect();
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+    }
+}
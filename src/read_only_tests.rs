@@ -0,0 +1,38 @@
+// `read_only`: which protected location a file is in, by its own path or a folder above it.
+
+use crate::read_only::protected_by;
+use std::path::Path;
+
+fn locations(lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|l| l.to_string()).collect()
+}
+
+#[cfg(unix)]
+#[test]
+fn files_are_protected_by_the_folders_they_are_in() {
+    let locations = locations(&["# canonical eval runs", "", "/mnt/*/canonical/", "/data/prod"]);
+    let protected = |path: &str| protected_by(Path::new(path), &locations);
+    assert_eq!(protected("/mnt/a/canonical/run.jsonl"), Some("/mnt/*/canonical/"));
+    assert_eq!(protected("/mnt/a/canonical/2024/05/run.jsonl"), Some("/mnt/*/canonical/"));
+    assert_eq!(protected("/mnt/a/canonical"), Some("/mnt/*/canonical/"));
+    assert_eq!(protected("/data/prod/traces/run.jsonl"), Some("/data/prod"));
+    // `*` stays within one folder name
+    assert_eq!(protected("/mnt/a/b/canonical/run.jsonl"), None);
+    assert_eq!(protected("/data/production/run.jsonl"), None);
+    assert_eq!(protected("/home/me/run.jsonl"), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn backslashes_in_locations_are_folder_separators() {
+    let locations = locations(&[r"\data\prod"]);
+    assert_eq!(protected_by(Path::new("/data/prod/run.jsonl"), &locations), Some(r"\data\prod"));
+}
+
+#[cfg(windows)]
+#[test]
+fn windows_paths_match_locations_written_with_slashes() {
+    let locations = locations(&["C:/data/*/canonical"]);
+    assert_eq!(protected_by(Path::new(r"C:\data\evals\canonical\run.jsonl"), &locations), Some("C:/data/*/canonical"));
+    assert_eq!(protected_by(Path::new(r"C:\data\evals\run.jsonl"), &locations), None);
+}
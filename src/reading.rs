@@ -0,0 +1,139 @@
+// Reading-time estimates for budgeting review: the whole conversation in the top bar, each message
+// on its avatar, each conversation in the Sessions pane, and the longest messages one click away.
+// Words are counted for Latin text; CJK text has no spaces and is counted per character.
+
+use crate::{format_duration, locale::Formatter, role_label, AppState, Msg};
+use eframe::egui::{self, RichText};
+use std::collections::HashMap;
+
+// Average adult reading pace for prose
+const READ_WPM: f64 = 230.0;
+// Skimming for the gist
+const SKIM_WPM: f64 = 700.0;
+// About 500 CJK characters per minute at the reading pace above
+const WORDS_PER_CJK_CHAR: f64 = 0.46;
+// Entries in the Longest menu
+const LONGEST: usize = 5;
+const SNIPPET_CHARS: usize = 60;
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // Hiragana, Katakana
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}' // CJK ideographs
+        | '\u{ac00}'..='\u{d7af}' // Hangul
+        | '\u{f900}'..='\u{faff}')
+}
+
+// Words, with CJK characters converted to the equivalent number of words.
+pub(crate) fn words(text: &str) -> f64 {
+    let (mut words, mut cjk, mut in_word) = (0usize, 0usize, false);
+    for c in text.chars() {
+        if is_cjk(c) {
+            cjk += 1;
+            in_word = false;
+        } else if c.is_whitespace() {
+            in_word = false;
+        } else if c.is_alphanumeric() && !in_word {
+            words += 1;
+            in_word = true;
+        }
+    }
+    words as f64 + cjk as f64 * WORDS_PER_CJK_CHAR
+}
+
+fn duration(words: f64, wpm: f64) -> String {
+    // At least a second, so short messages do not read as "0s"
+    format!("~{}", format_duration(((words / wpm * 60_000.0) as i64).max(1000)))
+}
+
+pub(crate) fn reading_time(words: f64) -> String {
+    duration(words, READ_WPM)
+}
+
+// "~2m to read, ~40s to skim (430 words)"
+pub(crate) fn describe(words: f64, fmt: &Formatter) -> String {
+    format!(
+        "{} to read, {} to skim ({} words)",
+        reading_time(words),
+        duration(words, SKIM_WPM),
+        fmt.count(words.round() as usize)
+    )
+}
+
+// Word counts of whole conversations, which are needed every frame. Keyed by message count and
+// total length, so conversations changed by unescaping, scripts or splitting are counted again.
+#[derive(Default)]
+pub(crate) struct ReadingCache(HashMap<(usize, usize), f64>);
+
+impl ReadingCache {
+    pub(crate) fn words(&mut self, system: Option<&str>, messages: &[Msg]) -> f64 {
+        let system = system.unwrap_or_default();
+        let key = (
+            messages.len(),
+            system.len() + messages.iter().map(|m| m.content.len()).sum::<usize>(),
+        );
+        *self
+            .0
+            .entry(key)
+            .or_insert_with(|| words(system) + messages.iter().map(|m| words(&m.content)).sum::<f64>())
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+fn snippet(content: &str) -> String {
+    let line = content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or_default();
+    let mut short: String = line.chars().take(SNIPPET_CHARS).collect();
+    if short.len() < content.trim().len() {
+        short.push('…');
+    }
+    short
+}
+
+impl AppState {
+    // "Reading: ~12m" in the top bar, and the Longest menu that jumps to the biggest messages.
+    pub(crate) fn reading_ui(&mut self, ui: &mut egui::Ui) {
+        if self.messages.is_empty() {
+            return;
+        }
+        let total = self.reading.words(self.system.as_deref(), &self.messages);
+        ui.separator();
+        ui.label(format!("Reading: {}", reading_time(total)))
+            .on_hover_text(describe(total, &self.fmt));
+        let mut jump = None;
+        ui.menu_button("Longest", |ui| {
+            let mut longest: Vec<(usize, f64)> = self
+                .messages
+                .iter()
+                .enumerate()
+                .map(|(i, m)| (i, words(&m.content)))
+                .collect();
+            longest.sort_by(|a, b| b.1.total_cmp(&a.1));
+            for &(i, w) in longest.iter().take(LONGEST) {
+                let msg = &self.messages[i];
+                let label = format!("#{} {} · {}", i + 1, role_label(&msg.role), reading_time(w));
+                let resp = ui.button(RichText::new(label).strong()).on_hover_text(format!(
+                    "{}\n{}",
+                    describe(w, &self.fmt),
+                    snippet(&msg.content)
+                ));
+                if resp.clicked() {
+                    jump = Some(i);
+                    ui.close_menu();
+                }
+            }
+        })
+        .response
+        .on_hover_text(format!("The {LONGEST} messages that take longest to read"));
+        if jump.is_some() {
+            self.scroll_to_message = jump;
+        }
+    }
+}
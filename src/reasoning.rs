@@ -0,0 +1,90 @@
+// Reasoning models write out their thinking before the answer, either in the text between
+// `<think>` and `</think>` (`<thinking>` for some) or in a field of its own (`reasoning_content`,
+// Anthropic `thinking` blocks, Ollama's `thinking`), which the loaders turn into the same tags.
+// The viewer shows it as a "Reasoning" section that is collapsed until opened, and the HTML export
+// as a closed `<details>`. A block that is never closed (a reply cut off while thinking) runs to
+// the end of the message. Tags inside code blocks are left alone.
+
+use eframe::egui::{self, collapsing_header::CollapsingState, Id, RichText};
+use std::borrow::Cow;
+
+const TAGS: &[(&str, &str)] = &[("<think>", "</think>"), ("<thinking>", "</thinking>")];
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Part<'a> {
+    Text(&'a str),
+    Reasoning(&'a str),
+}
+
+fn push_text<'a>(parts: &mut Vec<Part<'a>>, text: &'a str) {
+    let text = text.trim_matches(['\n', '\r']);
+    if !text.trim().is_empty() {
+        parts.push(Part::Text(text));
+    }
+}
+
+// `text` split into answer text and reasoning blocks, in order. Text without reasoning is one part.
+pub(crate) fn parts(text: &str) -> Vec<Part<'_>> {
+    if !text.contains("<think") {
+        return vec![Part::Text(text)];
+    }
+    let mut parts = Vec::new();
+    // Start of the text not yet added, and of the line looked at
+    let mut start = 0;
+    let mut pos = 0;
+    let mut in_fence = false;
+    while pos < text.len() {
+        let end = text[pos..].find('\n').map_or(text.len(), |n| pos + n + 1);
+        let line = text[pos..end].trim_start();
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if let Some((open, close)) = TAGS.iter().find(|(open, _)| !in_fence && line.starts_with(open)) {
+            push_text(&mut parts, &text[start..pos]);
+            let inner = end - line.len() + open.len();
+            let (reasoning, after) = match text[inner..].find(close) {
+                Some(n) => (&text[inner..inner + n], inner + n + close.len()),
+                None => (&text[inner..], text.len()),
+            };
+            if !reasoning.trim().is_empty() {
+                parts.push(Part::Reasoning(reasoning.trim()));
+            }
+            start = after;
+            pos = after;
+            continue;
+        }
+        pos = end;
+    }
+    push_text(&mut parts, &text[start..]);
+    parts
+}
+
+// `text` without its reasoning, for one-line previews; the whole text if there is nothing else.
+pub(crate) fn answer(text: &str) -> Cow<'_, str> {
+    let parts = parts(text);
+    let answer: Vec<&str> = parts
+        .iter()
+        .filter_map(|p| match p {
+            Part::Text(t) => Some(*t),
+            Part::Reasoning(_) => None,
+        })
+        .collect();
+    match answer.as_slice() {
+        [] => Cow::Borrowed(text),
+        [one] => Cow::Borrowed(one),
+        many => Cow::Owned(many.join("\n\n")),
+    }
+}
+
+// A collapsed "Reasoning" header with the reasoning, drawn by `add_body`, under it when opened.
+pub(crate) fn show(ui: &mut egui::Ui, viewer_key: &str, reasoning: &str, add_body: impl FnOnce(&mut egui::Ui)) {
+    let id = Id::new(("reasoning", viewer_key));
+    CollapsingState::load_with_default_open(ui.ctx(), id, false)
+        .show_header(ui, |ui| {
+            ui.label(RichText::new("Reasoning").italics().weak())
+                .on_hover_text(format!("{} characters", reasoning.chars().count()));
+        })
+        .body(|ui| {
+            ui.visuals_mut().override_text_color = Some(ui.visuals().weak_text_color());
+            add_body(ui);
+        });
+}
@@ -0,0 +1,39 @@
+// The window is redrawn on input and when a background job reports in, not continuously. egui's
+// spinner and animated progress bar redraw at the full frame rate for as long as they are shown,
+// which kept a CPU core busy through a long load or a tool that never finishes; the busy
+// indicator here turns at about a dozen frames a second instead. A slow heartbeat picks up what
+// changes without an event reaching egui, such as the cache sizes in the status bar.
+
+use eframe::egui::{self, emath::lerp, vec2, Pos2, Sense, Shape, Stroke};
+use std::time::Duration;
+
+const BUSY_FRAME: Duration = Duration::from_millis(80);
+const HEARTBEAT: Duration = Duration::from_secs(2);
+
+pub(crate) fn heartbeat(ctx: &egui::Context) {
+    ctx.request_repaint_after(HEARTBEAT);
+}
+
+// egui's spinner, redrawn every BUSY_FRAME instead of every frame.
+pub(crate) fn busy(ui: &mut egui::Ui) -> egui::Response {
+    let size = ui.spacing().interact_size.y;
+    let (rect, response) = ui.allocate_exact_size(vec2(size, size), Sense::hover());
+    if !ui.is_rect_visible(rect) {
+        return response;
+    }
+    ui.ctx().request_repaint_after(BUSY_FRAME);
+    let radius = rect.height() / 2.0 - 2.0;
+    let time = ui.input(|i| i.time);
+    let start_angle = time * std::f64::consts::TAU;
+    let end_angle = start_angle + 240f64.to_radians() * time.sin();
+    let points: Vec<Pos2> = (0..20)
+        .map(|i| {
+            let angle = lerp(start_angle..=end_angle, i as f64 / 20.0);
+            let (sin, cos) = angle.sin_cos();
+            rect.center() + radius * vec2(cos as f32, sin as f32)
+        })
+        .collect();
+    ui.painter()
+        .add(Shape::line(points, Stroke::new(3.0, ui.visuals().strong_text_color())));
+    response
+}
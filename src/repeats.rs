@@ -0,0 +1,219 @@
+// Agents waiting on something leave long stretches of near-identical tool calls and results behind
+// ("still running", "3 of 5 done", …). Three or more such steps in a row are collapsed to the last
+// one, the latest state, with "×N similar results" above it to show the rest. A step is a tool
+// result and the assistant turn that only calls the tool before it, if there is one. Steps are
+// similar when their text is the same once numbers, ids, punctuation and whitespace are ignored.
+
+use crate::{is_tool_card, locale::Formatter, Msg, Role};
+use eframe::egui::{self, collapsing_header::CollapsingState, Id, RichText};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    ops::Range,
+};
+
+const MIN_RUN: usize = 3;
+
+#[derive(Clone, Copy)]
+struct Fingerprint {
+    hash: u64,
+    // A different message at the same index is fingerprinted again
+    content_len: usize,
+}
+
+// Fingerprints of the messages looked at so far; computed once per message.
+#[derive(Default)]
+pub(crate) struct Repeats {
+    fingerprints: Vec<Option<Fingerprint>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Run {
+    // First message of the first step and of the last one
+    pub start: usize,
+    pub last: usize,
+    // Steps in the run
+    pub count: usize,
+    pub depth: usize,
+}
+
+impl Run {
+    fn state(&self, ctx: &egui::Context) -> CollapsingState {
+        CollapsingState::load_with_default_open(ctx, Id::new(("repeats", self.start)), false)
+    }
+
+    pub(crate) fn is_open(&self, ctx: &egui::Context) -> bool {
+        self.state(ctx).is_open()
+    }
+
+    pub(crate) fn open(&self, ctx: &egui::Context) {
+        let mut state = self.state(ctx);
+        state.set_open(true);
+        state.store(ctx);
+    }
+
+    // Earlier steps are hidden until the run is opened.
+    pub(crate) fn hides(&self, ctx: &egui::Context, index: usize) -> bool {
+        index < self.last && !self.is_open(ctx)
+    }
+
+    // The marker sits above the last step, or above the first one once the run is open.
+    pub(crate) fn marker_at(&self, ctx: &egui::Context, index: usize) -> bool {
+        index == if self.is_open(ctx) { self.start } else { self.last }
+    }
+
+    // "×17 similar results", or "Hide 16 similar results" when shown.
+    pub(crate) fn marker_ui(&self, ui: &mut egui::Ui, fmt: &Formatter) {
+        let mut state = self.state(ui.ctx());
+        let text = if state.is_open() {
+            format!("Hide {} similar results", fmt.count(self.count - 1))
+        } else {
+            format!("×{} similar results", fmt.count(self.count))
+        };
+        ui.horizontal(|ui| {
+            ui.add_space(36.0 + 16.0 * self.depth as f32);
+            if ui
+                .link(RichText::new(text).small())
+                .on_hover_text("Tool calls and results that only differ in numbers and ids; the latest is shown")
+                .clicked()
+            {
+                state.toggle(ui);
+            }
+        });
+        state.store(ui.ctx());
+    }
+}
+
+// Runs among the shown messages, looked up by message index.
+pub(crate) struct Runs {
+    start: usize,
+    of: Vec<Option<usize>>,
+    runs: Vec<Run>,
+}
+
+impl Runs {
+    pub(crate) fn get(&self, index: usize) -> Option<&Run> {
+        let run = (*self.of.get(index.checked_sub(self.start)?)?)?;
+        self.runs.get(run)
+    }
+}
+
+struct Step {
+    start: usize,
+    end: usize,
+    depth: usize,
+    key: (Option<u64>, u64),
+}
+
+// Assistant turns made of nothing but tool calls (see `tool_call_markdown`)
+fn is_call_only(msg: &Msg) -> bool {
+    matches!(msg.role, Role::Assistant) && msg.content.trim_start().starts_with("**Tool call:")
+}
+
+// Words of the text with every token containing a digit (counters, times, ids) made the same.
+pub(crate) fn hash_words(text: &str, hasher: &mut impl Hasher) {
+    for token in text.split(|c: char| !c.is_alphanumeric()).filter(|t| !t.is_empty()) {
+        if token.chars().any(|c| c.is_ascii_digit()) {
+            "0".hash(hasher);
+        } else {
+            token.hash(hasher);
+        }
+    }
+}
+
+fn fingerprint(msg: &Msg) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    msg.name.hash(&mut hasher);
+    hash_words(&msg.content, &mut hasher);
+    hasher.finish()
+}
+
+impl Repeats {
+    fn fingerprint(&mut self, index: usize, msg: &Msg) -> u64 {
+        if self.fingerprints.len() <= index {
+            self.fingerprints.resize(index + 1, None);
+        }
+        match self.fingerprints[index] {
+            Some(f) if f.content_len == msg.content.len() => f.hash,
+            _ => {
+                let hash = fingerprint(msg);
+                self.fingerprints[index] = Some(Fingerprint {
+                    hash,
+                    content_len: msg.content.len(),
+                });
+                hash
+            }
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.fingerprints.clear();
+    }
+
+    pub(crate) fn runs(&mut self, messages: &[Msg], shown: Range<usize>) -> Runs {
+        let mut steps = Vec::new();
+        let mut i = shown.start;
+        while i < shown.end {
+            let msg = &messages[i];
+            let result = messages
+                .get(i + 1)
+                .filter(|next| i + 1 < shown.end && next.depth == msg.depth);
+            if is_call_only(msg) && result.is_some_and(is_tool_card) {
+                let key = (
+                    Some(self.fingerprint(i, msg)),
+                    self.fingerprint(i + 1, &messages[i + 1]),
+                );
+                steps.push(Step {
+                    start: i,
+                    end: i + 1,
+                    depth: msg.depth,
+                    key,
+                });
+                i += 2;
+            } else {
+                if is_tool_card(msg) {
+                    let key = (None, self.fingerprint(i, msg));
+                    steps.push(Step {
+                        start: i,
+                        end: i,
+                        depth: msg.depth,
+                        key,
+                    });
+                }
+                i += 1;
+            }
+        }
+
+        let mut runs = Runs {
+            start: shown.start,
+            of: vec![None; shown.len()],
+            runs: Vec::new(),
+        };
+        let mut group = 0;
+        while group < steps.len() {
+            let first = &steps[group];
+            let mut next = group + 1;
+            while next < steps.len()
+                && steps[next].start == steps[next - 1].end + 1
+                && steps[next].depth == first.depth
+                && steps[next].key == first.key
+            {
+                next += 1;
+            }
+            if next - group >= MIN_RUN {
+                let last = &steps[next - 1];
+                for slot in &mut runs.of[first.start - shown.start..=last.end - shown.start] {
+                    *slot = Some(runs.runs.len());
+                }
+                runs.runs.push(Run {
+                    start: first.start,
+                    last: last.start,
+                    count: next - group,
+                    depth: first.depth,
+                });
+            }
+            group = next;
+        }
+        runs
+    }
+}
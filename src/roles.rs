@@ -0,0 +1,194 @@
+// Role names vary between tools ("human", "bot", "model", "function", …). Built-in aliases are
+// resolved while normalizing; aliases added under **Roles** cover the rest, so unfamiliar
+// speakers stop rendering as generic gray bubbles. System and tool messages can also be drawn at
+// a smaller text size than the dialogue.
+
+use crate::{is_developer, is_tool_card, save_settings, AppState, Msg, Role};
+use eframe::egui::{self, RichText};
+use serde::{Deserialize, Serialize};
+
+const BUILTIN_ALIASES: &[(&str, &str)] = &[
+    ("human", "user"),
+    ("prompter", "user"),
+    ("ai", "assistant"),
+    ("bot", "assistant"),
+    ("chatbot", "assistant"),
+    ("model", "assistant"),
+    ("gpt", "assistant"),
+    ("function", "tool"),
+    ("ipython", "tool"),
+    ("tool_result", "tool"),
+    ("observation", "tool"),
+];
+
+const TARGETS: &[(&str, &str)] = &[
+    ("user", "User"),
+    ("assistant", "Assistant"),
+    ("system", "System"),
+    ("tool", "Tool"),
+];
+
+// A user-defined alias, e.g. `agent_a` → `assistant`. Matched case-insensitively.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub(crate) struct RoleAlias {
+    pub from: String,
+    pub to: String,
+}
+
+// Text size of machine-generated turns relative to the Text size of the dialogue
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub(crate) struct RoleScales {
+    // The system prompt card, system messages and `developer` messages
+    pub system: f32,
+    pub tool: f32,
+}
+
+impl Default for RoleScales {
+    fn default() -> Self {
+        Self { system: 1.0, tool: 1.0 }
+    }
+}
+
+impl RoleScales {
+    pub(crate) fn for_msg(&self, msg: &Msg) -> f32 {
+        if is_tool_card(msg) {
+            self.tool
+        } else if matches!(msg.role, Role::System) || is_developer(msg) {
+            self.system
+        } else {
+            1.0
+        }
+    }
+}
+
+// Lowercased role with built-in aliases resolved.
+pub(crate) fn canonical_role(role: &str) -> String {
+    let lower = role.trim().to_lowercase();
+    BUILTIN_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == lower)
+        .map_or(lower, |(_, to)| to.to_string())
+}
+
+pub(crate) fn role_from_name(role: &str) -> Role {
+    match canonical_role(role).as_str() {
+        "system" => Role::System,
+        "user" => Role::User,
+        "assistant" => Role::Assistant,
+        other => Role::Other(other.to_string()),
+    }
+}
+
+// Re-role messages still shown as "Other" that match a user alias.
+pub(crate) fn apply_aliases(messages: &mut [Msg], aliases: &[RoleAlias]) {
+    for msg in messages {
+        let Role::Other(name) = &msg.role else { continue };
+        let Some(alias) = aliases
+            .iter()
+            .find(|a| !a.to.is_empty() && a.from.trim().eq_ignore_ascii_case(name))
+        else {
+            continue;
+        };
+        msg.role = role_from_name(&alias.to);
+    }
+}
+
+impl AppState {
+    fn apply_role_aliases(&mut self) {
+        let aliases = &self.settings.role_aliases;
+        apply_aliases(&mut self.messages, aliases);
+        for session in &mut self.sessions {
+            apply_aliases(&mut session.messages, aliases);
+        }
+    }
+
+    pub(crate) fn roles_menu(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut remove = None;
+        egui::Grid::new("role_aliases").num_columns(3).show(ui, |ui| {
+            for (i, alias) in self.settings.role_aliases.iter_mut().enumerate() {
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut alias.from)
+                            .hint_text("role in the file")
+                            .desired_width(120.0),
+                    )
+                    .lost_focus();
+                let selected = TARGETS
+                    .iter()
+                    .find(|(t, _)| *t == alias.to)
+                    .map_or("—", |(_, label)| label);
+                // A submenu rather than a combo box, whose popup would close the menu
+                ui.menu_button(selected, |ui| {
+                    for (target, label) in TARGETS {
+                        changed |= ui.selectable_value(&mut alias.to, target.to_string(), *label).changed();
+                    }
+                });
+                if ui.small_button("✕").on_hover_text("Remove").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            self.settings.role_aliases.remove(i);
+            changed = true;
+        }
+        if ui.button("Add alias").clicked() {
+            self.settings.role_aliases.push(RoleAlias {
+                from: String::new(),
+                to: "assistant".to_string(),
+            });
+        }
+        ui.separator();
+        if ui
+            .checkbox(&mut self.settings.merge_developer, "Merge developer into system")
+            .on_hover_text("Show leading developer messages in the system card")
+            .changed()
+        {
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+        let builtin = BUILTIN_ALIASES
+            .iter()
+            .map(|(from, to)| format!("{from} → {to}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        ui.label(RichText::new(format!("Built in: {builtin}")).weak().small());
+        if changed {
+            self.apply_role_aliases();
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+    }
+
+    pub(crate) fn role_scale_menu(&mut self, ui: &mut egui::Ui) {
+        let scales = &mut self.settings.role_scales;
+        let mut save = false;
+        egui::Grid::new("role_scales").num_columns(2).show(ui, |ui| {
+            for (label, value) in [("System", &mut scales.system), ("Tool", &mut scales.tool)] {
+                ui.label(label);
+                let resp = ui.add(
+                    egui::Slider::new(value, 0.5..=1.0)
+                        .step_by(0.05)
+                        .custom_formatter(|v, _| format!("{:.0}%", v * 100.0)),
+                );
+                save |= resp.drag_stopped() || (resp.changed() && !resp.dragged());
+                ui.end_row();
+            }
+        });
+        ui.label(
+            RichText::new("Relative to the Text size of user and assistant messages")
+                .weak()
+                .small(),
+        );
+        if save {
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+    }
+}
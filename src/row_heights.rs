@@ -0,0 +1,71 @@
+// Long conversations are drawn only where they are on screen; drawing thousands of bubbles every
+// frame made long logs crawl. Each message row (gap marker, bubble and the buttons under it) is
+// measured whenever it is drawn, and rows outside the viewport just reserve that much space. Rows
+// that have never been drawn reserve an estimate from their length until they scroll into view.
+
+use crate::{is_tool_card, Msg};
+use eframe::egui;
+
+// Rows this far outside the viewport are still drawn, so nothing pops in at the edges
+const OVERSCAN: f32 = 300.0;
+
+#[derive(Clone, Copy)]
+struct Row {
+    height: f32,
+    // A different message at the same index is measured again
+    content_len: usize,
+}
+
+#[derive(Default)]
+pub(crate) struct RowHeights {
+    rows: Vec<Option<Row>>,
+}
+
+impl RowHeights {
+    // Measured height of the row, or an estimate if it has not been drawn yet. Heights measured at
+    // another width or text size are only off until the row is drawn again.
+    pub(crate) fn height(&self, index: usize, msg: &Msg, width: f32, scale: f32) -> f32 {
+        match self.rows.get(index).copied().flatten() {
+            Some(row) if row.content_len == msg.content.len() => row.height,
+            _ => estimate(msg, width, scale),
+        }
+    }
+
+    pub(crate) fn set(&mut self, index: usize, msg: &Msg, height: f32) {
+        if self.rows.len() <= index {
+            self.rows.resize(index + 1, None);
+        }
+        self.rows[index] = Some(Row {
+            height,
+            content_len: msg.content.len(),
+        });
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.rows.clear();
+    }
+}
+
+// Whether a row starting at `top` reaches into the viewport of the scroll area `ui` is in.
+pub(crate) fn is_visible(ui: &egui::Ui, top: f32, height: f32) -> bool {
+    let visible = ui.clip_rect().expand2(egui::vec2(0.0, OVERSCAN));
+    top <= visible.bottom() && top + height >= visible.top()
+}
+
+// Wrapped lines at the body font size, plus the bubble padding and the Copy bar.
+fn estimate(msg: &Msg, width: f32, scale: f32) -> f32 {
+    // Collapsed by default
+    if is_tool_card(msg) {
+        return 32.0;
+    }
+    let font_size = 14.0 * scale;
+    let char_w = font_size * 0.55;
+    let line_h = font_size * 1.4;
+    let text_width = (width.min(800.0) - 60.0).max(100.0);
+    let lines: f32 = msg
+        .content
+        .lines()
+        .map(|l| (l.chars().count() as f32 * char_w / text_width).ceil().max(1.0))
+        .sum();
+    lines.max(1.0) * line_h + 48.0
+}
@@ -0,0 +1,405 @@
+// User scripts (Rhai) in `<config dir>/scripts/*.rhai`, an escape hatch for formats and
+// conventions the viewer does not know. A script may define any of:
+//
+//   fn parse(text)   -> array of #{role, content, name, timestamp} maps, an array of
+//                       #{title, messages} for several conversations, or () to decline
+//   fn transform(m)  -> the message map, modified, or () to drop the message
+//   fn badge(m)      -> a short label shown under the message, or () / "" for none
+//
+// Messages are passed as #{role, content, name, timestamp, index}.
+
+use crate::{
+    charset, config_dir,
+    incremental::ReadProgress,
+    mapping::{parse_mapped, FormatMapping},
+    parse::{assign_agents, compression, decompress, load_from_path, normalize_sessions, ParseOptions, RawMsg, RawSession},
+    roles::role_from_name,
+    AppState, Loaded, Msg, Role,
+};
+use anyhow::{anyhow, Context, Result};
+use eframe::egui::{self, Color32, RichText};
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// Keeps a runaway script from freezing the UI
+const MAX_OPERATIONS: u64 = 5_000_000;
+const MAX_EXPR_DEPTH: usize = 64;
+// MAX_OPERATIONS counts per call; a hook that is cheap per message can still stall a large
+// file, so each pass over a file (parse, transform, badges) also gets this much time in total
+const PASS_BUDGET: Duration = Duration::from_secs(5);
+// Clock checks are spaced out so they do not slow scripts down
+const BUDGET_CHECK_EVERY: u64 = 10_000;
+// parse() receives the whole file; larger files go straight to the built-in loaders
+const MAX_STRING_SIZE: usize = 64 << 20;
+const MAX_ARRAY_SIZE: usize = 1_000_000;
+const MAX_MAP_SIZE: usize = 10_000;
+
+struct Script {
+    name: String,
+    ast: AST,
+    hooks: Vec<&'static str>,
+}
+
+pub(crate) struct Scripts {
+    engine: Engine,
+    scripts: Vec<Script>,
+    // Compile errors from the last (re)load
+    load_errors: Vec<String>,
+    // badge() results for the shown conversation, by message index
+    pub badges: HashMap<usize, String>,
+    // End of the current pass's time budget; the engine stops scripts once it has passed
+    deadline: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        // Debug builds default to a nesting limit that ordinary scripts hit
+        engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH);
+        engine.set_max_string_size(MAX_STRING_SIZE);
+        engine.set_max_array_size(MAX_ARRAY_SIZE);
+        engine.set_max_map_size(MAX_MAP_SIZE);
+        let deadline: Arc<Mutex<Option<Instant>>> = Arc::default();
+        let passed = Arc::clone(&deadline);
+        engine.on_progress(move |ops| {
+            let expired = ops % BUDGET_CHECK_EVERY == 0
+                && passed.lock().is_ok_and(|d| d.is_some_and(|d| Instant::now() >= d));
+            expired.then(|| "out of time".into())
+        });
+        Self {
+            engine,
+            scripts: Vec::new(),
+            load_errors: Vec::new(),
+            badges: HashMap::new(),
+            deadline,
+        }
+    }
+}
+
+pub(crate) fn scripts_dir() -> Option<PathBuf> {
+    config_dir().map(|d| d.join("scripts"))
+}
+
+fn msg_map(role: &str, content: &str, name: Option<&str>, timestamp: Option<i64>, index: usize) -> Dynamic {
+    let mut map = Map::new();
+    map.insert("role".into(), role.into());
+    map.insert("content".into(), content.into());
+    map.insert("name".into(), name.map_or(Dynamic::UNIT, |n| n.into()));
+    map.insert("timestamp".into(), timestamp.map_or(Dynamic::UNIT, Dynamic::from));
+    map.insert("index".into(), Dynamic::from(index as i64));
+    Dynamic::from_map(map)
+}
+
+fn role_name(role: &Role) -> String {
+    match role {
+        Role::System => "system".to_string(),
+        Role::User => "user".to_string(),
+        Role::Assistant => "assistant".to_string(),
+        Role::Other(r) => r.clone(),
+    }
+}
+
+fn parse_result(value: Dynamic) -> Result<Vec<RawSession>> {
+    let items = value
+        .try_cast::<rhai::Array>()
+        .ok_or_else(|| anyhow!("parse() must return an array or ()"))?;
+    let is_session = |d: &Dynamic| d.read_lock::<Map>().is_some_and(|m| m.contains_key("messages"));
+    if items.first().is_some_and(is_session) {
+        items
+            .iter()
+            .map(|s| {
+                let map = s
+                    .read_lock::<Map>()
+                    .ok_or_else(|| anyhow!("conversation must be a map"))?;
+                let title = map.get("title").and_then(|t| t.clone().into_string().ok());
+                let messages = map.get("messages").cloned().unwrap_or_default();
+                Ok(RawSession {
+                    title,
+                    messages: rhai::serde::from_dynamic(&messages).map_err(|e| anyhow!("{e}"))?,
+                    ..Default::default()
+                })
+            })
+            .collect()
+    } else {
+        let messages: Vec<RawMsg> =
+            rhai::serde::from_dynamic(&Dynamic::from_array(items)).map_err(|e| anyhow!("{e}"))?;
+        Ok(vec![RawSession {
+            messages,
+            ..Default::default()
+        }])
+    }
+}
+
+impl Scripts {
+    // (Re)compile every script in the scripts folder.
+    pub fn reload(&mut self) {
+        self.scripts.clear();
+        self.load_errors.clear();
+        let Some(dir) = scripts_dir() else { return };
+        let Ok(entries) = fs::read_dir(&dir) else { return };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|e| e == "rhai"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let compiled = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|src| self.engine.compile(src).map_err(|e| e.to_string()));
+            match compiled {
+                Ok(ast) => {
+                    let hooks = ["parse", "transform", "badge"]
+                        .into_iter()
+                        .filter(|h| ast.iter_functions().any(|f| f.name == *h && f.params.len() == 1))
+                        .collect();
+                    self.scripts.push(Script { name, ast, hooks });
+                }
+                Err(e) => self.load_errors.push(format!("{name}: {e}")),
+            }
+        }
+    }
+
+    fn with_hook(&self, hook: &'static str) -> impl Iterator<Item = &Script> {
+        self.scripts.iter().filter(move |s| s.hooks.contains(&hook))
+    }
+
+    fn start_pass(&self) {
+        if let Ok(mut deadline) = self.deadline.lock() {
+            *deadline = Some(Instant::now() + PASS_BUDGET);
+        }
+    }
+
+    fn out_of_time(&self) -> bool {
+        self.deadline.lock().is_ok_and(|d| d.is_some_and(|d| Instant::now() >= d))
+    }
+
+    // Once the pass is out of time every further call fails at once, so the rest of the
+    // messages are left as they are.
+    fn call(&self, script: &Script, hook: &str, arg: Dynamic) -> Result<Dynamic> {
+        let timed_out = || anyhow!("{}: {hook}(): took longer than {} s for this file", script.name, PASS_BUDGET.as_secs());
+        if self.out_of_time() {
+            return Err(timed_out());
+        }
+        self.engine.call_fn::<Dynamic>(&mut Scope::new(), &script.ast, hook, (arg,)).map_err(|e| match *e {
+            rhai::EvalAltResult::ErrorTerminated(..) => timed_out(),
+            e => anyhow!("{}: {hook}(): {e}", script.name),
+        })
+    }
+
+    // The first script whose parse() accepts the text; None when every script declines.
+    pub fn parse(&self, text: &str) -> Option<Result<Loaded>> {
+        if text.len() > MAX_STRING_SIZE {
+            return None;
+        }
+        self.start_pass();
+        for script in self.with_hook("parse") {
+            match self.call(script, "parse", text.into()) {
+                Ok(v) if v.is_unit() => continue,
+                Ok(v) => {
+                    let parsed = parse_result(v).map_err(|e| anyhow!("{}: parse(): {e}", script.name));
+                    return Some(parsed.map(|sessions| Loaded { format: Some("User script"), ..normalize_sessions(sessions) }));
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+
+    pub fn has_parse(&self) -> bool {
+        self.with_hook("parse").next().is_some()
+    }
+
+    // Runs every transform() over the messages in order; returns the first error.
+    fn transform(&self, messages: &mut Vec<Msg>) -> Option<String> {
+        let mut error = None;
+        for script in self.with_hook("transform") {
+            let mut kept = Vec::with_capacity(messages.len());
+            for (i, mut msg) in std::mem::take(messages).into_iter().enumerate() {
+                let arg = msg_map(
+                    &role_name(&msg.role),
+                    &msg.content,
+                    msg.name.as_deref(),
+                    msg.timestamp,
+                    i + 1,
+                );
+                match self.call(script, "transform", arg) {
+                    Ok(v) if v.is_unit() => continue,
+                    Ok(v) => {
+                        if let Some(map) = v.read_lock::<Map>() {
+                            let text = |k: &str| map.get(k).and_then(|v| v.clone().into_string().ok());
+                            if let Some(role) = text("role") {
+                                msg.role = role_from_name(&role);
+                            }
+                            if let Some(content) = text("content") {
+                                msg.content = content;
+                            }
+                            msg.name = text("name");
+                            msg.timestamp = map.get("timestamp").and_then(|t| t.as_int().ok());
+                        }
+                        kept.push(msg);
+                    }
+                    Err(e) => {
+                        error.get_or_insert(e.to_string());
+                        kept.push(msg);
+                    }
+                }
+            }
+            *messages = kept;
+        }
+        // Speaker names may have changed; only multi-agent logs have agent colors to update
+        if messages.iter().any(|m| m.agent.is_some()) {
+            messages.iter_mut().for_each(|m| m.agent = None);
+            assign_agents(messages);
+        }
+        error
+    }
+
+    // Apply transform() to everything that was loaded, including the other conversations.
+    pub fn transform_loaded(&self, loaded: &mut Loaded) {
+        if self.with_hook("transform").next().is_none() {
+            return;
+        }
+        self.start_pass();
+        let mut errors: Vec<String> = self.transform(&mut loaded.messages).into_iter().collect();
+        for session in &mut loaded.sessions {
+            errors.extend(self.transform(&mut session.messages));
+        }
+        errors.dedup();
+        loaded.errors.extend(errors);
+    }
+
+    // Recompute badges for the shown conversation; returns the first error.
+    pub fn reset_for(&mut self, messages: &[Msg]) -> Option<String> {
+        let mut badges: HashMap<usize, String> = HashMap::new();
+        let mut error = None;
+        self.start_pass();
+        for script in self.with_hook("badge") {
+            for (i, msg) in messages.iter().enumerate() {
+                let arg = msg_map(
+                    &role_name(&msg.role),
+                    &msg.content,
+                    msg.name.as_deref(),
+                    msg.timestamp,
+                    i + 1,
+                );
+                match self.call(script, "badge", arg) {
+                    Ok(v) => {
+                        let Some(label) = v.into_string().ok().filter(|l| !l.trim().is_empty()) else {
+                            continue;
+                        };
+                        let badge = badges.entry(i).or_default();
+                        if !badge.is_empty() {
+                            badge.push_str(" · ");
+                        }
+                        badge.push_str(label.trim());
+                    }
+                    Err(e) => {
+                        error.get_or_insert(e.to_string());
+                    }
+                }
+            }
+        }
+        self.badges = badges;
+        error
+    }
+}
+
+// Contents of a (possibly compressed) UTF-8 or UTF-16 text file; None for binary formats and text
+// that only decodes with a warning, which the built-in loaders report.
+pub(crate) fn read_text(path: &Path) -> Result<Option<String>> {
+    let mut bytes = fs::read(path)?;
+    if let Some(format) = compression(&bytes) {
+        bytes = decompress(bytes.as_slice(), format)?;
+    }
+    let (text, warning) = charset::decode(&bytes);
+    Ok(warning.is_none().then(|| text.into_owned()))
+}
+
+// load_from_path, except that format mappings get the first look at text files. Runs no scripts,
+// so files can also be loaded this way on a loader thread.
+pub(crate) fn load_mapped(
+    mappings: &[FormatMapping],
+    path: &Path,
+    progress: Option<&ReadProgress>,
+    options: ParseOptions,
+) -> Result<Loaded> {
+    if mappings.is_empty() {
+        return load_from_path(path, progress, options);
+    }
+    let Some(text) = read_text(path).with_context(|| format!("Failed to read {}", path.display()))? else {
+        return load_from_path(path, progress, options);
+    };
+    match parse_mapped(mappings, &text) {
+        Some(loaded) => with_file_name(loaded?, path),
+        None => load_from_path(path, progress, options),
+    }
+}
+
+pub(crate) fn with_file_name(mut loaded: Loaded, path: &Path) -> Result<Loaded> {
+    loaded.file_name = path.file_name().map(|s| s.to_string_lossy().to_string());
+    loaded.source = Some(path.to_path_buf());
+    Ok(loaded)
+}
+
+impl AppState {
+    // load_mapped, except that parse() hooks get the first look at text files.
+    pub(crate) fn load_path(&self, path: &Path) -> Result<Loaded> {
+        let options = self.parse_options();
+        if !self.scripts.has_parse() {
+            return load_mapped(&self.settings.format_mappings, path, None, options);
+        }
+        let Some(text) = read_text(path).with_context(|| format!("Failed to read {}", path.display()))? else {
+            return load_from_path(path, None, options);
+        };
+        let parsed = self.scripts.parse(&text).or_else(|| parse_mapped(&self.settings.format_mappings, &text));
+        match parsed {
+            Some(loaded) => with_file_name(loaded?, path),
+            None => load_from_path(path, None, options),
+        }
+    }
+
+    pub(crate) fn reset_badges(&mut self) {
+        if let Some(e) = self.scripts.reset_for(&self.messages) {
+            self.errors.push(e);
+        }
+    }
+
+    pub(crate) fn scripts_menu(&mut self, ui: &mut egui::Ui) {
+        let folder = scripts_dir().map_or("(no config directory)".to_string(), |d| d.display().to_string());
+        ui.label(RichText::new(folder).monospace().small());
+        if self.scripts.scripts.is_empty() && self.scripts.load_errors.is_empty() {
+            ui.label(RichText::new("No .rhai scripts in this folder").italics().weak());
+        }
+        for script in &self.scripts.scripts {
+            let hooks = if script.hooks.is_empty() {
+                "no hooks".to_string()
+            } else {
+                script.hooks.join(", ")
+            };
+            ui.label(format!("{} — {hooks}", script.name));
+        }
+        for e in &self.scripts.load_errors {
+            ui.colored_label(Color32::from_rgb(183, 28, 28), e);
+        }
+        ui.separator();
+        if ui
+            .button("Reload scripts")
+            .on_hover_text("Applies to the next file you open")
+            .clicked()
+        {
+            self.scripts.reload();
+            self.reset_badges();
+        }
+    }
+}
@@ -0,0 +1,70 @@
+// "Copy as image" for a single message: asks the backend for a screenshot of the next frame,
+// crops it to the message (avatar + bubble) and puts the pixels on the system clipboard.
+
+use crate::AppState;
+use anyhow::{anyhow, Result};
+use eframe::egui::{self, ColorImage, Id, Rect};
+use std::borrow::Cow;
+
+// Space around the bubble so rounded corners are not cut off
+const MARGIN: f32 = 8.0;
+
+fn pending_id() -> Id {
+    Id::new("message_snapshot")
+}
+
+// Called from the bubble: remember the area to keep and request a screenshot.
+pub(crate) fn request_snapshot(ctx: &egui::Context, rect: Rect) {
+    ctx.data_mut(|d| d.insert_temp(pending_id(), rect.expand(MARGIN)));
+    ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot);
+}
+
+// Crop `image` to `rect` (in points), clamped to what is on screen.
+fn crop(image: &ColorImage, rect: Rect, pixels_per_point: f32) -> Option<ColorImage> {
+    let [w, h] = image.size;
+    let px = |v: f32, max: usize| ((v * pixels_per_point).round().max(0.0) as usize).min(max);
+    let (x0, x1) = (px(rect.min.x, w), px(rect.max.x, w));
+    let (y0, y1) = (px(rect.min.y, h), px(rect.max.y, h));
+    (x1 > x0 && y1 > y0).then(|| {
+        let region = Rect::from_min_max(egui::pos2(x0 as f32, y0 as f32), egui::pos2(x1 as f32, y1 as f32));
+        image.region(&region, None)
+    })
+}
+
+fn copy_image(image: &ColorImage) -> Result<()> {
+    let bytes: Vec<u8> = image.pixels.iter().flat_map(|c| c.to_srgba_unmultiplied()).collect();
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| anyhow!("Clipboard unavailable: {e}"))?;
+    clipboard
+        .set_image(arboard::ImageData {
+            width: image.size[0],
+            height: image.size[1],
+            bytes: Cow::Owned(bytes),
+        })
+        .map_err(|e| anyhow!("Failed to copy image: {e}"))
+}
+
+impl AppState {
+    // Completes a pending "Copy as image" once the screenshot arrives.
+    pub(crate) fn poll_snapshot(&mut self, ctx: &egui::Context) {
+        let Some(rect) = ctx.data(|d| d.get_temp::<Rect>(pending_id())) else {
+            return;
+        };
+        let screenshot = ctx.input(|i| {
+            i.events.iter().find_map(|e| match e {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        let Some(screenshot) = screenshot else {
+            return;
+        };
+        ctx.data_mut(|d| d.remove::<Rect>(pending_id()));
+        let result = match crop(&screenshot, rect, ctx.pixels_per_point()) {
+            Some(image) => copy_image(&image),
+            None => Err(anyhow!("The message is not on screen")),
+        };
+        if let Err(e) = result {
+            self.errors.push(e.to_string());
+        }
+    }
+}
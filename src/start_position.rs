@@ -0,0 +1,77 @@
+// Where a conversation opens: at the top, at the latest message, or where the reader left off last
+// time. Last-read positions are remembered per file (and per conversation in multi-conversation
+// files) in settings.json.
+
+use crate::{save_settings, AppState};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+// Files whose last-read position is remembered; the least recently read are forgotten first
+const MAX_REMEMBERED: usize = 200;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum StartPosition {
+    #[default]
+    Top,
+    // Latest message first; a large file that is still loading keeps following new messages
+    Bottom,
+    LastRead,
+}
+
+impl AppState {
+    // Same key as the scroll area; None for text that did not come from a file.
+    fn position_key(&self) -> Option<&str> {
+        let name = self.file_name.as_deref()?;
+        (!name.starts_with('(')).then_some(self.scroll_area_key.as_str())
+    }
+
+    // Called before another file or conversation replaces the one shown.
+    pub(crate) fn remember_position(&mut self) {
+        let (Some(key), Some(position)) = (self.position_key(), self.reading_position) else {
+            return;
+        };
+        let key = key.to_string();
+        let last_read = &mut self.settings.last_read;
+        last_read.retain(|(k, _)| *k != key);
+        last_read.insert(0, (key, position));
+        last_read.truncate(MAX_REMEMBERED);
+        if let Err(e) = save_settings(&self.settings) {
+            self.errors.push(format!("Failed to save settings: {e}"));
+        }
+    }
+
+    // Called once a file or conversation is shown.
+    pub(crate) fn apply_start_position(&mut self) {
+        match self.settings.start_position {
+            StartPosition::Top => {}
+            StartPosition::Bottom => self.scroll_to_end = true,
+            StartPosition::LastRead => {
+                let key = self.position_key();
+                let last = self.settings.last_read.iter().find(|(k, _)| Some(k.as_str()) == key);
+                // Positions are 1-based; the conversation may have changed since
+                if let Some(&(_, position)) = last.filter(|(_, p)| (1..=self.messages.len()).contains(p)) {
+                    self.scroll_to_message = Some(position - 1);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn start_position_menu(&mut self, ui: &mut egui::Ui) {
+        let start = &mut self.settings.start_position;
+        let mut changed = false;
+        changed |= ui.radio_value(start, StartPosition::Top, "Top").changed();
+        changed |= ui
+            .radio_value(start, StartPosition::Bottom, "Latest message")
+            .on_hover_text("Large files that are still loading keep scrolling to new messages")
+            .changed();
+        changed |= ui
+            .radio_value(start, StartPosition::LastRead, "Where I left off")
+            .on_hover_text("The message at the top of the view when the file was last closed")
+            .changed();
+        if changed {
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+    }
+}
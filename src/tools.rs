@@ -0,0 +1,361 @@
+// External tools: user-configured shell commands (`jq .`, a linter, a classifier script) run on
+// one message at a time. The message content is piped to stdin and the output is collected in
+// the "Tool output" pane. Export hooks use the same plumbing to post-process exported files.
+
+use crate::{backup::Backup, dock::Pane, repaint, role_label, save_settings, AppState, Msg};
+use anyhow::{bail, Context, Result};
+use eframe::egui::{self, Color32, RichText, ScrollArea};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    sync::mpsc,
+    thread,
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub(crate) struct ExternalTool {
+    pub name: String,
+    // Run through the shell (`sh -c` / `cmd /C`)
+    pub command: String,
+}
+
+// Commands each export is piped through before it is written; empty means none.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub(crate) struct ExportHooks {
+    pub markdown: String,
+    pub html: String,
+    pub notebook: String,
+    pub outline: String,
+    pub skeleton: String,
+}
+
+struct ToolRun {
+    tool: String,
+    // 1-based message number
+    message: usize,
+    stdout: String,
+    stderr: String,
+    // Exit code, or why the command could not be run
+    status: Result<Option<i32>, String>,
+}
+
+// An export written on a worker thread: what was exported ("Markdown", "notebook") and how it went
+struct ExportDone {
+    what: &'static str,
+    result: Result<()>,
+}
+
+pub(crate) struct ToolsUi {
+    tx: mpsc::Sender<ToolRun>,
+    rx: mpsc::Receiver<ToolRun>,
+    running: usize,
+    // Newest first
+    results: Vec<ToolRun>,
+    export_tx: mpsc::Sender<ExportDone>,
+    export_rx: mpsc::Receiver<ExportDone>,
+    exporting: usize,
+}
+
+impl Default for ToolsUi {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        let (export_tx, export_rx) = mpsc::channel();
+        Self {
+            tx,
+            rx,
+            running: 0,
+            results: Vec::new(),
+            export_tx,
+            export_rx,
+            exporting: 0,
+        }
+    }
+}
+
+impl ToolsUi {
+    // Collect finished runs and exports; called every frame. Returns the failed exports.
+    pub fn poll(&mut self) -> Vec<String> {
+        while let Ok(run) = self.rx.try_recv() {
+            self.running = self.running.saturating_sub(1);
+            self.results.insert(0, run);
+        }
+        let mut failed = Vec::new();
+        while let Ok(done) = self.export_rx.try_recv() {
+            self.exporting = self.exporting.saturating_sub(1);
+            if let Err(e) = done.result {
+                failed.push(format!("Failed to export {}: {e}", done.what));
+            }
+        }
+        failed
+    }
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(target_os = "windows") {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+// Run `cmd` with `input` on stdin and collect its output.
+fn pipe_through(cmd: &mut Command, input: String) -> std::io::Result<Output> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Written from another thread so a command that prints before reading its input cannot deadlock
+    let writer = child.stdin.take().map(|mut stdin| {
+        thread::spawn(move || {
+            let _ = stdin.write_all(input.as_bytes());
+        })
+    });
+    let output = child.wait_with_output();
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    output
+}
+
+fn run_tool(tool: &ExternalTool, msg: &Msg, message: usize) -> ToolRun {
+    let mut cmd = shell(&tool.command);
+    cmd.env("LLM_LOG_ROLE", role_label(&msg.role))
+        .env("LLM_LOG_MESSAGE", message.to_string());
+    let (stdout, stderr, status) = match pipe_through(&mut cmd, msg.content.clone()) {
+        Ok(output) => (
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+            Ok(output.status.code()),
+        ),
+        Err(e) => (String::new(), String::new(), Err(format!("Failed to start: {e}"))),
+    };
+    ToolRun {
+        tool: tool.name.clone(),
+        message,
+        stdout,
+        stderr,
+        status,
+    }
+}
+
+// Write an export, piped through `hook` first when one is configured. The destination is
+// passed as LLM_LOG_EXPORT_PATH; the hook's stdout becomes the file.
+fn write_export(path: &Path, content: String, hook: &str, backups: bool) -> Result<()> {
+    let bytes = if hook.trim().is_empty() {
+        content.into_bytes()
+    } else {
+        let mut cmd = shell(hook);
+        cmd.env("LLM_LOG_EXPORT_PATH", path);
+        let output = pipe_through(&mut cmd, content).with_context(|| format!("Failed to run `{hook}`"))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("`{hook}` failed ({}): {}", output.status, stderr.trim());
+        }
+        output.stdout
+    };
+    Backup::new(backups).keep(path)?;
+    fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+// "Run tool" menu under a message; returns the picked tool.
+pub(crate) fn run_tool_menu(ui: &mut egui::Ui, tools: &[ExternalTool]) -> Option<usize> {
+    let mut picked = None;
+    ui.menu_button("Run tool", |ui| {
+        for (i, tool) in tools.iter().enumerate() {
+            if ui
+                .button(&tool.name)
+                .on_hover_text(RichText::new(&tool.command).monospace())
+                .clicked()
+            {
+                picked = Some(i);
+                ui.close_menu();
+            }
+        }
+    });
+    picked
+}
+
+impl AppState {
+    // Write an export on a worker thread, like a tool run: a hook may take a while, and the
+    // window stays responsive meanwhile. Failures show up with the other errors.
+    pub(crate) fn save_export(&mut self, ctx: &egui::Context, what: &'static str, path: PathBuf, content: String, hook: String) {
+        if let Err(e) = self.check_writable(&path) {
+            self.errors.push(format!("Failed to export {what}: {e}"));
+            return;
+        }
+        let (tx, ctx, backups) = (self.tools.export_tx.clone(), ctx.clone(), self.settings.backups);
+        self.tools.exporting += 1;
+        thread::spawn(move || {
+            let _ = tx.send(ExportDone { what, result: write_export(&path, content, &hook, backups) });
+            ctx.request_repaint();
+        });
+    }
+
+    // Shown in the top bar while exports are being written.
+    pub(crate) fn export_indicator(&self, ui: &mut egui::Ui) {
+        if self.tools.exporting > 0 {
+            repaint::busy(ui);
+            ui.label(RichText::new("Exporting…").weak());
+        }
+    }
+
+    pub(crate) fn run_external_tool(&mut self, tool: usize, index: usize, ctx: &egui::Context) {
+        let (Some(tool), Some(msg)) = (self.settings.external_tools.get(tool), self.messages.get(index)) else {
+            return;
+        };
+        let (tool, msg, tx, ctx) = (tool.clone(), msg.clone(), self.tools.tx.clone(), ctx.clone());
+        self.tools.running += 1;
+        self.open_pane(Pane::ToolOutput);
+        thread::spawn(move || {
+            let _ = tx.send(run_tool(&tool, &msg, index + 1));
+            ctx.request_repaint();
+        });
+    }
+
+    pub(crate) fn tools_menu(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut remove = None;
+        egui::Grid::new("external_tools").num_columns(3).show(ui, |ui| {
+            for (i, tool) in self.settings.external_tools.iter_mut().enumerate() {
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut tool.name)
+                            .hint_text("Name")
+                            .desired_width(100.0),
+                    )
+                    .lost_focus();
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut tool.command)
+                            .hint_text("jq .")
+                            .code_editor()
+                            .desired_width(260.0),
+                    )
+                    .lost_focus();
+                if ui.small_button("✕").on_hover_text("Remove").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            self.settings.external_tools.remove(i);
+            changed = true;
+        }
+        if ui.button("Add tool").clicked() {
+            self.settings.external_tools.push(ExternalTool::default());
+        }
+        ui.label(
+            RichText::new("The message is passed on stdin, with LLM_LOG_ROLE and LLM_LOG_MESSAGE set.")
+                .weak()
+                .small(),
+        );
+        ui.separator();
+        ui.label(RichText::new("Export hooks").strong());
+        egui::Grid::new("export_hooks").num_columns(2).show(ui, |ui| {
+            let hooks = &mut self.settings.export_hooks;
+            for (label, command, hint) in [
+                ("Markdown", &mut hooks.markdown, "prettier --parser markdown"),
+                ("HTML", &mut hooks.html, "prettier --parser html"),
+                ("Notebook", &mut hooks.notebook, "jq ."),
+                ("Outline", &mut hooks.outline, "prettier --parser markdown"),
+                ("Skeleton", &mut hooks.skeleton, "jq -S ."),
+            ] {
+                ui.label(label);
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(command)
+                            .hint_text(hint)
+                            .code_editor()
+                            .desired_width(260.0),
+                    )
+                    .lost_focus();
+                ui.end_row();
+            }
+        });
+        ui.label(
+            RichText::new("Exports are piped through the command and its output is saved.")
+                .weak()
+                .small(),
+        );
+        if changed {
+            self.settings
+                .external_tools
+                .retain(|t| !t.command.trim().is_empty() || !t.name.trim().is_empty());
+            if let Err(e) = save_settings(&self.settings) {
+                self.errors.push(format!("Failed to save settings: {e}"));
+            }
+        }
+        ui.separator();
+        self.backup_ui(ui);
+        ui.separator();
+        self.protected_locations_ui(ui);
+    }
+
+    pub(crate) fn tool_output_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if self.tools.running > 0 {
+                repaint::busy(ui);
+                ui.label(format!("Running {}", self.fmt.count(self.tools.running)));
+            }
+            if ui
+                .add_enabled(!self.tools.results.is_empty(), egui::Button::new("Clear"))
+                .clicked()
+            {
+                self.tools.results.clear();
+            }
+        });
+        if self.tools.results.is_empty() && self.tools.running == 0 {
+            ui.label(
+                RichText::new("Run a tool from the row under a message")
+                    .italics()
+                    .weak(),
+            );
+            return;
+        }
+        ui.separator();
+        ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+            for (i, run) in self.tools.results.iter().enumerate() {
+                let status = match &run.status {
+                    Ok(Some(code)) => format!("exit {code}"),
+                    Ok(None) => "terminated".to_string(),
+                    Err(e) => e.clone(),
+                };
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(format!("{} · message {}", run.tool, run.message)).strong());
+                    let failed = !matches!(run.status, Ok(Some(0)));
+                    let status = RichText::new(status).weak();
+                    ui.label(if failed {
+                        status.color(Color32::from_rgb(183, 28, 28))
+                    } else {
+                        status
+                    });
+                    if ui.small_button("Copy").clicked() {
+                        ui.output_mut(|o| o.copied_text = run.stdout.clone());
+                    }
+                });
+                if !run.stdout.is_empty() {
+                    ui.label(RichText::new(run.stdout.trim_end()).monospace());
+                }
+                if !run.stderr.is_empty() {
+                    ui.colored_label(
+                        Color32::from_rgb(183, 28, 28),
+                        RichText::new(run.stderr.trim_end()).monospace(),
+                    );
+                }
+                if i + 1 < self.tools.results.len() {
+                    ui.separator();
+                }
+            }
+        });
+    }
+}
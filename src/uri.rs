@@ -0,0 +1,128 @@
+// `llmlog://open?path=/logs/chat.jsonl&msg=42` links from wikis and dashboards: the OS starts
+// the viewer with the URI as its argument, and "Register llmlog:// links" sets that up for the
+// current user.
+
+use crate::{AppState, MessageRef};
+use anyhow::{anyhow, bail, Context, Result};
+use eframe::egui;
+use std::path::PathBuf;
+
+pub(crate) const URI_SCHEME: &str = "llmlog";
+
+// Query parameters: `path` (required), `msg` (1-based message) and `session` (1-based
+// conversation in multi-conversation files). A link can come from any page, so its path must
+// be local and may not climb out with `..`; paths given on the command line are not limited.
+pub(crate) fn parse_open_uri(uri: &str) -> Result<(PathBuf, Option<MessageRef>)> {
+    let url = url::Url::parse(uri).context("Invalid link")?;
+    if url.scheme() != URI_SCHEME || url.host_str() != Some("open") {
+        bail!("Unsupported link: {uri}");
+    }
+    let param = |name: &str| url.query_pairs().find(|(k, _)| k == name).map(|(_, v)| v.into_owned());
+    let path = param("path").ok_or_else(|| anyhow!("The link has no path"))?;
+    // `\\host\share` and `//host/share` would reach out to another machine
+    if path.starts_with(r"\\") || path.starts_with("//") {
+        bail!("Links cannot open network paths: {path}");
+    }
+    if path.split(['/', '\\']).any(|part| part == "..") {
+        bail!("Links cannot contain `..`: {path}");
+    }
+    let number = |name: &str| -> Result<Option<usize>> {
+        param(name)
+            .map(|v| v.parse().with_context(|| format!("Invalid {name}: {v}")))
+            .transpose()
+    };
+    let session = number("session")?;
+    let reference = number("msg")?.map(|message| MessageRef { session, message });
+    Ok((PathBuf::from(path), reference))
+}
+
+#[cfg(target_os = "linux")]
+fn register() -> Result<String> {
+    let exe = std::env::current_exe()?;
+    let data = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")));
+    let dir = data.ok_or_else(|| anyhow!("No data directory"))?.join("applications");
+    let name = "llm-log-viewer-url.desktop";
+    let entry = format!(
+        "[Desktop Entry]\nType=Application\nName=LLM Log Viewer\nExec=\"{}\" %u\nNoDisplay=true\nTerminal=false\nMimeType=x-scheme-handler/{URI_SCHEME};\n",
+        exe.display()
+    );
+    crate::write_file_creating_dirs(&dir.join(name), entry.as_bytes())?;
+    let status = std::process::Command::new("xdg-mime")
+        .args(["default", name, &format!("x-scheme-handler/{URI_SCHEME}")])
+        .status()
+        .context("Failed to run xdg-mime")?;
+    if !status.success() {
+        bail!("xdg-mime exited with {status}");
+    }
+    Ok(format!(
+        "Registered {URI_SCHEME}:// links ({})",
+        dir.join(name).display()
+    ))
+}
+
+#[cfg(target_os = "windows")]
+fn register() -> Result<String> {
+    let exe = std::env::current_exe()?;
+    let key = format!(r"HKCU\Software\Classes\{URI_SCHEME}");
+    let command = format!("\"{}\" \"%1\"", exe.display());
+    // (key, value name or None for the default value, data)
+    let entries = [
+        (key.clone(), None, "URL:LLM Log Viewer".to_string()),
+        (key.clone(), Some("URL Protocol"), String::new()),
+        (format!(r"{key}\shell\open\command"), None, command),
+    ];
+    for (path, value, data) in &entries {
+        let mut cmd = std::process::Command::new("reg");
+        cmd.args(["add", path.as_str()]);
+        match value {
+            Some(name) => cmd.args(["/v", *name]),
+            None => cmd.arg("/ve"),
+        };
+        let status = cmd.args(["/d", data.as_str(), "/f"]).status().context("Failed to run reg")?;
+        if !status.success() {
+            bail!("reg exited with {status}");
+        }
+    }
+    Ok(format!("Registered {URI_SCHEME}:// links"))
+}
+
+// macOS hands URLs to a running app as Apple Events rather than arguments, which the windowing
+// layer does not pass on; the menu explains this instead of offering the button.
+const CAN_REGISTER: bool = cfg!(any(target_os = "linux", target_os = "windows"));
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn register() -> Result<String> {
+    bail!("Opening {URI_SCHEME}:// links is only supported on Linux and Windows")
+}
+
+impl AppState {
+    pub(crate) fn links_menu(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new(format!("{URI_SCHEME}://open?path=…&msg=N"))
+                .monospace()
+                .weak(),
+        );
+        if ui
+            .add_enabled(CAN_REGISTER, egui::Button::new(format!("Register {URI_SCHEME}:// links")))
+            .on_hover_text("Open these links with this viewer")
+            .on_disabled_hover_text(
+                "macOS delivers links to apps as Apple Events, which the viewer cannot receive yet. \
+                 Open `file#msg-N` from the command line instead.",
+            )
+            .clicked()
+        {
+            self.links_status = Some(register().map_err(|e| format!("Failed to register links: {e}")));
+        }
+        match &self.links_status {
+            Some(Ok(msg)) => {
+                ui.label(msg);
+            }
+            Some(Err(msg)) => {
+                ui.colored_label(egui::Color32::from_rgb(183, 28, 28), msg);
+            }
+            None => {}
+        }
+    }
+}
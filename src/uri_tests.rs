@@ -0,0 +1,22 @@
+// `uri`: what an llmlog:// link may open.
+
+use crate::uri::parse_open_uri;
+use std::path::PathBuf;
+
+#[test]
+fn links_name_a_local_file_and_message() {
+    let (path, reference) = parse_open_uri("llmlog://open?path=%2Flogs%2Fchat.jsonl&session=3&msg=42").unwrap();
+    assert_eq!(path, PathBuf::from("/logs/chat.jsonl"));
+    let reference = reference.unwrap();
+    assert_eq!((reference.session, reference.message), (Some(3), 42));
+    assert!(parse_open_uri("llmlog://open?path=chat.jsonl").unwrap().1.is_none());
+}
+
+#[test]
+fn links_cannot_reach_network_or_parent_paths() {
+    for path in ["%5C%5Chost%5Cshare%5Cchat.jsonl", "%2F%2Fhost%2Fshare%2Fchat.jsonl", "%2Flogs%2F..%2F..%2Fetc%2Fpasswd", "logs%5C..%5Csecret.json"] {
+        assert!(parse_open_uri(&format!("llmlog://open?path={path}")).is_err(), "{path}");
+    }
+    assert!(parse_open_uri("llmlog://open?path=%2Flogs%2Fv1..v2.jsonl").is_ok());
+    assert!(parse_open_uri("https://open?path=chat.jsonl").is_err());
+}
@@ -0,0 +1,179 @@
+// `llm_log_viewer validate FILE…` checks log files against the chat schema without opening a
+// window, for dataset pipelines in CI. The report on stdout is JSON: per file, the detected format,
+// message counts and every issue found with its location. Messages are checked as written, before
+// the viewer fills in empty turns. Errors (lines that fail to parse, messages without a role or
+// content) make the exit status 1; roles outside the usual set are only warnings. A file that
+// cannot be read or recognized at all makes it 2.
+
+use crate::{
+    parse::{parse_path, ParseOptions, RawLoaded},
+    roles::canonical_role,
+};
+use serde::Serialize;
+use std::path::Path;
+
+// Roles of the chat schema after built-in aliases (`human`, `function`, …) are resolved
+const KNOWN_ROLES: &[&str] = &["system", "developer", "user", "assistant", "tool"];
+
+#[derive(Serialize)]
+pub(crate) struct Issue {
+    severity: &'static str,
+    kind: &'static str,
+    // 1-based line of a JSONL file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    // 1-based conversation and message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conversation: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<usize>,
+    detail: String,
+}
+
+impl Issue {
+    fn error(kind: &'static str, detail: String) -> Self {
+        Self {
+            severity: "error",
+            kind,
+            line: None,
+            conversation: None,
+            message: None,
+            detail,
+        }
+    }
+
+    fn at(self, conversation: usize, message: usize) -> Self {
+        Self {
+            conversation: Some(conversation + 1),
+            message: Some(message + 1),
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
+struct FileReport {
+    file: String,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<&'static str>,
+    conversations: usize,
+    messages: usize,
+    // Lines that failed to parse; only the first few hundred are listed as issues
+    bad_lines: usize,
+    errors: usize,
+    warnings: usize,
+    issues: Vec<Issue>,
+}
+
+#[derive(Serialize)]
+struct Report {
+    ok: bool,
+    files: Vec<FileReport>,
+}
+
+pub(crate) fn check(raw: &RawLoaded) -> Vec<Issue> {
+    let mut issues: Vec<Issue> = raw
+        .failed
+        .lines
+        .iter()
+        .map(|e| {
+            // WireMsg requires a role, so a line without one fails to parse
+            let kind = if e.error.starts_with("missing field `role`") {
+                "missing_role"
+            } else {
+                "bad_line"
+            };
+            Issue {
+                line: Some(e.line),
+                ..Issue::error(kind, e.error.clone())
+            }
+        })
+        .collect();
+    for (c, session) in raw.sessions.iter().enumerate() {
+        if session.messages.is_empty() {
+            issues.push(Issue {
+                conversation: Some(c + 1),
+                ..Issue::error("no_messages", "conversation has no messages".to_string())
+            });
+        }
+        for (m, msg) in session.messages.iter().enumerate() {
+            let role = canonical_role(&msg.role);
+            if role.is_empty() {
+                issues.push(Issue::error("missing_role", "role is empty".to_string()).at(c, m));
+            } else if !KNOWN_ROLES.contains(&role.as_str()) {
+                let detail = format!("unknown role \"{}\"", msg.role);
+                issues.push(
+                    Issue {
+                        severity: "warning",
+                        ..Issue::error("unknown_role", detail)
+                    }
+                    .at(c, m),
+                );
+            }
+            if msg.content.trim().is_empty() && msg.attachments.is_empty() {
+                issues.push(Issue::error("empty_content", "content is empty".to_string()).at(c, m));
+            }
+        }
+    }
+    issues
+}
+
+fn validate_file(path: &Path) -> FileReport {
+    let file = path.display().to_string();
+    // Malformed lines are reported as issues, so they must not fail the whole file
+    let raw = match parse_path(path, None, ParseOptions::default()) {
+        Ok(raw) => raw,
+        Err(e) => {
+            return FileReport {
+                file,
+                errors: 1,
+                issues: vec![Issue::error("unreadable", format!("{e:#}"))],
+                ..Default::default()
+            }
+        }
+    };
+    let mut issues = check(&raw);
+    issues.extend(raw.warnings.iter().map(|w| Issue {
+        severity: "warning",
+        ..Issue::error("decoding", w.clone())
+    }));
+    let errors = issues.iter().filter(|i| i.severity == "error").count();
+    FileReport {
+        file,
+        ok: errors == 0,
+        format: Some(raw.format),
+        conversations: raw.sessions.len(),
+        messages: raw.sessions.iter().map(|s| s.messages.len()).sum(),
+        bad_lines: raw.failed.count,
+        errors,
+        warnings: issues.len() - errors,
+        issues,
+    }
+}
+
+// Prints the report and returns the exit status.
+pub(crate) fn run(paths: &[String]) -> i32 {
+    if paths.is_empty() {
+        eprintln!("usage: llm_log_viewer validate FILE…");
+        return 2;
+    }
+    let files: Vec<FileReport> = paths.iter().map(|p| validate_file(Path::new(p))).collect();
+    let unreadable = files.iter().any(|f| f.format.is_none());
+    let report = Report {
+        ok: files.iter().all(|f| f.ok),
+        files,
+    };
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => println!("{json}"),
+        Err(e) => {
+            eprintln!("Failed to write the report: {e}");
+            return 2;
+        }
+    }
+    match (unreadable, report.ok) {
+        (true, _) => 2,
+        (false, false) => 1,
+        (false, true) => 0,
+    }
+}
@@ -0,0 +1,28 @@
+// `validate`: schema issues are found in messages as written, with their location.
+
+use crate::{parse::{parse_bytes, ParseOptions}, validate::check};
+
+#[test]
+fn schema_issues_are_reported() {
+    let text = "{\"role\":\"user\",\"content\":\"hi\"}\n{\"content\":\"x\"}\n{\"role\":\"assistant\",\"content\":\"\"}\n{\"role\":\"narrator\",\"content\":\"ok\"}\nnot json\n";
+    let raw = parse_bytes(text.as_bytes(), ParseOptions::default()).unwrap();
+    let issues = serde_json::to_value(check(&raw)).unwrap();
+    let summary: Vec<String> = issues
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| format!("{} {} {} {}", i["severity"], i["kind"], i["line"], i["message"]))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            "\"error\" \"missing_role\" 2 null",
+            "\"error\" \"bad_line\" 5 null",
+            "\"error\" \"empty_content\" null 2",
+            "\"warning\" \"unknown_role\" null 3",
+        ]
+    );
+
+    let clean = parse_bytes(b"[{\"role\":\"human\",\"content\":\"hi\"},{\"role\":\"function\",\"content\":\"{}\"}]", ParseOptions::default()).unwrap();
+    assert!(check(&clean).is_empty());
+}